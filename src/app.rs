@@ -1,24 +1,176 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use crate::config::Config;
-use crate::models::gemini::{self, get_gemini_response};
+use crate::attachment::Attachment;
+use crate::config::{Config, GenerationPreset, PanelLabel, Provider, RedactionAction, Tone};
+use crate::credentials;
+use crate::diff;
+use crate::emoji;
+use crate::language;
+use crate::linkify;
+use crate::metrics;
+use crate::models::{self, Provider as _, gemini};
+use crate::partial_markdown;
+use crate::preferences;
+use crate::profile::Profile;
+use crate::redact::{self, Finding, Pattern};
+use crate::remote;
+use crate::scripting::Scripts;
+use crate::session::{self, HistoryStore, Session};
+use crate::spending;
+use cosmic::applet::cosmic_panel_config::PanelAnchor;
 use cosmic::cosmic_config::{self, CosmicConfigEntry};
+use cosmic::iced::event::{PlatformSpecific, wayland};
+use cosmic::iced::widget::scrollable::RelativeOffset;
 use cosmic::iced::{Subscription, widget::column, widget::markdown, window::Id};
 use cosmic::iced_winit::commands::popup::{destroy_popup, get_popup};
 use cosmic::prelude::*;
 use cosmic::widget;
 use cosmic::{Element, iced};
 use futures_util::SinkExt;
-use rdev::display_size;
-use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
 
-use iced_selection::{cosmic_select, cosmic_text};
+use iced_selection::cosmic_select;
+
+mod attachments;
+mod background;
+mod batch;
+mod chips;
+mod compare;
+mod conversations;
+mod details;
+mod diagnostics;
+mod downloads;
+mod emoji_picker;
+mod follow_ups;
+mod import;
+mod inspector;
+mod jobs;
+mod lock;
+mod pinned;
+mod quick_actions;
+mod quick_reply;
+mod read_aloud;
+mod refine;
+mod settings;
+mod share;
+mod spelling;
+mod templates;
+mod viewer;
+mod welcome;
 
 pub const APPID: &str = "com.github.Ignavar.cosmic-ai-interface";
 
+/// The prompt input, focused whenever the popup opens.
+static INPUT_ID: LazyLock<widget::Id> = LazyLock::new(|| widget::Id::new("prompt-input"));
+
+/// Earlier answers longer than this many lines are cut short until expanded.
+const COLLAPSE_AFTER_LINES: usize = 25;
+
+/// How long a cleared conversation can be brought back.
+const UNDO_CLEAR_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Logical size assumed for the panel's output until the compositor describes it.
+const FALLBACK_OUTPUT_SIZE: (i32, i32) = (1280, 720);
+
+/// The chat, scrolled back to where it was when the popup opens.
+static CHAT_ID: LazyLock<widget::Id> = LazyLock::new(|| widget::Id::new("chat"));
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Chat {
     pub role: String,
     pub content: String,
+    /// The answer this one replaced when it was regenerated.
+    pub previous: Option<String>,
+    /// Both answers are shown until the user picks the better one.
+    pub awaiting_choice: bool,
+    /// Model and preset that produced the answer.
+    pub generated_by: Option<String>,
+    /// Model and preset that produced `previous`.
+    pub previous_generated_by: Option<String>,
+    /// Usage of the request that produced the answer.
+    pub stats: Option<AnswerStats>,
+    /// A failure reported in place of an answer.
+    pub error: bool,
+    /// How many more times the same failure followed.
+    pub repeated: u32,
+    /// Files sent with the prompt.
+    pub attachments: Vec<Attachment>,
+    /// When the message was sent or received, in seconds since the Unix
+    /// epoch. 0 for messages saved before it was recorded.
+    pub sent_at: u64,
+}
+
+impl Chat {
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: "user".into(),
+            content: content.into(),
+            sent_at: now(),
+            ..Default::default()
+        }
+    }
+
+    pub fn model(content: impl Into<String>) -> Self {
+        Self {
+            role: "model".into(),
+            content: content.into(),
+            sent_at: now(),
+            ..Default::default()
+        }
+    }
+
+    pub fn error(content: impl Into<String>) -> Self {
+        Self {
+            error: true,
+            ..Self::model(content)
+        }
+    }
+}
+
+/// Seconds since the Unix epoch.
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// What answering took, shown when hovering over an answer.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnswerStats {
+    pub prompt_tokens: u32,
+    /// Part of the prompt the provider had cached.
+    #[serde(default)]
+    pub cached_tokens: u32,
+    pub completion_tokens: u32,
+    /// Whether the token counts are estimates rather than reported by the provider.
+    pub estimated: bool,
+    /// Estimated price in US dollars.
+    pub cost: f64,
+    pub latency: Duration,
+    pub finish_reason: Option<String>,
+    /// The provider's explanation of an unusual finish.
+    #[serde(default)]
+    pub finish_message: Option<String>,
+    /// Version of the model that answered, when the provider says.
+    #[serde(default)]
+    pub model_version: Option<String>,
+    /// Safety ratings of the answer, as category and probability.
+    #[serde(default)]
+    pub safety_ratings: Vec<(String, String)>,
+    /// Web pages the answer is grounded in.
+    #[serde(default)]
+    pub sources: Vec<gemini::Source>,
+    /// Searches run to ground the answer.
+    #[serde(default)]
+    pub search_queries: Vec<String>,
+    /// Every part of the answer, thoughts included, described one by one.
+    #[serde(default)]
+    pub parts: Vec<String>,
 }
 
 /// The application model stores app-specific state used to describe its interface and
@@ -29,14 +181,274 @@ pub struct AppModel {
     core: cosmic::Core,
     /// The popup id.
     popup: Option<Id>,
+    /// Profile this instance is bound to.
+    profile: Profile,
+    /// Where conversations are kept between runs.
+    history_store: Box<dyn HistoryStore>,
+    /// Id of the profile's configuration.
+    config_id: &'static str,
     /// Configuration data that persists between application runs.
     config: Config,
+    /// Handle used to write configuration changes.
+    config_handler: Option<cosmic_config::Config>,
+    /// Page shown in the popup.
+    page: Page,
+    /// State of the settings page.
+    settings: settings::State,
+    /// State of the batch question page.
+    batch: batch::State,
+    /// State of the compare page.
+    compare: compare::State,
+    /// State of the diagnostics page.
+    diagnostics: diagnostics::State,
+    /// State of the model downloads page.
+    downloads: downloads::State,
+    /// State of the share page.
+    share: share::State,
+    /// Attachments queued for the next prompt.
+    attachments: attachments::State,
+    /// Image shown on the viewer page.
+    viewer: viewer::State,
+    /// Suggested follow-up questions for the latest answer.
+    follow_ups: follow_ups::State,
+    chips: chips::State,
+    /// Tip rotation of the empty conversation's welcome.
+    welcome: welcome::State,
+    /// Improved version of the draft prompt.
+    refine: refine::State,
+    /// State of the quick action palette.
+    quick_actions: quick_actions::State,
+    /// One-shot question opened from a keyboard shortcut.
+    quick_reply: quick_reply::State,
+    /// State of the templates page.
+    templates: templates::State,
+    /// Paused scheduled jobs and when they last ran.
+    jobs: jobs::State,
+    /// Auxiliary requests waiting for their turn.
+    background: background::State,
+    /// Title of the conversation, once the model came up with one.
+    title: Option<String>,
+    /// User scripts hooking into prompts and answers.
+    scripts: Scripts,
+    /// Files pinned to the conversation.
+    pinned: pinned::State,
+    /// Whether the conversation is locked, and the one it was copied from.
+    lock: lock::State,
+    /// Conversations of the workspaces that aren't active, by workspace name.
+    workspace_conversations: HashMap<String, ParkedConversation>,
+    /// Conversations set aside to switch back to.
+    conversations: conversations::State,
+    /// "No workspace" followed by the workspace names, for the header dropdown.
+    workspace_names: Vec<String>,
+    /// Emoji picker above the prompt.
+    emoji_picker: emoji_picker::State,
+    /// Spelling suggestions for the prompt.
+    spelling: spelling::State,
+    /// Read-aloud queue and playback.
+    read_aloud: read_aloud::State,
+    /// Last successful exchange, shown in the debug inspector.
+    last_reply: Option<gemini::Reply>,
+    /// Request editor and replay of the debug inspector.
+    inspector: inspector::State,
+    /// Details panel of an answer.
+    details: details::State,
+    /// Conversation files that couldn't be opened.
+    import: import::State,
     /// Input text field.
     input_text: String,
     /// Chat history.
     chat_history: Arc<Vec<Chat>>,
+    /// Number of messages in the history the user has seen.
+    read_up_to: usize,
+    /// The chat is scrolled to its end, so new answers are in view.
+    chat_at_bottom: bool,
+    /// Relative scroll position of the chat, 1 at its end.
+    scroll_offset: f32,
+    /// Logical sizes of the outputs by name, which already account for their scale.
+    output_sizes: HashMap<String, (i32, i32)>,
     ///
     is_loading: bool,
+    /// Tag of the request whose answer is awaited. Answers with any other tag
+    /// belong to a conversation that was since replaced and are dropped.
+    in_flight: Option<u64>,
+    /// Stops the request in flight when its answer is no longer wanted.
+    answer_task: Option<cosmic::iced::task::Handle>,
+    /// Requests still answering conversations that were set aside, by tag.
+    background_answers: HashMap<u64, cosmic::iced::task::Handle>,
+    /// Next free tag for a request or a conversation.
+    next_request: u64,
+    /// Tag of the conversation in view, which changes whenever another one is
+    /// shown, so background results for the one before are dropped.
+    conversation_tag: u64,
+    /// When the request in flight was sent.
+    request_started: Option<Instant>,
+    /// Text of the answer in flight as far as it arrived.
+    partial_answer: Option<String>,
+    /// Conversation cleared moments ago, with a tag for when it expires.
+    cleared: Option<(u64, ParkedConversation)>,
+    /// Prompts entered while an answer was generating, sent in order after it.
+    pending_prompts: VecDeque<String>,
+    /// Answer removed by a regeneration that is still in flight.
+    regenerating: Option<Chat>,
+    /// Long answers shown in full.
+    expanded: HashSet<usize>,
+    /// Message whose changes since regeneration are shown, with the diff once
+    /// it has been computed.
+    diff: Option<(usize, Option<Vec<diff::Change>>)>,
+    /// Setup guidance shown after a request failed for lack of an API key.
+    api_key_help: Option<ApiKeyHelp>,
+    /// Shown when the configured model's serving version changed.
+    version_change: Option<VersionChange>,
+    /// Language most prompts of the conversation are written in.
+    conversation_language: Option<&'static str>,
+    /// Prompt held back because it or its attachments contain sensitive text.
+    redaction_warning: Option<RedactionWarning>,
+    /// Prompt a script failed on, with the error.
+    script_error: Option<(String, String)>,
+    /// Estimated spending of the conversation so far, in US dollars.
+    conversation_cost: f64,
+    /// Request held back because it would go over a spending cap, with the reason.
+    cost_warning: Option<(OverCap, String)>,
+    /// Estimated spending of today, for the daily cap.
+    spending: spending::Spending,
+}
+
+/// A conversation that isn't shown, with the files pinned to it.
+#[derive(Default, Clone)]
+struct ParkedConversation {
+    history: Arc<Vec<Chat>>,
+    pinned: pinned::State,
+    lock: lock::State,
+    /// Name of the template it was started from.
+    template: String,
+    tone: Tone,
+    title: Option<String>,
+    cost: f64,
+    /// The answer still coming in, if it was generating when set aside.
+    generating: Option<Generating>,
+}
+
+/// An answer generating for a conversation that is out of view.
+#[derive(Clone)]
+struct Generating {
+    request: u64,
+    /// Text of the answer as far as it arrived.
+    partial: Option<String>,
+    started: Option<Instant>,
+    /// Settings it was sent with.
+    config: Box<Config>,
+}
+
+impl ParkedConversation {
+    fn saved(&self) -> session::Conversation {
+        session::Conversation {
+            history: self.history.to_vec(),
+            pinned_files: self.pinned.files.clone(),
+            template: self.template.clone(),
+            tone: self.tone,
+            title: self.title.clone(),
+            cost: self.cost,
+            locked: self.lock.locked,
+            original: self
+                .lock
+                .original
+                .as_ref()
+                .map(|original| Box::new(original.saved())),
+        }
+    }
+
+    fn from_saved(conversation: session::Conversation) -> Self {
+        Self {
+            history: Arc::new(conversation.history),
+            pinned: pinned::State::with_files(conversation.pinned_files),
+            lock: lock::State {
+                locked: conversation.locked,
+                original: conversation
+                    .original
+                    .map(|original| Box::new(Self::from_saved(*original))),
+            },
+            template: conversation.template,
+            tone: conversation.tone,
+            title: conversation.title,
+            cost: conversation.cost,
+            generating: None,
+        }
+    }
+}
+
+/// Pages of the popup.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Page {
+    #[default]
+    Chat,
+    Settings,
+    Inspector,
+    Batch,
+    Compare,
+    Diagnostics,
+    Downloads,
+    Share,
+    Viewer,
+    QuickActions,
+    Templates,
+    Jobs,
+    Conversations,
+}
+
+impl Page {
+    /// Pages that stay reachable while the content policy is locked.
+    fn allowed_when_locked(self) -> bool {
+        matches!(
+            self,
+            Self::Chat
+                | Self::Settings
+                | Self::Viewer
+                | Self::QuickActions
+                | Self::Templates
+                | Self::Conversations
+        )
+    }
+}
+
+/// A prompt held back because of sensitive text, with what was found.
+struct RedactionWarning {
+    text: String,
+    /// Findings in `text`.
+    findings: Vec<Finding>,
+    /// Kinds found in text attachments, which are masked separately.
+    in_attachments: Vec<Pattern>,
+}
+
+/// A request held back by a spending cap until the user decides.
+enum OverCap {
+    Prompt(String),
+    Regenerate,
+}
+
+/// A model that started being served by another version.
+#[derive(Debug, Clone)]
+pub struct VersionChange {
+    model: String,
+    old: String,
+    new: String,
+}
+
+/// What we found out about the API key when it couldn't be loaded.
+#[derive(Debug, Clone, Copy)]
+pub struct ApiKeyHelp {
+    /// The key is exported by the login shell but not visible to the session.
+    in_login_shell: bool,
+    /// Provider whose key is missing.
+    provider: Provider,
+}
+
+impl ApiKeyHelp {
+    /// Environment variable the provider's key is read from.
+    fn var(self) -> &'static str {
+        models::provider(self.provider)
+            .api_key_var()
+            .unwrap_or(gemini::API_KEY_VAR)
+    }
 }
 
 /// Messages emitted by the application and its widgets.
@@ -48,14 +460,76 @@ pub enum Message {
     UpdateConfig(Config),
     SubmitInput(String),
     InputChanged(String),
-    GeminiMessage(gemini::Message),
+    /// Answer to the request with this tag.
+    GeminiMessage(u64, gemini::Message),
+    /// Text of the answer to the request with this tag, as far as it arrived.
+    PartialAnswer(u64, String),
     UrlClicked(markdown::Url),
-}
-
-impl From<gemini::Message> for Message {
-    fn from(message: gemini::Message) -> Self {
-        Self::GeminiMessage(message)
-    }
+    OpenApiKeyPage,
+    OpenEnvFile,
+    DismissVersionChange,
+    DismissApiKeyHelp,
+    TogglePage(Page),
+    Batch(batch::Message),
+    Compare(compare::Message),
+    Diagnostics(diagnostics::Message),
+    Downloads(downloads::Message),
+    Share(share::Message),
+    ReadAloud(read_aloud::Message),
+    Spelling(spelling::Message),
+    EmojiPicker(emoji_picker::Message),
+    Attachments(attachments::Message),
+    Viewer(viewer::Message),
+    FollowUps(follow_ups::Message),
+    Chips(chips::Message),
+    Refine(refine::Message),
+    Pinned(pinned::Message),
+    QuickActions(quick_actions::Message),
+    QuickReply(quick_reply::Message),
+    /// A command run from a keyboard shortcut.
+    Remote(remote::Command),
+    Templates(templates::Message),
+    Conversations(conversations::Message),
+    Welcome(welcome::Message),
+    Inspector(inspector::Message),
+    Details(details::Message),
+    Lock(lock::Message),
+    Import(import::Message),
+    Jobs(jobs::Message),
+    Background(background::Message),
+    ChatScrolled(widget::scrollable::Viewport),
+    /// Index into the header's workspace list, 0 for none.
+    WorkspaceSelected(usize),
+    /// Checks whether a scheduled backup is due.
+    BackupTick,
+    SyncTick,
+    Settings(settings::Message),
+    PresetSelected(usize),
+    Regenerate,
+    PreferAnswer {
+        regenerated: bool,
+    },
+    ToggleDiff(usize),
+    DiffComputed(usize, Vec<diff::Change>),
+    SendAnyway,
+    /// Reloads the scripts and submits the prompt they failed on again.
+    RetryScript,
+    /// Puts the prompt a script failed on back into the prompt field.
+    EditFailedPrompt,
+    MaskAndSend,
+    DismissRedactionWarning,
+    SendOverCap,
+    DismissCostWarning,
+    RemovePending(usize),
+    ToggleExpanded(usize),
+    /// Stops waiting for the answer and for watched files.
+    StopAnswer,
+    ClearConversation,
+    UndoClear,
+    /// The cleared conversation with this tag can no longer be brought back.
+    ForgetCleared(u64),
+    /// An output appeared or changed, with its name and logical size.
+    OutputChanged(String, (i32, i32)),
 }
 
 /// Create a COSMIC application from the app model
@@ -64,7 +538,7 @@ impl cosmic::Application for AppModel {
     type Executor = cosmic::executor::Default;
 
     /// Data that your application receives to its init method.
-    type Flags = ();
+    type Flags = Profile;
 
     /// Messages which the application and its widgets will emit.
     type Message = Message;
@@ -83,13 +557,18 @@ impl cosmic::Application for AppModel {
     /// Initializes the application with any given flags and startup commands.
     fn init(
         core: cosmic::Core,
-        _flags: Self::Flags,
+        profile: Self::Flags,
     ) -> (Self, Task<cosmic::Action<Self::Message>>) {
+        // Subscriptions need a static id, and there's only one per process.
+        let config_id: &'static str = Box::leak(profile.config_id().into_boxed_str());
+        let config_handler = cosmic_config::Config::new(config_id, Config::VERSION).ok();
+
         // Construct the app model with the runtime's core.
-        let app = AppModel {
+        let mut app = AppModel {
             core,
-            config: cosmic_config::Config::new(Self::APP_ID, Config::VERSION)
-                .map(|context| match Config::get_entry(&context) {
+            config: config_handler
+                .as_ref()
+                .map(|context| match Config::get_entry(context) {
                     Ok(config) => config,
                     Err((_errors, config)) => {
                         // for why in errors {
@@ -100,10 +579,33 @@ impl cosmic::Application for AppModel {
                     }
                 })
                 .unwrap_or_default(),
+            config_handler,
+            profile,
+            config_id,
+            chat_at_bottom: true,
             ..Default::default()
         };
+        app.history_store = app.open_history_store();
+        if let Some(dir) = app.profile.data_dir() {
+            app.spending = spending::Spending::load(&dir);
+        }
+        app.refresh_preferences();
+        app.refresh_local_models();
+        app.refresh_voices();
+        app.refresh_backups();
+        app.refresh_workspace_names();
+        if app.config.scripting {
+            app.scripts = Scripts::load();
+        }
+        let popup_open = app.restore_session();
 
-        (app, Task::none())
+        let task = if popup_open {
+            app.open_popup()
+        } else {
+            Task::none()
+        };
+        let task = Task::batch([app.load_keyring(), task]);
+        (app, task)
     }
 
     fn on_close_requested(&self, id: Id) -> Option<Message> {
@@ -116,26 +618,231 @@ impl cosmic::Application for AppModel {
     /// This view should emit messages to toggle the applet's popup window, which will
     /// be drawn using the `view_window` method.
     fn view(&self) -> Element<'_, Self::Message> {
-        self.core
-            .applet
-            .icon_button(constcat::concat!(APPID, "-symbolic"))
-            .on_press(Message::TogglePopup)
-            .into()
+        let applet = &self.core.applet;
+        let icon_name = self.config.panel_icon.icon_name();
+        let label = match self.config.panel_label_source {
+            PanelLabel::Text => self.config.panel_label.trim().to_string(),
+            PanelLabel::Model => models::model_name(&self.config.for_workspace()),
+        };
+        let button = if label.is_empty() {
+            applet.icon_button(icon_name)
+        } else {
+            let icon = widget::icon::from_name(icon_name).size(applet.suggested_size(true).0);
+            // Vertical panels are too narrow for text beside the icon.
+            let content: Element<_> = if applet.is_horizontal() {
+                widget::row::with_children(vec![icon.into(), applet.text(label).into()])
+                    .spacing(4)
+                    .align_y(iced::Alignment::Center)
+                    .into()
+            } else {
+                widget::column::with_children(vec![
+                    icon.into(),
+                    widget::text::caption(label).into(),
+                ])
+                .spacing(2)
+                .align_x(iced::Alignment::Center)
+                .into()
+            };
+            widget::button::custom(content)
+                .class(cosmic::theme::Button::AppletIcon)
+                .padding(applet.suggested_padding(true))
+        }
+        .on_press(Message::TogglePopup);
+        let button = widget::mouse_area(button)
+            .on_right_press(Message::QuickActions(quick_actions::Message::Open));
+
+        let unread = self.unread_count();
+        if unread == 0 {
+            return button.into();
+        }
+        // Count of unseen answers in the corner of the panel icon.
+        let badge = widget::container(widget::text::caption(unread.min(99).to_string()))
+            .padding([0, 4])
+            .class(cosmic::theme::Container::custom(|theme| {
+                let cosmic = theme.cosmic();
+                iced::widget::container::Style {
+                    background: Some(iced::Color::from(cosmic.accent_color()).into()),
+                    text_color: Some(cosmic.on_accent_color().into()),
+                    border: iced::Border {
+                        radius: 8.0.into(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                }
+            }));
+        // Keep the badge on the corner facing away from the screen edge.
+        let badge = widget::container(badge).align_top(iced::Length::Fill);
+        let badge = match applet.anchor {
+            PanelAnchor::Right => badge.align_left(iced::Length::Fill),
+            _ => badge.align_right(iced::Length::Fill),
+        };
+        iced::widget::stack![button, badge].into()
     }
 
     /// The applet's popup window will be drawn using this view method. If there are
     /// multiple poups, you may match the id parameter to determine which popup to
     /// create a view for.
     fn view_window(&self, _id: Id) -> Element<'_, Self::Message> {
-        let (width, height) = display_size().unwrap_or((1280, 720));
+        let (width, height) = self.output_size();
+        if self.quick_reply.open {
+            let content = widget::container(self.quick_reply_view().map(Message::QuickReply))
+                .padding([18, 10]);
+            return self
+                .core
+                .applet
+                .popup_container(content)
+                .limits(
+                    cosmic::iced::Limits::NONE
+                        .min_width(width as f32 / 3.5)
+                        .max_width(width as f32 / 3.5)
+                        .max_height(height as f32 / 2.0),
+                )
+                .into();
+        }
+        let page: cosmic::Element<_> = match self.page {
+            Page::Chat => {
+                widget::dnd_destination::DndDestination::for_data::<import::DroppedFiles>(
+                    column!(
+                        self.chips_view().map(Message::Chips),
+                        match self.details_view() {
+                            Some(details) => iced::widget::stack![
+                                self.chat_view(),
+                                details.map(Message::Details)
+                            ]
+                            .into(),
+                            None => self.chat_view(),
+                        },
+                        self.read_aloud_controls()
+                            .map(|controls| controls.map(Message::ReadAloud))
+                            .unwrap_or_else(|| widget::Space::new(0, 0).into()),
+                        self.api_key_help_view(),
+                        self.import_error_view()
+                            .map(|error| error.map(Message::Import))
+                            .unwrap_or_else(|| widget::Space::new(0, 0).into()),
+                        self.undo_clear_view(),
+                        self.script_error_view(),
+                        self.version_change_view(),
+                        self.redaction_warning_view(),
+                        self.cost_warning_view(),
+                        self.pending_view(),
+                        self.spelling_view()
+                            .map(|suggestions| suggestions.map(Message::Spelling))
+                            .unwrap_or_else(|| widget::Space::new(0, 0).into()),
+                        self.emoji_picker_view()
+                            .map(|picker| picker.map(Message::EmojiPicker))
+                            .unwrap_or_else(|| widget::Space::new(0, 0).into()),
+                        self.selection_view()
+                            .map(|selection| selection.map(Message::Share))
+                            .unwrap_or_else(|| widget::Space::new(0, 0).into()),
+                        self.pinned_view()
+                            .map(|pinned| pinned.map(Message::Pinned))
+                            .unwrap_or_else(|| widget::Space::new(0, 0).into()),
+                        self.attachments_view()
+                            .map(|attachments| attachments.map(Message::Attachments))
+                            .unwrap_or_else(|| widget::Space::new(0, 0).into()),
+                        self.refine_view()
+                            .map(|refine| refine.map(Message::Refine))
+                            .unwrap_or_else(|| widget::Space::new(0, 0).into()),
+                        match self.locked_view() {
+                            Some(locked) => locked.map(Message::Lock),
+                            None => self.prompt_row(),
+                        }
+                    )
+                    .spacing(10),
+                    |files, _action| Message::Import(import::Message::Dropped(files)),
+                )
+                .into()
+            }
+            Page::Settings => self.settings_view().map(Message::Settings),
+            Page::Inspector => self.inspector_view().map(Message::Inspector),
+            Page::Batch => self.batch_view().map(Message::Batch),
+            Page::Compare => self.compare_view().map(Message::Compare),
+            Page::Diagnostics => self.diagnostics_view().map(Message::Diagnostics),
+            Page::Downloads => self.downloads_view().map(Message::Downloads),
+            Page::Share => self.share_view().map(Message::Share),
+            Page::Viewer => self.viewer_view().map(Message::Viewer),
+            Page::QuickActions => self.quick_actions_view().map(Message::QuickActions),
+            Page::Templates => self.templates_view().map(Message::Templates),
+            Page::Jobs => self.jobs_view().map(Message::Jobs),
+            Page::Conversations => self.conversations_view().map(Message::Conversations),
+        };
+        let mut header: Vec<cosmic::Element<_>> =
+            vec![widget::text::heading(self.profile.name().unwrap_or_default().to_string()).into()];
+        if self.page == Page::Chat
+            && let Some(budget) = self.token_budget_view()
+        {
+            header.push(budget);
+        }
+        if self.page == Page::Chat
+            && let Some(language) = self.conversation_language
+        {
+            header.push(widget::text::caption(language).into());
+        }
+        if self.page == Page::Chat
+            && let Some(title) = &self.title
+        {
+            header.push(widget::text::caption(title.as_str()).into());
+        }
+        if self.page == Page::Chat && !self.config.template.is_empty() {
+            header.push(widget::text::caption(self.config.template.as_str()).into());
+        }
+        header.push(widget::horizontal_space().into());
+        let locked = self.config.is_locked();
+        if !self.chat_history.is_empty() && self.page == Page::Chat {
+            header.push(
+                widget::button::icon(widget::icon::from_name("list-add-symbolic"))
+                    .on_press(Message::Conversations(conversations::Message::New))
+                    .tooltip("New chat")
+                    .into(),
+            );
+            header.push(
+                widget::button::icon(widget::icon::from_name("edit-clear-all-symbolic"))
+                    .on_press(Message::ClearConversation)
+                    .tooltip("Clear conversation")
+                    .into(),
+            );
+        }
+        if self.page == Page::Chat {
+            header.extend(
+                self.lock_buttons()
+                    .into_iter()
+                    .map(|button| button.map(Message::Lock)),
+            );
+        }
+        if !self.chat_history.is_empty() && !locked {
+            header.push(
+                widget::button::icon(widget::icon::from_name("emblem-shared-symbolic"))
+                    .on_press(Message::Share(share::Message::Open))
+                    .selected(self.page == Page::Share)
+                    .into(),
+            );
+        }
+        let mut pages = vec![
+            (Page::Conversations, "view-list-bullet-symbolic"),
+            (Page::Templates, "document-new-symbolic"),
+            (Page::QuickActions, "system-run-symbolic"),
+        ];
+        if !locked {
+            pages.push((Page::Compare, "view-dual-symbolic"));
+            pages.push((Page::Batch, "view-list-symbolic"));
+            pages.push((Page::Jobs, "preferences-system-time-symbolic"));
+        }
+        if self.config.debug_inspector && !locked {
+            pages.push((Page::Inspector, "utilities-terminal-symbolic"));
+        }
+        pages.push((Page::Settings, "emblem-system-symbolic"));
+        for (page, icon) in pages {
+            header.push(
+                widget::button::icon(widget::icon::from_name(icon))
+                    .on_press(Message::TogglePage(page))
+                    .selected(self.page == page)
+                    .into(),
+            );
+        }
         let content = widget::container(
             column!(
-                self.chat_view(),
-                widget::text_input("Enter text", &self.input_text)
-                    .on_input(Message::InputChanged)
-                    .width(cosmic::iced::Length::Fill)
-                    .padding(10)
-                    .on_submit(Message::SubmitInput)
+                widget::row::with_children(header).align_y(iced::Alignment::Center),
+                page
             )
             .spacing(10),
         )
@@ -163,7 +870,7 @@ impl cosmic::Application for AppModel {
     fn subscription(&self) -> Subscription<Self::Message> {
         struct MySubscription;
 
-        Subscription::batch(vec![
+        let mut subscriptions = vec![
             // Create a subscription which emits updates through a channel.
             Subscription::run_with_id(
                 std::any::TypeId::of::<MySubscription>(),
@@ -175,7 +882,7 @@ impl cosmic::Application for AppModel {
             ),
             // Watch for application configuration changes.
             self.core()
-                .watch_config::<Config>(Self::APP_ID)
+                .watch_config::<Config>(self.config_id)
                 .map(|update| {
                     // for why in update.errors {
                     //     tracing::error!(?why, "app config error");
@@ -183,7 +890,78 @@ impl cosmic::Application for AppModel {
 
                     Message::UpdateConfig(update.config)
                 }),
-        ])
+        ];
+        if self.config.backup.interval.period().is_some() {
+            subscriptions.push(
+                cosmic::iced::time::every(jobs::BACKUP_CHECK_INTERVAL).map(|_| Message::BackupTick),
+            );
+        }
+
+        if self.config.sync.enabled {
+            subscriptions
+                .push(cosmic::iced::time::every(jobs::SYNC_INTERVAL).map(|_| Message::SyncTick));
+        }
+
+        subscriptions.push(cosmic::iced::event::listen_with(
+            |event, _, _| match event {
+                iced::Event::PlatformSpecific(PlatformSpecific::Wayland(
+                    wayland::Event::Output(
+                        wayland::OutputEvent::Created(Some(info))
+                        | wayland::OutputEvent::InfoUpdate(info),
+                        _,
+                    ),
+                )) => Some(Message::OutputChanged(info.name?, info.logical_size?)),
+                _ => None,
+            },
+        ));
+
+        if self.config.metrics.port != 0 {
+            let port = self.config.metrics.port;
+            subscriptions.push(Subscription::run_with_id(
+                ("metrics", port),
+                cosmic::iced::stream::channel(1, move |_channel| async move {
+                    metrics::serve(port).await;
+                    futures_util::future::pending().await
+                }),
+            ));
+        }
+
+        if self.background.is_waiting() {
+            subscriptions.push(
+                cosmic::iced::time::every(background::RETRY_INTERVAL)
+                    .map(|_| Message::Background(background::Message::Retry)),
+            );
+        }
+
+        if self.pinned.is_watching() {
+            subscriptions.push(
+                cosmic::iced::time::every(pinned::WATCH_INTERVAL)
+                    .map(|_| Message::Pinned(pinned::Message::CheckWatched)),
+            );
+        }
+
+        if let Some(path) = remote::socket_path(&self.profile) {
+            subscriptions.push(Subscription::run_with_id(
+                ("remote", path.clone()),
+                cosmic::iced::stream::channel(1, move |mut channel| async move {
+                    match remote::listen(&path) {
+                        Ok(listener) => loop {
+                            match remote::next(&listener).await {
+                                Ok(command) => _ = channel.send(Message::Remote(command)).await,
+                                Err(err) => {
+                                    eprintln!("stopped listening for commands: {err}");
+                                    break;
+                                }
+                            }
+                        },
+                        Err(err) => eprintln!("failed to listen on {}: {err}", path.display()),
+                    }
+                    futures_util::future::pending().await
+                }),
+            ));
+        }
+
+        Subscription::batch(subscriptions)
     }
 
     /// Handles messages emitted by the application and its widgets.
@@ -195,102 +973,446 @@ impl cosmic::Application for AppModel {
         match message {
             Message::InputChanged(text) => {
                 self.input_text = text;
+                return self.check_spelling();
             }
             Message::SubmitInput(text) => {
                 if self.is_loading {
+                    // Runs through the checks below once the answer has arrived.
+                    if !text.trim().is_empty() {
+                        self.pending_prompts.push_back(text);
+                        self.input_text.clear();
+                    }
                     return Task::none();
                 }
-                let Some(history) = Arc::get_mut(&mut self.chat_history) else {
-                    return Task::none();
+                self.script_error = None;
+                let text = if self.config.scripting {
+                    match self.scripts.on_prompt(text.clone()) {
+                        Ok(text) => text,
+                        Err(err) => {
+                            self.script_error = Some((text, err));
+                            self.input_text.clear();
+                            return Task::none();
+                        }
+                    }
+                } else {
+                    text
                 };
-                self.is_loading = true;
-                history.push(Chat {
-                    role: "user".into(),
-                    content: text.into(),
-                });
-                self.input_text.clear();
-                let cloned = Arc::clone(&self.chat_history);
-                return cosmic::task::future(async move {
-                    Message::GeminiMessage(get_gemini_response(cloned).await)
+                if text.trim().is_empty() {
+                    return Task::none();
+                }
+                let findings = redact::scan(&text, &self.config.redaction);
+                let (masked, warnings): (Vec<_>, Vec<_>) = findings
+                    .into_iter()
+                    .partition(|finding| finding.action == RedactionAction::Mask);
+                let in_attachments = self.attachment_warnings();
+                if warnings.is_empty() && in_attachments.is_empty() {
+                    self.mask_attachments(false);
+                    return self.submit(redact::mask(&text, &masked));
+                }
+                // Keep the masked findings so both kinds can be masked at once.
+                self.redaction_warning = Some(RedactionWarning {
+                    text,
+                    findings: [masked, warnings].concat(),
+                    in_attachments,
                 });
             }
-            Message::UrlClicked(_) => {}
+            Message::RetryScript => {
+                if let Some((text, _)) = self.script_error.take() {
+                    // Picks up a script fixed in the meantime.
+                    self.scripts = Scripts::load();
+                    return Task::done(cosmic::Action::App(Message::SubmitInput(text)));
+                }
+            }
+            Message::EditFailedPrompt => {
+                if let Some((text, _)) = self.script_error.take() {
+                    self.input_text = text;
+                    return widget::text_input::focus(INPUT_ID.clone());
+                }
+            }
+            Message::SendAnyway => {
+                if let Some(warning) = self.redaction_warning.take() {
+                    let masked: Vec<_> = warning
+                        .findings
+                        .into_iter()
+                        .filter(|finding| finding.action == RedactionAction::Mask)
+                        .collect();
+                    self.mask_attachments(false);
+                    let task = self.submit(redact::mask(&warning.text, &masked));
+                    return Task::batch([task, self.send_pending()]);
+                }
+            }
+            Message::MaskAndSend => {
+                if let Some(mut warning) = self.redaction_warning.take() {
+                    warning.findings.sort_by_key(|finding| finding.range.start);
+                    self.mask_attachments(true);
+                    let task = self.submit(redact::mask(&warning.text, &warning.findings));
+                    return Task::batch([task, self.send_pending()]);
+                }
+            }
+            Message::DismissRedactionWarning => {
+                self.redaction_warning = None;
+                return self.send_pending();
+            }
+            Message::SendOverCap => {
+                if let Some((held, _)) = self.cost_warning.take() {
+                    let task = match held {
+                        OverCap::Prompt(text) => self.send_prompt(text),
+                        OverCap::Regenerate => self.regenerate(),
+                    };
+                    return Task::batch([task, self.send_pending()]);
+                }
+            }
+            Message::DismissCostWarning => {
+                self.cost_warning = None;
+                return self.send_pending();
+            }
+            Message::RemovePending(index) => {
+                self.pending_prompts.remove(index);
+            }
+            Message::ToggleExpanded(index) => {
+                if !self.expanded.remove(&index) {
+                    self.expanded.insert(index);
+                }
+            }
+            Message::StopAnswer => {
+                if !self.is_loading {
+                    return Task::none();
+                }
+                self.abandon_request();
+                self.pinned.stop_watching();
+                self.save_session();
+            }
+            Message::ClearConversation => {
+                return self.clear_conversation();
+            }
+            Message::UndoClear => {
+                if let Some((_, conversation)) = self.cleared.take() {
+                    self.abandon_request();
+                    self.show_conversation(conversation);
+                    self.save_config();
+                    self.save_session();
+                }
+            }
+            Message::ForgetCleared(tag) => {
+                if self
+                    .cleared
+                    .as_ref()
+                    .is_some_and(|(cleared, _)| *cleared == tag)
+                {
+                    self.forget_cleared();
+                }
+            }
+            Message::Regenerate => {
+                if self.is_loading
+                    || self.lock.locked
+                    || self
+                        .chat_history
+                        .last()
+                        .is_none_or(|chat| chat.role != "model")
+                {
+                    return Task::none();
+                }
+                // The answer to be replaced is counted too, which errs on the safe side.
+                if let Some(reason) = self.cost_cap_exceeded("") {
+                    self.cost_warning = Some((OverCap::Regenerate, reason));
+                    return Task::none();
+                }
+                return self.regenerate();
+            }
+            Message::ToggleDiff(index) => {
+                if self.diff.as_ref().is_some_and(|(shown, _)| *shown == index) {
+                    self.diff = None;
+                } else if let Some(chat) = self.chat_history.get(index)
+                    && let Some(previous) = chat.previous.clone()
+                {
+                    self.diff = Some((index, None));
+                    let content = chat.content.clone();
+                    // Quadratic in the length of the answers, so kept off the UI thread.
+                    return cosmic::task::future(async move {
+                        let changes =
+                            tokio::task::spawn_blocking(move || diff::words(&previous, &content))
+                                .await
+                                .unwrap_or_default();
+                        Message::DiffComputed(index, changes)
+                    });
+                }
+            }
+            Message::DiffComputed(index, changes) => {
+                if let Some((shown, diff @ None)) = &mut self.diff
+                    && *shown == index
+                {
+                    *diff = Some(changes);
+                }
+            }
+            Message::PreferAnswer { regenerated } => {
+                let history = Arc::make_mut(&mut self.chat_history);
+                let Some(chat) = history.last_mut().filter(|chat| chat.awaiting_choice) else {
+                    return Task::none();
+                };
+                chat.awaiting_choice = false;
+                let regenerated_by = chat.generated_by.clone().unwrap_or_default();
+                let original_by = chat.previous_generated_by.take().unwrap_or_default();
+                let (winner, loser) = if regenerated {
+                    (regenerated_by, original_by)
+                } else {
+                    // Keep the original, the regenerated answer stays available as `previous`.
+                    if let Some(previous) = chat.previous.as_mut() {
+                        std::mem::swap(previous, &mut chat.content);
+                    }
+                    chat.generated_by = Some(original_by.clone());
+                    (original_by, regenerated_by)
+                };
+                if let Some(dir) = self.profile.data_dir()
+                    && let Err(err) = preferences::record(&dir, &winner, &loser)
+                {
+                    eprintln!("failed to record answer preference: {err}");
+                }
+                self.refresh_preferences();
+            }
+            Message::UrlClicked(url) => {
+                if url.scheme() == "file"
+                    && let Some((path, line)) = pinned::cited_line(&url)
+                {
+                    self.open_at_line(&path, line);
+                    return Task::none();
+                }
+                // Saved images open in the viewer rather than another application.
+                if url.scheme() == "file"
+                    && let Ok(path) = url.to_file_path()
+                    && Attachment::is_image_path(&path)
+                {
+                    return self.update_viewer(viewer::Message::OpenFile(path));
+                }
+                // Other files are shown in the file manager, in their folder.
+                if url.scheme() == "file"
+                    && let Ok(path) = url.to_file_path()
+                    && !path.is_dir()
+                    && let Some(folder) = path.parent()
+                {
+                    open_uri(&folder.to_string_lossy());
+                    return Task::none();
+                }
+                open_uri(url.as_str());
+            }
+            Message::OpenApiKeyPage => {
+                let provider = self
+                    .api_key_help
+                    .map_or(Provider::Gemini, |help| help.provider);
+                open_uri(
+                    models::provider(provider)
+                        .api_key_url()
+                        .unwrap_or(gemini::API_KEY_URL),
+                );
+            }
+            Message::OpenEnvFile => match credentials::ensure_env_file(
+                self.api_key_help
+                    .map_or(gemini::API_KEY_VAR, ApiKeyHelp::var),
+            ) {
+                Ok(path) => open_uri(&path.to_string_lossy()),
+                Err(err) => eprintln!("failed to create env file: {err}"),
+            },
+            Message::DismissApiKeyHelp => {
+                self.api_key_help = None;
+            }
+            Message::DismissVersionChange => {
+                self.version_change = None;
+            }
+            Message::TogglePage(page) => {
+                if self.config.is_locked() && !page.allowed_when_locked() {
+                    return Task::none();
+                }
+                self.page = if self.page == page { Page::Chat } else { page };
+                self.mark_read_if_visible();
+                if self.page == Page::Settings {
+                    return self.update_settings(settings::Message::RefreshStorage);
+                }
+            }
+            Message::ChatScrolled(viewport) => {
+                self.scroll_offset = viewport.relative_offset().y;
+                self.chat_at_bottom = self.scroll_offset >= 0.99;
+                self.mark_read_if_visible();
+            }
+            Message::Batch(message) => {
+                return self.update_batch(message);
+            }
+            Message::Compare(message) => {
+                return self.update_compare(message);
+            }
+            Message::Diagnostics(message) => {
+                return self.update_diagnostics(message);
+            }
+            Message::Downloads(message) => {
+                return self.update_downloads(message);
+            }
+            Message::Share(message) => {
+                return self.update_share(message);
+            }
+            Message::ReadAloud(message) => {
+                return self.update_read_aloud(message);
+            }
+            Message::Spelling(message) => {
+                return self.update_spelling(message);
+            }
+            Message::EmojiPicker(message) => {
+                return self.update_emoji_picker(message);
+            }
+            Message::Attachments(message) => {
+                return self.update_attachments(message);
+            }
+            Message::Viewer(message) => {
+                return self.update_viewer(message);
+            }
+            Message::FollowUps(message) => {
+                return self.update_follow_ups(message);
+            }
+            Message::Chips(message) => {
+                return self.update_chips(message);
+            }
+            Message::Refine(message) => {
+                return self.update_refine(message);
+            }
+            Message::Pinned(message) => {
+                return self.update_pinned(message);
+            }
+            Message::QuickActions(message) => {
+                return self.update_quick_actions(message);
+            }
+            Message::QuickReply(message) => {
+                return self.update_quick_reply(message);
+            }
+            Message::Remote(command) => match command {
+                remote::Command::QuickReply => {
+                    return self.update_quick_reply(quick_reply::Message::Open);
+                }
+            },
+            Message::Templates(message) => {
+                return self.update_templates(message);
+            }
+            Message::Conversations(message) => {
+                return self.update_conversations(message);
+            }
+            Message::Welcome(message) => {
+                return self.update_welcome(message);
+            }
+            Message::Inspector(message) => {
+                return self.update_inspector(message);
+            }
+            Message::Details(message) => {
+                return self.update_details(message);
+            }
+            Message::Lock(message) => {
+                return self.update_lock(message);
+            }
+            Message::Import(message) => {
+                return self.update_import(message);
+            }
+            Message::Jobs(message) => {
+                return self.update_jobs(message);
+            }
+            Message::Background(message) => {
+                return self.update_background(message);
+            }
+            Message::BackupTick => {
+                if self.jobs.is_paused(jobs::Job::Backup) {
+                    return Task::none();
+                }
+                return self.start_backup(false);
+            }
+            Message::SyncTick => {
+                self.jobs.sync_ticked();
+                if self.jobs.is_paused(jobs::Job::Sync) {
+                    return Task::none();
+                }
+                return self.start_sync();
+            }
+            Message::Settings(message) => {
+                return self.update_settings(message);
+            }
+            Message::PresetSelected(index) => {
+                if let Some(preset) = GenerationPreset::ALL.get(index) {
+                    // A workspace's own preset takes precedence, so change that one.
+                    let active = self.config.workspace.clone();
+                    match self
+                        .config
+                        .workspaces
+                        .iter_mut()
+                        .find(|workspace| workspace.name == active && workspace.preset.is_some())
+                    {
+                        Some(workspace) => workspace.preset = Some(*preset),
+                        None => self.config.preset = *preset,
+                    }
+                    self.save_config();
+                }
+            }
+            Message::WorkspaceSelected(index) => {
+                let name = if index == 0 {
+                    String::new()
+                } else {
+                    match self.config.workspaces.get(index - 1) {
+                        Some(workspace) => workspace.name.clone(),
+                        None => return Task::none(),
+                    }
+                };
+                self.switch_workspace(name);
+            }
+            Message::OutputChanged(name, size) => {
+                self.output_sizes.insert(name, size);
+            }
             Message::SubscriptionChannel => {
                 // For example purposes only.
             }
             Message::UpdateConfig(config) => {
                 self.config = config;
+                self.refresh_workspace_names();
+                if self.config.is_locked() && !self.page.allowed_when_locked() {
+                    self.page = Page::Chat;
+                }
             }
             Message::TogglePopup => {
-                return if let Some(p) = self.popup.take() {
+                self.quick_reply = quick_reply::State::default();
+                let task = if let Some(p) = self.popup.take() {
                     destroy_popup(p)
                 } else {
-                    let new_id = Id::unique();
-                    self.popup.replace(new_id);
-                    let popup_settings = self.core.applet.get_popup_settings(
-                        self.core.main_window_id().unwrap(),
-                        new_id,
-                        None,
-                        None,
-                        None,
-                    );
-                    get_popup(popup_settings)
+                    self.open_popup()
                 };
+                self.save_session();
+                return task;
             }
             Message::PopupClosed(id) => {
                 if self.popup.as_ref() == Some(&id) {
                     self.popup = None;
+                    self.quick_reply = quick_reply::State::default();
+                    self.save_session();
                 }
             }
-            Message::GeminiMessage(message) => {
-                self.is_loading = false;
-                let Some(history) = Arc::get_mut(&mut self.chat_history) else {
-                    return Task::none();
-                };
-                match message {
-                    gemini::Message::RequestError(error) => {
-                        history.push(Chat {
-                            role: "model".into(),
-                            content: error,
-                        });
-                    }
-                    gemini::Message::ApiKeyNotSet => {
-                        history.push(Chat {
-                            role: "model".into(),
-                            content: "API key not set".into(),
-                        });
+            Message::PartialAnswer(request, text) => {
+                if self.in_flight == Some(request) {
+                    self.partial_answer = Some(text);
+                    if self.chat_at_bottom {
+                        return widget::scrollable::snap_to(CHAT_ID.clone(), RelativeOffset::END);
                     }
-                    gemini::Message::ApiResultParsingError(error) => {
-                        history.push(Chat {
-                            role: "model".into(),
-                            content: format!("API result parsing error: {}", error),
-                        });
-                    }
-                    gemini::Message::ApiError(error) => {
-                        history.push(Chat {
-                            role: "model".into(),
-                            content: format!("API error: {}", error),
-                        });
-                    }
-                    gemini::Message::EmptyResponse => {
-                        history.push(Chat {
-                            role: "model".into(),
-                            content: "No response from model".into(),
-                        });
-                    }
-                    gemini::Message::PromptBlocked(error) => {
-                        history.push(Chat {
-                            role: "model".into(),
-                            content: format!("Prompt blocked: {}", error),
-                        });
-                    }
-                    gemini::Message::Response(response) => {
-                        history.push(Chat {
-                            role: "model".into(),
-                            content: response.into(),
-                        });
+                } else if let Some(generating) = self
+                    .generating_aside(request)
+                    .and_then(|parked| parked.generating.as_mut())
+                {
+                    generating.partial = Some(text);
+                }
+            }
+            Message::GeminiMessage(request, message) => {
+                if self.in_flight != Some(request) {
+                    if self.generating_aside(request).is_some() {
+                        self.receive_aside(request, message);
+                        self.save_session();
+                    } else {
+                        eprintln!(
+                            "dropped the answer to request {request}, its conversation is gone"
+                        );
                     }
+                    return Task::none();
                 }
+                let task = self.receive_answer(message);
+                self.save_session();
+                return Task::batch([task, self.send_pending()]);
             }
         }
         Task::none()
@@ -302,45 +1424,1287 @@ impl cosmic::Application for AppModel {
 }
 
 impl AppModel {
-    fn chat_view(&self) -> cosmic::Element<'_, Message> {
-        if self.chat_history.is_empty() {
-            widget::container(cosmic_text!("Start a new Chat!"))
-                .center_y(cosmic::iced::Length::Fill)
-                .center_x(cosmic::iced::Length::Fill)
-                .into()
-        } else {
-            let mut chats: Vec<cosmic::Element<_>> = Vec::with_capacity(self.chat_history.len());
-
-            for chat in self.chat_history.iter() {
-                let markdown: Vec<markdown::Item> = markdown::parse(&chat.content).collect();
-                let content = cosmic_select::markdown::view(
-                    &markdown,
-                    markdown::Settings::with_text_size(15),
-                    markdown::Style::from_palette(iced::Theme::TokyoNight.palette()),
-                )
-                .map(Message::UrlClicked);
-                let bubble = if chat.role == "user" {
-                    widget::container(
-                        widget::container(content)
-                            .class(cosmic::theme::Container::List)
-                            .padding(10),
-                    )
-                    .align_right(iced::Length::Fill)
-                    .into()
-                } else {
-                    widget::container(
-                        widget::container(content)
-                            .class(cosmic::theme::Container::List)
-                            .padding(10),
-                    )
-                    .align_left(iced::Length::Fill)
-                    .into()
-                };
-                chats.push(bubble);
+    /// Adds the answer, or what went wrong, to the conversation.
+    fn receive_answer(&mut self, message: gemini::Message) -> Task<cosmic::Action<Message>> {
+        self.is_loading = false;
+        self.in_flight = None;
+        self.answer_task = None;
+        self.partial_answer = None;
+        let latency = self
+            .request_started
+            .take()
+            .map(|started| started.elapsed())
+            .unwrap_or_default();
+        self.record_metrics(matches!(message, gemini::Message::Response(_)), latency);
+        let regenerated = self.regenerating.take();
+        // The answer is kept even if the popup was closed meanwhile, and the panel
+        // icon counts it as unread. A running export holding the history keeps its copy.
+        let history = Arc::make_mut(&mut self.chat_history);
+        // A failed regeneration keeps the answer it was meant to replace.
+        if let Some(previous) = &regenerated
+            && !matches!(message, gemini::Message::Response(_))
+        {
+            history.push(previous.clone());
+        }
+        match message {
+            gemini::Message::RequestError(error) => {
+                push_error(history, error);
+            }
+            gemini::Message::ApiKeyNotSet { in_login_shell } => {
+                // Keep the prompt around so it can be resent once the key is set.
+                if regenerated.is_none()
+                    && let Some(chat) = history.pop()
+                {
+                    self.input_text = chat.content;
+                    self.attachments.queue = chat.attachments;
+                }
+                self.api_key_help = Some(ApiKeyHelp {
+                    in_login_shell,
+                    provider: self.config.for_workspace().provider,
+                });
+            }
+            gemini::Message::ApiResultParsingError(error) => {
+                push_error(history, format!("API result parsing error: {}", error));
+            }
+            gemini::Message::ApiError(error) => {
+                push_error(history, format!("API error: {}", error));
+            }
+            gemini::Message::EmptyResponse => {
+                push_error(history, "No response from model".to_string());
+            }
+            gemini::Message::RequestTooLarge { size, limit } => {
+                // Give the prompt back so it can be shortened and resent.
+                if regenerated.is_none()
+                    && let Some(chat) = history.pop()
+                {
+                    self.input_text = chat.content;
+                    self.attachments.queue = chat.attachments;
+                }
+                let size = downloads::format_size(size as u64);
+                push_error(
+                    history,
+                    match limit {
+                        Some(limit) => format!(
+                            "Request too large: {size} (limit is {})",
+                            downloads::format_size(limit as u64)
+                        ),
+                        None => format!("Request too large: {size}"),
+                    },
+                );
+            }
+            gemini::Message::PromptBlocked(error) => {
+                push_error(history, format!("Prompt blocked: {}", error));
+            }
+            gemini::Message::Response(reply) => {
+                let (config, pinned) = (self.config.clone(), self.pinned.files.clone());
+                let content = self.answer_content(&reply, &config, &pinned);
+                let config = self.config.for_workspace();
+                let generated_by =
+                    format!("{} · {}", models::model_name(&config), config.preset.name());
+                let awaiting_choice = regenerated.is_some() && self.config.ab_regeneration;
+                let stats = self.record_cost(&reply, latency);
+                if let Some(version) = &reply.model_version {
+                    self.record_model_version(version);
+                }
+                let history = Arc::make_mut(&mut self.chat_history);
+                history.push(Chat {
+                    stats: Some(stats),
+                    previous: regenerated.as_ref().map(|chat| chat.content.clone()),
+                    previous_generated_by: regenerated.and_then(|chat| chat.generated_by),
+                    awaiting_choice,
+                    generated_by: Some(generated_by),
+                    ..Chat::model(content)
+                });
+                self.inspector.load(&reply.raw_request);
+                self.last_reply = Some(reply);
+                self.mark_read_if_visible();
+                return Task::batch([self.request_follow_ups(), self.request_title()]);
+            }
+        }
+        Task::none()
+    }
+
+    /// The text of `reply` as shown: run through the response script, with
+    /// paths and `pinned` files linked and generated images saved.
+    fn answer_content(
+        &mut self,
+        reply: &gemini::Reply,
+        config: &Config,
+        pinned: &[std::path::PathBuf],
+    ) -> String {
+        let mut content = reply.text.clone();
+        if config.scripting {
+            content = self
+                .scripts
+                .on_response(content)
+                .unwrap_or_else(|err| format!("{}\n\n*Script error: {err}*", reply.text));
+        }
+        let directory = config
+            .active_workspace()
+            .and_then(|workspace| workspace.directory());
+        content = linkify::linkify(&content, directory.as_deref());
+        if !pinned.is_empty() {
+            content = pinned::link_citations(&content, pinned);
+        }
+        for path in self.save_images(&reply.images) {
+            let Ok(url) = markdown::Url::from_file_path(&path) else {
+                continue;
+            };
+            content.push_str(&format!(
+                "\n\n[{}]({url})",
+                path.file_name().unwrap_or_default().to_string_lossy(),
+            ));
+        }
+        content
+    }
+
+    /// The conversation set aside that `request` is answering.
+    fn generating_aside(&mut self, request: u64) -> Option<&mut ParkedConversation> {
+        self.conversations
+            .list
+            .iter_mut()
+            .chain(self.workspace_conversations.values_mut())
+            .chain(self.cleared.iter_mut().map(|(_, parked)| parked))
+            .find(|parked| {
+                parked
+                    .generating
+                    .as_ref()
+                    .is_some_and(|generating| generating.request == request)
+            })
+    }
+
+    /// Adds the answer to `request` to the conversation set aside it belongs to.
+    fn receive_aside(&mut self, request: u64, message: gemini::Message) {
+        let Some(parked) = self.generating_aside(request) else {
+            return;
+        };
+        let Some(generating) = parked.generating.take() else {
+            return;
+        };
+        let history = Arc::clone(&parked.history);
+        let pinned = parked.pinned.files.clone();
+        self.background_answers.remove(&request);
+        let latency = generating
+            .started
+            .map(|started| started.elapsed())
+            .unwrap_or_default();
+        self.record_metrics(matches!(message, gemini::Message::Response(_)), latency);
+
+        let answer = match message {
+            gemini::Message::Response(reply) => {
+                let content = self.answer_content(&reply, &generating.config, &pinned);
+                let stats = answer_stats(&generating.config, &history, &reply, latency);
+                self.add_spending(stats.cost);
+                Ok(Chat {
+                    generated_by: Some(format!(
+                        "{} · {}",
+                        models::model_name(&generating.config),
+                        generating.config.preset.name()
+                    )),
+                    stats: Some(stats),
+                    ..Chat::model(content)
+                })
+            }
+            gemini::Message::EmptyResponse => Err("No response from model".to_string()),
+            message => Err(message.into_result().err().unwrap_or_default()),
+        };
+
+        // Found again, it was let go of to save images and record the cost.
+        let Some(parked) = self
+            .conversations
+            .list
+            .iter_mut()
+            .chain(self.workspace_conversations.values_mut())
+            .chain(self.cleared.iter_mut().map(|(_, parked)| parked))
+            .find(|parked| Arc::ptr_eq(&parked.history, &history))
+        else {
+            return;
+        };
+        drop(history);
+        let chats = Arc::make_mut(&mut parked.history);
+        match answer {
+            Ok(chat) => {
+                parked.cost += chat.stats.as_ref().map_or(0.0, |stats| stats.cost);
+                chats.push(chat);
+            }
+            Err(err) => push_error(chats, err),
+        }
+    }
+
+    /// Counts the finished request and writes the metrics file, if there is one.
+    fn record_metrics(&self, answered: bool, latency: Duration) {
+        metrics::record(
+            self.config.for_workspace().provider.id(),
+            !answered,
+            latency,
+        );
+        let file = self.config.metrics.file.trim();
+        if !file.is_empty()
+            && let Err(err) = metrics::write(std::path::Path::new(file))
+        {
+            eprintln!("failed to write metrics: {err}");
+        }
+    }
+
+    /// Submits the next prompt entered while the last answer was generating.
+    fn send_pending(&mut self) -> Task<cosmic::Action<Message>> {
+        // A warning waiting on the user holds back the queue until it is answered.
+        if self.is_loading || self.redaction_warning.is_some() || self.cost_warning.is_some() {
+            return Task::none();
+        }
+        match self.pending_prompts.pop_front() {
+            Some(text) => Task::done(cosmic::Action::App(Message::SubmitInput(text))),
+            None => Task::none(),
+        }
+    }
+
+    /// Keeps the current conversation with its workspace and shows the one of `name`.
+    fn switch_workspace(&mut self, name: String) {
+        if name == self.config.workspace {
+            return;
+        }
+        // A cleared conversation can't be brought back into another workspace.
+        self.forget_cleared();
+        let previous = self.park_conversation();
+        self.workspace_conversations.insert(
+            std::mem::replace(&mut self.config.workspace, name),
+            previous,
+        );
+        let next = self
+            .workspace_conversations
+            .remove(&self.config.workspace)
+            .unwrap_or_default();
+        self.show_conversation(next);
+        self.save_config();
+        self.save_session();
+    }
+
+    /// Clears the conversation, keeping it for a moment so it can be brought back.
+    fn clear_conversation(&mut self) -> Task<cosmic::Action<Message>> {
+        if self.chat_history.is_empty() {
+            return Task::none();
+        }
+        let tag = self.next_request;
+        self.next_request += 1;
+        self.cleared = Some((tag, self.park_conversation()));
+        self.save_config();
+        self.save_session();
+        cosmic::task::future(async move {
+            tokio::time::sleep(UNDO_CLEAR_TIMEOUT).await;
+            Message::ForgetCleared(tag)
+        })
+    }
+
+    /// Takes the current conversation out of view, leaving an empty one.
+    fn park_conversation(&mut self) -> ParkedConversation {
+        let generating = self.answer_aside();
+        let parked = ParkedConversation {
+            history: std::mem::take(&mut self.chat_history),
+            pinned: std::mem::take(&mut self.pinned),
+            lock: std::mem::take(&mut self.lock),
+            template: std::mem::take(&mut self.config.template),
+            tone: std::mem::take(&mut self.config.tone),
+            title: self.title.take(),
+            cost: std::mem::take(&mut self.conversation_cost),
+            generating,
+        };
+        self.show_conversation(ParkedConversation::default());
+        parked
+    }
+
+    fn show_conversation(&mut self, conversation: ParkedConversation) {
+        self.conversation_tag = self.next_request;
+        self.next_request += 1;
+        self.chat_history = conversation.history;
+        self.pinned = conversation.pinned;
+        self.lock = conversation.lock;
+        self.config.template = conversation.template;
+        self.config.tone = conversation.tone;
+        self.title = conversation.title;
+        self.conversation_cost = conversation.cost;
+        if let Some(generating) = conversation.generating {
+            self.is_loading = true;
+            self.in_flight = Some(generating.request);
+            self.answer_task = self.background_answers.remove(&generating.request);
+            self.request_started = generating.started;
+            self.partial_answer = generating.partial;
+        }
+        self.read_up_to = self.chat_history.len();
+        self.conversation_language = language::of_conversation(&self.chat_history);
+        self.follow_ups = follow_ups::State::default();
+        self.diff = None;
+        self.expanded.clear();
+        self.details.close();
+        self.share.clear_selection();
+    }
+
+    fn refresh_workspace_names(&mut self) {
+        self.workspace_names = std::iter::once("No workspace".to_string())
+            .chain(
+                self.config
+                    .workspaces
+                    .iter()
+                    .map(|workspace| workspace.name.clone()),
+            )
+            .collect();
+    }
+
+    fn open_popup(&mut self) -> Task<cosmic::Action<Message>> {
+        let Some(main_window) = self.core.main_window_id() else {
+            return Task::none();
+        };
+        let new_id = Id::unique();
+        self.popup.replace(new_id);
+        self.mark_read_if_visible();
+        self.welcome.next_tip();
+        // Anchored to the panel's edge of the screen, so the popup opens
+        // beside a vertical panel and above a bottom one.
+        let popup_settings =
+            self.core
+                .applet
+                .get_popup_settings(main_window, new_id, None, None, None);
+        // Input methods are only enabled for a focused text field, so
+        // focus the prompt right away for preedit to work from the first key.
+        Task::batch([
+            get_popup(popup_settings),
+            self.import_inbox(),
+            widget::text_input::focus(INPUT_ID.clone()),
+            widget::scrollable::snap_to(
+                CHAT_ID.clone(),
+                RelativeOffset {
+                    x: 0.0,
+                    y: if self.chat_at_bottom {
+                        1.0
+                    } else {
+                        self.scroll_offset
+                    },
+                },
+            ),
+        ])
+    }
+
+    /// Logical size of the output the panel is on, which the popup is sized after.
+    fn output_size(&self) -> (i32, i32) {
+        self.output_sizes
+            .get(&self.core.applet.output_name)
+            .or_else(|| {
+                // A panel spanning all outputs has no name of its own; one output is a safe bet.
+                (self.output_sizes.len() == 1)
+                    .then(|| self.output_sizes.values().next())
+                    .flatten()
+            })
+            .copied()
+            .unwrap_or(FALLBACK_OUTPUT_SIZE)
+    }
+
+    /// Where the session is kept, which is only in memory if conversations
+    /// aren't to outlive the applet.
+    fn open_history_store(&self) -> Box<dyn HistoryStore> {
+        match self.profile.data_dir() {
+            Some(dir) if !self.config.forget_conversations => {
+                Box::new(session::JsonStore::new(dir))
+            }
+            _ => Box::default(),
+        }
+    }
+
+    /// Restores the conversations and popup state of the last run, returning
+    /// whether the popup was open.
+    fn restore_session(&mut self) -> bool {
+        let mut session = self.history_store.load();
+
+        for (workspace, conversation) in session.conversations.drain() {
+            self.workspace_conversations
+                .insert(workspace, ParkedConversation::from_saved(conversation));
+        }
+        self.conversations.list = session
+            .set_aside
+            .into_iter()
+            .map(ParkedConversation::from_saved)
+            .collect();
+        if let Some(active) = self.workspace_conversations.remove(&self.config.workspace) {
+            self.chat_history = active.history;
+            self.pinned = active.pinned;
+            self.lock = active.lock;
+            self.config.template = active.template;
+            self.config.tone = active.tone;
+            self.title = active.title;
+            self.conversation_cost = active.cost;
+        } else {
+            self.config.template.clear();
+            self.config.tone = Tone::default();
+        }
+        self.read_up_to = self.chat_history.len();
+        self.conversation_language = language::of_conversation(&self.chat_history);
+        self.scroll_offset = session.scroll;
+        self.chat_at_bottom = session.scroll >= 0.99 || self.chat_history.is_empty();
+        session.popup_open
+    }
+
+    /// Replaces the conversations in memory with the saved ones, e.g. after
+    /// a backup was restored over them.
+    fn reload_session(&mut self) {
+        self.abandon_request();
+        for (_, handle) in self.background_answers.drain() {
+            handle.abort();
+        }
+        self.cleared = None;
+        self.workspace_conversations.clear();
+        self.show_conversation(ParkedConversation::default());
+        self.restore_session();
+    }
+
+    /// Saves the conversations and popup state for the next run.
+    fn save_session(&self) {
+        if self.settings.restoring || self.settings.sync_running {
+            return;
+        }
+        let mut conversations: HashMap<String, session::Conversation> = self
+            .workspace_conversations
+            .iter()
+            .map(|(workspace, parked)| (workspace.clone(), parked.saved()))
+            .collect();
+        conversations.insert(
+            self.config.workspace.clone(),
+            session::Conversation {
+                history: self.chat_history.to_vec(),
+                pinned_files: self.pinned.files.clone(),
+                template: self.config.template.clone(),
+                tone: self.config.tone,
+                title: self.title.clone(),
+                cost: self.conversation_cost,
+                locked: self.lock.locked,
+                original: self
+                    .lock
+                    .original
+                    .as_ref()
+                    .map(|original| Box::new(original.saved())),
+            },
+        );
+
+        let session = Session {
+            conversations,
+            set_aside: self
+                .conversations
+                .list
+                .iter()
+                .map(ParkedConversation::saved)
+                .collect(),
+            scroll: if self.chat_at_bottom {
+                1.0
+            } else {
+                self.scroll_offset
+            },
+            // The quick reply popover is only for the moment.
+            popup_open: self.popup.is_some() && !self.quick_reply.open,
+        };
+        if let Err(err) = self.history_store.save(&session) {
+            eprintln!("failed to save the session: {err}");
+        }
+    }
+
+    /// Sends the chat history to the model.
+    fn send_history(&mut self) -> Task<cosmic::Action<Message>> {
+        let request = self.next_request;
+        self.next_request += 1;
+        self.in_flight = Some(request);
+        self.request_started = Some(Instant::now());
+        let cloned = Arc::clone(&self.chat_history);
+        let config = self.config.for_workspace();
+        let pinned_files = self.pinned.files.clone();
+        let answer = cosmic::iced::stream::channel(16, move |mut output| async move {
+            let history = if pinned_files.is_empty() {
+                cloned
+            } else {
+                Arc::new(pinned::with_pinned_files(&cloned, &pinned_files, &config.redaction).await)
+            };
+            let message = models::stream_response(history, config, |text| {
+                // Each update holds all text so far, so one dropped while the
+                // app is busy is made up for by the next.
+                let _ = output.try_send(Message::PartialAnswer(request, text.to_string()));
+            })
+            .await;
+            let _ = output.send(Message::GeminiMessage(request, message)).await;
+        });
+        let (task, handle) = Task::run(answer, cosmic::Action::App).abortable();
+        self.answer_task = Some(handle);
+        task
+    }
+
+    /// Stops waiting for the current answer before the conversation is replaced,
+    /// so it can't show up in the next one.
+    pub(super) fn abandon_request(&mut self) {
+        if let Some(handle) = self.answer_task.take() {
+            handle.abort();
+        }
+        let stopped = self.in_flight.take().is_some();
+        let partial = self.partial_answer.take();
+        self.is_loading = false;
+        self.pending_prompts.clear();
+        // The answer being regenerated stays with its conversation. Otherwise the
+        // prompt is marked as stopped so it doesn't look like it is still waiting.
+        if let Some(previous) = self.regenerating.take() {
+            Arc::make_mut(&mut self.chat_history).push(previous);
+        } else if stopped {
+            let stopped = match partial {
+                Some(text) => format!("{text}\n\n*Stopped*"),
+                None => "*Stopped*".to_string(),
+            };
+            Arc::make_mut(&mut self.chat_history).push(Chat::model(stopped));
+        }
+    }
+
+    /// Lets the answer in flight finish in the background while its
+    /// conversation is set aside. A regeneration is stopped instead, since the
+    /// answer it replaces is put back.
+    fn answer_aside(&mut self) -> Option<Generating> {
+        let request = self.in_flight.filter(|_| self.regenerating.is_none());
+        let Some(request) = request else {
+            self.abandon_request();
+            return None;
+        };
+        self.in_flight = None;
+        self.is_loading = false;
+        self.pending_prompts.clear();
+        if let Some(handle) = self.answer_task.take() {
+            self.background_answers.insert(request, handle);
+        }
+        Some(Generating {
+            request,
+            partial: self.partial_answer.take(),
+            started: self.request_started.take(),
+            config: Box::new(self.config.for_workspace()),
+        })
+    }
+
+    /// Stops the answer generating for a conversation that is going away.
+    fn stop_answer_aside(&mut self, parked: &ParkedConversation) {
+        if let Some(generating) = &parked.generating
+            && let Some(handle) = self.background_answers.remove(&generating.request)
+        {
+            handle.abort();
+        }
+    }
+
+    /// Drops the conversation cleared moments ago for good.
+    fn forget_cleared(&mut self) {
+        if let Some((_, cleared)) = self.cleared.take() {
+            self.stop_answer_aside(&cleared);
+        }
+    }
+
+    /// Opens `path` at `line` with the configured editor, or with its default
+    /// application if there is none.
+    fn open_at_line(&self, path: &std::path::Path, line: u32) {
+        let mut words = self.config.editor_command.split_whitespace();
+        let Some(program) = words.next() else {
+            open_uri(&path.to_string_lossy());
+            return;
+        };
+        let file = path.to_string_lossy();
+        let line = line.to_string();
+        let args = words.map(|word| word.replace("{file}", &file).replace("{line}", &line));
+        if let Err(err) = self.workspace_command(program).args(args).spawn() {
+            eprintln!("failed to run {program}: {err}");
+        }
+    }
+
+    /// A command for `program` that runs in the active workspace's folder with
+    /// its environment variables.
+    fn workspace_command(&self, program: &str) -> std::process::Command {
+        let mut command = std::process::Command::new(program);
+        if let Some(workspace) = self.config.active_workspace() {
+            if let Some(directory) = workspace.directory().filter(|directory| directory.is_dir()) {
+                command.current_dir(directory);
+            }
+            command.envs(workspace.environment());
+        }
+        command
+    }
+
+    /// Writes generated images to the profile's data directory, returning their paths.
+    fn save_images(&self, images: &[gemini::GeneratedImage]) -> Vec<std::path::PathBuf> {
+        let Some(dir) = self.profile.data_dir().map(|dir| dir.join("images")) else {
+            return Vec::new();
+        };
+        if let Err(err) = std::fs::create_dir_all(&dir) {
+            eprintln!("failed to create {}: {err}", dir.display());
+            return Vec::new();
+        }
+
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        images
+            .iter()
+            .enumerate()
+            .filter_map(|(index, image)| {
+                let extension = image.mime_type.strip_prefix("image/").unwrap_or("bin");
+                let path = dir.join(format!("{stamp}-{index}.{extension}"));
+                match std::fs::write(&path, &image.data) {
+                    Ok(()) => Some(path),
+                    Err(err) => {
+                        eprintln!("failed to save {}: {err}", path.display());
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Answers that arrived since the user last saw the end of the chat.
+    fn unread_count(&self) -> usize {
+        self.chat_history
+            .get(self.read_up_to..)
+            .unwrap_or_default()
+            .iter()
+            .filter(|chat| chat.role == "model")
+            .count()
+    }
+
+    /// Marks the history as read when the end of the chat is on screen.
+    fn mark_read_if_visible(&mut self) {
+        if self.popup.is_some() && self.page == Page::Chat && self.chat_at_bottom {
+            self.read_up_to = self.chat_history.len();
+        }
+    }
+
+    /// Estimated share of the model's context window used by the conversation.
+    fn token_budget_view(&self) -> Option<cosmic::Element<'_, Message>> {
+        // Past these shares of the context window, the oldest messages would have to go.
+        const WARNING: f32 = 0.75;
+        const CRITICAL: f32 = 0.9;
+
+        if self.chat_history.is_empty() {
+            return None;
+        }
+        let used: u32 = self
+            .chat_history
+            .iter()
+            .map(|chat| models::estimate_tokens(&chat.content))
+            .sum::<u32>()
+            + models::estimate_tokens(&self.input_text);
+        let limit = models::context_limit(&self.config.for_workspace());
+        let share = used as f32 / limit as f32;
+
+        let mut caption = widget::text::caption(format!(
+            "{} / {} tokens",
+            format_tokens(used),
+            format_tokens(limit)
+        ));
+        if share >= CRITICAL {
+            caption = caption.class(cosmic::theme::Text::Color(iced::Color::from_rgb(
+                0.9, 0.2, 0.2,
+            )));
+        } else if share >= WARNING {
+            caption = caption.class(cosmic::theme::Text::Color(iced::Color::from_rgb(
+                0.95, 0.65, 0.1,
+            )));
+        }
+
+        Some(
+            widget::row::with_children(vec![
+                widget::progress_bar(0.0..=1.0, share.min(1.0))
+                    .width(cosmic::iced::Length::Fixed(80.0))
+                    .height(cosmic::iced::Length::Fixed(6.0))
+                    .into(),
+                caption.into(),
+            ])
+            .spacing(6)
+            .align_y(iced::Alignment::Center)
+            .into(),
+        )
+    }
+
+    /// Sends a prompt, unless it would go over a spending cap.
+    fn submit(&mut self, text: String) -> Task<cosmic::Action<Message>> {
+        if self.is_loading {
+            return Task::none();
+        }
+        if let Some(reason) = self.cost_cap_exceeded(&text) {
+            self.cost_warning = Some((OverCap::Prompt(text), reason));
+            return Task::none();
+        }
+        self.send_prompt(text)
+    }
+
+    /// Asks for another answer in place of the last one.
+    fn regenerate(&mut self) -> Task<cosmic::Action<Message>> {
+        if self.is_loading || self.lock.locked {
+            return Task::none();
+        }
+        let history = Arc::make_mut(&mut self.chat_history);
+        if history.last().is_none_or(|chat| chat.role != "model") {
+            return Task::none();
+        }
+        self.regenerating = history.pop();
+        self.diff = None;
+        self.is_loading = true;
+        self.send_history()
+    }
+
+    /// Adds a prompt to the history and sends it.
+    fn send_prompt(&mut self, text: String) -> Task<cosmic::Action<Message>> {
+        if self.is_loading || self.lock.locked {
+            return Task::none();
+        }
+        let history = Arc::make_mut(&mut self.chat_history);
+        self.is_loading = true;
+        self.api_key_help = None;
+        // Undoing the clear would throw away the new conversation.
+        self.forget_cleared();
+        self.pinned.prompt_typed();
+        history.push(Chat {
+            attachments: std::mem::take(&mut self.attachments.queue),
+            ..Chat::user(emoji::replace_shortcodes(&text))
+        });
+        self.conversation_language = language::of_conversation(history);
+        self.input_text.clear();
+        self.clear_spelling();
+        self.refine = refine::State::default();
+        self.send_history()
+    }
+
+    /// Why sending `text` would go over a spending cap, if it would.
+    fn cost_cap_exceeded(&self, text: &str) -> Option<String> {
+        let cap = self.config.cost_cap;
+        if cap.per_conversation <= 0.0 && cap.per_day <= 0.0 && cap.per_prompt <= 0.0 {
+            return None;
+        }
+        self.over_cost_cap(self.estimate_prompt_cost(text), true)
+    }
+
+    /// Why a request costing about `cost` would go over a spending cap, if it
+    /// would. The conversation's cap only counts for requests `in_conversation`.
+    fn over_cost_cap(&self, cost: f64, in_conversation: bool) -> Option<String> {
+        let cap = self.config.cost_cap;
+        if cost == 0.0 {
+            return None;
+        }
+
+        if cap.per_prompt > 0.0 && cost > cap.per_prompt {
+            return Some(format!(
+                "This prompt would cost about ${cost:.2}, over the cap of ${:.2} per prompt.",
+                cap.per_prompt
+            ));
+        }
+        if in_conversation
+            && cap.per_conversation > 0.0
+            && self.conversation_cost + cost > cap.per_conversation
+        {
+            return Some(format!(
+                "This prompt would bring the conversation to about ${:.2}, over its cap of ${:.2}.",
+                self.conversation_cost + cost,
+                cap.per_conversation
+            ));
+        }
+        if cap.per_day > 0.0 {
+            let today = self.spending.today();
+            if today + cost > cap.per_day {
+                return Some(format!(
+                    "This prompt would bring today's spending to about ${:.2}, over the cap of \
+                     ${:.2}.",
+                    today + cost,
+                    cap.per_day
+                ));
+            }
+        }
+        None
+    }
+
+    /// Estimated price in US dollars of sending `text` after the conversation.
+    /// The answer is assumed to be as long as the last one.
+    fn estimate_prompt_cost(&self, text: &str) -> f64 {
+        // Answers are assumed this long until the conversation has one.
+        const FIRST_ANSWER_TOKENS: u32 = 500;
+
+        let input = self
+            .chat_history
+            .iter()
+            .map(|chat| models::estimate_tokens(&chat.content))
+            .sum::<u32>()
+            + models::estimate_tokens(text);
+        let output = self
+            .chat_history
+            .iter()
+            .rev()
+            .find(|chat| chat.role == "model")
+            .map_or(FIRST_ANSWER_TOKENS, |chat| {
+                models::estimate_tokens(&chat.content)
+            });
+        models::estimate_cost(&self.config.for_workspace(), input, output)
+    }
+
+    /// Adds the cost of `reply` to the spending, counting its tokens from the
+    /// conversation when the provider didn't report them.
+    fn record_cost(&mut self, reply: &gemini::Reply, latency: Duration) -> AnswerStats {
+        let stats = answer_stats(
+            &self.config.for_workspace(),
+            &self.chat_history,
+            reply,
+            latency,
+        );
+        self.conversation_cost += stats.cost;
+        self.add_spending(stats.cost);
+        stats
+    }
+
+    /// Adds the estimated price of a billed request to today's spending.
+    fn add_spending(&mut self, cost: f64) {
+        if cost == 0.0 {
+            return;
+        }
+        let dir = self.profile.data_dir();
+        if let Err(err) = self.spending.record(dir.as_deref(), cost) {
+            eprintln!("failed to record spending: {err}");
+        }
+    }
+
+    /// Prompts waiting for the current answer, each with a button to drop it.
+    fn pending_view(&self) -> cosmic::Element<'_, Message> {
+        let mut column = widget::column::with_capacity(self.pending_prompts.len()).spacing(4);
+        for (index, text) in self.pending_prompts.iter().enumerate() {
+            column = column.push(
+                widget::row::with_children(vec![
+                    widget::text::caption(format!("Pending: {text}"))
+                        .width(cosmic::iced::Length::Fill)
+                        .into(),
+                    widget::button::icon(widget::icon::from_name("window-close-symbolic"))
+                        .on_press(Message::RemovePending(index))
+                        .tooltip("Don't send")
+                        .into(),
+                ])
+                .align_y(iced::Alignment::Center),
+            );
+        }
+        column.into()
+    }
+
+    fn cost_warning_view(&self) -> cosmic::Element<'_, Message> {
+        let Some((_, reason)) = &self.cost_warning else {
+            return widget::Space::new(0, 0).into();
+        };
+
+        widget::container(
+            column!(
+                widget::text::heading("Spending cap reached"),
+                widget::text::body(reason.as_str()),
+                widget::row::with_children(vec![
+                    widget::button::destructive("Send anyway")
+                        .on_press(Message::SendOverCap)
+                        .into(),
+                    widget::horizontal_space().into(),
+                    widget::button::text("Edit")
+                        .on_press(Message::DismissCostWarning)
+                        .into(),
+                ])
+                .spacing(8),
+            )
+            .spacing(8),
+        )
+        .class(cosmic::theme::Container::Card)
+        .padding(12)
+        .width(cosmic::iced::Length::Fill)
+        .into()
+    }
+
+    fn redaction_warning_view(&self) -> cosmic::Element<'_, Message> {
+        let Some(warning) = &self.redaction_warning else {
+            return widget::Space::new(0, 0).into();
+        };
+
+        let mut kinds: Vec<&str> = Vec::new();
+        let patterns = warning
+            .findings
+            .iter()
+            .map(|finding| finding.pattern)
+            .chain(warning.in_attachments.iter().copied());
+        for pattern in patterns {
+            if !kinds.contains(&pattern.name()) {
+                kinds.push(pattern.name());
+            }
+        }
+
+        widget::container(
+            column!(
+                widget::text::heading("This prompt may contain sensitive data"),
+                widget::text::body(format!("Found: {}.", kinds.join(", ").to_lowercase())),
+                widget::row::with_children(vec![
+                    widget::button::suggested("Mask and send")
+                        .on_press(Message::MaskAndSend)
+                        .into(),
+                    widget::button::destructive("Send anyway")
+                        .on_press(Message::SendAnyway)
+                        .into(),
+                    widget::horizontal_space().into(),
+                    widget::button::text("Edit")
+                        .on_press(Message::DismissRedactionWarning)
+                        .into(),
+                ])
+                .spacing(8),
+            )
+            .spacing(8),
+        )
+        .class(cosmic::theme::Container::Card)
+        .padding(12)
+        .width(cosmic::iced::Length::Fill)
+        .into()
+    }
+
+    /// Estimated cost of sending what is typed, next to the prompt field.
+    fn cost_preview_view(&self) -> cosmic::Element<'_, Message> {
+        if self.input_text.trim().is_empty() {
+            return widget::Space::new(0, 0).into();
+        }
+        let cost = self.estimate_prompt_cost(&self.input_text);
+        if cost == 0.0 {
+            return widget::Space::new(0, 0).into();
+        }
+        let text = if cost < 0.01 {
+            "< $0.01".to_string()
+        } else {
+            format!("≈ ${cost:.2}")
+        };
+        widget::text::caption(text).into()
+    }
+
+    fn script_error_view(&self) -> cosmic::Element<'_, Message> {
+        let Some((_, error)) = &self.script_error else {
+            return widget::Space::new(0, 0).into();
+        };
+
+        widget::container(
+            column!(
+                widget::text::heading("A script failed on this prompt"),
+                widget::text::body(error.as_str()),
+                widget::row::with_children(vec![
+                    widget::button::standard("Retry")
+                        .on_press(Message::RetryScript)
+                        .into(),
+                    widget::horizontal_space().into(),
+                    widget::button::text("Edit prompt")
+                        .on_press(Message::EditFailedPrompt)
+                        .into(),
+                ])
+                .spacing(8),
+            )
+            .spacing(8),
+        )
+        .class(cosmic::theme::Container::Card)
+        .padding(12)
+        .width(cosmic::iced::Length::Fill)
+        .into()
+    }
+
+    fn version_change_view(&self) -> cosmic::Element<'_, Message> {
+        let Some(change) = &self.version_change else {
+            return widget::Space::new(0, 0).into();
+        };
+
+        widget::container(
+            column!(
+                widget::text::heading("Model version changed"),
+                widget::text::body(format!(
+                    "{} is now served by {}, earlier answers came from {}.",
+                    change.model, change.new, change.old
+                )),
+                widget::row::with_children(vec![
+                    widget::horizontal_space().into(),
+                    widget::button::text("Dismiss")
+                        .on_press(Message::DismissVersionChange)
+                        .into(),
+                ]),
+            )
+            .spacing(8),
+        )
+        .class(cosmic::theme::Container::Card)
+        .padding(12)
+        .width(cosmic::iced::Length::Fill)
+        .into()
+    }
+
+    /// Remembers the version serving the configured model, raising an alert if
+    /// it differs from the one seen before.
+    fn record_model_version(&mut self, version: &str) {
+        let config = self.config.for_workspace();
+        let model = models::model_name(&config);
+        let key = format!("{}/{model}", config.provider.id());
+        let previous = self.config.model_versions.insert(key, version.to_string());
+        if previous.as_deref() == Some(version) {
+            return;
+        }
+        if let Some(old) = previous
+            && self.config.version_alerts
+        {
+            self.version_change = Some(VersionChange {
+                model,
+                old,
+                new: version.to_string(),
+            });
+        }
+        self.save_config();
+    }
+
+    fn undo_clear_view(&self) -> cosmic::Element<'_, Message> {
+        if self.cleared.is_none() {
+            return widget::Space::new(0, 0).into();
+        }
+
+        widget::container(
+            widget::row::with_children(vec![
+                widget::text::body("Conversation cleared")
+                    .width(cosmic::iced::Length::Fill)
+                    .into(),
+                widget::button::text("Undo")
+                    .on_press(Message::UndoClear)
+                    .into(),
+            ])
+            .align_y(iced::Alignment::Center),
+        )
+        .class(cosmic::theme::Container::Card)
+        .padding(12)
+        .width(cosmic::iced::Length::Fill)
+        .into()
+    }
+
+    fn api_key_help_view(&self) -> cosmic::Element<'_, Message> {
+        let Some(help) = self.api_key_help else {
+            return widget::Space::new(0, 0).into();
+        };
+
+        let env_file = credentials::env_file_path()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|| "~/.config/cosmic-ext-clankers/env".into());
+        let explanation = if help.in_login_shell {
+            format!(
+                "{} is set in your shell, but panel applets don't inherit shell variables. \
+                 Add it to {env_file} instead.",
+                help.var()
+            )
+        } else {
+            format!(
+                "No API key was found. Enter one on the settings page, or set {} in your \
+                 session environment or in {env_file}.",
+                help.var()
+            )
+        };
+
+        widget::container(
+            column!(
+                widget::text::heading("API key not set"),
+                widget::text::body(explanation),
+                widget::row::with_children(vec![
+                    widget::button::suggested("Enter a key")
+                        .on_press(Message::TogglePage(Page::Settings))
+                        .into(),
+                    widget::button::standard("Open env file")
+                        .on_press(Message::OpenEnvFile)
+                        .into(),
+                    widget::button::standard("Get a key")
+                        .on_press(Message::OpenApiKeyPage)
+                        .into(),
+                    widget::horizontal_space().into(),
+                    widget::button::text("Dismiss")
+                        .on_press(Message::DismissApiKeyHelp)
+                        .into(),
+                ])
+                .spacing(8),
+            )
+            .spacing(8),
+        )
+        .class(cosmic::theme::Container::Card)
+        .padding(12)
+        .width(cosmic::iced::Length::Fill)
+        .into()
+    }
+
+    /// The prompt field with the buttons and choices beside it.
+    fn prompt_row(&self) -> cosmic::Element<'_, Message> {
+        widget::row::with_children(vec![
+            widget::text_input("Enter text", &self.input_text)
+                .id(INPUT_ID.clone())
+                .on_input(Message::InputChanged)
+                .width(cosmic::iced::Length::Fill)
+                .padding(10)
+                .on_submit(Message::SubmitInput)
+                .into(),
+            self.cost_preview_view(),
+            widget::button::icon(widget::icon::from_name("face-smile-symbolic"))
+                .on_press(Message::EmojiPicker(emoji_picker::Message::Toggle))
+                .tooltip("Emoji")
+                .into(),
+            widget::button::icon(widget::icon::from_name("document-edit-symbolic"))
+                .on_press_maybe(
+                    (!self.input_text.trim().is_empty())
+                        .then_some(Message::Refine(refine::Message::Refine)),
+                )
+                .tooltip("Refine prompt")
+                .into(),
+            self.attach_buttons().map(Message::Attachments),
+            self.pin_button().map(Message::Pinned),
+            widget::dropdown(
+                &GenerationPreset::NAMES,
+                Some(self.config.for_workspace().preset.index()),
+                Message::PresetSelected,
+            )
+            .into(),
+        ])
+        .spacing(8)
+        .align_y(iced::Alignment::Center)
+        .into()
+    }
+
+    fn chat_view(&self) -> cosmic::Element<'_, Message> {
+        if self.chat_history.is_empty() {
+            self.welcome_view().map(Message::Welcome)
+        } else {
+            let mut chats: Vec<cosmic::Element<_>> = Vec::with_capacity(self.chat_history.len());
+
+            for (index, chat) in self.chat_history.iter().enumerate() {
+                let is_last = index + 1 == self.chat_history.len();
+                if chat.role == "user" {
+                    if !chat.attachments.is_empty() {
+                        chats.push(attachments_bubble(&chat.attachments));
+                    }
+                    if !chat.content.is_empty() {
+                        chats.push(bubble(&chat.content, true));
+                    }
+                    continue;
+                }
+
+                match &chat.previous {
+                    Some(previous) if chat.awaiting_choice => {
+                        chats.push(bubble(previous, false));
+                        chats.push(
+                            widget::button::standard("Keep this answer")
+                                .on_press(Message::PreferAnswer { regenerated: false })
+                                .into(),
+                        );
+                        chats.push(bubble(&chat.content, false));
+                        chats.push(
+                            widget::button::standard("Keep this answer")
+                                .on_press(Message::PreferAnswer { regenerated: true })
+                                .into(),
+                        );
+                    }
+                    _ => match &self.diff {
+                        Some((shown, Some(changes))) if *shown == index => {
+                            chats.push(diff_bubble(changes));
+                        }
+                        _ if chat.error => chats.push(error_bubble(&chat.content, chat.repeated)),
+                        _ => {
+                            // The latest answer is always shown in full.
+                            let shown = match collapsed(&chat.content) {
+                                Some(start) if !is_last && !self.expanded.contains(&index) => start,
+                                _ => chat.content.as_str(),
+                            };
+                            let bubble = self
+                                .reading_bubble(index)
+                                .unwrap_or_else(|| bubble(shown, false));
+                            chats.push(match &chat.stats {
+                                Some(stats) => widget::tooltip(
+                                    bubble,
+                                    stats_view(stats, chat.generated_by.as_deref()),
+                                    widget::tooltip::Position::Top,
+                                )
+                                .into(),
+                                None => bubble,
+                            });
+                            if let Some(caption) = chat.stats.as_ref().and_then(|stats| {
+                                models::finish_caption(
+                                    stats.finish_reason.as_deref()?,
+                                    stats.finish_message.as_deref(),
+                                )
+                            }) {
+                                chats.push(widget::text::caption(caption).into());
+                            }
+                        }
+                    },
+                }
+
+                let mut actions: Vec<cosmic::Element<_>> = vec![
+                    widget::button::icon(widget::icon::from_name("audio-speakers-symbolic"))
+                        .tooltip("Read aloud")
+                        .on_press(Message::ReadAloud(read_aloud::Message::Enqueue(index)))
+                        .selected(self.is_queued_for_reading(index))
+                        .into(),
+                ];
+                if chat.stats.is_some() {
+                    actions.push(
+                        widget::button::icon(widget::icon::from_name(
+                            "dialog-information-symbolic",
+                        ))
+                        .tooltip("Details")
+                        .on_press(Message::Details(details::Message::Toggle(index)))
+                        .selected(self.details_open(index))
+                        .into(),
+                    );
+                }
+                if !self.config.is_locked() {
+                    actions.push(
+                        widget::button::icon(widget::icon::from_name("object-select-symbolic"))
+                            .tooltip("Select for sharing")
+                            .on_press(Message::Share(share::Message::ToggleSelected(index)))
+                            .selected(self.share.is_selected(index))
+                            .into(),
+                    );
+                }
+                if !is_last && collapsed(&chat.content).is_some() {
+                    let expanded = self.expanded.contains(&index);
+                    actions.push(
+                        widget::button::text(if expanded { "Show less" } else { "Show more" })
+                            .on_press(Message::ToggleExpanded(index))
+                            .into(),
+                    );
+                }
+                if chat.previous.is_some() && !chat.awaiting_choice {
+                    actions.push(
+                        widget::button::icon(widget::icon::from_name("document-compare-symbolic"))
+                            .tooltip("Show changes")
+                            .on_press(Message::ToggleDiff(index))
+                            .selected(self.diff.as_ref().is_some_and(|(shown, _)| *shown == index))
+                            .into(),
+                    );
+                }
+                if is_last && !self.is_loading && !self.lock.locked {
+                    actions.push(
+                        widget::button::icon(widget::icon::from_name("view-refresh-symbolic"))
+                            .tooltip("Regenerate")
+                            .on_press(Message::Regenerate)
+                            .into(),
+                    );
+                }
+                chats.push(widget::row::with_children(actions).spacing(4).into());
+                if is_last && let Some(follow_ups) = self.follow_ups_view() {
+                    chats.push(follow_ups.map(Message::FollowUps));
+                }
+            }
+
+            if self.is_loading {
+                if let Some(partial) = &self.partial_answer {
+                    chats.push(bubble(&partial_markdown::close(partial), false));
+                }
+                chats.push(
+                    widget::row::with_children(vec![
+                        widget::container(widget::text::caption("typing…"))
+                            .class(cosmic::theme::Container::List)
+                            .padding(10)
+                            .into(),
+                        widget::button::text("Stop")
+                            .on_press(Message::StopAnswer)
+                            .into(),
+                    ])
+                    .spacing(8)
+                    .align_y(iced::Alignment::Center)
+                    .into(),
+                );
             }
 
             widget::container(
                 widget::scrollable(widget::Column::with_children(chats).spacing(20))
+                    .id(CHAT_ID.clone())
+                    .on_scroll(Message::ChatScrolled)
                     .spacing(2)
                     .scroller_width(0)
                     .scrollbar_width(0),
@@ -351,3 +2715,207 @@ impl AppModel {
         }
     }
 }
+
+/// Renders a chat message as a markdown bubble.
+fn bubble<'a>(content: &str, from_user: bool) -> cosmic::Element<'a, Message> {
+    let markdown: Vec<markdown::Item> =
+        markdown::parse(&emoji::replace_shortcodes(content)).collect();
+    let content = cosmic_select::markdown::view(
+        &markdown,
+        markdown::Settings::with_text_size(15),
+        markdown::Style::from_palette(iced::Theme::TokyoNight.palette()),
+    )
+    .map(Message::UrlClicked);
+    let bubble = widget::container(
+        widget::container(content)
+            .class(cosmic::theme::Container::List)
+            .padding(10),
+    );
+
+    if from_user {
+        bubble.align_right(iced::Length::Fill).into()
+    } else {
+        bubble.align_left(iced::Length::Fill).into()
+    }
+}
+
+/// Usage and estimated price of the request for `history` that got `reply`.
+fn answer_stats(
+    config: &Config,
+    history: &[Chat],
+    reply: &gemini::Reply,
+    latency: Duration,
+) -> AnswerStats {
+    let (prompt_tokens, completion_tokens) = models::reply_tokens(history, reply);
+    AnswerStats {
+        prompt_tokens,
+        cached_tokens: reply.usage.map_or(0, |usage| usage.cached_tokens),
+        completion_tokens,
+        estimated: reply.usage.is_none(),
+        cost: models::estimate_cost(config, prompt_tokens, completion_tokens),
+        latency,
+        finish_reason: reply.finish_reason.clone(),
+        finish_message: reply.finish_message.clone(),
+        model_version: reply.model_version.clone(),
+        safety_ratings: reply.safety_ratings.clone(),
+        sources: reply.sources.clone(),
+        search_queries: reply.search_queries.clone(),
+        parts: reply.parts.clone(),
+    }
+}
+
+/// Adds a failure to the conversation, or counts it on the last one if it is
+/// the same failure again, so retries against a dead endpoint don't flood it.
+fn push_error(history: &mut Vec<Chat>, error: String) {
+    let is_same = |chat: &Chat| chat.error && chat.content == error;
+    // A failed regeneration puts the failure it was retrying back first.
+    if let Some(last) = history.last_mut().filter(|chat| is_same(chat)) {
+        last.repeated += 1;
+        return;
+    }
+    // The same prompt sent again right after it failed.
+    if let [.., prompt, failure, retry] = history.as_slice()
+        && is_same(failure)
+        && retry.role == "user"
+        && retry.content == prompt.content
+    {
+        history.pop();
+        if let Some(failure) = history.last_mut() {
+            failure.repeated += 1;
+        }
+        return;
+    }
+    history.push(Chat::error(error));
+}
+
+/// The start of `content` if it is too long to show in full.
+fn collapsed(content: &str) -> Option<&str> {
+    content
+        .match_indices('\n')
+        .nth(COLLAPSE_AFTER_LINES - 1)
+        .map(|(end, _)| end)
+        .filter(|end| !content[*end..].trim().is_empty())
+        .map(|end| &content[..end])
+}
+
+/// A failure reported in place of an answer, with how often it repeated.
+fn error_bubble(error: &str, repeated: u32) -> cosmic::Element<'_, Message> {
+    let mut row = vec![
+        widget::icon::from_name("dialog-warning-symbolic")
+            .size(16)
+            .into(),
+        widget::text::body(error).width(iced::Length::Fill).into(),
+    ];
+    if repeated > 0 {
+        row.push(widget::text::caption(format!("×{}", repeated + 1)).into());
+    }
+
+    widget::container(
+        widget::row::with_children(row)
+            .spacing(8)
+            .align_y(iced::Alignment::Center),
+    )
+    .class(cosmic::theme::Container::Card)
+    .padding(10)
+    .width(iced::Length::Fill)
+    .into()
+}
+
+/// Token counts, cost, model, latency and finish reason of an answer.
+fn stats_view<'a>(stats: &AnswerStats, generated_by: Option<&str>) -> cosmic::Element<'a, Message> {
+    let about = if stats.estimated { "about " } else { "" };
+    let mut lines = vec![format!(
+        "{about}{} prompt + {} answer tokens",
+        stats.prompt_tokens, stats.completion_tokens
+    )];
+    if stats.cached_tokens > 0 {
+        lines.push(format!("{} prompt tokens cached", stats.cached_tokens));
+    }
+    if stats.cost > 0.0 {
+        lines.push(format!("about ${:.4}", stats.cost));
+    }
+    if let Some(generated_by) = generated_by {
+        lines.push(generated_by.to_string());
+    }
+    if let Some(version) = &stats.model_version {
+        lines.push(format!("served by {version}"));
+    }
+    let mut timing = format!("{:.1} s", stats.latency.as_secs_f32());
+    if let Some(reason) = &stats.finish_reason {
+        timing.push_str(&format!(" · finished: {reason}"));
+    }
+    lines.push(timing);
+
+    widget::container(widget::text::caption(lines.join("\n")))
+        .class(cosmic::theme::Container::Card)
+        .padding(8)
+        .into()
+}
+
+/// Thumbnails of the images sent with a prompt, which open in the viewer,
+/// and the names of other files.
+fn attachments_bubble(attachments: &[Attachment]) -> cosmic::Element<'_, Message> {
+    let items: Vec<cosmic::Element<'_, Message>> = attachments
+        .iter()
+        .map(|attachment| {
+            if let Some(preview) = attachment.preview() {
+                widget::button::custom(
+                    widget::image(preview.clone())
+                        .width(iced::Length::Fixed(96.0))
+                        .height(iced::Length::Fixed(96.0)),
+                )
+                .on_press(Message::Viewer(viewer::Message::Open(attachment.clone())))
+                .padding(0)
+                .into()
+            } else {
+                widget::text::caption(attachment.name.as_str()).into()
+            }
+        })
+        .collect();
+
+    widget::container(widget::flex_row(items).spacing(6))
+        .align_right(iced::Length::Fill)
+        .into()
+}
+
+/// Renders the changes between a regenerated answer and the one it replaced.
+fn diff_bubble(changes: &[diff::Change]) -> cosmic::Element<'_, Message> {
+    let spans: Vec<iced::widget::text::Span<'_, Message>> = changes
+        .iter()
+        .map(|change| match change {
+            diff::Change::Same(text) => iced::widget::span(text.as_str()),
+            diff::Change::Removed(text) => iced::widget::span(text.as_str())
+                .color(iced::Color::from_rgb(0.9, 0.3, 0.3))
+                .strikethrough(true),
+            diff::Change::Added(text) => {
+                iced::widget::span(text.as_str()).color(iced::Color::from_rgb(0.3, 0.8, 0.4))
+            }
+        })
+        .collect();
+
+    widget::container(
+        widget::container(iced::widget::rich_text(spans))
+            .class(cosmic::theme::Container::List)
+            .padding(10),
+    )
+    .align_left(iced::Length::Fill)
+    .into()
+}
+
+/// Short token count, e.g. `12k` or `1M`.
+fn format_tokens(tokens: u32) -> String {
+    if tokens >= 1_000_000 {
+        format!("{:.1}M", tokens as f32 / 1_048_576.0).replace(".0M", "M")
+    } else if tokens >= 1000 {
+        format!("{}k", tokens / 1000)
+    } else {
+        tokens.to_string()
+    }
+}
+
+/// Opens a URL or file with the desktop's default handler.
+fn open_uri(uri: &str) {
+    if let Err(err) = std::process::Command::new("xdg-open").arg(uri).spawn() {
+        eprintln!("failed to open {uri}: {err}");
+    }
+}
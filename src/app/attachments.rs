@@ -0,0 +1,226 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Attachments queued for the next prompt, shown as chips above the input.
+
+use super::AppModel;
+use crate::attachment::Attachment;
+use crate::config::RedactionAction;
+use crate::redact::{self, Pattern};
+use cosmic::dialog::file_chooser;
+use cosmic::iced::{Alignment, Length};
+use cosmic::prelude::*;
+use cosmic::widget;
+
+const THUMBNAIL_SIZE: f32 = 40.0;
+
+#[derive(Default)]
+pub struct State {
+    /// Attachments in the order they will be sent.
+    pub(super) queue: Vec<Attachment>,
+    /// Error from the last file that couldn't be attached.
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Pick,
+    Picked(Result<Vec<Attachment>, String>),
+    PasteText,
+    Pasted(Option<String>),
+    Remove(usize),
+    /// Moves an attachment one place earlier.
+    MoveBack(usize),
+    DismissError,
+}
+
+impl AppModel {
+    /// Queued attachments with buttons to reorder and remove them.
+    pub(super) fn attachments_view(&self) -> Option<cosmic::Element<'_, Message>> {
+        let state = &self.attachments;
+        if state.queue.is_empty() && state.error.is_none() {
+            return None;
+        }
+
+        let mut chips: Vec<cosmic::Element<'_, Message>> = Vec::new();
+        for (index, attachment) in state.queue.iter().enumerate() {
+            let thumbnail: cosmic::Element<'_, Message> =
+                if let Some(preview) = attachment.preview() {
+                    widget::image(preview.clone())
+                        .width(Length::Fixed(THUMBNAIL_SIZE))
+                        .height(Length::Fixed(THUMBNAIL_SIZE))
+                        .into()
+                } else {
+                    widget::icon::from_name("text-x-generic-symbolic")
+                        .size(THUMBNAIL_SIZE as u16)
+                        .into()
+                };
+
+            let mut chip = vec![
+                thumbnail,
+                widget::text::caption(attachment.name.as_str()).into(),
+            ];
+            if index > 0 {
+                chip.push(
+                    widget::button::icon(widget::icon::from_name("go-previous-symbolic"))
+                        .on_press(Message::MoveBack(index))
+                        .tooltip("Move earlier")
+                        .into(),
+                );
+            }
+            chip.push(
+                widget::button::icon(widget::icon::from_name("window-close-symbolic"))
+                    .on_press(Message::Remove(index))
+                    .tooltip("Remove")
+                    .into(),
+            );
+            chips.push(
+                widget::container(
+                    widget::row::with_children(chip)
+                        .spacing(4)
+                        .align_y(Alignment::Center),
+                )
+                .class(cosmic::theme::Container::Card)
+                .padding(4)
+                .into(),
+            );
+        }
+
+        let mut column = widget::column::with_children(vec![
+            widget::scrollable::horizontal(widget::row::with_children(chips).spacing(6)).into(),
+        ])
+        .spacing(4);
+        if let Some(error) = &state.error {
+            column = column.push(
+                widget::row::with_children(vec![
+                    widget::text::caption(error.as_str()).into(),
+                    widget::button::text("Dismiss")
+                        .on_press(Message::DismissError)
+                        .into(),
+                ])
+                .spacing(8)
+                .align_y(Alignment::Center),
+            );
+        }
+
+        Some(column.into())
+    }
+
+    /// Buttons that add attachments, shown next to the input.
+    pub(super) fn attach_buttons(&self) -> cosmic::Element<'_, Message> {
+        widget::row::with_children(vec![
+            widget::button::icon(widget::icon::from_name("mail-attachment-symbolic"))
+                .on_press(Message::Pick)
+                .tooltip("Attach files")
+                .into(),
+            widget::button::icon(widget::icon::from_name("edit-paste-symbolic"))
+                .on_press(Message::PasteText)
+                .tooltip("Attach clipboard text")
+                .into(),
+        ])
+        .spacing(8)
+        .into()
+    }
+
+    /// Kinds of sensitive text in the queued text attachments that the
+    /// settings ask to be warned about.
+    pub(super) fn attachment_warnings(&self) -> Vec<Pattern> {
+        let mut kinds = Vec::new();
+        for text in self.attachments.queue.iter().filter_map(Attachment::text) {
+            for finding in redact::scan(text, &self.config.redaction) {
+                if finding.action == RedactionAction::Warn && !kinds.contains(&finding.pattern) {
+                    kinds.push(finding.pattern);
+                }
+            }
+        }
+        kinds
+    }
+
+    /// Masks sensitive text in the queued text attachments: everything found
+    /// if `all`, or else only what the settings mask without asking.
+    pub(super) fn mask_attachments(&mut self, all: bool) {
+        for attachment in &mut self.attachments.queue {
+            let Some(text) = attachment.text() else {
+                continue;
+            };
+            let findings: Vec<_> = redact::scan(text, &self.config.redaction)
+                .into_iter()
+                .filter(|finding| all || finding.action == RedactionAction::Mask)
+                .collect();
+            if !findings.is_empty() {
+                let masked = redact::mask(text, &findings);
+                *attachment = attachment.with_text(masked);
+            }
+        }
+    }
+
+    pub(super) fn update_attachments(
+        &mut self,
+        message: Message,
+    ) -> Task<cosmic::Action<super::Message>> {
+        match message {
+            Message::Pick => {
+                return cosmic::task::future(async {
+                    super::Message::Attachments(Message::Picked(pick_files().await))
+                });
+            }
+            Message::Picked(result) => match result {
+                Ok(attachments) => self.attachments.queue.extend(attachments),
+                Err(err) => self.attachments.error = Some(err),
+            },
+            Message::PasteText => {
+                return cosmic::iced::clipboard::read().map(|text| {
+                    cosmic::Action::App(super::Message::Attachments(Message::Pasted(text)))
+                });
+            }
+            Message::Pasted(text) => match text.filter(|text| !text.is_empty()) {
+                Some(text) => match Attachment::pasted_text(text).fit_to_send() {
+                    Ok(attachment) => self.attachments.queue.push(attachment),
+                    Err(err) => self.attachments.error = Some(err),
+                },
+                None => self.attachments.error = Some("The clipboard holds no text".into()),
+            },
+            Message::Remove(index) => {
+                if index < self.attachments.queue.len() {
+                    self.attachments.queue.remove(index);
+                }
+            }
+            Message::MoveBack(index) => {
+                if index > 0 && index < self.attachments.queue.len() {
+                    self.attachments.queue.swap(index - 1, index);
+                }
+            }
+            Message::DismissError => {
+                self.attachments.error = None;
+            }
+        }
+
+        Task::none()
+    }
+}
+
+/// Asks for files through the desktop portal and reads them.
+async fn pick_files() -> Result<Vec<Attachment>, String> {
+    let response = match file_chooser::open::Dialog::new()
+        .title("Attach files")
+        .open_files()
+        .await
+    {
+        Ok(response) => response,
+        Err(file_chooser::Error::Cancelled) => return Ok(Vec::new()),
+        Err(err) => return Err(err.to_string()),
+    };
+
+    let mut attachments = Vec::new();
+    for url in response.urls() {
+        let path = url
+            .to_file_path()
+            .map_err(|()| format!("{url} is not a local file"))?;
+        let attachment = Attachment::load(&path).await?;
+        // Scaling images down takes a while.
+        let fitted = tokio::task::spawn_blocking(move || attachment.fit_to_send())
+            .await
+            .map_err(|err| err.to_string())?;
+        attachments.push(fitted?);
+    }
+    Ok(attachments)
+}
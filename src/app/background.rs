@@ -0,0 +1,216 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Low-priority queue for auxiliary requests, such as titles and follow-up
+//! suggestions.
+//!
+//! Jobs run one at a time and only while no prompt is waiting for an answer,
+//! at most [`MAX_PER_MINUTE`] of them, and within the daily token and spending
+//! caps. What they cost counts toward the day's spending.
+
+use super::{AppModel, Chat, follow_ups};
+use crate::config::{Config, ResponseModalities};
+use crate::models;
+use cosmic::prelude::*;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Background requests started in any one minute.
+const MAX_PER_MINUTE: usize = 4;
+
+/// How often waiting jobs are retried.
+pub(super) const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+const TITLE_PROMPT: &str = "Give this conversation a short title of at most six words. \
+    Reply with the title only, without quotes or a trailing period.";
+
+/// What a background request is for, with where its result goes.
+#[derive(Debug, Clone, Copy)]
+pub enum Job {
+    /// Follow-up questions for the history of this length.
+    FollowUps(usize),
+    /// A title for the conversation with this tag.
+    Title(u64),
+}
+
+struct Queued {
+    job: Job,
+    history: Vec<Chat>,
+    config: Config,
+}
+
+#[derive(Default)]
+pub struct State {
+    queue: VecDeque<Queued>,
+    running: bool,
+    /// When the requests of the last minute were started.
+    started: VecDeque<Instant>,
+    /// Day the tokens below were counted on, in days since the epoch.
+    day: u64,
+    /// Estimated tokens used by background requests on that day.
+    tokens: u32,
+}
+
+impl State {
+    pub(super) fn is_waiting(&self) -> bool {
+        !self.queue.is_empty()
+    }
+
+    /// Estimated tokens used today, counting from zero again at midnight UTC.
+    fn tokens_today(&mut self) -> u32 {
+        let today = today();
+        if self.day != today {
+            self.day = today;
+            self.tokens = 0;
+        }
+        self.tokens
+    }
+
+    fn charge(&mut self, tokens: u32) {
+        self.tokens = self.tokens_today().saturating_add(tokens);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Retry,
+    /// The result of a job, with its estimated price in US dollars.
+    Finished(Job, f64, Result<String, String>),
+}
+
+impl AppModel {
+    /// Queues a request for `prompt` after the current conversation.
+    pub(super) fn queue_background(
+        &mut self,
+        job: Job,
+        prompt: &str,
+    ) -> Task<cosmic::Action<super::Message>> {
+        let mut history = self.chat_history.to_vec();
+        history.push(Chat::user(prompt));
+        let mut config = self.config.for_workspace();
+        config.response_modalities = ResponseModalities::Text;
+
+        // A newer job of the same kind makes the queued one pointless.
+        self.background
+            .queue
+            .retain(|queued| std::mem::discriminant(&queued.job) != std::mem::discriminant(&job));
+        self.background.queue.push_back(Queued {
+            job,
+            history,
+            config,
+        });
+        self.run_background()
+    }
+
+    /// Starts the next job if nothing else is in flight and the limits allow it.
+    pub(super) fn run_background(&mut self) -> Task<cosmic::Action<super::Message>> {
+        let state = &mut self.background;
+        if state.running || self.is_loading {
+            return Task::none();
+        }
+        let now = Instant::now();
+        while state
+            .started
+            .front()
+            .is_some_and(|started| now.duration_since(*started) >= Duration::from_secs(60))
+        {
+            state.started.pop_front();
+        }
+        if state.started.len() >= MAX_PER_MINUTE {
+            return Task::none();
+        }
+        let Some(queued) = state.queue.pop_front() else {
+            return Task::none();
+        };
+
+        let tokens: u32 = queued
+            .history
+            .iter()
+            .map(|chat| models::estimate_tokens(&chat.content))
+            .sum();
+        let cap = self.config.background_token_cap;
+        if cap > 0 && state.tokens_today().saturating_add(tokens) > cap {
+            eprintln!("skipped background request: daily token cap of {cap} reached");
+            state.queue.clear();
+            return Task::none();
+        }
+        // Only the prompt is known up front.
+        let cost = models::estimate_cost(&queued.config, tokens, 0);
+        if let Some(reason) = self.over_cost_cap(cost, false) {
+            eprintln!("skipped background request: {reason}");
+            self.background.queue.clear();
+            return Task::none();
+        }
+        let state = &mut self.background;
+        state.charge(tokens);
+        state.started.push_back(now);
+        state.running = true;
+
+        let Queued {
+            job,
+            history,
+            config,
+        } = queued;
+        cosmic::task::future(async move {
+            let history = Arc::new(history);
+            let message = models::get_response(Arc::clone(&history), config.clone()).await;
+            let cost = models::message_cost(&config, &history, &message);
+            let result = message.into_result().map(|reply| reply.text);
+            super::Message::Background(Message::Finished(job, cost, result))
+        })
+    }
+
+    pub(super) fn update_background(
+        &mut self,
+        message: Message,
+    ) -> Task<cosmic::Action<super::Message>> {
+        match message {
+            Message::Retry => {}
+            Message::Finished(job, cost, result) => {
+                self.background.running = false;
+                self.add_spending(cost);
+                if let Ok(text) = &result {
+                    self.background.charge(models::estimate_tokens(text));
+                }
+                let done = match job {
+                    Job::FollowUps(for_len) => {
+                        let result = result.map(|text| follow_ups::parse(&text));
+                        self.update_follow_ups(follow_ups::Message::Generated(for_len, result))
+                    }
+                    Job::Title(tag) => {
+                        match result {
+                            Ok(_) if tag != self.conversation_tag => {
+                                eprintln!("dropped a title, its conversation is gone");
+                            }
+                            Ok(text) => {
+                                let title = text.trim().trim_matches('"').trim();
+                                self.title = (!title.is_empty()).then(|| title.to_string());
+                            }
+                            Err(err) => eprintln!("failed to title the conversation: {err}"),
+                        }
+                        Task::none()
+                    }
+                };
+                return Task::batch([done, self.run_background()]);
+            }
+        }
+
+        self.run_background()
+    }
+
+    /// Asks for a title once the conversation has its first answer.
+    pub(super) fn request_title(&mut self) -> Task<cosmic::Action<super::Message>> {
+        if !self.config.auto_titles || self.title.is_some() || self.chat_history.len() != 2 {
+            return Task::none();
+        }
+        self.queue_background(Job::Title(self.conversation_tag), TITLE_PROMPT)
+    }
+}
+
+fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        / (24 * 60 * 60)
+}
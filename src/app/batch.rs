@@ -0,0 +1,300 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Batch question mode: runs a list of independent prompts one after another.
+
+use super::{AppModel, Chat};
+use crate::models::{self, gemini};
+use cosmic::iced::widget::{column, text_editor};
+use cosmic::iced::{Alignment, Length};
+use cosmic::prelude::*;
+use cosmic::widget;
+use std::sync::Arc;
+
+/// UI state of the batch page.
+#[derive(Default)]
+pub struct State {
+    /// Prompts, one per line.
+    prompts: text_editor::Content,
+    /// File to load prompts from.
+    file: String,
+    /// Prompts of the current or last run and their results.
+    items: Vec<Item>,
+    /// The run should stop after the current item.
+    cancelled: bool,
+    /// The run should wait after the current item, keeping the rest for later.
+    paused: bool,
+    /// Error from loading the prompt file.
+    error: Option<String>,
+}
+
+struct Item {
+    prompt: String,
+    status: Status,
+}
+
+enum Status {
+    Pending,
+    Running,
+    Done(String),
+    Failed(String),
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Edit(text_editor::Action),
+    FileChanged(String),
+    LoadFile,
+    Run,
+    Cancel,
+    /// The answer to the item at this index, with its estimated price in US dollars.
+    Finished(usize, f64, gemini::Message),
+    Copy(usize),
+    CopyAll,
+}
+
+impl AppModel {
+    pub(super) fn batch_view(&self) -> cosmic::Element<'_, Message> {
+        let state = &self.batch;
+        let running = state
+            .items
+            .iter()
+            .any(|item| matches!(item.status, Status::Running));
+
+        let mut controls = vec![
+            widget::text_input("File with one prompt per line", &state.file)
+                .on_input(Message::FileChanged)
+                .on_submit(|_| Message::LoadFile)
+                .width(Length::Fill)
+                .into(),
+            widget::button::standard("Load")
+                .on_press(Message::LoadFile)
+                .into(),
+        ];
+        controls.push(if running {
+            widget::button::destructive("Stop")
+                .on_press(Message::Cancel)
+                .into()
+        } else {
+            widget::button::suggested("Run")
+                .on_press(Message::Run)
+                .into()
+        });
+
+        let mut page = column!(
+            text_editor(&state.prompts)
+                .placeholder("One prompt per line")
+                .on_action(Message::Edit)
+                .height(Length::Fixed(120.0)),
+            widget::row::with_children(controls)
+                .spacing(8)
+                .align_y(Alignment::Center),
+        )
+        .spacing(10);
+
+        if let Some(error) = &state.error {
+            page = page.push(widget::text::caption(error));
+        }
+
+        let mut results = widget::settings::section().title("Results");
+        for (index, item) in state.items.iter().enumerate() {
+            let (answer, copyable) = match &item.status {
+                Status::Pending => ("Waiting".to_string(), false),
+                Status::Running => ("Generating…".to_string(), false),
+                Status::Done(answer) => (answer.clone(), true),
+                Status::Failed(error) => (format!("Failed: {error}"), false),
+            };
+            let mut copy = widget::button::icon(widget::icon::from_name("edit-copy-symbolic"));
+            if copyable {
+                copy = copy.on_press(Message::Copy(index));
+            }
+
+            results = results.add(
+                widget::row::with_children(vec![
+                    widget::text::body(&item.prompt)
+                        .width(Length::FillPortion(1))
+                        .into(),
+                    widget::text::body(answer)
+                        .width(Length::FillPortion(2))
+                        .into(),
+                    copy.into(),
+                ])
+                .spacing(8),
+            );
+        }
+
+        if !state.items.is_empty() {
+            page = page
+                .push(widget::scrollable(results).height(Length::Fill))
+                .push(widget::button::standard("Copy all as Markdown").on_press(Message::CopyAll));
+        }
+
+        page.into()
+    }
+
+    pub(super) fn update_batch(
+        &mut self,
+        message: Message,
+    ) -> Task<cosmic::Action<super::Message>> {
+        match message {
+            Message::Edit(action) => {
+                self.batch.prompts.perform(action);
+            }
+            Message::FileChanged(file) => {
+                self.batch.file = file;
+            }
+            Message::LoadFile => match std::fs::read_to_string(self.batch.file.trim()) {
+                Ok(prompts) => {
+                    self.batch.prompts = text_editor::Content::with_text(&prompts);
+                    self.batch.error = None;
+                }
+                Err(err) => self.batch.error = Some(format!("Could not read file: {err}")),
+            },
+            Message::Run => {
+                if self
+                    .batch
+                    .items
+                    .iter()
+                    .any(|item| matches!(item.status, Status::Running))
+                {
+                    return Task::none();
+                }
+                self.batch.cancelled = false;
+                self.batch.paused = false;
+                self.batch.items = self
+                    .batch
+                    .prompts
+                    .text()
+                    .lines()
+                    .map(str::trim)
+                    .filter(|prompt| !prompt.is_empty())
+                    .map(|prompt| Item {
+                        prompt: prompt.to_string(),
+                        status: Status::Pending,
+                    })
+                    .collect();
+                return self.run_next_batch_item();
+            }
+            Message::Cancel => {
+                self.batch.cancelled = true;
+            }
+            Message::Finished(index, cost, message) => {
+                self.add_spending(cost);
+                if let Some(item) = self.batch.items.get_mut(index) {
+                    item.status = match message.into_result() {
+                        Ok(reply) => Status::Done(reply.text),
+                        Err(error) => Status::Failed(error),
+                    };
+                }
+                if self.batch.cancelled {
+                    self.batch
+                        .items
+                        .retain(|item| !matches!(item.status, Status::Pending));
+                    return Task::none();
+                }
+                if self.batch.paused {
+                    return Task::none();
+                }
+                return self.run_next_batch_item();
+            }
+            Message::Copy(index) => {
+                if let Some(Item {
+                    status: Status::Done(answer),
+                    ..
+                }) = self.batch.items.get(index)
+                {
+                    return cosmic::iced::clipboard::write(answer.clone());
+                }
+            }
+            Message::CopyAll => {
+                let markdown = self
+                    .batch
+                    .items
+                    .iter()
+                    .map(|item| {
+                        let answer = match &item.status {
+                            Status::Done(answer) => answer.clone(),
+                            Status::Failed(error) => format!("*Failed: {error}*"),
+                            Status::Pending | Status::Running => "*No answer*".to_string(),
+                        };
+                        format!("## {}\n\n{answer}\n", item.prompt)
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                return cosmic::iced::clipboard::write(markdown);
+            }
+        }
+
+        Task::none()
+    }
+
+    /// Sends the first pending prompt, each one in a conversation of its own.
+    /// Number of finished items and of all items in the current or last run,
+    /// with the last result.
+    pub(super) fn batch_progress(&self) -> (usize, usize, Option<String>) {
+        let mut finished = 0;
+        let mut last = None;
+        for item in &self.batch.items {
+            match &item.status {
+                Status::Done(answer) => last = Some(answer.clone()),
+                Status::Failed(error) => last = Some(format!("Failed: {error}")),
+                Status::Pending | Status::Running => continue,
+            }
+            finished += 1;
+        }
+        (finished, self.batch.items.len(), last)
+    }
+
+    pub(super) fn batch_is_running(&self) -> bool {
+        self.batch
+            .items
+            .iter()
+            .any(|item| matches!(item.status, Status::Running))
+    }
+
+    pub(super) fn batch_is_paused(&self) -> bool {
+        self.batch.paused
+    }
+
+    /// Lets the run stop after the current item, keeping the remaining ones.
+    pub(super) fn pause_batch(&mut self) {
+        self.batch.paused = true;
+    }
+
+    /// Continues a paused run, or runs the prompts again once all are finished.
+    pub(super) fn resume_batch(&mut self) -> Task<cosmic::Action<super::Message>> {
+        self.batch.paused = false;
+        if self.batch_is_running() {
+            return Task::none();
+        }
+        if self
+            .batch
+            .items
+            .iter()
+            .any(|item| matches!(item.status, Status::Pending))
+        {
+            return self.run_next_batch_item();
+        }
+        self.update_batch(Message::Run)
+    }
+
+    fn run_next_batch_item(&mut self) -> Task<cosmic::Action<super::Message>> {
+        let Some((index, item)) = self
+            .batch
+            .items
+            .iter_mut()
+            .enumerate()
+            .find(|(_, item)| matches!(item.status, Status::Pending))
+        else {
+            return Task::none();
+        };
+
+        item.status = Status::Running;
+        let history = Arc::new(vec![Chat::user(item.prompt.clone())]);
+        let config = self.config.for_workspace();
+        cosmic::task::future(async move {
+            let message = models::get_response(Arc::clone(&history), config.clone()).await;
+            let cost = models::message_cost(&config, &history, &message);
+            super::Message::Batch(Message::Finished(index, cost, message))
+        })
+    }
+}
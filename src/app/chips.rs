@@ -0,0 +1,282 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Chips above the conversation showing the model, workspace, persona, tone and
+//! safety preset in use, each opening a switcher for it.
+
+use super::{AppModel, settings};
+use crate::config::{Formality, Provider, SafetyPreset, Verbosity};
+use crate::models;
+use cosmic::iced::Length;
+use cosmic::prelude::*;
+use cosmic::widget;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip {
+    Model,
+    Workspace,
+    Persona,
+    Tone,
+    Safety,
+}
+
+#[derive(Default)]
+pub struct State {
+    /// Chip whose switcher is shown.
+    open: Option<Chip>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Toggle(Chip),
+    Model(String),
+    /// Index into the workspace names, 0 for no workspace.
+    Workspace(usize),
+    /// Index into the personas, 0 for no persona.
+    Persona(usize),
+    Formality(usize),
+    Verbosity(usize),
+    Safety(usize),
+}
+
+impl AppModel {
+    pub(super) fn chips_view(&self) -> cosmic::Element<'_, Message> {
+        let locked = self.config.is_locked();
+        let chip =
+            |label: String, chip: Chip, enabled: bool| -> cosmic::Element<'static, Message> {
+                widget::button::text(label)
+                    .class(if self.chips.open == Some(chip) {
+                        cosmic::theme::Button::Suggested
+                    } else {
+                        cosmic::theme::Button::Standard
+                    })
+                    .on_press_maybe(enabled.then_some(Message::Toggle(chip)))
+                    .into()
+            };
+
+        let mut chips = vec![chip(
+            models::model_name(&self.config.for_workspace()),
+            Chip::Model,
+            !locked,
+        )];
+        if !self.config.workspaces.is_empty() {
+            let name = match self.config.active_workspace() {
+                Some(workspace) => workspace.name.clone(),
+                None => "No workspace".to_string(),
+            };
+            chips.push(chip(name, Chip::Workspace, true));
+        }
+        if !self.config.personas.is_empty() {
+            let name = match self.config.active_persona() {
+                Some(persona) => persona.name.clone(),
+                None => "No persona".to_string(),
+            };
+            chips.push(chip(name, Chip::Persona, true));
+        }
+        chips.push(chip(self.config.tone.label(), Chip::Tone, true));
+        chips.push(chip(
+            format!("{} safety", self.config.safety_preset().name()),
+            Chip::Safety,
+            !locked,
+        ));
+
+        let mut column =
+            widget::column::with_children(vec![widget::flex_row(chips).spacing(6).into()])
+                .spacing(6);
+        if let Some(open) = self.chips.open {
+            column = column.push(self.switcher_view(open));
+        }
+        column.into()
+    }
+
+    fn switcher_view(&self, chip: Chip) -> cosmic::Element<'_, Message> {
+        let item = |label: String,
+                    selected: bool,
+                    message: Message|
+         -> cosmic::Element<'static, Message> {
+            widget::button::text(label)
+                .class(cosmic::theme::Button::MenuItem)
+                .selected(selected)
+                .on_press(message)
+                .width(Length::Fill)
+                .into()
+        };
+
+        let items: Vec<cosmic::Element<'_, Message>> = match chip {
+            Chip::Model => {
+                let config = self.config.for_workspace();
+                let current = config.model();
+                let choices = self.model_choices(config.provider);
+                if choices.is_empty() {
+                    vec![widget::text::caption("No models found.").into()]
+                } else {
+                    choices
+                        .into_iter()
+                        .map(|(label, model)| {
+                            let selected = model == current;
+                            item(label, selected, Message::Model(model))
+                        })
+                        .collect()
+                }
+            }
+            Chip::Workspace => {
+                let selected = self
+                    .config
+                    .workspaces
+                    .iter()
+                    .position(|workspace| workspace.name == self.config.workspace)
+                    .map_or(0, |index| index + 1);
+                self.workspace_names
+                    .iter()
+                    .enumerate()
+                    .map(|(index, name)| {
+                        item(name.clone(), index == selected, Message::Workspace(index))
+                    })
+                    .collect()
+            }
+            Chip::Persona => {
+                let active = self.config.active_persona();
+                std::iter::once(item(
+                    "No persona".to_string(),
+                    active.is_none(),
+                    Message::Persona(0),
+                ))
+                .chain(
+                    self.config
+                        .personas
+                        .iter()
+                        .enumerate()
+                        .map(|(index, persona)| {
+                            item(
+                                persona.name.clone(),
+                                active.is_some_and(|active| active.name == persona.name),
+                                Message::Persona(index + 1),
+                            )
+                        }),
+                )
+                .collect()
+            }
+            Chip::Tone => {
+                let tone = self.config.tone;
+                let mut items = vec![widget::text::caption("Formality").into()];
+                items.extend(Formality::ALL.iter().enumerate().map(|(index, formality)| {
+                    item(
+                        formality.name().to_string(),
+                        *formality == tone.formality,
+                        Message::Formality(index),
+                    )
+                }));
+                items.push(widget::text::caption("Length").into());
+                items.extend(Verbosity::ALL.iter().enumerate().map(|(index, verbosity)| {
+                    item(
+                        verbosity.name().to_string(),
+                        *verbosity == tone.verbosity,
+                        Message::Verbosity(index),
+                    )
+                }));
+                items
+            }
+            Chip::Safety => SafetyPreset::ALL
+                .iter()
+                .enumerate()
+                .map(|(index, preset)| {
+                    item(
+                        preset.name().to_string(),
+                        *preset == self.config.safety.preset,
+                        Message::Safety(index),
+                    )
+                })
+                .collect(),
+        };
+
+        widget::container(widget::column::with_children(items).spacing(2))
+            .class(cosmic::theme::Container::Card)
+            .padding(6)
+            .width(Length::Fill)
+            .into()
+    }
+
+    pub(super) fn update_chips(
+        &mut self,
+        message: Message,
+    ) -> Task<cosmic::Action<super::Message>> {
+        match message {
+            Message::Toggle(chip) => {
+                if self.chips.open == Some(chip) {
+                    self.chips.open = None;
+                    return Task::none();
+                }
+                self.chips.open = Some(chip);
+                // Models are only listed on request, so list them on first use.
+                if chip == Chip::Model {
+                    let provider = self.config.for_workspace().provider;
+                    let listed = !self.settings_models(provider).is_empty();
+                    return match provider {
+                        Provider::Gemini if !listed => {
+                            self.update_settings(settings::Message::RefreshModels)
+                        }
+                        Provider::OpenAi if !listed => {
+                            self.update_settings(settings::Message::RefreshOpenAiModels)
+                        }
+                        Provider::Compatible if !listed => {
+                            self.update_settings(settings::Message::RefreshCompatibleModels)
+                        }
+                        Provider::Local => {
+                            self.refresh_local_models();
+                            Task::none()
+                        }
+                        Provider::Gemini | Provider::OpenAi | Provider::Compatible => Task::none(),
+                    };
+                }
+            }
+            Message::Model(model) => {
+                if self.config.is_locked() {
+                    return Task::none();
+                }
+                self.config.choose_model(&model);
+                self.chips.open = None;
+                self.save_config();
+            }
+            Message::Workspace(index) => {
+                self.chips.open = None;
+                return Task::done(cosmic::Action::App(super::Message::WorkspaceSelected(
+                    index,
+                )));
+            }
+            // The conversation stays, only the next answers are in the new persona.
+            Message::Persona(index) => {
+                self.chips.open = None;
+                self.config.persona = match index.checked_sub(1) {
+                    Some(index) => match self.config.personas.get(index) {
+                        Some(persona) => persona.name.clone(),
+                        None => return Task::none(),
+                    },
+                    None => String::new(),
+                };
+                self.save_config();
+            }
+            // The switcher stays open, since formality and length are often set together.
+            Message::Formality(index) => {
+                let Some(formality) = Formality::ALL.get(index) else {
+                    return Task::none();
+                };
+                self.config.tone.formality = *formality;
+                self.save_config();
+                self.save_session();
+            }
+            Message::Verbosity(index) => {
+                let Some(verbosity) = Verbosity::ALL.get(index) else {
+                    return Task::none();
+                };
+                self.config.tone.verbosity = *verbosity;
+                self.save_config();
+                self.save_session();
+            }
+            Message::Safety(index) => {
+                self.chips.open = None;
+                return self.update_settings(settings::Message::SafetyPresetSelected(index));
+            }
+        }
+
+        Task::none()
+    }
+}
@@ -0,0 +1,188 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Compare view: sends one prompt to several models at once.
+
+use super::{AppModel, Chat};
+use crate::config::Provider;
+use crate::models::{self, gemini};
+use cosmic::iced::widget::column;
+use cosmic::iced::{Alignment, Length};
+use cosmic::prelude::*;
+use cosmic::widget;
+use iced_selection::cosmic_text;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Most models that can be compared at once.
+const MAX_MODELS: usize = 3;
+
+/// UI state of the compare page.
+#[derive(Default)]
+pub struct State {
+    prompt: String,
+    /// One answer slot per compared model, in the order of `Config::compare_models`.
+    answers: Vec<Answer>,
+}
+
+#[derive(Clone)]
+enum Answer {
+    Running(Instant),
+    Done { text: String, latency: Duration },
+    Failed { error: String, latency: Duration },
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    PromptChanged(String),
+    ModelChanged(usize, String),
+    AddModel,
+    RemoveModel(usize),
+    Run,
+    /// The answer of the model at this index, with its estimated price in US dollars.
+    Finished(usize, f64, gemini::Message),
+}
+
+impl AppModel {
+    pub(super) fn compare_view(&self) -> cosmic::Element<'_, Message> {
+        let models = self.config.compare_models();
+
+        let mut model_inputs = widget::row::with_capacity(MAX_MODELS + 1).spacing(8);
+        for (index, model) in models.iter().enumerate() {
+            model_inputs = model_inputs.push(
+                widget::row::with_children(vec![
+                    widget::text_input(gemini::DEFAULT_MODEL, model.clone())
+                        .on_input(move |model| Message::ModelChanged(index, model))
+                        .width(Length::Fill)
+                        .into(),
+                    widget::button::icon(widget::icon::from_name("window-close-symbolic"))
+                        .on_press_maybe((models.len() > 2).then_some(Message::RemoveModel(index)))
+                        .into(),
+                ])
+                .align_y(Alignment::Center)
+                .width(Length::FillPortion(1)),
+            );
+        }
+        if models.len() < MAX_MODELS {
+            model_inputs = model_inputs.push(
+                widget::button::icon(widget::icon::from_name("list-add-symbolic"))
+                    .on_press(Message::AddModel),
+            );
+        }
+
+        let mut answers = widget::row::with_capacity(models.len()).spacing(8);
+        for answer in &self.compare.answers {
+            let (caption, body) = match answer {
+                Answer::Running(_) => ("Generating…".to_string(), String::new()),
+                Answer::Done { text, latency } => (format_latency(*latency), text.clone()),
+                Answer::Failed { error, latency } => (
+                    format!("{} · failed", format_latency(*latency)),
+                    error.clone(),
+                ),
+            };
+            answers = answers.push(
+                widget::container(
+                    column!(widget::text::caption(caption), cosmic_text!("{body}")).spacing(6),
+                )
+                .class(cosmic::theme::Container::List)
+                .padding(10)
+                .width(Length::FillPortion(1)),
+            );
+        }
+
+        column!(
+            model_inputs.align_y(Alignment::Center),
+            widget::text_input("Prompt to compare", &self.compare.prompt)
+                .on_input(Message::PromptChanged)
+                .on_submit(|_| Message::Run)
+                .padding(10),
+            widget::scrollable(answers).height(Length::Fill),
+        )
+        .spacing(10)
+        .into()
+    }
+
+    pub(super) fn update_compare(
+        &mut self,
+        message: Message,
+    ) -> Task<cosmic::Action<super::Message>> {
+        match message {
+            Message::PromptChanged(prompt) => {
+                self.compare.prompt = prompt;
+            }
+            Message::ModelChanged(index, model) => {
+                let mut models = self.config.compare_models();
+                if let Some(slot) = models.get_mut(index) {
+                    *slot = model;
+                    self.config.compare_models = models;
+                    self.save_config();
+                }
+            }
+            Message::AddModel => {
+                let mut models = self.config.compare_models();
+                if models.len() < MAX_MODELS {
+                    models.push(String::new());
+                    self.config.compare_models = models;
+                    self.save_config();
+                }
+            }
+            Message::RemoveModel(index) => {
+                let mut models = self.config.compare_models();
+                if index < models.len() {
+                    models.remove(index);
+                    self.config.compare_models = models;
+                    self.save_config();
+                }
+            }
+            Message::Run => {
+                let prompt = self.compare.prompt.trim();
+                if prompt.is_empty()
+                    || self
+                        .compare
+                        .answers
+                        .iter()
+                        .any(|answer| matches!(answer, Answer::Running(_)))
+                {
+                    return Task::none();
+                }
+
+                let history = Arc::new(vec![Chat::user(prompt)]);
+                let models = self.config.compare_models();
+                self.compare.answers = vec![Answer::Running(Instant::now()); models.len()];
+
+                return Task::batch(models.into_iter().enumerate().map(|(index, model)| {
+                    let mut config = self.config.clone();
+                    config.provider = Provider::Gemini;
+                    config.gemini.model = model;
+                    let history = Arc::clone(&history);
+                    cosmic::task::future(async move {
+                        let message =
+                            models::get_response(Arc::clone(&history), config.clone()).await;
+                        let cost = models::message_cost(&config, &history, &message);
+                        super::Message::Compare(Message::Finished(index, cost, message))
+                    })
+                }));
+            }
+            Message::Finished(index, cost, message) => {
+                self.add_spending(cost);
+                if let Some(answer) = self.compare.answers.get_mut(index)
+                    && let Answer::Running(started) = *answer
+                {
+                    let latency = started.elapsed();
+                    *answer = match message.into_result() {
+                        Ok(reply) => Answer::Done {
+                            text: reply.text,
+                            latency,
+                        },
+                        Err(error) => Answer::Failed { error, latency },
+                    };
+                }
+            }
+        }
+
+        Task::none()
+    }
+}
+
+fn format_latency(latency: Duration) -> String {
+    format!("{:.1} s", latency.as_secs_f32())
+}
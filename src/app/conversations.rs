@@ -0,0 +1,287 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Conversations set aside with "New chat", listed to switch back to them.
+
+use super::{AppModel, Chat, Page, ParkedConversation, welcome};
+use cosmic::iced::{Alignment, Length};
+use cosmic::prelude::*;
+use cosmic::widget;
+use std::sync::Arc;
+
+/// Characters of an arriving answer shown under its conversation.
+const SNIPPET_LENGTH: usize = 60;
+
+#[derive(Default)]
+pub struct State {
+    /// Conversations not in view, most recently set aside first.
+    pub(super) list: Vec<ParkedConversation>,
+    /// Merged messages are ordered by when they were sent, rather than
+    /// appended after the current conversation.
+    interleave: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// Sets the conversation in view aside and starts an empty one.
+    New,
+    Open(usize),
+    Delete(usize),
+    /// Moves the messages of a conversation into the one in view.
+    Merge(usize),
+    InterleaveToggled(bool),
+}
+
+impl AppModel {
+    pub(super) fn conversations_view(&self) -> cosmic::Element<'_, Message> {
+        let mut column = widget::column::with_children(vec![
+            widget::row::with_children(vec![
+                widget::text::caption(
+                    "Conversations open in the current workspace, with its model and \
+                     instructions.",
+                )
+                .width(Length::Fill)
+                .into(),
+                widget::button::suggested("New chat")
+                    .on_press_maybe((!self.chat_history.is_empty()).then_some(Message::New))
+                    .into(),
+            ])
+            .spacing(8)
+            .align_y(Alignment::Center)
+            .into(),
+        ])
+        .spacing(8);
+
+        if self.conversations.list.is_empty() {
+            column = column.push(widget::text::body("No other conversations yet."));
+        } else {
+            column = column.push(
+                widget::row::with_children(vec![
+                    widget::text::caption("Interleave merged messages by when they were sent")
+                        .width(Length::Fill)
+                        .into(),
+                    widget::toggler(self.conversations.interleave)
+                        .on_toggle(Message::InterleaveToggled)
+                        .into(),
+                ])
+                .spacing(8)
+                .align_y(Alignment::Center),
+            );
+        }
+        // Locked conversations are kept as they are.
+        let can_merge = !self.is_loading && !self.lock.locked;
+        for (index, parked) in self.conversations.list.iter().enumerate() {
+            let count = parked.history.len();
+            let mut label = widget::column::with_children(vec![
+                widget::text::body(welcome::label(parked)).into(),
+            ]);
+            if let Some(partial) = parked
+                .generating
+                .as_ref()
+                .and_then(|generating| generating.partial.as_deref())
+            {
+                label = label.push(widget::text::caption(snippet(partial)));
+            }
+            column = column.push(
+                widget::row::with_children(vec![
+                    widget::button::custom(
+                        widget::row::with_children(vec![
+                            label.width(Length::Fill).into(),
+                            widget::text::caption(if parked.generating.is_some() {
+                                "typing…".to_string()
+                            } else if count == 1 {
+                                "1 message".to_string()
+                            } else {
+                                format!("{count} messages")
+                            })
+                            .into(),
+                        ])
+                        .spacing(8)
+                        .align_y(Alignment::Center),
+                    )
+                    .class(cosmic::theme::Button::MenuItem)
+                    .on_press(Message::Open(index))
+                    .width(Length::Fill)
+                    .into(),
+                    widget::button::icon(widget::icon::from_name("edit-paste-symbolic"))
+                        .on_press_maybe(
+                            (can_merge && !parked.lock.locked && parked.generating.is_none())
+                                .then_some(Message::Merge(index)),
+                        )
+                        .tooltip("Merge into the current conversation")
+                        .into(),
+                    widget::button::icon(widget::icon::from_name("user-trash-symbolic"))
+                        .on_press(Message::Delete(index))
+                        .tooltip("Delete conversation")
+                        .into(),
+                ])
+                .spacing(4)
+                .align_y(Alignment::Center),
+            );
+        }
+
+        widget::scrollable(column).height(Length::Fill).into()
+    }
+
+    pub(super) fn update_conversations(
+        &mut self,
+        message: Message,
+    ) -> Task<cosmic::Action<super::Message>> {
+        match message {
+            Message::New => {
+                self.page = Page::Chat;
+                if self.chat_history.is_empty() {
+                    return Task::none();
+                }
+                let current = self.park_conversation();
+                self.conversations.list.insert(0, current);
+            }
+            Message::Open(index) => {
+                if index >= self.conversations.list.len() {
+                    return Task::none();
+                }
+                let next = self.conversations.list.remove(index);
+                let current = self.park_conversation();
+                if !current.history.is_empty() {
+                    self.conversations.list.insert(0, current);
+                }
+                self.show_conversation(next);
+                self.page = Page::Chat;
+            }
+            Message::Delete(index) => {
+                if index < self.conversations.list.len() {
+                    let removed = self.conversations.list.remove(index);
+                    self.stop_answer_aside(&removed);
+                }
+            }
+            Message::Merge(index) => {
+                let Some(other) = self.conversations.list.get(index) else {
+                    return Task::none();
+                };
+                if self.is_loading
+                    || self.lock.locked
+                    || other.lock.locked
+                    || other.generating.is_some()
+                {
+                    return Task::none();
+                }
+                let other = self.conversations.list.remove(index);
+                let mut current = self.park_conversation();
+                current.history = Arc::new(merge_histories(
+                    current.history.to_vec(),
+                    other.history.to_vec(),
+                    self.conversations.interleave,
+                ));
+                for file in other.pinned.files {
+                    if !current.pinned.files.contains(&file) {
+                        current.pinned.files.push(file);
+                    }
+                }
+                current.title = current.title.or(other.title);
+                current.cost += other.cost;
+                self.show_conversation(current);
+                self.page = Page::Chat;
+            }
+            Message::InterleaveToggled(interleave) => {
+                self.conversations.interleave = interleave;
+                return Task::none();
+            }
+        }
+
+        self.save_config();
+        self.save_session();
+        Task::none()
+    }
+}
+
+/// The end of the answer arriving for a conversation, on one line.
+fn snippet(partial: &str) -> String {
+    let line = partial
+        .lines()
+        .rev()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+    match line.char_indices().rev().nth(SNIPPET_LENGTH - 1) {
+        Some((start, _)) if start > 0 => format!("…{}", &line[start..]),
+        _ => line.to_string(),
+    }
+}
+
+/// The messages of `ours` followed by those of `theirs`, or with `interleave`
+/// both ordered by when they were sent. Each prompt stays together with the
+/// answers that followed it, and messages saved before send times were
+/// recorded count as the oldest.
+fn merge_histories(ours: Vec<Chat>, theirs: Vec<Chat>, interleave: bool) -> Vec<Chat> {
+    if !interleave {
+        return ours.into_iter().chain(theirs).collect();
+    }
+    let mut exchanges: Vec<Vec<Chat>> = Vec::new();
+    for history in [ours, theirs] {
+        let start = exchanges.len();
+        for chat in history {
+            if exchanges.len() > start
+                && chat.role != "user"
+                && let Some(exchange) = exchanges.last_mut()
+            {
+                exchange.push(chat);
+            } else {
+                exchanges.push(vec![chat]);
+            }
+        }
+    }
+    // Stable, so exchanges sent at the same time keep ours first.
+    exchanges.sort_by_key(|exchange| exchange[0].sent_at);
+    exchanges.into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chat(role: &str, content: &str, sent_at: u64) -> Chat {
+        Chat {
+            role: role.into(),
+            content: content.into(),
+            sent_at,
+            ..Chat::default()
+        }
+    }
+
+    fn contents(history: &[Chat]) -> Vec<&str> {
+        history.iter().map(|chat| chat.content.as_str()).collect()
+    }
+
+    fn histories() -> (Vec<Chat>, Vec<Chat>) {
+        let ours = vec![
+            chat("user", "a", 10),
+            chat("model", "a'", 11),
+            chat("user", "b", 30),
+            chat("model", "b'", 31),
+        ];
+        let theirs = vec![
+            chat("user", "c", 20),
+            chat("model", "c'", 21),
+            chat("model", "c''", 40),
+            chat("user", "d", 30),
+        ];
+        (ours, theirs)
+    }
+
+    #[test]
+    fn merged_messages_are_appended() {
+        let (ours, theirs) = histories();
+        assert_eq!(
+            contents(&merge_histories(ours, theirs, false)),
+            ["a", "a'", "b", "b'", "c", "c'", "c''", "d"]
+        );
+    }
+
+    #[test]
+    fn interleaved_exchanges_stay_together_in_send_order() {
+        let (ours, theirs) = histories();
+        assert_eq!(
+            contents(&merge_histories(ours, theirs, true)),
+            ["a", "a'", "c", "c'", "c''", "b", "b'", "d"]
+        );
+    }
+}
@@ -0,0 +1,164 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! A panel sliding over the chat with everything the provider reported about
+//! one answer: usage, finish reason, safety ratings, grounding sources and the
+//! parts it was made of, without reading the debug JSON.
+
+use super::{AnswerStats, AppModel};
+use cosmic::iced::{Alignment, Length};
+use cosmic::prelude::*;
+use cosmic::widget;
+
+/// Width of the panel, leaving part of the chat visible beside it.
+const PANEL_WIDTH: f32 = 320.0;
+
+#[derive(Default)]
+pub struct State {
+    /// Index of the answer whose details are shown.
+    open: Option<usize>,
+}
+
+impl State {
+    pub(super) fn close(&mut self) {
+        self.open = None;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// Shows the details of the answer at this index, or hides them if shown.
+    Toggle(usize),
+    Close,
+    OpenSource(String),
+}
+
+impl AppModel {
+    /// Whether the details of the answer at `index` are shown.
+    pub(super) fn details_open(&self, index: usize) -> bool {
+        self.details.open == Some(index)
+    }
+
+    /// The panel for the chosen answer, if one is chosen and still there.
+    pub(super) fn details_view(&self) -> Option<cosmic::Element<'_, Message>> {
+        let index = self.details.open?;
+        let chat = self.chat_history.get(index)?;
+        let stats = chat.stats.as_ref()?;
+
+        let header = widget::row::with_children(vec![
+            widget::text::heading("Answer details")
+                .width(Length::Fill)
+                .into(),
+            widget::button::icon(widget::icon::from_name("window-close-symbolic"))
+                .on_press(Message::Close)
+                .into(),
+        ])
+        .align_y(Alignment::Center);
+
+        let mut sections = vec![header.into(), usage_section(stats).into()];
+
+        let mut model = widget::settings::section().title("Model");
+        if let Some(generated_by) = &chat.generated_by {
+            model = model.add(widget::text::body(generated_by.as_str()));
+        }
+        if let Some(version) = &stats.model_version {
+            model = model.add(widget::text::caption(format!("served by {version}")));
+        }
+        model = model.add(widget::text::body(format!(
+            "Finished: {}",
+            stats.finish_reason.as_deref().unwrap_or("not reported")
+        )));
+        if let Some(message) = &stats.finish_message {
+            model = model.add(widget::text::caption(message.as_str()));
+        }
+        sections.push(model.into());
+
+        if !stats.safety_ratings.is_empty() {
+            let mut safety = widget::settings::section().title("Safety ratings");
+            for (category, probability) in &stats.safety_ratings {
+                safety = safety.add(widget::settings::item(
+                    category.as_str(),
+                    widget::text::body(probability.as_str()),
+                ));
+            }
+            sections.push(safety.into());
+        }
+
+        if !stats.sources.is_empty() || !stats.search_queries.is_empty() {
+            let mut sources = widget::settings::section().title("Sources");
+            for source in &stats.sources {
+                sources = sources.add(
+                    widget::button::link(source.title.clone())
+                        .on_press(Message::OpenSource(source.uri.clone())),
+                );
+            }
+            for query in &stats.search_queries {
+                sources = sources.add(widget::text::caption(format!("searched for “{query}”")));
+            }
+            sections.push(sources.into());
+        }
+
+        if !stats.parts.is_empty() {
+            let mut parts = widget::settings::section().title("Parts");
+            for part in &stats.parts {
+                parts = parts.add(widget::text::monotext(part.as_str()));
+            }
+            sections.push(parts.into());
+        }
+
+        let panel = widget::container(
+            widget::scrollable(widget::column::with_children(sections).spacing(12))
+                .height(Length::Fill),
+        )
+        .class(cosmic::theme::Container::Card)
+        .padding(12)
+        .width(Length::Fixed(PANEL_WIDTH))
+        .height(Length::Fill);
+        Some(widget::container(panel).align_right(Length::Fill).into())
+    }
+
+    pub(super) fn update_details(
+        &mut self,
+        message: Message,
+    ) -> Task<cosmic::Action<super::Message>> {
+        match message {
+            Message::Toggle(index) => {
+                self.details.open = (!self.details_open(index)).then_some(index);
+            }
+            Message::Close => self.details.close(),
+            Message::OpenSource(uri) => super::open_uri(&uri),
+        }
+
+        Task::none()
+    }
+}
+
+/// Token counts, cost and latency of an answer.
+fn usage_section<'a>(stats: &AnswerStats) -> widget::settings::Section<'a, Message> {
+    let about = if stats.estimated { "about " } else { "" };
+    let mut usage = widget::settings::section()
+        .title("Usage")
+        .add(widget::settings::item(
+            "Prompt tokens",
+            widget::text::body(format!("{about}{}", stats.prompt_tokens)),
+        ));
+    if stats.cached_tokens > 0 {
+        usage = usage.add(widget::settings::item(
+            "Cached prompt tokens",
+            widget::text::body(stats.cached_tokens.to_string()),
+        ));
+    }
+    usage = usage.add(widget::settings::item(
+        "Answer tokens",
+        widget::text::body(format!("{about}{}", stats.completion_tokens)),
+    ));
+    if stats.cost > 0.0 {
+        usage = usage.add(widget::settings::item(
+            "Cost",
+            widget::text::body(format!("about ${:.4}", stats.cost)),
+        ));
+    }
+    usage.add(widget::settings::item(
+        "Latency",
+        widget::text::body(format!("{:.1} s", stats.latency.as_secs_f32())),
+    ))
+}
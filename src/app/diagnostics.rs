@@ -0,0 +1,305 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Diagnostics page: checks the configured providers, benchmarks the models
+//! with a fixed prompt suite and creates debug reports.
+
+use super::{AppModel, Chat};
+use crate::config::Provider;
+use crate::models::{self, Health, HealthStatus, gemini};
+use crate::report;
+use cosmic::iced::widget::column;
+use cosmic::iced::{Color, Length};
+use cosmic::prelude::*;
+use cosmic::widget;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Prompts every model is timed against, from trivial to longer answers.
+const SUITE: &[&str] = &[
+    "Reply with the single word: ready",
+    "In two sentences, explain why the sky is blue.",
+    "Write a Rust function that reverses the words in a string.",
+];
+
+/// UI state of the diagnostics page.
+#[derive(Default)]
+pub struct State {
+    /// Results of the last provider health check.
+    health: Vec<Health>,
+    /// A health check is running.
+    checking: bool,
+    /// Benchmark results per model.
+    runs: Vec<Run>,
+    /// A debug report is being written.
+    reporting: bool,
+    /// Where the last debug report went, or why it failed.
+    report_status: Option<String>,
+}
+
+struct Run {
+    model: String,
+    /// Latency of each finished prompt, `None` when it failed.
+    results: Vec<Option<Duration>>,
+    /// Start of the prompt currently running.
+    started: Option<Instant>,
+    last_error: Option<String>,
+}
+
+impl Run {
+    fn finished(&self) -> bool {
+        self.results.len() == SUITE.len()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    CheckHealth,
+    HealthChecked(Vec<Health>),
+    RunBenchmark,
+    BenchmarkStep(usize, f64, gemini::Message),
+    CreateReport,
+    ReportCreated(Result<PathBuf, String>),
+}
+
+impl AppModel {
+    pub(super) fn diagnostics_view(&self) -> cosmic::Element<'_, Message> {
+        let mut providers =
+            widget::settings::section()
+                .title("Providers")
+                .add(widget::settings::item(
+                    "Key, model availability and latency",
+                    widget::button::standard(if self.diagnostics.checking {
+                        "Checking…"
+                    } else {
+                        "Check"
+                    })
+                    .on_press_maybe((!self.diagnostics.checking).then_some(Message::CheckHealth)),
+                ));
+        for health in &self.diagnostics.health {
+            let color = match health.status {
+                HealthStatus::Healthy => Color::from_rgb(0.3, 0.8, 0.4),
+                HealthStatus::Degraded => Color::from_rgb(0.95, 0.65, 0.1),
+                HealthStatus::Failing => Color::from_rgb(0.9, 0.2, 0.2),
+            };
+            let mut detail = health.detail.clone();
+            if let Some(latency) = health.latency {
+                detail.push_str(&format!(" · {} ms", latency.as_millis()));
+            }
+            providers = providers.add(widget::settings::item(
+                health.provider,
+                widget::row::with_children(vec![
+                    widget::text::body("●")
+                        .class(cosmic::theme::Text::Color(color))
+                        .into(),
+                    widget::text::caption(detail).into(),
+                ])
+                .spacing(6)
+                .align_y(cosmic::iced::Alignment::Center),
+            ));
+        }
+
+        let running = self
+            .diagnostics
+            .runs
+            .iter()
+            .any(|run| run.started.is_some());
+
+        let mut benchmark =
+            widget::settings::section()
+                .title("Benchmark")
+                .add(widget::settings::item(
+                    format!("{} prompts per model", SUITE.len()),
+                    widget::button::standard("Run")
+                        .on_press_maybe((!running).then_some(Message::RunBenchmark)),
+                ));
+
+        for run in &self.diagnostics.runs {
+            let timings: Vec<Duration> = run.results.iter().flatten().copied().collect();
+            let failures = run.results.iter().filter(|result| result.is_none()).count();
+            let summary = if !run.finished() {
+                format!("running {}/{}", run.results.len() + 1, SUITE.len())
+            } else if timings.is_empty() {
+                "all prompts failed".to_string()
+            } else {
+                let total: Duration = timings.iter().sum();
+                format!(
+                    "avg {:.2} s · min {:.2} s · max {:.2} s{}",
+                    (total / timings.len() as u32).as_secs_f32(),
+                    timings
+                        .iter()
+                        .min()
+                        .copied()
+                        .unwrap_or_default()
+                        .as_secs_f32(),
+                    timings
+                        .iter()
+                        .max()
+                        .copied()
+                        .unwrap_or_default()
+                        .as_secs_f32(),
+                    if failures > 0 {
+                        format!(" · {failures} failed")
+                    } else {
+                        String::new()
+                    }
+                )
+            };
+
+            let mut details = column!(widget::text::body(summary)).spacing(2);
+            if let Some(error) = &run.last_error {
+                details = details.push(widget::text::caption(error.as_str()));
+            }
+            benchmark = benchmark.add(widget::settings::item(run.model.as_str(), details));
+        }
+
+        let mut report =
+            widget::settings::section()
+                .title("Debug report")
+                .add(widget::settings::item(
+                    "Version, settings without secrets, the last request without its text, and \
+                 recent log messages",
+                    widget::button::standard(if self.diagnostics.reporting {
+                        "Working…"
+                    } else {
+                        "Create"
+                    })
+                    .on_press_maybe((!self.diagnostics.reporting).then_some(Message::CreateReport)),
+                ));
+        if let Some(status) = &self.diagnostics.report_status {
+            report = report.add(widget::text::caption(status.as_str()));
+        }
+
+        widget::scrollable(widget::settings::view_column(vec![
+            providers.into(),
+            benchmark.into(),
+            report.into(),
+        ]))
+        .height(Length::Fill)
+        .into()
+    }
+
+    pub(super) fn update_diagnostics(
+        &mut self,
+        message: Message,
+    ) -> Task<cosmic::Action<super::Message>> {
+        match message {
+            Message::CheckHealth => {
+                self.diagnostics.checking = true;
+                let config = self.config.for_workspace();
+                return cosmic::task::future(async move {
+                    super::Message::Diagnostics(Message::HealthChecked(
+                        models::check_health(config).await,
+                    ))
+                });
+            }
+            Message::HealthChecked(health) => {
+                self.diagnostics.checking = false;
+                self.diagnostics.health = health;
+            }
+            Message::RunBenchmark => {
+                let mut models = vec![gemini::model_name(&self.config.gemini)];
+                for model in self.config.compare_models() {
+                    let mut settings = self.config.gemini.clone();
+                    settings.model = model;
+                    let model = gemini::model_name(&settings);
+                    if !models.contains(&model) {
+                        models.push(model);
+                    }
+                }
+
+                self.diagnostics.runs = models
+                    .into_iter()
+                    .map(|model| Run {
+                        model,
+                        results: Vec::new(),
+                        started: None,
+                        last_error: None,
+                    })
+                    .collect();
+                return self.run_benchmark_step();
+            }
+            Message::BenchmarkStep(index, cost, message) => {
+                self.add_spending(cost);
+                if let Some(run) = self.diagnostics.runs.get_mut(index)
+                    && let Some(started) = run.started.take()
+                {
+                    let latency = started.elapsed();
+                    match message.into_result() {
+                        Ok(_) => run.results.push(Some(latency)),
+                        Err(error) => {
+                            run.results.push(None);
+                            run.last_error = Some(error);
+                        }
+                    }
+                }
+                return self.run_benchmark_step();
+            }
+            Message::CreateReport => {
+                let stamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                let Some(folder) = dirs::download_dir().or_else(dirs::home_dir) else {
+                    self.diagnostics.report_status =
+                        Some("Failed to create the report: no folder to save it in".to_string());
+                    return Task::none();
+                };
+                let path = folder.join(format!("cosmic-ext-clankers-debug-{stamp}.zip"));
+                let profile = self.profile.name().map(String::from);
+                let config = self.config.clone();
+                let last_request = self
+                    .last_reply
+                    .as_ref()
+                    .map(|reply| reply.raw_request.clone());
+                self.diagnostics.reporting = true;
+
+                return cosmic::task::future(async move {
+                    let result = tokio::task::spawn_blocking(move || {
+                        report::create(&path, profile.as_deref(), &config, last_request.as_deref())
+                            .map(|()| path)
+                    })
+                    .await
+                    .map_err(|err| err.to_string())
+                    .and_then(|result| result.map_err(|err| err.to_string()));
+                    super::Message::Diagnostics(Message::ReportCreated(result))
+                });
+            }
+            Message::ReportCreated(result) => {
+                self.diagnostics.reporting = false;
+                self.diagnostics.report_status = Some(match result {
+                    Ok(path) => format!("Saved to {}", path.display()),
+                    Err(err) => format!("Failed to create the report: {err}"),
+                });
+            }
+        }
+
+        Task::none()
+    }
+
+    /// Sends the next prompt of the suite. Models run one after another so they
+    /// don't compete for bandwidth.
+    fn run_benchmark_step(&mut self) -> Task<cosmic::Action<super::Message>> {
+        let Some((index, run)) = self
+            .diagnostics
+            .runs
+            .iter_mut()
+            .enumerate()
+            .find(|(_, run)| !run.finished())
+        else {
+            return Task::none();
+        };
+
+        let history = Arc::new(vec![Chat::user(SUITE[run.results.len()])]);
+        let mut config = self.config.clone();
+        config.provider = Provider::Gemini;
+        config.gemini.model.clone_from(&run.model);
+        run.started = Some(Instant::now());
+
+        cosmic::task::future(async move {
+            let message = models::get_response(Arc::clone(&history), config.clone()).await;
+            let cost = models::message_cost(&config, &history, &message);
+            super::Message::Diagnostics(Message::BenchmarkStep(index, cost, message))
+        })
+    }
+}
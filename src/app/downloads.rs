@@ -0,0 +1,259 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Downloads page: fetches GGUF models and manages the ones on disk.
+
+use super::AppModel;
+use crate::config::Provider;
+use crate::models::{self, local, local::download};
+use cosmic::iced::widget::column;
+use cosmic::iced::{Alignment, Length};
+use cosmic::prelude::*;
+use cosmic::widget;
+use futures_util::StreamExt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// UI state of the downloads page.
+#[derive(Default)]
+pub struct State {
+    /// URL or Hugging Face path of the model to fetch.
+    source: String,
+    /// Expected SHA-256 of the file, optional.
+    sha256: String,
+    active: Option<Active>,
+    /// Outcome of the last download or removal.
+    status: Option<String>,
+    /// Installed models and their sizes in bytes.
+    models: Vec<(PathBuf, u64)>,
+}
+
+struct Active {
+    received: u64,
+    total: Option<u64>,
+    cancel: Arc<AtomicBool>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    SourceChanged(String),
+    Sha256Changed(String),
+    Start,
+    Cancel,
+    Download(download::Event),
+    Use(usize),
+    Remove(usize),
+}
+
+impl AppModel {
+    pub(super) fn downloads_view(&self) -> cosmic::Element<'_, Message> {
+        let state = &self.downloads;
+
+        let mut fetch = widget::settings::section()
+            .title("Download")
+            .add(
+                widget::text_input("owner/repo/model.gguf or URL", &state.source)
+                    .on_input(Message::SourceChanged)
+                    .on_submit(|_| Message::Start),
+            )
+            .add(
+                widget::text_input("SHA-256 (optional)", &state.sha256)
+                    .on_input(Message::Sha256Changed),
+            );
+        if let Some(active) = &state.active {
+            let progress = match active.total {
+                Some(total) if total > 0 => {
+                    format!("{} of {}", format_size(active.received), format_size(total))
+                }
+                _ => format_size(active.received),
+            };
+            fetch = fetch.add(
+                widget::row::with_children(vec![
+                    widget::progress_bar(
+                        0.0..=1.0,
+                        active
+                            .total
+                            .filter(|total| *total > 0)
+                            .map_or(0.0, |total| active.received as f32 / total as f32),
+                    )
+                    .width(Length::Fill)
+                    .into(),
+                    widget::text::caption(progress).into(),
+                    widget::button::standard("Cancel")
+                        .on_press(Message::Cancel)
+                        .into(),
+                ])
+                .spacing(8)
+                .align_y(Alignment::Center),
+            );
+        } else {
+            fetch = fetch.add(widget::button::suggested("Download").on_press(Message::Start));
+        }
+        if let Some(status) = &state.status {
+            fetch = fetch.add(widget::text::caption(status.as_str()));
+        }
+
+        let used: u64 = state.models.iter().map(|(_, size)| size).sum();
+        let mut installed =
+            widget::settings::section().title(format!("Installed models ({})", format_size(used)));
+        if state.models.is_empty()
+            && let Some(dir) = local::models_dir()
+        {
+            installed = installed.add(widget::text::caption(format!(
+                "No models in {}",
+                dir.display()
+            )));
+        }
+        for (index, (path, size)) in state.models.iter().enumerate() {
+            let selected = self.config.provider == Provider::Local
+                && path.as_os_str() == self.config.local.model_path.trim();
+            installed = installed.add(widget::settings::item(
+                path.file_name().unwrap_or_default().to_string_lossy(),
+                widget::row::with_children(vec![
+                    widget::text::caption(format_size(*size)).into(),
+                    widget::button::standard(if selected { "In use" } else { "Use" })
+                        .on_press_maybe((!selected).then_some(Message::Use(index)))
+                        .into(),
+                    widget::button::icon(widget::icon::from_name("edit-delete-symbolic"))
+                        .on_press(Message::Remove(index))
+                        .into(),
+                ])
+                .spacing(8)
+                .align_y(Alignment::Center),
+            ));
+        }
+
+        widget::scrollable(widget::settings::view_column(vec![
+            fetch.into(),
+            installed.into(),
+        ]))
+        .height(Length::Fill)
+        .into()
+    }
+
+    pub(super) fn update_downloads(
+        &mut self,
+        message: Message,
+    ) -> Task<cosmic::Action<super::Message>> {
+        match message {
+            Message::SourceChanged(source) => {
+                self.downloads.source = source;
+            }
+            Message::Sha256Changed(sha256) => {
+                self.downloads.sha256 = sha256;
+            }
+            Message::Start => {
+                if self.downloads.active.is_some() {
+                    return Task::none();
+                }
+                if let Err(err) = models::ensure_network_allowed(&self.config) {
+                    self.downloads.status = Some(err);
+                    return Task::none();
+                }
+                let url = match download::resolve_url(&self.downloads.source) {
+                    Ok(url) => url,
+                    Err(err) => {
+                        self.downloads.status = Some(err);
+                        return Task::none();
+                    }
+                };
+
+                let cancel = Arc::new(AtomicBool::new(false));
+                self.downloads.active = Some(Active {
+                    received: 0,
+                    total: None,
+                    cancel: cancel.clone(),
+                });
+                self.downloads.status = None;
+                let sha256 = Some(self.downloads.sha256.clone());
+                return cosmic::task::stream(
+                    download::download(url, sha256, cancel)
+                        .map(|event| super::Message::Downloads(Message::Download(event))),
+                );
+            }
+            Message::Cancel => {
+                if let Some(active) = &self.downloads.active {
+                    active.cancel.store(true, Ordering::Relaxed);
+                }
+            }
+            Message::Download(download::Event::Progress { received, total }) => {
+                if let Some(active) = &mut self.downloads.active {
+                    active.received = received;
+                    active.total = total;
+                }
+            }
+            Message::Download(download::Event::Finished(result)) => {
+                self.downloads.active = None;
+                self.downloads.status = Some(match result {
+                    Ok((path, sha256)) => {
+                        if self.config.local.model_path.trim().is_empty() {
+                            self.config.local.model_path = path.to_string_lossy().into_owned();
+                            self.save_config();
+                        }
+                        self.downloads.source.clear();
+                        self.downloads.sha256.clear();
+                        format!(
+                            "Downloaded {}, SHA-256 {sha256}",
+                            path.file_name().unwrap_or_default().to_string_lossy()
+                        )
+                    }
+                    Err(err) => err,
+                });
+                self.refresh_local_models();
+            }
+            Message::Use(index) => {
+                if let Some((path, _)) = self.downloads.models.get(index) {
+                    self.config.provider = Provider::Local;
+                    self.config.local.model_path = path.to_string_lossy().into_owned();
+                    self.save_config();
+                }
+            }
+            Message::Remove(index) => {
+                if let Some((path, _)) = self.downloads.models.get(index) {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    self.downloads.status = Some(match std::fs::remove_file(path) {
+                        Ok(()) => format!("Removed {name}"),
+                        Err(err) => format!("Could not remove {name}: {err}"),
+                    });
+                    if path.as_os_str() == self.config.local.model_path.trim() {
+                        self.config.local.model_path.clear();
+                        self.save_config();
+                    }
+                }
+                self.refresh_local_models();
+            }
+        }
+
+        Task::none()
+    }
+
+    /// Rescans the installed models for the downloads page.
+    pub(super) fn refresh_installed_models(&mut self) {
+        self.downloads.models = local::installed_models()
+            .into_iter()
+            .map(|path| {
+                let size = std::fs::metadata(&path)
+                    .map(|meta| meta.len())
+                    .unwrap_or_default();
+                (path, size)
+            })
+            .collect();
+    }
+}
+
+/// Human readable size, e.g. `4.1 GB`.
+pub(super) fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
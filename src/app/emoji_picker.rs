@@ -0,0 +1,81 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Emoji picker shown above the prompt.
+
+use super::AppModel;
+use crate::emoji;
+use cosmic::iced::Length;
+use cosmic::prelude::*;
+use cosmic::widget;
+
+/// Most emoji shown at once, the search narrows them down.
+const MAX_SHOWN: usize = 120;
+
+#[derive(Default)]
+pub struct State {
+    open: bool,
+    query: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Toggle,
+    QueryChanged(String),
+    Pick(&'static str),
+}
+
+impl AppModel {
+    pub(super) fn emoji_picker_view(&self) -> Option<cosmic::Element<'_, Message>> {
+        if !self.emoji_picker.open {
+            return None;
+        }
+
+        let emoji: Vec<cosmic::Element<'_, Message>> = emoji::search(&self.emoji_picker.query)
+            .take(MAX_SHOWN)
+            .map(|emoji| {
+                widget::button::text(emoji.as_str())
+                    .on_press(Message::Pick(emoji.as_str()))
+                    .tooltip(emoji.name())
+                    .into()
+            })
+            .collect();
+
+        Some(
+            widget::container(
+                widget::column::with_children(vec![
+                    widget::search_input("Search emoji", &self.emoji_picker.query)
+                        .on_input(Message::QueryChanged)
+                        .into(),
+                    widget::scrollable(widget::flex_row(emoji).spacing(2))
+                        .height(Length::Fixed(160.0))
+                        .into(),
+                ])
+                .spacing(8),
+            )
+            .class(cosmic::theme::Container::Card)
+            .padding(8)
+            .width(Length::Fill)
+            .into(),
+        )
+    }
+
+    pub(super) fn update_emoji_picker(
+        &mut self,
+        message: Message,
+    ) -> Task<cosmic::Action<super::Message>> {
+        match message {
+            Message::Toggle => {
+                self.emoji_picker.open = !self.emoji_picker.open;
+                self.emoji_picker.query.clear();
+            }
+            Message::QueryChanged(query) => {
+                self.emoji_picker.query = query;
+            }
+            Message::Pick(emoji) => {
+                self.input_text.push_str(emoji);
+            }
+        }
+
+        Task::none()
+    }
+}
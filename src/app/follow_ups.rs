@@ -0,0 +1,120 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Suggested follow-up questions shown under the latest answer.
+//!
+//! They come from a second, text-only request in the background queue once
+//! the answer has arrived, so the answer itself stays untouched.
+
+use super::AppModel;
+use super::background::Job;
+use cosmic::prelude::*;
+use cosmic::widget;
+
+const MAX_SUGGESTIONS: usize = 3;
+/// Longer lines are likely prose rather than a question.
+const MAX_LENGTH: usize = 120;
+
+const PROMPT: &str = "Suggest up to three short follow-up questions I could ask next about \
+    this conversation. Write each question on its own line, without numbering or any other text.";
+
+#[derive(Default)]
+pub struct State {
+    suggestions: Vec<String>,
+    /// Length of the history the suggestions were made for.
+    for_len: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Generated(usize, Result<Vec<String>, String>),
+    Pick(String),
+}
+
+impl AppModel {
+    /// Suggestions for the latest answer, if they are still current.
+    pub(super) fn follow_ups_view(&self) -> Option<cosmic::Element<'_, Message>> {
+        let state = &self.follow_ups;
+        if state.suggestions.is_empty() || state.for_len != self.chat_history.len() {
+            return None;
+        }
+
+        let chips = state
+            .suggestions
+            .iter()
+            .map(|suggestion| {
+                widget::button::standard(suggestion.as_str())
+                    .on_press(Message::Pick(suggestion.clone()))
+                    .into()
+            })
+            .collect();
+        Some(widget::flex_row(chips).spacing(6).into())
+    }
+
+    /// Asks the model for follow-up questions to the latest answer.
+    pub(super) fn request_follow_ups(&mut self) -> Task<cosmic::Action<super::Message>> {
+        self.follow_ups.suggestions.clear();
+        if !self.config.follow_up_suggestions || self.chat_history.is_empty() {
+            return Task::none();
+        }
+
+        let for_len = self.chat_history.len();
+        self.queue_background(Job::FollowUps(for_len), PROMPT)
+    }
+
+    pub(super) fn update_follow_ups(
+        &mut self,
+        message: Message,
+    ) -> Task<cosmic::Action<super::Message>> {
+        match message {
+            Message::Generated(for_len, result) => match result {
+                Ok(suggestions) => {
+                    self.follow_ups.suggestions = suggestions;
+                    self.follow_ups.for_len = for_len;
+                }
+                Err(err) => eprintln!("failed to suggest follow-up questions: {err}"),
+            },
+            Message::Pick(suggestion) => {
+                self.follow_ups.suggestions.clear();
+                return self.submit(suggestion);
+            }
+        }
+
+        Task::none()
+    }
+}
+
+/// One question per line, with any list markers the model added anyway removed.
+pub(super) fn parse(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| {
+            line.trim()
+                .trim_start_matches(|c: char| {
+                    c.is_ascii_digit() || matches!(c, '-' | '*' | '.' | ')' | '•')
+                })
+                .trim()
+        })
+        .filter(|line| !line.is_empty() && line.chars().count() <= MAX_LENGTH)
+        .take(MAX_SUGGESTIONS)
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_markers_are_removed() {
+        assert_eq!(
+            parse("1. What next?\n- How?\n\n* Why?"),
+            ["What next?", "How?", "Why?"]
+        );
+    }
+
+    #[test]
+    fn long_and_extra_questions_are_dropped() {
+        let long = "x".repeat(MAX_LENGTH + 1);
+        let text = format!("{long}\nOne?\n• Two?\n3) Three?\nFour?");
+        assert_eq!(parse(&text), ["One?", "Two?", "Three?"]);
+    }
+}
@@ -0,0 +1,144 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Continues conversations someone else exported, from conversation files
+//! dropped onto the popup or opened in the file manager.
+//!
+//! The conversation in view is cleared first, so it can be brought back with
+//! the usual undo.
+
+use super::{AppModel, ParkedConversation};
+use crate::conversation_file::{self, ConversationFile};
+use cosmic::iced::clipboard::mime::AllowedMimeTypes;
+use cosmic::iced::widget::markdown::Url;
+use cosmic::iced::{Alignment, Length};
+use cosmic::prelude::*;
+use cosmic::widget;
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[derive(Default)]
+pub struct State {
+    /// Why the last file couldn't be opened.
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// Files dropped onto the popup, `None` if they couldn't be read.
+    Dropped(Option<DroppedFiles>),
+    DismissError,
+}
+
+/// Files dragged from a file manager, which offers them as a URI list.
+#[derive(Debug, Clone)]
+pub struct DroppedFiles(Vec<PathBuf>);
+
+impl AllowedMimeTypes for DroppedFiles {
+    fn allowed() -> Cow<'static, [String]> {
+        Cow::Owned(vec!["text/uri-list".to_string()])
+    }
+}
+
+impl TryFrom<(Vec<u8>, String)> for DroppedFiles {
+    type Error = String;
+
+    fn try_from((data, _mime): (Vec<u8>, String)) -> Result<Self, Self::Error> {
+        let list = String::from_utf8(data).map_err(|err| err.to_string())?;
+        Ok(Self(
+            list.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .filter_map(|line| Url::parse(line).ok()?.to_file_path().ok())
+                .collect(),
+        ))
+    }
+}
+
+impl AppModel {
+    /// Why the last conversation file couldn't be opened.
+    pub(super) fn import_error_view(&self) -> Option<cosmic::Element<'_, Message>> {
+        let error = self.import.error.as_ref()?;
+        Some(
+            widget::container(
+                widget::row::with_children(vec![
+                    widget::text::body(format!("Could not open the conversation: {error}"))
+                        .width(Length::Fill)
+                        .into(),
+                    widget::button::text("Dismiss")
+                        .on_press(Message::DismissError)
+                        .into(),
+                ])
+                .spacing(8)
+                .align_y(Alignment::Center),
+            )
+            .class(cosmic::theme::Container::Card)
+            .padding(12)
+            .width(Length::Fill)
+            .into(),
+        )
+    }
+
+    /// Continues the conversations opened in the file manager since the popup
+    /// was last open, ending with the latest.
+    pub(super) fn import_inbox(&mut self) -> Task<cosmic::Action<super::Message>> {
+        let Some(dir) = self.profile.data_dir() else {
+            return Task::none();
+        };
+        let tasks: Vec<_> = conversation_file::take_inbox(&dir)
+            .into_iter()
+            .map(|file| self.continue_conversation(file))
+            .collect();
+        Task::batch(tasks)
+    }
+
+    pub(super) fn update_import(
+        &mut self,
+        message: Message,
+    ) -> Task<cosmic::Action<super::Message>> {
+        match message {
+            Message::Dropped(files) => {
+                let Some(path) = files.and_then(|DroppedFiles(paths)| {
+                    paths.into_iter().find(|path| {
+                        path.to_string_lossy()
+                            .ends_with(conversation_file::EXTENSION)
+                    })
+                }) else {
+                    self.import.error = Some(format!(
+                        "only {} files can be dropped here",
+                        conversation_file::EXTENSION
+                    ));
+                    return Task::none();
+                };
+                return self.continue_conversation(conversation_file::read(&path));
+            }
+            Message::DismissError => self.import.error = None,
+        }
+
+        Task::none()
+    }
+
+    /// Shows the conversation in `file` in place of the current one.
+    fn continue_conversation(
+        &mut self,
+        file: Result<ConversationFile, String>,
+    ) -> Task<cosmic::Action<super::Message>> {
+        let file = match file {
+            Ok(file) => file,
+            Err(err) => {
+                self.import.error = Some(err);
+                return Task::none();
+            }
+        };
+        self.import.error = None;
+        let task = self.clear_conversation();
+        self.show_conversation(ParkedConversation {
+            history: Arc::new(file.history),
+            title: file.title,
+            ..ParkedConversation::default()
+        });
+        self.page = super::Page::Chat;
+        self.save_session();
+        task
+    }
+}
@@ -0,0 +1,188 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The debug inspector, showing the raw data of the last exchange. Its request
+//! can be edited and sent again, to see how the provider answers and how the
+//! answer parses without going through the conversation.
+
+use super::AppModel;
+use crate::models::{self, Replay, gemini};
+use cosmic::iced::widget::{column, text_editor};
+use cosmic::iced::{Alignment, Length};
+use cosmic::prelude::*;
+use cosmic::widget;
+
+#[derive(Default)]
+pub struct State {
+    /// Request body to replay, starting as the last one sent.
+    body: text_editor::Content,
+    /// A replay is on its way.
+    replaying: bool,
+    /// Response to the last replay.
+    replay: Option<Replay>,
+}
+
+impl State {
+    /// Puts `raw_request` in the editor, pretty-printed when it is JSON, and
+    /// drops the previous replay.
+    pub(super) fn load(&mut self, raw_request: &str) {
+        let body = serde_json::from_str::<serde_json::Value>(raw_request)
+            .and_then(|value| serde_json::to_string_pretty(&value))
+            .unwrap_or_else(|_| raw_request.to_string());
+        self.body = text_editor::Content::with_text(&body);
+        self.replay = None;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Edit(text_editor::Action),
+    /// Puts the last request sent back in the editor.
+    Reset,
+    Replay,
+    Replayed(Replay),
+}
+
+impl AppModel {
+    pub(super) fn inspector_view(&self) -> cosmic::Element<'_, Message> {
+        let Some(reply) = &self.last_reply else {
+            return widget::container(widget::text::body("Nothing to inspect yet"))
+                .center(Length::Fill)
+                .into();
+        };
+        let state = &self.inspector;
+
+        let mut sections = vec![
+            widget::settings::section()
+                .title("Request")
+                .add(
+                    text_editor(&state.body)
+                        .on_action(Message::Edit)
+                        .font(cosmic::font::mono())
+                        .height(Length::Fixed(240.0)),
+                )
+                .add(
+                    widget::row::with_children(vec![
+                        widget::text::caption(format!(
+                            "Replays go to {}",
+                            models::model_name(&self.config.for_workspace())
+                        ))
+                        .width(Length::Fill)
+                        .into(),
+                        widget::button::standard("Reset")
+                            .on_press(Message::Reset)
+                            .into(),
+                        widget::button::suggested(if state.replaying {
+                            "Replaying…"
+                        } else {
+                            "Replay"
+                        })
+                        .on_press_maybe((!state.replaying).then_some(Message::Replay))
+                        .into(),
+                    ])
+                    .spacing(8)
+                    .align_y(Alignment::Center),
+                )
+                .into(),
+        ];
+
+        if let Some(replay) = &state.replay {
+            let outcome = match &replay.outcome {
+                gemini::Message::Response(reply) => format!("Parsed, answer: {}", reply.text),
+                gemini::Message::EmptyResponse => "Parsed, without an answer".to_string(),
+                gemini::Message::ApiResultParsingError(err) => format!("Could not parse: {err}"),
+                outcome => format!(
+                    "Failed: {}",
+                    outcome.clone().into_result().err().unwrap_or_default()
+                ),
+            };
+            let mut section = widget::settings::section()
+                .title("Replay")
+                .add(widget::text::body(outcome));
+            if !replay.raw_response.is_empty() {
+                section = section.add(widget::text::monotext(&replay.raw_response));
+            }
+            sections.push(section.into());
+        }
+
+        sections.push(
+            widget::settings::section()
+                .title("Response")
+                .add(widget::text::monotext(&reply.raw_response))
+                .into(),
+        );
+
+        if !reply.parse_issues.is_empty() {
+            let mut issues = widget::settings::section().title("Parse issues");
+            for issue in &reply.parse_issues {
+                issues = issues.add(widget::text::monotext(issue));
+            }
+            sections.push(issues.into());
+        }
+
+        if !reply.logprobs.is_empty() {
+            let mut tokens = column!().spacing(4);
+            for token in &reply.logprobs {
+                let alternatives = token
+                    .alternatives
+                    .iter()
+                    .map(|(alternative, logprob)| {
+                        format!("{alternative:?} {:.1}%", logprob.exp() * 100.0)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                tokens = tokens.push(widget::text::monotext(format!(
+                    "{:?} {:.1}%  {alternatives}",
+                    token.token,
+                    token.log_probability.exp() * 100.0
+                )));
+            }
+            sections.push(
+                widget::settings::section()
+                    .title("Token probabilities")
+                    .add(tokens)
+                    .into(),
+            );
+        }
+
+        widget::scrollable(widget::settings::view_column(sections))
+            .height(Length::Fill)
+            .into()
+    }
+
+    pub(super) fn update_inspector(
+        &mut self,
+        message: Message,
+    ) -> Task<cosmic::Action<super::Message>> {
+        match message {
+            Message::Edit(action) => {
+                self.inspector.body.perform(action);
+            }
+            Message::Reset => {
+                if let Some(reply) = &self.last_reply {
+                    self.inspector.load(&reply.raw_request);
+                }
+            }
+            Message::Replay => {
+                if self.inspector.replaying
+                    || !self.config.debug_inspector
+                    || self.config.is_locked()
+                {
+                    return Task::none();
+                }
+                self.inspector.replaying = true;
+                let body = self.inspector.body.text();
+                let config = self.config.for_workspace();
+                return cosmic::task::future(async move {
+                    super::Message::Inspector(Message::Replayed(models::replay(body, config).await))
+                });
+            }
+            Message::Replayed(replay) => {
+                self.inspector.replaying = false;
+                self.inspector.replay = Some(replay);
+            }
+        }
+
+        Task::none()
+    }
+}
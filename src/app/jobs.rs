@@ -0,0 +1,252 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Jobs that run on their own, scheduled backups and syncs and batch runs, with
+//! their status and controls to pause them or run them now.
+
+use super::{AppModel, settings::format_age};
+use cosmic::iced::{Alignment, Length};
+use cosmic::prelude::*;
+use cosmic::widget;
+use std::collections::HashSet;
+use std::time::{Duration, SystemTime};
+
+/// How often a scheduled backup is checked for being due.
+pub(super) const BACKUP_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Time between scheduled syncs.
+pub(super) const SYNC_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Longest snippet of a result, in characters.
+const MAX_SNIPPET: usize = 120;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Job {
+    Backup,
+    Sync,
+    Batch,
+}
+
+#[derive(Default)]
+pub struct State {
+    /// Scheduled jobs skipped until resumed.
+    paused: HashSet<Job>,
+    /// When the scheduled sync last came up.
+    last_sync_tick: Option<SystemTime>,
+}
+
+impl State {
+    pub(super) fn is_paused(&self, job: Job) -> bool {
+        self.paused.contains(&job)
+    }
+
+    /// The scheduled sync came up now, so the next one is an interval away.
+    pub(super) fn sync_ticked(&mut self) {
+        self.last_sync_tick = Some(SystemTime::now());
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Pause(Job),
+    Resume(Job),
+    RunNow(Job),
+}
+
+impl AppModel {
+    pub(super) fn jobs_view(&self) -> cosmic::Element<'_, Message> {
+        let mut cards = Vec::new();
+
+        if let Some(period) = self.config.backup.interval.period() {
+            let (running, result, newest) = self.backup_progress();
+            let paused = self.jobs.is_paused(Job::Backup);
+            let mut next = match newest.and_then(|newest| newest.checked_add(period)) {
+                _ if paused => "Paused".to_string(),
+                Some(due) if due > SystemTime::now() => {
+                    format!("Next backup {}", format_until(due))
+                }
+                _ => "Due, runs within the hour".to_string(),
+            };
+            if let Some(newest) = newest {
+                next.push_str(&format!(
+                    " · last one {}",
+                    format_age(newest).to_lowercase()
+                ));
+            }
+            cards.push(job_card(
+                "Scheduled backup",
+                if running { "Running" } else { "Waiting" },
+                next,
+                result,
+                Job::Backup,
+                Some(paused),
+                !running && !self.config.backup.folder.trim().is_empty(),
+            ));
+        }
+
+        if self.config.sync.enabled {
+            let (running, result) = self.sync_progress();
+            let paused = self.jobs.is_paused(Job::Sync);
+            let next = match self.jobs.last_sync_tick {
+                _ if paused => "Paused".to_string(),
+                Some(last) => format!("Next sync {}", format_until(last + SYNC_INTERVAL)),
+                None => "Next sync within 15 minutes".to_string(),
+            };
+            cards.push(job_card(
+                "Sync",
+                if running { "Running" } else { "Waiting" },
+                next,
+                result,
+                Job::Sync,
+                Some(paused),
+                !running,
+            ));
+        }
+
+        let (finished, total, last) = self.batch_progress();
+        if total > 0 {
+            let running = self.batch_is_running();
+            let paused = self.batch_is_paused();
+            let status = if running && paused {
+                "Pausing after the current prompt"
+            } else if running {
+                "Running"
+            } else if paused && finished < total {
+                "Paused"
+            } else if finished < total {
+                "Stopped"
+            } else {
+                "Finished"
+            };
+            cards.push(job_card(
+                "Batch",
+                status,
+                format!("{finished} of {total} prompts answered"),
+                last.as_deref(),
+                Job::Batch,
+                // Only a run that is going or has prompts left can be paused.
+                if running && !paused {
+                    Some(false)
+                } else if paused && finished < total {
+                    Some(true)
+                } else {
+                    None
+                },
+                !running,
+            ));
+        }
+
+        if cards.is_empty() {
+            return widget::text::body(
+                "No jobs. Scheduled backups and syncs are set up on the settings page, and \
+                 batch runs on the batch page.",
+            )
+            .into();
+        }
+
+        widget::scrollable(widget::column::with_children(cards).spacing(8))
+            .height(Length::Fill)
+            .into()
+    }
+
+    pub(super) fn update_jobs(&mut self, message: Message) -> Task<cosmic::Action<super::Message>> {
+        // Nothing runs outside the chat while the policy is locked.
+        if self.config.is_locked() && matches!(message, Message::Resume(_) | Message::RunNow(_)) {
+            return Task::none();
+        }
+        match message {
+            Message::Pause(Job::Batch) => {
+                self.pause_batch();
+            }
+            Message::Pause(job) => {
+                self.jobs.paused.insert(job);
+            }
+            Message::Resume(Job::Batch) | Message::RunNow(Job::Batch) => {
+                return self.resume_batch();
+            }
+            Message::Resume(job) => {
+                self.jobs.paused.remove(&job);
+            }
+            Message::RunNow(Job::Backup) => {
+                return self.start_backup(true);
+            }
+            Message::RunNow(Job::Sync) => {
+                return self.start_sync();
+            }
+        }
+
+        Task::none()
+    }
+}
+
+/// A job with its controls. `paused` is `None` for a job that can't be paused now.
+fn job_card<'a>(
+    name: &'a str,
+    status: &'a str,
+    next: String,
+    result: Option<&str>,
+    job: Job,
+    paused: Option<bool>,
+    can_run: bool,
+) -> cosmic::Element<'a, Message> {
+    let mut column = widget::column::with_children(vec![
+        widget::row::with_children(vec![
+            widget::text::heading(name).width(Length::Fill).into(),
+            widget::text::caption(status).into(),
+        ])
+        .align_y(Alignment::Center)
+        .into(),
+        widget::text::caption(next).into(),
+    ])
+    .spacing(4);
+    if let Some(result) = result {
+        column = column.push(widget::text::body(snippet(result)));
+    }
+    let mut controls = Vec::new();
+    match paused {
+        Some(true) => controls.push(
+            widget::button::standard("Resume")
+                .on_press(Message::Resume(job))
+                .into(),
+        ),
+        Some(false) => controls.push(
+            widget::button::standard("Pause")
+                .on_press(Message::Pause(job))
+                .into(),
+        ),
+        None => {}
+    }
+    controls.push(
+        widget::button::standard("Run now")
+            .on_press_maybe(can_run.then_some(Message::RunNow(job)))
+            .into(),
+    );
+    column = column.push(widget::row::with_children(controls).spacing(8));
+
+    widget::container(column)
+        .class(cosmic::theme::Container::Card)
+        .padding(12)
+        .width(Length::Fill)
+        .into()
+}
+
+/// First line of `text`, cut to `MAX_SNIPPET` characters.
+fn snippet(text: &str) -> String {
+    let line = text.trim().lines().next().unwrap_or_default();
+    match line.char_indices().nth(MAX_SNIPPET) {
+        Some((end, _)) => format!("{}…", line[..end].trim_end()),
+        None => line.to_string(),
+    }
+}
+
+fn format_until(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(SystemTime::now())
+        .unwrap_or_default()
+        .as_secs();
+    match seconds {
+        0..60 => "in under a minute".into(),
+        60..3600 => format!("in {} min", seconds / 60),
+        3600..86400 => format!("in {} h", seconds / 3600),
+        _ => format!("in {} days", seconds / 86400),
+    }
+}
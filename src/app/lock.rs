@@ -0,0 +1,132 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Locks a finished conversation kept for reference, so typing or regenerating
+//! can't change it by accident.
+//!
+//! A locked conversation is continued in a copy, which keeps the original to
+//! go back to.
+
+use super::{AppModel, ParkedConversation};
+use cosmic::iced::{Alignment, Length};
+use cosmic::prelude::*;
+use cosmic::widget;
+
+#[derive(Default, Clone)]
+pub struct State {
+    pub(super) locked: bool,
+    /// The locked conversation this one was copied from.
+    pub(super) original: Option<Box<ParkedConversation>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Toggle,
+    /// Continues the locked conversation in a copy.
+    Duplicate,
+    /// Shows the locked conversation the one in view was copied from.
+    BackToOriginal,
+}
+
+impl AppModel {
+    /// Buttons in the header for locking the conversation and going back to
+    /// the one it was copied from.
+    pub(super) fn lock_buttons(&self) -> Vec<cosmic::Element<'_, Message>> {
+        let mut buttons = Vec::new();
+        if self.lock.original.is_some() {
+            buttons.push(
+                widget::button::icon(widget::icon::from_name("go-previous-symbolic"))
+                    .on_press(Message::BackToOriginal)
+                    .tooltip("Back to the original")
+                    .into(),
+            );
+        }
+        if !self.chat_history.is_empty() {
+            let (icon, tooltip) = if self.lock.locked {
+                ("changes-prevent-symbolic", "Unlock conversation")
+            } else {
+                ("changes-allow-symbolic", "Lock conversation")
+            };
+            buttons.push(
+                widget::button::icon(widget::icon::from_name(icon))
+                    .on_press_maybe((!self.is_loading).then_some(Message::Toggle))
+                    .selected(self.lock.locked)
+                    .tooltip(tooltip)
+                    .into(),
+            );
+        }
+        buttons
+    }
+
+    /// Shown in place of the prompt field while the conversation is locked.
+    pub(super) fn locked_view(&self) -> Option<cosmic::Element<'_, Message>> {
+        if !self.lock.locked {
+            return None;
+        }
+        Some(
+            widget::container(
+                widget::row::with_children(vec![
+                    widget::text::body("This conversation is locked for reference.")
+                        .width(Length::Fill)
+                        .into(),
+                    widget::button::text("Unlock")
+                        .on_press(Message::Toggle)
+                        .into(),
+                    widget::button::suggested("Duplicate to continue")
+                        .on_press(Message::Duplicate)
+                        .into(),
+                ])
+                .spacing(8)
+                .align_y(Alignment::Center),
+            )
+            .class(cosmic::theme::Container::Card)
+            .padding(12)
+            .width(Length::Fill)
+            .into(),
+        )
+    }
+
+    pub(super) fn update_lock(&mut self, message: Message) -> Task<cosmic::Action<super::Message>> {
+        match message {
+            Message::Toggle => {
+                if self.is_loading {
+                    return Task::none();
+                }
+                self.lock.locked = !self.lock.locked;
+            }
+            Message::Duplicate => {
+                if !self.lock.locked {
+                    return Task::none();
+                }
+                let copy = ParkedConversation {
+                    history: self.chat_history.clone(),
+                    pinned: super::pinned::State::with_files(self.pinned.files.clone()),
+                    template: self.config.template.clone(),
+                    tone: self.config.tone,
+                    title: self.title.as_ref().map(|title| format!("{title} (copy)")),
+                    ..ParkedConversation::default()
+                };
+                let original = self.park_conversation();
+                self.show_conversation(ParkedConversation {
+                    lock: State {
+                        locked: false,
+                        original: Some(Box::new(original)),
+                    },
+                    ..copy
+                });
+            }
+            Message::BackToOriginal => {
+                let Some(original) = self.lock.original.as_deref().cloned() else {
+                    return Task::none();
+                };
+                // The copy can still be brought back with the usual undo.
+                let task = self.clear_conversation();
+                self.show_conversation(original);
+                self.save_session();
+                return task;
+            }
+        }
+
+        self.save_session();
+        Task::none()
+    }
+}
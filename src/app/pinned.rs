@@ -0,0 +1,365 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Text files pinned to the conversation, whose current contents are sent
+//! along with every prompt.
+//!
+//! While watched, a change to any of them sends a standing prompt on its own,
+//! so e.g. a review is posted whenever the file is saved.
+//!
+//! Their lines are numbered for the model to cite, and citations in answers
+//! become links to the lines they point at.
+
+use super::{AppModel, Chat};
+use crate::config::{RedactionSettings, WatchLimits};
+use crate::redact;
+use cosmic::dialog::file_chooser;
+use cosmic::iced::Alignment;
+use cosmic::iced::widget::markdown::Url;
+use cosmic::prelude::*;
+use cosmic::widget;
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Files larger than this are cut off, so one log file can't fill the context.
+const MAX_FILE_BYTES: usize = 256 * 1024;
+
+const CITATION_INSTRUCTIONS: &str = "Lines are numbered. When your answer relies on one \
+    of these files, cite it right after the statement as [F1:12] for line 12 of file F1, \
+    or [F1:12-20] for a range.";
+
+/// A citation asked for by [`CITATION_INSTRUCTIONS`].
+static CITATION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[F(\d+):(\d+)(?:-(\d+))?\]").expect("valid citation regex"));
+
+/// How often watched files are checked for changes.
+pub(super) const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Default, Clone)]
+pub struct State {
+    pub(super) files: Vec<PathBuf>,
+    error: Option<String>,
+    /// Prompt sent whenever a pinned file changes.
+    watch_prompt: String,
+    watching: bool,
+    /// When watching was switched on.
+    watch_started: Option<Instant>,
+    /// Prompts sent for changes since the last typed one.
+    watch_prompts: u32,
+    /// Modification times the files had when last checked.
+    modified: HashMap<PathBuf, Option<SystemTime>>,
+}
+
+impl State {
+    pub(super) fn with_files(files: Vec<PathBuf>) -> Self {
+        Self {
+            files,
+            ..Self::default()
+        }
+    }
+
+    pub(super) fn is_watching(&self) -> bool {
+        self.watching && !self.files.is_empty()
+    }
+
+    pub(super) fn stop_watching(&mut self) {
+        self.watching = false;
+        self.watch_started = None;
+    }
+
+    /// A prompt was typed, so the ones sent for changes are no longer in a row.
+    pub(super) fn prompt_typed(&mut self) {
+        self.watch_prompts = 0;
+    }
+
+    /// Why watching has to stop before sending another prompt, if it has to.
+    fn limit_reached(&self, limits: WatchLimits) -> Option<String> {
+        if limits.max_prompts > 0 && self.watch_prompts >= limits.max_prompts {
+            return Some(format!(
+                "Stopped watching after {} prompts in a row",
+                self.watch_prompts
+            ));
+        }
+        let minutes = self.watch_started?.elapsed().as_secs() / 60;
+        (limits.max_minutes > 0 && minutes >= u64::from(limits.max_minutes))
+            .then(|| format!("Stopped watching after {minutes} minutes"))
+    }
+
+    /// Remembers the files as they are now, so only later changes count.
+    fn snapshot(&mut self) {
+        self.modified = self
+            .files
+            .iter()
+            .map(|path| (path.clone(), modified_at(path)))
+            .collect();
+    }
+
+    /// Whether any file changed since the last check, remembering the new times.
+    fn take_changes(&mut self) -> bool {
+        let mut changed = false;
+        for path in &self.files {
+            let now = modified_at(path);
+            if self.modified.insert(path.clone(), now) != Some(now) {
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Pick,
+    Picked(Result<Vec<PathBuf>, String>),
+    Unpin(usize),
+    WatchPromptChanged(String),
+    WatchToggled(bool),
+    CheckWatched,
+}
+
+impl AppModel {
+    /// The pinned files with buttons to unpin them.
+    pub(super) fn pinned_view(&self) -> Option<cosmic::Element<'_, Message>> {
+        let state = &self.pinned;
+        if state.files.is_empty() && state.error.is_none() {
+            return None;
+        }
+
+        let mut chips: Vec<cosmic::Element<'_, Message>> = state
+            .files
+            .iter()
+            .enumerate()
+            .map(|(index, path)| {
+                widget::row::with_children(vec![
+                    widget::icon::from_name("view-pin-symbolic").size(16).into(),
+                    widget::text::caption(path.file_name().unwrap_or_default().to_string_lossy())
+                        .into(),
+                    widget::button::icon(widget::icon::from_name("window-close-symbolic"))
+                        .on_press(Message::Unpin(index))
+                        .tooltip(format!("Unpin {}", path.display()))
+                        .into(),
+                ])
+                .spacing(4)
+                .align_y(Alignment::Center)
+                .into()
+            })
+            .collect();
+        if let Some(error) = &state.error {
+            chips.push(widget::text::caption(error.as_str()).into());
+        }
+        if state.files.is_empty() {
+            return Some(widget::flex_row(chips).spacing(8).into());
+        }
+
+        let watch = widget::row::with_children(vec![
+            widget::text_input("Ask on change, e.g. review for bugs", &state.watch_prompt)
+                .on_input(Message::WatchPromptChanged)
+                .width(cosmic::iced::Length::Fill)
+                .into(),
+            widget::text::caption("Watch").into(),
+            widget::toggler(state.watching)
+                .on_toggle(Message::WatchToggled)
+                .into(),
+        ])
+        .spacing(8)
+        .align_y(Alignment::Center);
+
+        Some(
+            widget::column::with_children(vec![
+                widget::flex_row(chips).spacing(8).into(),
+                watch.into(),
+            ])
+            .spacing(6)
+            .into(),
+        )
+    }
+
+    pub(super) fn pin_button(&self) -> cosmic::Element<'_, Message> {
+        widget::button::icon(widget::icon::from_name("view-pin-symbolic"))
+            .on_press(Message::Pick)
+            .tooltip("Pin files to the conversation")
+            .into()
+    }
+
+    pub(super) fn update_pinned(
+        &mut self,
+        message: Message,
+    ) -> Task<cosmic::Action<super::Message>> {
+        match message {
+            Message::Pick => {
+                return cosmic::task::future(async {
+                    super::Message::Pinned(Message::Picked(pick_files().await))
+                });
+            }
+            Message::Picked(result) => match result {
+                Ok(paths) => {
+                    self.pinned.error = None;
+                    for path in paths {
+                        if !self.pinned.files.contains(&path) {
+                            self.pinned.files.push(path);
+                        }
+                    }
+                    self.pinned.snapshot();
+                }
+                Err(err) => self.pinned.error = Some(err),
+            },
+            Message::Unpin(index) => {
+                if index < self.pinned.files.len() {
+                    self.pinned.files.remove(index);
+                    self.pinned.snapshot();
+                }
+            }
+            Message::WatchPromptChanged(text) => {
+                self.pinned.watch_prompt = text;
+            }
+            Message::WatchToggled(watching) => {
+                self.pinned.watching = watching && !self.pinned.watch_prompt.trim().is_empty();
+                self.pinned.watch_started = self.pinned.watching.then(Instant::now);
+                self.pinned.watch_prompts = 0;
+                self.pinned.error = None;
+                self.pinned.snapshot();
+            }
+            Message::CheckWatched => {
+                // Changes made while an answer is pending are picked up once it arrived.
+                if !self.pinned.is_watching() || self.is_loading || !self.pinned.take_changes() {
+                    return Task::none();
+                }
+                let prompt = self.pinned.watch_prompt.trim().to_string();
+                if prompt.is_empty() || self.lock.locked {
+                    return Task::none();
+                }
+                if let Some(reason) = self.pinned.limit_reached(self.config.watch_limits) {
+                    self.pinned.stop_watching();
+                    self.pinned.error = Some(reason);
+                    return Task::none();
+                }
+                self.pinned.watch_prompts += 1;
+                // Goes through the same checks as a typed prompt.
+                return Task::done(cosmic::Action::App(super::Message::SubmitInput(prompt)));
+            }
+        }
+
+        Task::none()
+    }
+}
+
+/// A copy of `history` whose latest prompt starts with the contents of `files`
+/// as they are now. Nobody reviews them before they are sent, so anything
+/// `redaction` finds in them is masked.
+pub(super) async fn with_pinned_files(
+    history: &[Chat],
+    files: &[PathBuf],
+    redaction: &RedactionSettings,
+) -> Vec<Chat> {
+    let mut context = format!(
+        "Current contents of the files pinned to this conversation. {CITATION_INSTRUCTIONS}\n"
+    );
+    for (index, path) in files.iter().enumerate() {
+        let contents = match tokio::fs::read(path).await {
+            Ok(mut data) => {
+                data.truncate(MAX_FILE_BYTES);
+                let text = String::from_utf8_lossy(&data);
+                redact::mask(&text, &redact::scan(&text, redaction))
+                    .lines()
+                    .enumerate()
+                    .map(|(line, text)| format!("{}: {text}\n", line + 1))
+                    .collect()
+            }
+            Err(err) => format!("(could not be read: {err})\n"),
+        };
+        context.push_str(&format!(
+            "\n[F{}] {}:\n```\n{contents}```\n",
+            index + 1,
+            path.display()
+        ));
+    }
+
+    let mut history = history.to_vec();
+    if let Some(prompt) = history.iter_mut().rev().find(|chat| chat.role == "user") {
+        prompt.content = format!("{context}\n{}", prompt.content);
+    }
+    history
+}
+
+/// `answer` with its citations of `files` turned into numbered links, which
+/// are listed again as sources at its end.
+pub(super) fn link_citations(answer: &str, files: &[PathBuf]) -> String {
+    let mut sources: Vec<(String, Url)> = Vec::new();
+    let linked = CITATION.replace_all(answer, |captures: &regex::Captures| {
+        let cited = captures
+            .get(1)
+            .and_then(|index| index.as_str().parse::<usize>().ok())
+            .and_then(|index| files.get(index.checked_sub(1)?));
+        let Some(path) = cited else {
+            return captures[0].to_string();
+        };
+        let Some(url) = line_url(path, &captures[2]) else {
+            return captures[0].to_string();
+        };
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let lines = match captures.get(3) {
+            Some(end) => format!("{name}, lines {}–{}", &captures[2], end.as_str()),
+            None => format!("{name}, line {}", &captures[2]),
+        };
+        let number = match sources.iter().position(|(source, _)| *source == lines) {
+            Some(position) => position + 1,
+            None => {
+                sources.push((lines, url.clone()));
+                sources.len()
+            }
+        };
+        format!("[\\[{number}\\]]({url})")
+    });
+    if sources.is_empty() {
+        return answer.to_string();
+    }
+
+    let mut linked = linked.into_owned();
+    linked.push_str("\n\nSources:\n");
+    for (number, (lines, url)) in sources.iter().enumerate() {
+        linked.push_str(&format!("{}. [{lines}]({url})\n", number + 1));
+    }
+    linked
+}
+
+/// Link to `line` of `path`, which opens it there when clicked.
+fn line_url(path: &Path, line: &str) -> Option<Url> {
+    let mut url = Url::from_file_path(path).ok()?;
+    url.set_fragment(Some(&format!("L{line}")));
+    Some(url)
+}
+
+/// The line a link made by [`link_citations`] points at.
+pub(super) fn cited_line(url: &Url) -> Option<(PathBuf, u32)> {
+    let line = url.fragment()?.strip_prefix('L')?.parse().ok()?;
+    Some((url.to_file_path().ok()?, line))
+}
+
+fn modified_at(path: &std::path::Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+async fn pick_files() -> Result<Vec<PathBuf>, String> {
+    let response = match file_chooser::open::Dialog::new()
+        .title("Pin files")
+        .open_files()
+        .await
+    {
+        Ok(response) => response,
+        Err(file_chooser::Error::Cancelled) => return Ok(Vec::new()),
+        Err(err) => return Err(err.to_string()),
+    };
+
+    response
+        .urls()
+        .iter()
+        .map(|url| {
+            url.to_file_path()
+                .map_err(|()| format!("{url} is not a local file"))
+        })
+        .collect()
+}
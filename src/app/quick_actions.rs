@@ -0,0 +1,125 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Palette of the user's quick actions, opened by right-clicking the panel icon.
+
+use super::{AppModel, Page};
+use crate::config::QuickActionInput;
+use cosmic::iced::{Alignment, Length};
+use cosmic::prelude::*;
+use cosmic::widget;
+
+#[derive(Default)]
+pub struct State {
+    query: String,
+    /// Why the last action couldn't run.
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Open,
+    QueryChanged(String),
+    Run(usize),
+    /// Input of the action at the index, read from the selection or clipboard.
+    InputRead(usize, Option<String>),
+}
+
+impl AppModel {
+    pub(super) fn quick_actions_view(&self) -> cosmic::Element<'_, Message> {
+        let state = &self.quick_actions;
+        let query = state.query.to_lowercase();
+
+        let mut column = widget::column::with_children(vec![
+            widget::search_input("Search quick actions", &state.query)
+                .on_input(Message::QueryChanged)
+                .into(),
+        ])
+        .spacing(8);
+
+        if self.config.quick_actions.is_empty() {
+            column = column.push(widget::text::body(
+                "No quick actions yet. Add them on the settings page.",
+            ));
+        }
+        for (index, action) in self.config.quick_actions.iter().enumerate() {
+            if !action.name.to_lowercase().contains(&query) {
+                continue;
+            }
+            column = column.push(
+                widget::button::custom(
+                    widget::row::with_children(vec![
+                        widget::text::body(action.name.as_str())
+                            .width(Length::Fill)
+                            .into(),
+                        widget::text::caption(QuickActionInput::NAMES[action.input.index()]).into(),
+                    ])
+                    .align_y(Alignment::Center),
+                )
+                .class(cosmic::theme::Button::MenuItem)
+                .on_press(Message::Run(index))
+                .width(Length::Fill),
+            );
+        }
+        if let Some(error) = &state.error {
+            column = column.push(widget::text::caption(error.as_str()));
+        }
+
+        widget::scrollable(column).height(Length::Fill).into()
+    }
+
+    pub(super) fn update_quick_actions(
+        &mut self,
+        message: Message,
+    ) -> Task<cosmic::Action<super::Message>> {
+        match message {
+            Message::Open => {
+                self.quick_actions = State::default();
+                self.page = Page::QuickActions;
+                if self.popup.is_none() {
+                    return self.open_popup();
+                }
+            }
+            Message::QueryChanged(query) => {
+                self.quick_actions.query = query;
+            }
+            Message::Run(index) => {
+                let Some(action) = self.config.quick_actions.get(index) else {
+                    return Task::none();
+                };
+                let read = |text: Option<String>| {
+                    cosmic::Action::App(super::Message::QuickActions(Message::InputRead(
+                        index, text,
+                    )))
+                };
+                return match action.input {
+                    QuickActionInput::Selection => {
+                        cosmic::iced::clipboard::read_primary().map(read)
+                    }
+                    QuickActionInput::Clipboard => cosmic::iced::clipboard::read().map(read),
+                    QuickActionInput::Typed => Task::done(read(Some(self.input_text.clone()))),
+                };
+            }
+            Message::InputRead(index, text) => {
+                let Some(action) = self.config.quick_actions.get(index) else {
+                    return Task::none();
+                };
+                let input = text.unwrap_or_default();
+                let input = input.trim();
+                if input.is_empty() && action.input != QuickActionInput::Typed {
+                    self.quick_actions.error = Some(format!(
+                        "{} is empty",
+                        QuickActionInput::NAMES[action.input.index()]
+                    ));
+                    return Task::none();
+                }
+
+                let prompt = action.prompt(input);
+                self.page = Page::Chat;
+                // Goes through the same checks as a typed prompt.
+                return Task::done(cosmic::Action::App(super::Message::SubmitInput(prompt)));
+            }
+        }
+
+        Task::none()
+    }
+}
@@ -0,0 +1,180 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! A popover for one quick question, opened from a keyboard shortcut: a single
+//! prompt and its answer, without the conversation's history.
+//!
+//! The exchange can be continued in the full chat, as a new conversation.
+
+use super::{AppModel, Chat, conversations};
+use crate::config::ResponseModalities;
+use crate::{language, models, redact};
+use cosmic::iced::{Alignment, Length};
+use cosmic::prelude::*;
+use cosmic::widget;
+use std::sync::{Arc, LazyLock};
+
+static QUICK_INPUT_ID: LazyLock<widget::Id> =
+    LazyLock::new(|| widget::Id::new("quick-reply-input"));
+
+#[derive(Default)]
+pub struct State {
+    /// Whether the popup shows the popover instead of the full chat.
+    pub(super) open: bool,
+    prompt: String,
+    /// The prompt as it was sent, with sensitive text masked.
+    sent: Option<String>,
+    /// Tag of the request waiting for an answer.
+    request: Option<u64>,
+    answer: Option<Result<String, String>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// Opens the popover, or closes it if it is open.
+    Open,
+    PromptChanged(String),
+    Ask,
+    /// The answer to the request with this tag, with its estimated price in US dollars.
+    Answered(u64, f64, Result<String, String>),
+    /// Moves the question and its answer into a new conversation.
+    ContinueInChat,
+}
+
+impl AppModel {
+    pub(super) fn quick_reply_view(&self) -> cosmic::Element<'_, Message> {
+        let state = &self.quick_reply;
+
+        let mut column = widget::column::with_children(vec![
+            widget::text_input("Ask a quick question", &state.prompt)
+                .id(QUICK_INPUT_ID.clone())
+                .on_input(Message::PromptChanged)
+                .on_submit(|_| Message::Ask)
+                .width(Length::Fill)
+                .into(),
+        ])
+        .spacing(8);
+
+        if state.request.is_some() {
+            column = column.push(widget::text::caption("Generating…"));
+        }
+        match &state.answer {
+            Some(Ok(answer)) => {
+                column = column.push(
+                    widget::scrollable(widget::text::body(answer.as_str())).height(Length::Shrink),
+                );
+            }
+            Some(Err(err)) => {
+                column = column.push(widget::text::caption(format!("Failed: {err}")));
+            }
+            None => {}
+        }
+
+        column = column.push(
+            widget::row::with_children(vec![
+                widget::horizontal_space().into(),
+                widget::button::text("Continue in full chat")
+                    .on_press_maybe(
+                        (!state.prompt.trim().is_empty()).then_some(Message::ContinueInChat),
+                    )
+                    .into(),
+            ])
+            .align_y(Alignment::Center),
+        );
+
+        column.into()
+    }
+
+    pub(super) fn update_quick_reply(
+        &mut self,
+        message: Message,
+    ) -> Task<cosmic::Action<super::Message>> {
+        match message {
+            Message::Open => {
+                if self.quick_reply.open
+                    && let Some(popup) = self.popup.take()
+                {
+                    self.quick_reply = State::default();
+                    return cosmic::iced_winit::commands::popup::destroy_popup(popup);
+                }
+                self.quick_reply = State {
+                    open: true,
+                    ..State::default()
+                };
+                let focus = widget::text_input::focus(QUICK_INPUT_ID.clone());
+                return if self.popup.is_none() {
+                    Task::batch([self.open_popup(), focus])
+                } else {
+                    focus
+                };
+            }
+            Message::PromptChanged(prompt) => {
+                self.quick_reply.prompt = prompt;
+            }
+            Message::Ask => {
+                let prompt = self.quick_reply.prompt.trim();
+                if prompt.is_empty() || self.quick_reply.request.is_some() {
+                    return Task::none();
+                }
+                // There's no room to ask about findings, so they are masked.
+                let findings = redact::scan(prompt, &self.config.redaction);
+                let prompt = redact::mask(prompt, &findings);
+                let mut config = self.config.for_workspace();
+                config.response_modalities = ResponseModalities::Text;
+                // The same checks as a prompt sent from the chat, shown in place
+                // of the answer since there's no room for more.
+                let refused = if config.local_only && !config.runs_locally() {
+                    Some(models::LOCAL_ONLY_ERROR.to_string())
+                } else {
+                    let tokens = models::estimate_tokens(&prompt);
+                    self.over_cost_cap(models::estimate_cost(&config, tokens, tokens), false)
+                };
+                if let Some(reason) = refused {
+                    self.quick_reply.answer = Some(Err(reason));
+                    return Task::none();
+                }
+                let tag = self.next_request;
+                self.next_request += 1;
+                self.quick_reply.request = Some(tag);
+                self.quick_reply.sent = Some(prompt.clone());
+                self.quick_reply.answer = None;
+
+                return cosmic::task::future(async move {
+                    let history = Arc::new(vec![Chat::user(prompt)]);
+                    let message = models::get_response(Arc::clone(&history), config.clone()).await;
+                    let cost = models::message_cost(&config, &history, &message);
+                    let result = message
+                        .into_result()
+                        .map(|reply| reply.text.trim().to_string());
+                    super::Message::QuickReply(Message::Answered(tag, cost, result))
+                });
+            }
+            Message::Answered(tag, cost, result) => {
+                // Billed even if the popover was closed meanwhile.
+                self.add_spending(cost);
+                if self.quick_reply.request == Some(tag) {
+                    self.quick_reply.request = None;
+                    self.quick_reply.answer = Some(result);
+                }
+            }
+            Message::ContinueInChat => {
+                let state = std::mem::take(&mut self.quick_reply);
+                let task = self.update_conversations(conversations::Message::New);
+                match (state.answer, state.sent) {
+                    (Some(Ok(answer)), Some(sent)) => {
+                        let history = Arc::make_mut(&mut self.chat_history);
+                        history.push(Chat::user(sent));
+                        history.push(Chat::model(answer));
+                        self.read_up_to = self.chat_history.len();
+                        self.conversation_language = language::of_conversation(&self.chat_history);
+                        self.save_session();
+                    }
+                    // Without an answer, the question is asked again from there.
+                    _ => self.input_text = state.prompt,
+                }
+                return Task::batch([task, widget::text_input::focus(super::INPUT_ID.clone())]);
+            }
+        }
+
+        Task::none()
+    }
+}
@@ -0,0 +1,222 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Read-aloud queue: speaks answers sentence by sentence.
+
+use super::AppModel;
+use crate::tts;
+use cosmic::iced::{Alignment, Color, Length};
+use cosmic::prelude::*;
+use cosmic::widget;
+use std::collections::VecDeque;
+
+/// Playback state of the read-aloud queue.
+#[derive(Default)]
+pub struct State {
+    /// Messages waiting to be read, by index in the chat history.
+    queue: VecDeque<usize>,
+    /// Message being read.
+    current: Option<Playback>,
+    paused: bool,
+    /// Bumped whenever playback is interrupted, so results of cancelled
+    /// utterances can be told apart from the current one.
+    generation: u64,
+}
+
+struct Playback {
+    message: usize,
+    sentences: Vec<String>,
+    sentence: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Enqueue(usize),
+    Pause,
+    Resume,
+    Stop,
+    RateChanged(i32),
+    SentenceDone(u64, Result<(), String>),
+}
+
+impl AppModel {
+    /// Whether message `index` is being read or waiting in the queue.
+    pub(super) fn is_queued_for_reading(&self, index: usize) -> bool {
+        self.read_aloud
+            .current
+            .as_ref()
+            .is_some_and(|playback| playback.message == index)
+            || self.read_aloud.queue.contains(&index)
+    }
+
+    /// Renders message `index` with the sentence being spoken highlighted, if it's being read.
+    pub(super) fn reading_bubble(
+        &self,
+        index: usize,
+    ) -> Option<cosmic::Element<'_, super::Message>> {
+        let playback = self
+            .read_aloud
+            .current
+            .as_ref()
+            .filter(|playback| playback.message == index)?;
+
+        let spans: Vec<cosmic::iced::widget::text::Span<'_, super::Message>> = playback
+            .sentences
+            .iter()
+            .enumerate()
+            .map(|(index, sentence)| {
+                let span = cosmic::iced::widget::span(format!("{sentence} "));
+                if index == playback.sentence {
+                    span.color(Color::from_rgb(0.35, 0.65, 1.0))
+                } else {
+                    span
+                }
+            })
+            .collect();
+
+        Some(
+            widget::container(
+                widget::container(cosmic::iced::widget::rich_text(spans))
+                    .class(cosmic::theme::Container::List)
+                    .padding(10),
+            )
+            .align_left(Length::Fill)
+            .into(),
+        )
+    }
+
+    /// Playback controls, shown while something is queued.
+    pub(super) fn read_aloud_controls(&self) -> Option<cosmic::Element<'_, Message>> {
+        self.read_aloud.current.as_ref()?;
+
+        let play_pause = if self.read_aloud.paused {
+            widget::button::icon(widget::icon::from_name("media-playback-start-symbolic"))
+                .on_press(Message::Resume)
+        } else {
+            widget::button::icon(widget::icon::from_name("media-playback-pause-symbolic"))
+                .on_press(Message::Pause)
+        };
+
+        Some(
+            widget::row::with_children(vec![
+                play_pause.into(),
+                widget::button::icon(widget::icon::from_name("media-playback-stop-symbolic"))
+                    .on_press(Message::Stop)
+                    .into(),
+                widget::text::caption(format!("{} queued", self.read_aloud.queue.len())).into(),
+                widget::horizontal_space().into(),
+                widget::text::caption("Speed").into(),
+                widget::slider(-100..=100, self.config.speech_rate, Message::RateChanged)
+                    .step(10)
+                    .width(Length::Fixed(120.0))
+                    .into(),
+            ])
+            .spacing(8)
+            .align_y(Alignment::Center)
+            .into(),
+        )
+    }
+
+    pub(super) fn update_read_aloud(
+        &mut self,
+        message: Message,
+    ) -> Task<cosmic::Action<super::Message>> {
+        match message {
+            Message::Enqueue(index) => {
+                if !self.is_queued_for_reading(index) {
+                    self.read_aloud.queue.push_back(index);
+                }
+                if self.read_aloud.current.is_none() {
+                    return self.read_next_message();
+                }
+            }
+            Message::Pause => {
+                self.read_aloud.paused = true;
+                self.read_aloud.generation += 1;
+                tts::stop(self.config.speech_engine);
+            }
+            Message::Resume => {
+                self.read_aloud.paused = false;
+                // Start over from the beginning of the interrupted sentence.
+                return self.speak_current_sentence();
+            }
+            Message::Stop => {
+                self.read_aloud = State {
+                    generation: self.read_aloud.generation + 1,
+                    ..State::default()
+                };
+                tts::stop(self.config.speech_engine);
+            }
+            Message::RateChanged(rate) => {
+                self.config.speech_rate = rate;
+                self.save_config();
+            }
+            Message::SentenceDone(generation, result) => {
+                if generation != self.read_aloud.generation || self.read_aloud.paused {
+                    return Task::none();
+                }
+                if let Err(err) = result {
+                    eprintln!("read aloud failed: {err}");
+                    self.read_aloud = State {
+                        generation: self.read_aloud.generation + 1,
+                        ..State::default()
+                    };
+                    return Task::none();
+                }
+
+                let Some(playback) = self.read_aloud.current.as_mut() else {
+                    return Task::none();
+                };
+                playback.sentence += 1;
+                if playback.sentence < playback.sentences.len() {
+                    return self.speak_current_sentence();
+                }
+                return self.read_next_message();
+            }
+        }
+
+        Task::none()
+    }
+
+    fn read_next_message(&mut self) -> Task<cosmic::Action<super::Message>> {
+        self.read_aloud.current = None;
+        while let Some(index) = self.read_aloud.queue.pop_front() {
+            let Some(chat) = self.chat_history.get(index) else {
+                continue;
+            };
+            let sentences = tts::sentences(&chat.content);
+            if sentences.is_empty() {
+                continue;
+            }
+
+            self.read_aloud.current = Some(Playback {
+                message: index,
+                sentences,
+                sentence: 0,
+            });
+            return self.speak_current_sentence();
+        }
+
+        Task::none()
+    }
+
+    fn speak_current_sentence(&mut self) -> Task<cosmic::Action<super::Message>> {
+        let Some(playback) = &self.read_aloud.current else {
+            return Task::none();
+        };
+        let Some(sentence) = playback.sentences.get(playback.sentence).cloned() else {
+            return Task::none();
+        };
+
+        self.read_aloud.generation += 1;
+        let generation = self.read_aloud.generation;
+        let rate = self.config.speech_rate;
+        let voice = tts::Voice::from_config(&self.config);
+        cosmic::task::future(async move {
+            let result = match voice {
+                Ok(voice) => tts::speak(sentence, rate, voice).await,
+                Err(err) => Err(err),
+            };
+            super::Message::ReadAloud(Message::SentenceDone(generation, result))
+        })
+    }
+}
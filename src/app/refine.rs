@@ -0,0 +1,139 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Rewrites the draft prompt with the model's help before it is sent.
+
+use super::{AppModel, Chat};
+use crate::config::ResponseModalities;
+use crate::{models, redact};
+use cosmic::iced::Length;
+use cosmic::prelude::*;
+use cosmic::widget;
+use std::sync::Arc;
+
+const PROMPT: &str = "Improve the following prompt so that an AI assistant can answer it well: \
+    make it clear and specific, and keep its intent, language and any details it gives. Reply \
+    with the improved prompt only, without explanations or quotes.\n\nPrompt:\n";
+
+#[derive(Default)]
+pub struct State {
+    /// Improved prompt waiting to be accepted.
+    proposal: Option<String>,
+    running: bool,
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Refine,
+    /// The proposal, with the estimated price of asking for it in US dollars.
+    Refined(f64, Result<String, String>),
+    Accept,
+    Dismiss,
+}
+
+impl AppModel {
+    /// The improved prompt with buttons to use or discard it.
+    pub(super) fn refine_view(&self) -> Option<cosmic::Element<'_, Message>> {
+        let state = &self.refine;
+        let content: cosmic::Element<'_, Message> = if state.running {
+            widget::text::caption("Refining the prompt…").into()
+        } else if let Some(error) = &state.error {
+            widget::row::with_children(vec![
+                widget::text::caption(format!("Could not refine the prompt: {error}"))
+                    .width(Length::Fill)
+                    .into(),
+                widget::button::text("Dismiss")
+                    .on_press(Message::Dismiss)
+                    .into(),
+            ])
+            .spacing(8)
+            .into()
+        } else if let Some(proposal) = &state.proposal {
+            widget::column::with_children(vec![
+                widget::text::body(proposal.as_str()).into(),
+                widget::row::with_children(vec![
+                    widget::button::suggested("Use this prompt")
+                        .on_press(Message::Accept)
+                        .into(),
+                    widget::button::text("Keep mine")
+                        .on_press(Message::Dismiss)
+                        .into(),
+                ])
+                .spacing(8)
+                .into(),
+            ])
+            .spacing(8)
+            .into()
+        } else {
+            return None;
+        };
+
+        Some(
+            widget::container(content)
+                .class(cosmic::theme::Container::Card)
+                .padding(8)
+                .width(Length::Fill)
+                .into(),
+        )
+    }
+
+    pub(super) fn update_refine(
+        &mut self,
+        message: Message,
+    ) -> Task<cosmic::Action<super::Message>> {
+        match message {
+            Message::Refine => {
+                let draft = self.input_text.trim();
+                if draft.is_empty() || self.refine.running {
+                    return Task::none();
+                }
+                // Nothing was sent yet, so mask everything instead of asking.
+                let findings = redact::scan(draft, &self.config.redaction);
+                let draft = redact::mask(draft, &findings);
+                let mut config = self.config.for_workspace();
+                config.response_modalities = ResponseModalities::Text;
+                self.refine = State {
+                    running: true,
+                    ..State::default()
+                };
+
+                return cosmic::task::future(async move {
+                    let history = Arc::new(vec![Chat::user(format!("{PROMPT}{draft}"))]);
+                    let message = models::get_response(Arc::clone(&history), config.clone()).await;
+                    let cost = models::message_cost(&config, &history, &message);
+                    let result = message
+                        .into_result()
+                        .map(|reply| reply.text.trim().to_string())
+                        .and_then(|text| {
+                            if text.is_empty() {
+                                Err("the model returned nothing".into())
+                            } else {
+                                Ok(text)
+                            }
+                        });
+                    super::Message::Refine(Message::Refined(cost, result))
+                });
+            }
+            Message::Refined(cost, result) => {
+                self.refine.running = false;
+                self.add_spending(cost);
+                match result {
+                    Ok(proposal) => self.refine.proposal = Some(proposal),
+                    Err(err) => self.refine.error = Some(err),
+                }
+            }
+            Message::Accept => {
+                if let Some(proposal) = self.refine.proposal.take() {
+                    self.input_text = proposal;
+                    self.clear_spelling();
+                    return widget::text_input::focus(super::INPUT_ID.clone());
+                }
+            }
+            Message::Dismiss => {
+                self.refine = State::default();
+            }
+        }
+
+        Task::none()
+    }
+}
@@ -0,0 +1,2888 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The settings page of the popup.
+
+use super::AppModel;
+use crate::backup;
+use crate::config::{
+    BackupInterval, GenerationPreset, HarmCategory, HarmThreshold, HttpHeader, MediaResolution,
+    PanelIcon, PanelLabel, Persona, PolicyLock, Provider, QuickAction, QuickActionInput,
+    RedactionAction, ResponseModalities, SafetyPreset, ShareTarget, SpeechEngine, SyncBackend,
+    Workspace,
+};
+use crate::credentials;
+use crate::keyring::{self, Slot};
+use crate::models::{self, gemini, local, openai};
+use crate::paste;
+use crate::preferences;
+use crate::redact::Pattern;
+use crate::scripting::{self, Scripts};
+use crate::spellcheck;
+use crate::spending;
+use crate::storage;
+use crate::sync;
+use crate::tts;
+use cosmic::cosmic_config::CosmicConfigEntry;
+use cosmic::iced::widget::column;
+use cosmic::prelude::*;
+use cosmic::widget;
+use std::collections::HashMap;
+use std::time::SystemTime;
+use std::{env, path::PathBuf};
+
+/// UI state of the settings page that isn't part of the configuration.
+#[derive(Debug)]
+pub struct State {
+    /// File used by settings import and export.
+    transfer_path: String,
+    /// Include secrets when exporting settings.
+    export_secrets: bool,
+    /// Outcome of the last import or export.
+    status: Option<String>,
+    /// Why the last name typed for the workspace at this index was refused.
+    workspace_name_error: Option<(usize, String)>,
+    /// Models offered by the provider, filled on request.
+    models: Vec<String>,
+    /// Error from the last model listing.
+    models_error: Option<String>,
+    /// Chat models offered by OpenAI, filled on request.
+    openai_models: Vec<String>,
+    /// Error from the last OpenAI model listing.
+    openai_models_error: Option<String>,
+    /// Models offered by the OpenAI-compatible server, filled on request.
+    compatible_models: Vec<String>,
+    /// Error from the last listing of the OpenAI-compatible server's models.
+    compatible_models_error: Option<String>,
+    /// API keys as typed, saved to the keyring on request.
+    key_drafts: HashMap<Slot, String>,
+    /// Outcome of the last keyring access for each key.
+    key_status: HashMap<Slot, String>,
+    /// Tally of answer preferences from A/B regenerations.
+    preferences: Vec<(String, preferences::Tally)>,
+    /// GGUF files found in the local models directory.
+    local_models: Vec<PathBuf>,
+    /// File names of `local_models`, for the dropdown.
+    local_model_names: Vec<String>,
+    /// Downloaded Piper voices.
+    voices: Vec<String>,
+    /// Name of the Piper voice to download.
+    voice_download: String,
+    /// A voice download is running.
+    downloading_voice: bool,
+    /// Outcome of the last voice download.
+    voice_status: Option<String>,
+    /// Passphrase typed to lock or unlock the content policy.
+    passphrase: String,
+    /// Repeated passphrase when locking.
+    passphrase_confirmation: String,
+    /// Outcome of the last lock or unlock attempt.
+    policy_status: Option<String>,
+    /// Backups found in the backup folder, newest first.
+    backups: Vec<backup::Backup>,
+    /// A backup or restore is running.
+    backup_running: bool,
+    /// A restore is replacing the data, which the session mustn't be saved over.
+    pub(super) restoring: bool,
+    /// Outcome of the last backup or restore.
+    backup_status: Option<String>,
+    /// Space taken by each part of the profile's data, largest first.
+    storage: Vec<(String, u64)>,
+    /// A cleanup is running.
+    cleaning_up: bool,
+    /// Outcome of the last cleanup.
+    cleanup_status: Option<String>,
+    /// A sync is running.
+    pub(super) sync_running: bool,
+    /// Outcome of the last sync.
+    sync_status: Option<String>,
+    /// Spending caps as typed, kept while they don't parse yet.
+    conversation_cap_draft: Option<String>,
+    daily_cap_draft: Option<String>,
+    prompt_cap_draft: Option<String>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        let transfer_path = env::var_os("HOME")
+            .map(PathBuf::from)
+            .unwrap_or_default()
+            .join("cosmic-ext-clankers-settings.json");
+
+        Self {
+            transfer_path: transfer_path.to_string_lossy().into_owned(),
+            export_secrets: false,
+            status: None,
+            workspace_name_error: None,
+            models: Vec::new(),
+            models_error: None,
+            openai_models: Vec::new(),
+            openai_models_error: None,
+            compatible_models: Vec::new(),
+            compatible_models_error: None,
+            key_drafts: HashMap::new(),
+            key_status: HashMap::new(),
+            preferences: Vec::new(),
+            local_models: Vec::new(),
+            local_model_names: Vec::new(),
+            voices: Vec::new(),
+            voice_download: String::new(),
+            downloading_voice: false,
+            voice_status: None,
+            passphrase: String::new(),
+            passphrase_confirmation: String::new(),
+            policy_status: None,
+            backups: Vec::new(),
+            backup_running: false,
+            restoring: false,
+            backup_status: None,
+            storage: Vec::new(),
+            cleaning_up: false,
+            cleanup_status: None,
+            sync_running: false,
+            sync_status: None,
+            conversation_cap_draft: None,
+            daily_cap_draft: None,
+            prompt_cap_draft: None,
+        }
+    }
+}
+
+/// Messages emitted by the settings page.
+#[derive(Debug, Clone)]
+pub enum Message {
+    TransferPathChanged(String),
+    ExportSecretsToggled(bool),
+    Export,
+    Import,
+    AddHeader,
+    RemoveHeader(usize),
+    HeaderNameChanged(usize, String),
+    HeaderValueChanged(usize, String),
+    HeaderSecretToggled(usize, bool),
+    TemperatureChanged(f32),
+    TopPChanged(f32),
+    TopKChanged(String),
+    MaxOutputTokensChanged(String),
+    PresencePenaltyChanged(f32),
+    FrequencyPenaltyChanged(f32),
+    DebugInspectorToggled(bool),
+    VersionAlertsToggled(bool),
+    LenientParsingToggled(bool),
+    RawOnParseErrorToggled(bool),
+    PanelIconSelected(usize),
+    PanelLabelSelected(usize),
+    PanelLabelChanged(String),
+    OpenDiagnostics,
+    ModelChanged(String),
+    ModelSelected(usize),
+    RefreshModels,
+    ModelsLoaded(Result<Vec<String>, String>),
+    GeminiProjectChanged(String),
+    GeminiLocationChanged(String),
+    OpenAiModelChanged(String),
+    OpenAiOrganizationChanged(String),
+    OpenAiProjectChanged(String),
+    OpenAiModelSelected(usize),
+    RefreshOpenAiModels,
+    OpenAiModelsLoaded(Result<Vec<String>, String>),
+    CompatibleUrlChanged(String),
+    CompatibleModelChanged(String),
+    CompatibleModelSelected(usize),
+    KeyChanged(Slot, String),
+    StoreKey(Slot),
+    /// Stores the key from the slot's environment variable in the keyring.
+    MoveKeyFromEnv(Slot),
+    KeyStored(Slot, Result<(), String>),
+    KeyringLoaded(Result<(), String>),
+    RefreshCompatibleModels,
+    CompatibleModelsLoaded(Result<Vec<String>, String>),
+    AbRegenerationToggled(bool),
+    ResponseModalitiesSelected(usize),
+    MediaResolutionSelected(usize),
+    LogprobsChanged(String),
+    ProviderSelected(usize),
+    LocalOnlyToggled(bool),
+    LocalModelPathChanged(String),
+    LocalModelSelected(usize),
+    RefreshLocalModels,
+    OpenDownloads,
+    SpeechEngineSelected(usize),
+    VoiceSelected(usize),
+    VoiceDownloadChanged(String),
+    DownloadVoice,
+    VoiceDownloaded(Result<String, String>),
+    ShareTargetSelected(usize),
+    PasteUrlChanged(String),
+    RedactionSelected(Pattern, usize),
+    SafetyPresetSelected(usize),
+    InstructionsChanged(String),
+    ReplyLanguageChanged(String),
+    EditorCommandChanged(String),
+    FollowUpSuggestionsToggled(bool),
+    AutoTitlesToggled(bool),
+    ConversationCapChanged(String),
+    DailyCapChanged(String),
+    PromptCapChanged(String),
+    BackgroundTokenCapChanged(String),
+    WatchMaxPromptsChanged(String),
+    MetricsFileChanged(String),
+    MetricsPortChanged(String),
+    WatchMaxMinutesChanged(String),
+    AddWorkspace,
+    RemoveWorkspace(usize),
+    WorkspaceNameChanged(usize, String),
+    WorkspaceProviderSelected(usize, usize),
+    WorkspaceModelChanged(usize, String),
+    WorkspacePresetSelected(usize, usize),
+    WorkspaceInstructionsChanged(usize, String),
+    AddPersona,
+    RemovePersona(usize),
+    PersonaNameChanged(usize, String),
+    PersonaInstructionsChanged(usize, String),
+    PersonaPresetSelected(usize, usize),
+    WorkspaceDirectoryChanged(usize, String),
+    WorkspaceEnvironmentChanged(usize, String),
+    ScriptingToggled(bool),
+    ReloadScripts,
+    AddQuickAction,
+    RemoveQuickAction(usize),
+    QuickActionNameChanged(usize, String),
+    QuickActionTemplateChanged(usize, String),
+    QuickActionInputSelected(usize, usize),
+    RemoveTemplate(usize),
+    TemplateNameChanged(usize, String),
+    TemplateProviderSelected(usize, usize),
+    TemplateModelChanged(usize, String),
+    TemplateInstructionsChanged(usize, String),
+    TemplateFirstMessageChanged(usize, String),
+    SpellcheckToggled(bool),
+    SpellcheckLanguageChanged(String),
+    HarmThresholdChanged(HarmCategory, u8),
+    PassphraseChanged(String),
+    PassphraseConfirmationChanged(String),
+    LockPolicy,
+    UnlockPolicy,
+    LocalServerBinaryChanged(String),
+    LocalContextSizeChanged(String),
+    BackupFolderChanged(String),
+    BackupIntervalSelected(usize),
+    BackupKeepChanged(String),
+    BackUpNow,
+    BackupFinished(Result<Option<PathBuf>, String>),
+    RestoreConversationsToggled(bool),
+    RefreshStorage,
+    StorageMeasured(Vec<(String, u64)>),
+    CleanUpStorage,
+    StorageCleaned(Result<storage::Cleanup, String>),
+    RestoreBackup(usize),
+    Restored(Result<PathBuf, String>),
+    SyncToggled(bool),
+    SyncBackendSelected(usize),
+    SyncUrlChanged(String),
+    SyncBucketChanged(String),
+    SyncRegionChanged(String),
+    SyncUsernameChanged(String),
+    SyncNow,
+    SyncFinished(Result<sync::Summary, String>),
+}
+
+impl AppModel {
+    pub(super) fn settings_view(&self) -> cosmic::Element<'_, Message> {
+        let state = &self.settings;
+
+        let mut policy = widget::settings::section().title("Content policy");
+        if self.config.is_locked() {
+            policy = policy
+                .add(widget::text::caption(
+                    "Settings are locked. Enter the passphrase to change them.",
+                ))
+                .add(
+                    widget::secure_input("Passphrase", state.passphrase.as_str(), None, true)
+                        .on_input(Message::PassphraseChanged)
+                        .on_submit(|_| Message::UnlockPolicy),
+                )
+                .add(widget::button::standard("Unlock").on_press(Message::UnlockPolicy));
+            if let Some(status) = &state.policy_status {
+                policy = policy.add(widget::text::caption(status.as_str()));
+            }
+
+            return widget::scrollable(widget::settings::view_column(vec![policy.into()]))
+                .height(cosmic::iced::Length::Fill)
+                .into();
+        }
+        policy = policy
+            .add(widget::text::caption(
+                "Locking pins safety settings to the strictest level, disables sharing and \
+                 exporting conversations, and hides every page but the chat until unlocked.",
+            ))
+            .add(
+                widget::secure_input("Passphrase", state.passphrase.as_str(), None, true)
+                    .on_input(Message::PassphraseChanged),
+            )
+            .add(
+                widget::secure_input(
+                    "Repeat passphrase",
+                    state.passphrase_confirmation.as_str(),
+                    None,
+                    true,
+                )
+                .on_input(Message::PassphraseConfirmationChanged)
+                .on_submit(|_| Message::LockPolicy),
+            )
+            .add(widget::button::standard("Lock").on_press(Message::LockPolicy));
+        if let Some(status) = &state.policy_status {
+            policy = policy.add(widget::text::caption(status.as_str()));
+        }
+
+        let mut transfer = column!(
+            widget::text_input("Settings file", &state.transfer_path)
+                .on_input(Message::TransferPathChanged)
+                .width(cosmic::iced::Length::Fill),
+            widget::checkbox("Include secrets", state.export_secrets)
+                .on_toggle(Message::ExportSecretsToggled),
+            widget::row::with_children(vec![
+                widget::button::standard("Export")
+                    .on_press(Message::Export)
+                    .into(),
+                widget::button::standard("Import")
+                    .on_press(Message::Import)
+                    .into(),
+            ])
+            .spacing(8),
+        )
+        .spacing(8);
+
+        if let Some(status) = &state.status {
+            transfer = transfer.push(widget::text::caption(status));
+        }
+
+        let mut headers = widget::settings::section().title("Gemini headers");
+        for (index, header) in self.config.gemini.headers.iter().enumerate() {
+            let value_input = if header.secret {
+                widget::secure_input("Value", header.value.as_str(), None, true)
+            } else {
+                widget::text_input("Value", header.value.as_str())
+            };
+
+            headers = headers.add(
+                widget::row::with_children(vec![
+                    widget::text_input("Name", header.name.as_str())
+                        .on_input(move |name| Message::HeaderNameChanged(index, name))
+                        .into(),
+                    value_input
+                        .on_input(move |value| Message::HeaderValueChanged(index, value))
+                        .into(),
+                    widget::checkbox("Secret", header.secret)
+                        .on_toggle(move |secret| Message::HeaderSecretToggled(index, secret))
+                        .into(),
+                    widget::button::icon(widget::icon::from_name("edit-delete-symbolic"))
+                        .on_press(Message::RemoveHeader(index))
+                        .into(),
+                ])
+                .spacing(8)
+                .align_y(cosmic::iced::Alignment::Center),
+            );
+        }
+        headers = headers.add(widget::button::standard("Add header").on_press(Message::AddHeader));
+
+        let model_name = gemini::model_name(&self.config.gemini);
+        let mut model = widget::settings::section().title("Model").add(
+            widget::row::with_children(vec![
+                widget::text_input(gemini::DEFAULT_MODEL, self.config.gemini.model.as_str())
+                    .on_input(Message::ModelChanged)
+                    .width(cosmic::iced::Length::Fill)
+                    .into(),
+                widget::button::icon(widget::icon::from_name("view-refresh-symbolic"))
+                    .on_press(Message::RefreshModels)
+                    .into(),
+            ])
+            .spacing(8)
+            .align_y(cosmic::iced::Alignment::Center),
+        );
+        if !state.models.is_empty() {
+            model = model.add(widget::dropdown(
+                &state.models,
+                state.models.iter().position(|name| *name == model_name),
+                Message::ModelSelected,
+            ));
+        }
+        if let Some(error) = &state.models_error {
+            model = model.add(widget::text::caption(format!(
+                "Could not list models: {error}"
+            )));
+        }
+        if model_name.starts_with("tunedModels/") {
+            model = model.add(widget::text::caption(format!(
+                "Tuned models may require an OAuth access token in {}.",
+                gemini::ACCESS_TOKEN_VAR
+            )));
+        }
+        model = model
+            .add(self.key_item(Slot::Gemini, "Gemini API key"))
+            .add(widget::text::caption(format!(
+                "Saved in the keyring. Left empty, the key is read from {} in the environment \
+                 or the env file.",
+                gemini::API_KEY_VAR
+            )))
+            .add(widget::settings::item(
+                "Google Cloud project",
+                widget::text_input("None", self.config.gemini.project.as_str())
+                    .on_input(Message::GeminiProjectChanged),
+            ))
+            .add(widget::settings::item(
+                "Location",
+                widget::text_input("us-central1", self.config.gemini.location.as_str())
+                    .on_input(Message::GeminiLocationChanged),
+            ))
+            .add(widget::text::caption(format!(
+                "The project is billed for requests. With a location as well, requests go to \
+                 Vertex AI, authorized by an access token in {}.",
+                gemini::ACCESS_TOKEN_VAR
+            )));
+
+        let openai_model = openai::model_name(&self.config.openai);
+        let mut openai_section = widget::settings::section().title("OpenAI").add(
+            widget::row::with_children(vec![
+                widget::text_input(openai::DEFAULT_MODEL, self.config.openai.model.as_str())
+                    .on_input(Message::OpenAiModelChanged)
+                    .width(cosmic::iced::Length::Fill)
+                    .into(),
+                widget::button::icon(widget::icon::from_name("view-refresh-symbolic"))
+                    .on_press(Message::RefreshOpenAiModels)
+                    .into(),
+            ])
+            .spacing(8)
+            .align_y(cosmic::iced::Alignment::Center),
+        );
+        if !state.openai_models.is_empty() {
+            openai_section = openai_section.add(widget::dropdown(
+                &state.openai_models,
+                state
+                    .openai_models
+                    .iter()
+                    .position(|name| *name == openai_model),
+                Message::OpenAiModelSelected,
+            ));
+        }
+        if let Some(error) = &state.openai_models_error {
+            openai_section = openai_section.add(widget::text::caption(format!(
+                "Could not list models: {error}"
+            )));
+        }
+        openai_section = openai_section
+            .add(self.key_item(Slot::OpenAi, "API key"))
+            .add(widget::text::caption(format!(
+                "Used when OpenAI is the provider. Saved in the keyring. Left empty, the key is \
+                 read from {}.",
+                openai::API_KEY_VAR
+            )))
+            .add(widget::settings::item(
+                "Organization ID",
+                widget::text_input("Default", self.config.openai.organization.as_str())
+                    .on_input(Message::OpenAiOrganizationChanged),
+            ))
+            .add(widget::settings::item(
+                "Project ID",
+                widget::text_input("Default", self.config.openai.project.as_str())
+                    .on_input(Message::OpenAiProjectChanged),
+            ));
+
+        let compatible = &self.config.compatible;
+        let mut compatible_section = widget::settings::section()
+            .title("OpenAI-compatible server")
+            .add(widget::settings::item(
+                "Base URL",
+                widget::text_input("http://localhost:8080/v1", compatible.base_url.as_str())
+                    .on_input(Message::CompatibleUrlChanged),
+            ))
+            .add(
+                widget::row::with_children(vec![
+                    widget::text_input("Server default model", compatible.model.as_str())
+                        .on_input(Message::CompatibleModelChanged)
+                        .width(cosmic::iced::Length::Fill)
+                        .into(),
+                    widget::button::icon(widget::icon::from_name("view-refresh-symbolic"))
+                        .on_press(Message::RefreshCompatibleModels)
+                        .into(),
+                ])
+                .spacing(8)
+                .align_y(cosmic::iced::Alignment::Center),
+            );
+        if !state.compatible_models.is_empty() {
+            compatible_section = compatible_section.add(widget::dropdown(
+                &state.compatible_models,
+                state
+                    .compatible_models
+                    .iter()
+                    .position(|name| *name == compatible.model.trim()),
+                Message::CompatibleModelSelected,
+            ));
+        }
+        if let Some(error) = &state.compatible_models_error {
+            compatible_section = compatible_section.add(widget::text::caption(format!(
+                "Could not list models: {error}"
+            )));
+        }
+        compatible_section = compatible_section
+            .add(self.key_item(Slot::Compatible, "API key"))
+            .add(widget::text::caption(
+                "For llama.cpp's server, LM Studio, vLLM, LocalAI and the like. A server on \
+                 localhost stays available in local-only mode.",
+            ));
+
+        let mut local_section = widget::settings::section()
+            .title("Local model")
+            .add(widget::settings::item(
+                "Local only (disable cloud providers and network features)",
+                widget::toggler(self.config.local_only).on_toggle(Message::LocalOnlyToggled),
+            ))
+            .add(widget::settings::item(
+                "Provider",
+                widget::dropdown(
+                    &Provider::NAMES,
+                    Some(self.config.provider.index()),
+                    Message::ProviderSelected,
+                ),
+            ))
+            .add(
+                widget::row::with_children(vec![
+                    widget::text_input(
+                        "Path to a GGUF file",
+                        self.config.local.model_path.as_str(),
+                    )
+                    .on_input(Message::LocalModelPathChanged)
+                    .width(cosmic::iced::Length::Fill)
+                    .into(),
+                    widget::button::icon(widget::icon::from_name("view-refresh-symbolic"))
+                        .on_press(Message::RefreshLocalModels)
+                        .into(),
+                ])
+                .spacing(8)
+                .align_y(cosmic::iced::Alignment::Center),
+            );
+        if !state.local_models.is_empty() {
+            local_section = local_section.add(widget::dropdown(
+                &state.local_model_names,
+                state
+                    .local_models
+                    .iter()
+                    .position(|path| path.as_os_str() == self.config.local.model_path.trim()),
+                Message::LocalModelSelected,
+            ));
+        } else if let Some(dir) = local::models_dir() {
+            local_section = local_section.add(widget::text::caption(format!(
+                "Models placed in {} are listed here.",
+                dir.display()
+            )));
+        }
+        local_section = local_section
+            .add(widget::settings::item(
+                "Download and remove models",
+                widget::button::standard("Manage models").on_press(Message::OpenDownloads),
+            ))
+            .add(widget::settings::item(
+                "Server executable",
+                widget::text_input("llama-server", self.config.local.server_binary.as_str())
+                    .on_input(Message::LocalServerBinaryChanged),
+            ))
+            .add(widget::settings::item(
+                "Context size",
+                widget::text_input(
+                    "Model default",
+                    match self.config.local.context_size {
+                        0 => String::new(),
+                        size => size.to_string(),
+                    },
+                )
+                .on_input(Message::LocalContextSizeChanged),
+            ));
+
+        let custom = self.config.custom_generation;
+        let temperature = custom.temperature.unwrap_or(1.0);
+        let top_p = custom.top_p.unwrap_or(0.95);
+        let presence_penalty = custom.presence_penalty.unwrap_or_default();
+        let frequency_penalty = custom.frequency_penalty.unwrap_or_default();
+        let generation = widget::settings::section()
+            .title("Custom preset")
+            .add(widget::settings::item(
+                format!("Temperature ({temperature:.2})"),
+                widget::slider(0.0..=2.0, temperature, Message::TemperatureChanged).step(0.05),
+            ))
+            .add(widget::settings::item(
+                format!("Top P ({top_p:.2})"),
+                widget::slider(0.0..=1.0, top_p, Message::TopPChanged).step(0.01),
+            ))
+            .add(widget::settings::item(
+                "Top K",
+                widget::text_input(
+                    "Model default",
+                    custom.top_k.map(|k| k.to_string()).unwrap_or_default(),
+                )
+                .on_input(Message::TopKChanged),
+            ))
+            .add(widget::settings::item(
+                "Max output tokens",
+                widget::text_input(
+                    "Model default",
+                    custom
+                        .max_output_tokens
+                        .map(|tokens| tokens.to_string())
+                        .unwrap_or_default(),
+                )
+                .on_input(Message::MaxOutputTokensChanged),
+            ))
+            .add(widget::settings::item(
+                format!("Presence penalty ({presence_penalty:.1})"),
+                widget::slider(
+                    -2.0..=2.0,
+                    presence_penalty,
+                    Message::PresencePenaltyChanged,
+                )
+                .step(0.1),
+            ))
+            .add(widget::settings::item(
+                format!("Frequency penalty ({frequency_penalty:.1})"),
+                widget::slider(
+                    -2.0..=2.0,
+                    frequency_penalty,
+                    Message::FrequencyPenaltyChanged,
+                )
+                .step(0.1),
+            ));
+
+        let media = widget::settings::section()
+            .title("Media")
+            .add(widget::settings::item(
+                "Response output",
+                widget::dropdown(
+                    &ResponseModalities::NAMES,
+                    Some(self.config.response_modalities.index()),
+                    Message::ResponseModalitiesSelected,
+                ),
+            ))
+            .add(widget::settings::item(
+                "Input media resolution",
+                widget::dropdown(
+                    &MediaResolution::NAMES,
+                    Some(self.config.media_resolution.index()),
+                    Message::MediaResolutionSelected,
+                ),
+            ));
+
+        let cap_text = |draft: &Option<String>, cap: f64| {
+            draft.clone().unwrap_or_else(|| {
+                if cap > 0.0 {
+                    format!("{cap:.2}")
+                } else {
+                    String::new()
+                }
+            })
+        };
+        let spending = widget::settings::section()
+            .title("Spending")
+            .add(widget::text::caption(
+                "Prompts whose estimated cost in US dollars would go over a cap are held back \
+                 until confirmed. Estimates use list prices and may differ from the bill.",
+            ))
+            .add(widget::settings::item(
+                "Per conversation",
+                widget::text_input(
+                    "No cap",
+                    cap_text(
+                        &state.conversation_cap_draft,
+                        self.config.cost_cap.per_conversation,
+                    ),
+                )
+                .on_input(Message::ConversationCapChanged),
+            ))
+            .add(widget::settings::item(
+                "Per day",
+                widget::text_input(
+                    "No cap",
+                    cap_text(&state.daily_cap_draft, self.config.cost_cap.per_day),
+                )
+                .on_input(Message::DailyCapChanged),
+            ))
+            .add(widget::settings::item(
+                "Per prompt",
+                widget::text_input(
+                    "No cap",
+                    cap_text(&state.prompt_cap_draft, self.config.cost_cap.per_prompt),
+                )
+                .on_input(Message::PromptCapChanged),
+            ));
+
+        let replies = widget::settings::section()
+            .title("Replies")
+            .add(widget::settings::item(
+                "Instructions",
+                widget::text_input(
+                    "Instructions for the model",
+                    self.config.instructions.as_str(),
+                )
+                .on_input(Message::InstructionsChanged),
+            ))
+            .add(widget::text::caption(
+                "Given to the model in every conversation, e.g. \"Answer briefly\". Workspace \
+                 and template instructions are added after them.",
+            ))
+            .add(widget::settings::item(
+                "Reply language",
+                widget::text_input("Same as the prompt", self.config.reply_language.as_str())
+                    .on_input(Message::ReplyLanguageChanged),
+            ))
+            .add(widget::settings::item(
+                "Open cited lines with",
+                widget::text_input("Default application", self.config.editor_command.as_str())
+                    .on_input(Message::EditorCommandChanged),
+            ))
+            .add(widget::text::caption(
+                "For citations of pinned files, e.g. code --goto {file}:{line}",
+            ))
+            .add(widget::settings::item(
+                "Suggest follow-up questions",
+                widget::toggler(self.config.follow_up_suggestions)
+                    .on_toggle(Message::FollowUpSuggestionsToggled),
+            ))
+            .add(widget::settings::item(
+                "Title conversations",
+                widget::toggler(self.config.auto_titles).on_toggle(Message::AutoTitlesToggled),
+            ))
+            .add(widget::text::caption(
+                "Follow-up questions and titles are requested in the background, after the \
+                 answer and at most a few per minute.",
+            ))
+            .add(widget::settings::item(
+                "Daily token cap for background requests",
+                widget::text_input(
+                    "No cap",
+                    match self.config.background_token_cap {
+                        0 => String::new(),
+                        cap => cap.to_string(),
+                    },
+                )
+                .on_input(Message::BackgroundTokenCapChanged),
+            ));
+
+        let limits = self.config.watch_limits;
+        let watching = widget::settings::section()
+            .title("Watched files")
+            .add(widget::text::caption(
+                "Watching stops once either limit is reached, in case each answer changes a \
+                 watched file again.",
+            ))
+            .add(widget::settings::item(
+                "Prompts in a row",
+                widget::text_input("No limit", limit_text(limits.max_prompts))
+                    .on_input(Message::WatchMaxPromptsChanged),
+            ))
+            .add(widget::settings::item(
+                "Minutes",
+                widget::text_input("No limit", limit_text(limits.max_minutes))
+                    .on_input(Message::WatchMaxMinutesChanged),
+            ));
+
+        let mut quick_actions =
+            widget::settings::section()
+                .title("Quick actions")
+                .add(widget::text::caption(format!(
+                    "Run from the palette, opened by right-clicking the panel icon. {} in the \
+                 prompt is replaced by the action's input.",
+                    QuickAction::PLACEHOLDER
+                )));
+        for (index, action) in self.config.quick_actions.iter().enumerate() {
+            quick_actions = quick_actions.add(
+                widget::column::with_children(vec![
+                    widget::row::with_children(vec![
+                        widget::text_input("Name", action.name.as_str())
+                            .on_input(move |name| Message::QuickActionNameChanged(index, name))
+                            .into(),
+                        widget::dropdown(
+                            &QuickActionInput::NAMES,
+                            Some(action.input.index()),
+                            move |input| Message::QuickActionInputSelected(index, input),
+                        )
+                        .into(),
+                        widget::button::icon(widget::icon::from_name("edit-delete-symbolic"))
+                            .on_press(Message::RemoveQuickAction(index))
+                            .into(),
+                    ])
+                    .spacing(8)
+                    .align_y(cosmic::iced::Alignment::Center)
+                    .into(),
+                    widget::text_input("Summarize this: {input}", action.template.as_str())
+                        .on_input(move |template| {
+                            Message::QuickActionTemplateChanged(index, template)
+                        })
+                        .into(),
+                ])
+                .spacing(4),
+            );
+        }
+        quick_actions = quick_actions
+            .add(widget::button::standard("Add quick action").on_press(Message::AddQuickAction));
+
+        let mut templates = widget::settings::section()
+            .title("Conversation templates")
+            .add(widget::text::caption(
+                "Save a conversation as a template from the templates page, then start new \
+                 conversations from it with the same setup.",
+            ));
+        for (index, template) in self.config.templates.iter().enumerate() {
+            let mut fields = vec![
+                widget::row::with_children(vec![
+                    widget::text_input("Name", template.name.as_str())
+                        .on_input(move |name| Message::TemplateNameChanged(index, name))
+                        .into(),
+                    widget::button::icon(widget::icon::from_name("edit-delete-symbolic"))
+                        .on_press(Message::RemoveTemplate(index))
+                        .into(),
+                ])
+                .spacing(8)
+                .align_y(cosmic::iced::Alignment::Center)
+                .into(),
+                widget::settings::item(
+                    "Provider",
+                    widget::dropdown(
+                        &TEMPLATE_PROVIDERS,
+                        Some(template.provider.map_or(0, |provider| provider.index() + 1)),
+                        move |provider| Message::TemplateProviderSelected(index, provider),
+                    ),
+                )
+                .into(),
+                widget::settings::item(
+                    "Model",
+                    widget::text_input("Workspace model", template.model.as_str())
+                        .on_input(move |model| Message::TemplateModelChanged(index, model)),
+                )
+                .into(),
+                widget::text_input("Instructions for the model", template.instructions.as_str())
+                    .on_input(move |instructions| {
+                        Message::TemplateInstructionsChanged(index, instructions)
+                    })
+                    .into(),
+                widget::text_input("First message", template.first_message.as_str())
+                    .on_input(move |message| Message::TemplateFirstMessageChanged(index, message))
+                    .into(),
+            ];
+            if !template.pinned_files.is_empty() {
+                fields.push(
+                    widget::text::caption(format!("Pins {}", template.pinned_files.join(", ")))
+                        .into(),
+                );
+            }
+            templates = templates.add(widget::column::with_children(fields).spacing(4));
+        }
+
+        let mut workspaces =
+            widget::settings::section()
+                .title("Workspaces")
+                .add(widget::text::caption(
+                    "Each workspace keeps its own conversation, and its settings replace the \
+                 global ones while it is selected in the header.",
+                ));
+        for (index, workspace) in self.config.workspaces.iter().enumerate() {
+            let name_error = state
+                .workspace_name_error
+                .as_ref()
+                .filter(|(errored, _)| *errored == index)
+                .map(|(_, error)| error.as_str());
+            workspaces = workspaces.add(
+                widget::column::with_children(vec![
+                    widget::row::with_children(vec![
+                        widget::text_input("Name", workspace.name.as_str())
+                            .on_input(move |name| Message::WorkspaceNameChanged(index, name))
+                            .into(),
+                        widget::button::icon(widget::icon::from_name("edit-delete-symbolic"))
+                            .on_press(Message::RemoveWorkspace(index))
+                            .into(),
+                    ])
+                    .spacing(8)
+                    .align_y(cosmic::iced::Alignment::Center)
+                    .into(),
+                    widget::text::caption(name_error.unwrap_or_default()).into(),
+                    widget::settings::item(
+                        "Provider",
+                        widget::dropdown(
+                            &WORKSPACE_PROVIDERS,
+                            Some(
+                                workspace
+                                    .provider
+                                    .map_or(0, |provider| provider.index() + 1),
+                            ),
+                            move |provider| Message::WorkspaceProviderSelected(index, provider),
+                        ),
+                    )
+                    .into(),
+                    widget::settings::item(
+                        "Model",
+                        widget::text_input("Global model", workspace.model.as_str())
+                            .on_input(move |model| Message::WorkspaceModelChanged(index, model)),
+                    )
+                    .into(),
+                    widget::settings::item(
+                        "Preset",
+                        widget::dropdown(
+                            &WORKSPACE_PRESETS,
+                            Some(workspace.preset.map_or(0, |preset| preset.index() + 1)),
+                            move |preset| Message::WorkspacePresetSelected(index, preset),
+                        ),
+                    )
+                    .into(),
+                    widget::text_input(
+                        "Instructions for the model",
+                        workspace.instructions.as_str(),
+                    )
+                    .on_input(move |instructions| {
+                        Message::WorkspaceInstructionsChanged(index, instructions)
+                    })
+                    .into(),
+                    widget::settings::item(
+                        "Folder",
+                        widget::text_input("~/Projects/example", workspace.directory.as_str())
+                            .on_input(move |directory| {
+                                Message::WorkspaceDirectoryChanged(index, directory)
+                            }),
+                    )
+                    .into(),
+                    widget::settings::item(
+                        "Environment",
+                        widget::text_input("NAME=value", workspace.environment.as_str()).on_input(
+                            move |environment| {
+                                Message::WorkspaceEnvironmentChanged(index, environment)
+                            },
+                        ),
+                    )
+                    .into(),
+                ])
+                .spacing(4),
+            );
+        }
+        workspaces = workspaces
+            .add(widget::button::standard("Add workspace").on_press(Message::AddWorkspace));
+
+        let mut personas =
+            widget::settings::section()
+                .title("Personas")
+                .add(widget::text::caption(
+                    "Characters the model plays, switched with the persona chip above the \
+                 conversation. Their instructions follow the standing ones.",
+                ));
+        for (index, persona) in self.config.personas.iter().enumerate() {
+            personas = personas.add(
+                widget::column::with_children(vec![
+                    widget::row::with_children(vec![
+                        widget::text_input("Name", persona.name.as_str())
+                            .on_input(move |name| Message::PersonaNameChanged(index, name))
+                            .into(),
+                        widget::button::icon(widget::icon::from_name("edit-delete-symbolic"))
+                            .on_press(Message::RemovePersona(index))
+                            .into(),
+                    ])
+                    .spacing(8)
+                    .align_y(cosmic::iced::Alignment::Center)
+                    .into(),
+                    widget::settings::item(
+                        "Preset",
+                        widget::dropdown(
+                            &WORKSPACE_PRESETS,
+                            Some(persona.preset.map_or(0, |preset| preset.index() + 1)),
+                            move |preset| Message::PersonaPresetSelected(index, preset),
+                        ),
+                    )
+                    .into(),
+                    widget::text_input("Instructions for the model", persona.instructions.as_str())
+                        .on_input(move |instructions| {
+                            Message::PersonaInstructionsChanged(index, instructions)
+                        })
+                        .into(),
+                ])
+                .spacing(4),
+            );
+        }
+        personas =
+            personas.add(widget::button::standard("Add persona").on_press(Message::AddPersona));
+
+        let mut scripts = widget::settings::section()
+            .title("Scripts")
+            .add(widget::settings::item(
+                "Run scripts on prompts and answers",
+                widget::toggler(self.config.scripting).on_toggle(Message::ScriptingToggled),
+            ));
+        if self.config.scripting {
+            let loaded: Vec<&str> = self.scripts.names().collect();
+            let commands: Vec<String> = self
+                .scripts
+                .commands()
+                .map(|command| format!("/{command}"))
+                .collect();
+            scripts = scripts
+                .add(widget::text::caption(format!(
+                    "Rhai scripts are loaded from {}.",
+                    scripting::dir().unwrap_or_default().display()
+                )))
+                .add(widget::text::caption(if loaded.is_empty() {
+                    "No scripts loaded.".to_string()
+                } else {
+                    format!("Loaded: {}", loaded.join(", "))
+                }));
+            if !commands.is_empty() {
+                scripts = scripts.add(widget::text::caption(format!(
+                    "Commands: {}",
+                    commands.join(", ")
+                )));
+            }
+            for error in &self.scripts.errors {
+                scripts = scripts.add(widget::text::caption(error.as_str()));
+            }
+            scripts = scripts
+                .add(widget::button::standard("Reload scripts").on_press(Message::ReloadScripts));
+        }
+
+        let mut spelling =
+            widget::settings::section()
+                .title("Spelling")
+                .add(widget::settings::item(
+                    "Check spelling of prompts",
+                    widget::toggler(self.config.spellcheck).on_toggle(Message::SpellcheckToggled),
+                ));
+        if self.config.spellcheck {
+            spelling = spelling.add(widget::settings::item(
+                "Dictionary",
+                widget::text_input(
+                    spellcheck::dictionary(""),
+                    self.config.spellcheck_language.as_str(),
+                )
+                .on_input(Message::SpellcheckLanguageChanged),
+            ));
+        }
+
+        let mut regeneration =
+            widget::settings::section()
+                .title("Regeneration")
+                .add(widget::settings::item(
+                    "Keep both answers and pick the better one",
+                    widget::toggler(self.config.ab_regeneration)
+                        .on_toggle(Message::AbRegenerationToggled),
+                ));
+        for (source, tally) in &state.preferences {
+            regeneration = regeneration.add(widget::settings::item(
+                source.as_str(),
+                widget::text::body(format!(
+                    "preferred {} of {}",
+                    tally.wins,
+                    tally.wins + tally.losses
+                )),
+            ));
+        }
+
+        let mut speech =
+            widget::settings::section()
+                .title("Read aloud")
+                .add(widget::settings::item(
+                    "Voice engine",
+                    widget::dropdown(
+                        &SpeechEngine::NAMES,
+                        Some(self.config.speech_engine.index()),
+                        Message::SpeechEngineSelected,
+                    ),
+                ));
+        if self.config.speech_engine == SpeechEngine::Piper {
+            if !state.voices.is_empty() {
+                speech = speech.add(widget::settings::item(
+                    "Voice",
+                    widget::dropdown(
+                        &state.voices,
+                        state
+                            .voices
+                            .iter()
+                            .position(|voice| *voice == self.config.piper_voice),
+                        Message::VoiceSelected,
+                    ),
+                ));
+            }
+            speech = speech.add(
+                widget::row::with_children(vec![
+                    widget::text_input("en_US-lessac-medium", &state.voice_download)
+                        .on_input(Message::VoiceDownloadChanged)
+                        .on_submit(|_| Message::DownloadVoice)
+                        .width(cosmic::iced::Length::Fill)
+                        .into(),
+                    widget::button::standard(if state.downloading_voice {
+                        "Downloading…"
+                    } else {
+                        "Download voice"
+                    })
+                    .on_press_maybe((!state.downloading_voice).then_some(Message::DownloadVoice))
+                    .into(),
+                ])
+                .spacing(8)
+                .align_y(cosmic::iced::Alignment::Center),
+            );
+            if let Some(status) = &state.voice_status {
+                speech = speech.add(widget::text::caption(status.as_str()));
+            }
+        }
+
+        let mut sharing = widget::settings::section()
+            .title("Sharing")
+            .add(widget::settings::item(
+                "Upload shared conversations to",
+                widget::dropdown(
+                    &ShareTarget::NAMES,
+                    Some(self.config.share.target.index()),
+                    Message::ShareTargetSelected,
+                ),
+            ));
+        sharing = match self.config.share.target {
+            ShareTarget::Paste => sharing.add(
+                widget::text_input(
+                    "https://paste.example/",
+                    self.config.share.paste_url.as_str(),
+                )
+                .on_input(Message::PasteUrlChanged),
+            ),
+            ShareTarget::Gist => sharing.add(widget::text::caption(format!(
+                "Gists are created with the token in {}.",
+                paste::GITHUB_TOKEN_VAR
+            ))),
+        };
+
+        let mut safety = widget::settings::section()
+            .title("Safety")
+            .add(widget::settings::item(
+                "Preset",
+                widget::dropdown(
+                    &SafetyPreset::NAMES,
+                    Some(self.config.safety.preset.index()),
+                    Message::SafetyPresetSelected,
+                ),
+            ));
+        for category in HarmCategory::ALL {
+            let threshold = self
+                .config
+                .safety
+                .preset
+                .threshold()
+                .unwrap_or_else(|| self.config.safety.custom(category));
+            let label = if self.config.safety.preset == SafetyPreset::Default {
+                "Model default"
+            } else {
+                threshold.name()
+            };
+            safety = safety.add(widget::settings::item(
+                format!("{} ({label})", category.name()),
+                widget::slider(
+                    0..=(HarmThreshold::ALL.len() - 1) as u8,
+                    threshold.index() as u8,
+                    move |index| Message::HarmThresholdChanged(category, index),
+                ),
+            ));
+        }
+
+        let mut redaction = widget::settings::section().title("Sensitive data in prompts");
+        for pattern in Pattern::ALL {
+            redaction = redaction.add(widget::settings::item(
+                pattern.name(),
+                widget::dropdown(
+                    &RedactionAction::NAMES,
+                    Some(self.config.redaction.action(pattern).index()),
+                    move |index| Message::RedactionSelected(pattern, index),
+                ),
+            ));
+        }
+
+        let mut backups = widget::settings::section()
+            .title("Backups")
+            .add(widget::settings::item(
+                "Backup folder",
+                widget::text_input("Choose a folder", self.config.backup.folder.as_str())
+                    .on_input(Message::BackupFolderChanged),
+            ))
+            .add(widget::settings::item(
+                "Back up",
+                widget::dropdown(
+                    &BackupInterval::NAMES,
+                    Some(self.config.backup.interval.index()),
+                    Message::BackupIntervalSelected,
+                ),
+            ))
+            .add(widget::settings::item(
+                "Backups to keep",
+                widget::text_input("7", self.config.backup.keep.to_string())
+                    .on_input(Message::BackupKeepChanged),
+            ))
+            .add(
+                widget::button::standard(if state.backup_running {
+                    "Working…"
+                } else {
+                    "Back up now"
+                })
+                .on_press_maybe(
+                    (!state.backup_running && !self.config.backup.folder.trim().is_empty())
+                        .then_some(Message::BackUpNow),
+                ),
+            );
+        for (index, backup) in state.backups.iter().enumerate() {
+            backups = backups.add(widget::settings::item(
+                format_age(backup.created),
+                widget::button::text("Restore").on_press_maybe(
+                    (!state.backup_running).then_some(Message::RestoreBackup(index)),
+                ),
+            ));
+        }
+        if let Some(status) = &state.backup_status {
+            backups = backups.add(widget::text::caption(status.as_str()));
+        }
+
+        let mut disk = widget::settings::section()
+            .title("Storage")
+            .add(widget::settings::item(
+                "Restore conversations after a restart",
+                widget::toggler(!self.config.forget_conversations)
+                    .on_toggle(Message::RestoreConversationsToggled),
+            ));
+        for (label, size) in &state.storage {
+            disk = disk.add(widget::settings::item(
+                label.as_str(),
+                widget::text::body(super::downloads::format_size(*size)),
+            ));
+        }
+        disk = disk
+            .add(widget::text::caption(format!(
+                "{} in total",
+                super::downloads::format_size(state.storage.iter().map(|(_, size)| size).sum())
+            )))
+            .add(
+                widget::button::standard(if state.cleaning_up {
+                    "Cleaning up…"
+                } else {
+                    "Clean up"
+                })
+                .on_press_maybe((!state.cleaning_up).then_some(Message::CleanUpStorage)),
+            )
+            .add(widget::text::caption(
+                "Removes generated images no conversation links to anymore and files left \
+                 half-written, once they are a day old.",
+            ));
+        if let Some(status) = &state.cleanup_status {
+            disk = disk.add(widget::text::caption(status.as_str()));
+        }
+
+        let sync_settings = &self.config.sync;
+        let mut syncing = widget::settings::section()
+            .title("Sync")
+            .add(widget::settings::item(
+                "Sync with a server",
+                widget::toggler(sync_settings.enabled).on_toggle(Message::SyncToggled),
+            ))
+            .add(widget::settings::item(
+                "Server",
+                widget::dropdown(
+                    &SyncBackend::NAMES,
+                    Some(sync_settings.backend.index()),
+                    Message::SyncBackendSelected,
+                ),
+            ));
+        syncing = match sync_settings.backend {
+            SyncBackend::WebDav => syncing
+                .add(
+                    widget::text_input(
+                        "https://cloud.example/remote.php/dav/files/me/clankers",
+                        sync_settings.url.as_str(),
+                    )
+                    .on_input(Message::SyncUrlChanged),
+                )
+                .add(widget::settings::item(
+                    "User name",
+                    widget::text_input("", sync_settings.username.as_str())
+                        .on_input(Message::SyncUsernameChanged),
+                )),
+            SyncBackend::S3 => syncing
+                .add(
+                    widget::text_input("https://s3.example.com", sync_settings.url.as_str())
+                        .on_input(Message::SyncUrlChanged),
+                )
+                .add(widget::settings::item(
+                    "Bucket",
+                    widget::text_input("", sync_settings.bucket.as_str())
+                        .on_input(Message::SyncBucketChanged),
+                ))
+                .add(widget::settings::item(
+                    "Region",
+                    widget::text_input("us-east-1", sync_settings.region.as_str())
+                        .on_input(Message::SyncRegionChanged),
+                ))
+                .add(widget::settings::item(
+                    "Access key id",
+                    widget::text_input("", sync_settings.username.as_str())
+                        .on_input(Message::SyncUsernameChanged),
+                )),
+        };
+        syncing = syncing
+            .add(widget::text::caption(format!(
+                "The password or secret key is read from {}. Data is encrypted with the \
+                 passphrase in {}, which must be the same on every machine.",
+                sync::SECRET_VAR,
+                sync::PASSPHRASE_VAR
+            )))
+            .add(
+                widget::button::standard(if state.sync_running {
+                    "Syncing…"
+                } else {
+                    "Sync now"
+                })
+                .on_press_maybe(
+                    (sync_settings.enabled && !state.sync_running).then_some(Message::SyncNow),
+                ),
+            );
+        if let Some(status) = &state.sync_status {
+            syncing = syncing.add(widget::text::caption(status.as_str()));
+        }
+
+        let mut panel = widget::settings::section()
+            .title("Panel")
+            .add(widget::settings::item(
+                "Icon",
+                widget::dropdown(
+                    &PanelIcon::NAMES,
+                    Some(self.config.panel_icon.index()),
+                    Message::PanelIconSelected,
+                ),
+            ))
+            .add(widget::settings::item(
+                "Label",
+                widget::dropdown(
+                    &PanelLabel::NAMES,
+                    Some(self.config.panel_label_source.index()),
+                    Message::PanelLabelSelected,
+                ),
+            ));
+        if self.config.panel_label_source == PanelLabel::Text {
+            panel = panel.add(widget::settings::item(
+                "Text",
+                widget::text_input("Icon only", self.config.panel_label.as_str())
+                    .on_input(Message::PanelLabelChanged),
+            ));
+        }
+        panel = panel.add(widget::text::caption(
+            "Shown beside the icon on horizontal panels and under it on vertical ones.",
+        ));
+
+        let debugging = widget::settings::section()
+            .title("Debugging")
+            .add(widget::settings::item(
+                "Show debug inspector",
+                widget::toggler(self.config.debug_inspector)
+                    .on_toggle(Message::DebugInspectorToggled),
+            ))
+            .add(widget::settings::item(
+                "Token probabilities (top alternatives)",
+                widget::text_input(
+                    "Off",
+                    custom
+                        .logprobs
+                        .map(|count| count.to_string())
+                        .unwrap_or_default(),
+                )
+                .on_input(Message::LogprobsChanged),
+            ))
+            .add(widget::settings::item(
+                "Alert when a model's serving version changes",
+                widget::toggler(self.config.version_alerts)
+                    .on_toggle(Message::VersionAlertsToggled),
+            ))
+            .add(widget::settings::item(
+                "Lenient parsing (skip fields that don't parse)",
+                widget::toggler(self.config.lenient_parsing)
+                    .on_toggle(Message::LenientParsingToggled),
+            ))
+            .add(widget::settings::item(
+                "Show the raw response when parsing fails",
+                widget::toggler(self.config.raw_on_parse_error)
+                    .on_toggle(Message::RawOnParseErrorToggled),
+            ))
+            .add(widget::settings::item(
+                "Benchmark models",
+                widget::button::standard("Open diagnostics").on_press(Message::OpenDiagnostics),
+            ));
+
+        let metrics = widget::settings::section()
+            .title("Metrics")
+            .add(widget::text::caption(
+                "Request counts, failures and latencies in the Prometheus text format, counted \
+                 since the applet started.",
+            ))
+            .add(widget::settings::item(
+                "Write to file",
+                widget::text_input("Off", self.config.metrics.file.as_str())
+                    .on_input(Message::MetricsFileChanged),
+            ))
+            .add(widget::settings::item(
+                "Serve on localhost port",
+                widget::text_input(
+                    "Off",
+                    match self.config.metrics.port {
+                        0 => String::new(),
+                        port => port.to_string(),
+                    },
+                )
+                .on_input(Message::MetricsPortChanged),
+            ));
+
+        widget::scrollable(widget::settings::view_column(vec![
+            model.into(),
+            openai_section.into(),
+            compatible_section.into(),
+            local_section.into(),
+            generation.into(),
+            replies.into(),
+            watching.into(),
+            spending.into(),
+            workspaces.into(),
+            personas.into(),
+            quick_actions.into(),
+            templates.into(),
+            scripts.into(),
+            spelling.into(),
+            media.into(),
+            regeneration.into(),
+            safety.into(),
+            speech.into(),
+            redaction.into(),
+            sharing.into(),
+            headers.into(),
+            widget::settings::section()
+                .title("Import and export")
+                .add(transfer)
+                .into(),
+            backups.into(),
+            disk.into(),
+            syncing.into(),
+            panel.into(),
+            debugging.into(),
+            metrics.into(),
+            policy.into(),
+        ]))
+        .height(cosmic::iced::Length::Fill)
+        .into()
+    }
+
+    pub(super) fn update_settings(
+        &mut self,
+        message: Message,
+    ) -> Task<cosmic::Action<super::Message>> {
+        // Only unlocking is allowed while the policy is locked.
+        if self.config.is_locked()
+            && !matches!(
+                message,
+                Message::PassphraseChanged(_)
+                    | Message::UnlockPolicy
+                    | Message::BackupFinished(_)
+                    | Message::SyncFinished(_)
+                    | Message::RefreshStorage
+                    | Message::StorageMeasured(_)
+            )
+        {
+            return Task::none();
+        }
+
+        match message {
+            Message::TransferPathChanged(path) => {
+                self.settings.transfer_path = path;
+            }
+            Message::ExportSecretsToggled(export_secrets) => {
+                self.settings.export_secrets = export_secrets;
+            }
+            Message::Export => {
+                let path = PathBuf::from(&self.settings.transfer_path);
+                self.settings.status = Some(
+                    match self.config.export(&path, self.settings.export_secrets) {
+                        Ok(()) => format!("Exported settings to {}", path.display()),
+                        Err(err) => format!("Export failed: {err}"),
+                    },
+                );
+            }
+            Message::Import => {
+                let path = PathBuf::from(&self.settings.transfer_path);
+                self.settings.status = Some(match self.config.import(&path) {
+                    Ok(config) => {
+                        self.config = config;
+                        self.save_config();
+                        format!("Imported settings from {}", path.display())
+                    }
+                    Err(err) => format!("Import failed: {err}"),
+                });
+            }
+            Message::AddHeader => {
+                self.config.gemini.headers.push(HttpHeader::default());
+                self.save_config();
+            }
+            Message::RemoveHeader(index) => {
+                if index < self.config.gemini.headers.len() {
+                    self.config.gemini.headers.remove(index);
+                    self.save_config();
+                }
+            }
+            Message::HeaderNameChanged(index, name) => {
+                if let Some(header) = self.config.gemini.headers.get_mut(index) {
+                    header.name = name;
+                    self.save_config();
+                }
+            }
+            Message::HeaderValueChanged(index, value) => {
+                if let Some(header) = self.config.gemini.headers.get_mut(index) {
+                    header.value = value;
+                    self.save_config();
+                }
+            }
+            Message::HeaderSecretToggled(index, secret) => {
+                if let Some(header) = self.config.gemini.headers.get_mut(index) {
+                    header.secret = secret;
+                    self.save_config();
+                }
+            }
+            Message::TemperatureChanged(temperature) => {
+                self.config.custom_generation.temperature = Some(temperature);
+                self.save_config();
+            }
+            Message::TopPChanged(top_p) => {
+                self.config.custom_generation.top_p = Some(top_p);
+                self.save_config();
+            }
+            Message::TopKChanged(top_k) => {
+                let top_k = top_k.trim();
+                if top_k.is_empty() {
+                    self.config.custom_generation.top_k = None;
+                } else if let Ok(top_k) = top_k.parse() {
+                    self.config.custom_generation.top_k = Some(top_k);
+                } else {
+                    return Task::none();
+                }
+                self.save_config();
+            }
+            Message::MaxOutputTokensChanged(tokens) => {
+                let tokens = tokens.trim();
+                if tokens.is_empty() {
+                    self.config.custom_generation.max_output_tokens = None;
+                } else if let Ok(tokens) = tokens.parse() {
+                    self.config.custom_generation.max_output_tokens = Some(tokens);
+                } else {
+                    return Task::none();
+                }
+                self.save_config();
+            }
+            Message::PresencePenaltyChanged(penalty) => {
+                self.config.custom_generation.presence_penalty = Some(penalty);
+                self.save_config();
+            }
+            Message::FrequencyPenaltyChanged(penalty) => {
+                self.config.custom_generation.frequency_penalty = Some(penalty);
+                self.save_config();
+            }
+            Message::PanelIconSelected(index) => {
+                if let Some(icon) = PanelIcon::ALL.get(index) {
+                    self.config.panel_icon = *icon;
+                    self.save_config();
+                }
+            }
+            Message::PanelLabelSelected(index) => {
+                if let Some(label) = PanelLabel::ALL.get(index) {
+                    self.config.panel_label_source = *label;
+                    self.save_config();
+                }
+            }
+            Message::PanelLabelChanged(label) => {
+                self.config.panel_label = label;
+                self.save_config();
+            }
+            Message::DebugInspectorToggled(enabled) => {
+                self.config.debug_inspector = enabled;
+                self.save_config();
+            }
+            Message::VersionAlertsToggled(enabled) => {
+                self.config.version_alerts = enabled;
+                self.save_config();
+            }
+            Message::LenientParsingToggled(enabled) => {
+                self.config.lenient_parsing = enabled;
+                self.save_config();
+            }
+            Message::RawOnParseErrorToggled(enabled) => {
+                self.config.raw_on_parse_error = enabled;
+                self.save_config();
+            }
+            Message::ModelChanged(model) => {
+                self.config.gemini.model = model;
+                self.save_config();
+            }
+            Message::ModelSelected(index) => {
+                if let Some(model) = self.settings.models.get(index) {
+                    self.config.gemini.model.clone_from(model);
+                    self.save_config();
+                }
+            }
+            Message::KeyChanged(slot, key) => {
+                self.settings.key_drafts.insert(slot, key);
+            }
+            Message::StoreKey(slot) => {
+                let key = self
+                    .settings
+                    .key_drafts
+                    .get(&slot)
+                    .cloned()
+                    .unwrap_or_default();
+                return self.store_key(slot, key);
+            }
+            Message::MoveKeyFromEnv(slot) => {
+                if let Some(key) = slot.var().and_then(credentials::lookup) {
+                    self.settings.key_drafts.insert(slot, key.clone());
+                    return self.store_key(slot, key);
+                }
+            }
+            Message::KeyStored(slot, result) => {
+                let status = match result {
+                    Ok(()) => {
+                        self.legacy_key(slot).clear();
+                        self.save_config();
+                        if keyring::get(slot).is_some() {
+                            let provider = match slot {
+                                Slot::Gemini => Provider::Gemini,
+                                Slot::OpenAi => Provider::OpenAi,
+                                Slot::Compatible => Provider::Compatible,
+                            };
+                            if self
+                                .api_key_help
+                                .is_some_and(|help| help.provider == provider)
+                            {
+                                self.api_key_help = None;
+                            }
+                            "Saved in the keyring.".to_string()
+                        } else {
+                            "Removed from the keyring.".to_string()
+                        }
+                    }
+                    Err(err) => format!("Could not save the key: {err}"),
+                };
+                self.settings.key_status.insert(slot, status);
+            }
+            Message::KeyringLoaded(result) => {
+                if let Err(err) = result {
+                    eprintln!("failed to read the keyring: {err}");
+                    for slot in Slot::ALL {
+                        self.settings
+                            .key_status
+                            .insert(slot, format!("Could not read the keyring: {err}"));
+                    }
+                    return Task::none();
+                }
+                let mut tasks = Vec::new();
+                let mut cleared = false;
+                for slot in Slot::ALL {
+                    // Keys saved in the config by earlier versions move to the keyring,
+                    // unless it has one already, which is newer and kept.
+                    let legacy = self.legacy_key(slot).trim().to_string();
+                    if let Some(key) = keyring::get(slot) {
+                        self.settings.key_drafts.insert(slot, key);
+                        if !legacy.is_empty() {
+                            self.legacy_key(slot).clear();
+                            cleared = true;
+                        }
+                    } else if !legacy.is_empty() {
+                        self.settings.key_drafts.insert(slot, legacy.clone());
+                        tasks.push(self.store_key(slot, legacy));
+                    }
+                }
+                if cleared {
+                    self.save_config();
+                }
+                return Task::batch(tasks);
+            }
+            Message::GeminiProjectChanged(project) => {
+                self.config.gemini.project = project;
+                self.save_config();
+            }
+            Message::GeminiLocationChanged(location) => {
+                self.config.gemini.location = location;
+                self.save_config();
+            }
+            Message::OpenAiModelChanged(model) => {
+                self.config.openai.model = model;
+                self.save_config();
+            }
+            Message::OpenAiOrganizationChanged(organization) => {
+                self.config.openai.organization = organization;
+                self.save_config();
+            }
+            Message::OpenAiProjectChanged(project) => {
+                self.config.openai.project = project;
+                self.save_config();
+            }
+            Message::OpenAiModelSelected(index) => {
+                if let Some(model) = self.settings.openai_models.get(index) {
+                    self.config.openai.model.clone_from(model);
+                    self.save_config();
+                }
+            }
+            Message::CompatibleUrlChanged(url) => {
+                self.config.compatible.base_url = url;
+                self.settings.compatible_models.clear();
+                self.save_config();
+            }
+            Message::CompatibleModelChanged(model) => {
+                self.config.compatible.model = model;
+                self.save_config();
+            }
+            Message::CompatibleModelSelected(index) => {
+                if let Some(model) = self.settings.compatible_models.get(index) {
+                    self.config.compatible.model.clone_from(model);
+                    self.save_config();
+                }
+            }
+            Message::ProviderSelected(index) => {
+                if let Some(provider) = Provider::ALL.get(index)
+                    && (!self.config.local_only
+                        || *provider == Provider::Local
+                        || (*provider == Provider::Compatible
+                            && self.config.compatible.is_loopback()))
+                {
+                    self.config.provider = *provider;
+                    self.save_config();
+                }
+                if self.config.provider == Provider::Local {
+                    self.refresh_local_models();
+                }
+            }
+            Message::LocalOnlyToggled(local_only) => {
+                self.config.local_only = local_only;
+                if local_only && !self.config.runs_locally() {
+                    self.config.provider = Provider::Local;
+                    self.refresh_local_models();
+                }
+                self.save_config();
+            }
+            Message::LocalModelPathChanged(path) => {
+                self.config.local.model_path = path;
+                self.save_config();
+            }
+            Message::LocalModelSelected(index) => {
+                if let Some(path) = self.settings.local_models.get(index) {
+                    self.config.local.model_path = path.to_string_lossy().into_owned();
+                    self.save_config();
+                }
+            }
+            Message::RefreshLocalModels => {
+                self.refresh_local_models();
+            }
+            Message::SpeechEngineSelected(index) => {
+                if let Some(engine) = SpeechEngine::ALL.get(index) {
+                    self.config.speech_engine = *engine;
+                    self.save_config();
+                }
+            }
+            Message::VoiceSelected(index) => {
+                if let Some(voice) = self.settings.voices.get(index) {
+                    self.config.piper_voice.clone_from(voice);
+                    self.save_config();
+                }
+            }
+            Message::VoiceDownloadChanged(name) => {
+                self.settings.voice_download = name;
+            }
+            Message::DownloadVoice => {
+                if self.settings.voice_download.trim().is_empty() {
+                    return Task::none();
+                }
+                if let Err(err) = models::ensure_network_allowed(&self.config) {
+                    self.settings.voice_status = Some(err);
+                    return Task::none();
+                }
+                self.settings.downloading_voice = true;
+                self.settings.voice_status = None;
+                let name = self.settings.voice_download.clone();
+                return cosmic::task::future(async move {
+                    super::Message::Settings(Message::VoiceDownloaded(
+                        tts::download_voice(name).await,
+                    ))
+                });
+            }
+            Message::VoiceDownloaded(result) => {
+                self.settings.downloading_voice = false;
+                match result {
+                    Ok(name) => {
+                        self.settings.voice_status = Some(format!("Downloaded {name}"));
+                        self.settings.voice_download.clear();
+                        if self.config.piper_voice.trim().is_empty() {
+                            self.config.piper_voice = name;
+                            self.save_config();
+                        }
+                    }
+                    Err(err) => {
+                        self.settings.voice_status = Some(format!("Download failed: {err}"));
+                    }
+                }
+                self.refresh_voices();
+            }
+            Message::ShareTargetSelected(index) => {
+                if let Some(target) = ShareTarget::ALL.get(index) {
+                    self.config.share.target = *target;
+                    self.save_config();
+                }
+            }
+            Message::PasteUrlChanged(url) => {
+                self.config.share.paste_url = url;
+                self.save_config();
+            }
+            Message::RedactionSelected(pattern, index) => {
+                if let Some(action) = RedactionAction::ALL.get(index) {
+                    self.config.redaction.set_action(pattern, *action);
+                    self.save_config();
+                }
+            }
+            Message::SpellcheckToggled(enabled) => {
+                self.config.spellcheck = enabled;
+                self.save_config();
+            }
+            Message::SpellcheckLanguageChanged(language) => {
+                self.config.spellcheck_language = language;
+                self.save_config();
+            }
+            Message::InstructionsChanged(instructions) => {
+                self.config.instructions = instructions;
+                self.save_config();
+            }
+            Message::ReplyLanguageChanged(language) => {
+                self.config.reply_language = language;
+                self.save_config();
+            }
+            Message::EditorCommandChanged(command) => {
+                self.config.editor_command = command;
+                self.save_config();
+            }
+            Message::FollowUpSuggestionsToggled(enabled) => {
+                self.config.follow_up_suggestions = enabled;
+                self.save_config();
+            }
+            Message::AutoTitlesToggled(enabled) => {
+                self.config.auto_titles = enabled;
+                self.save_config();
+            }
+            Message::ConversationCapChanged(text) => {
+                if let Some(cap) = parse_cap(&text) {
+                    self.config.cost_cap.per_conversation = cap;
+                    self.save_config();
+                }
+                self.settings.conversation_cap_draft = Some(text);
+            }
+            Message::DailyCapChanged(text) => {
+                if let Some(cap) = parse_cap(&text) {
+                    self.config.cost_cap.per_day = cap;
+                    self.save_config();
+                }
+                self.settings.daily_cap_draft = Some(text);
+            }
+            Message::PromptCapChanged(text) => {
+                if let Some(cap) = parse_cap(&text) {
+                    self.config.cost_cap.per_prompt = cap;
+                    self.save_config();
+                }
+                self.settings.prompt_cap_draft = Some(text);
+            }
+            Message::BackgroundTokenCapChanged(cap) => {
+                let cap = cap.trim();
+                if cap.is_empty() {
+                    self.config.background_token_cap = 0;
+                } else if let Ok(cap) = cap.parse() {
+                    self.config.background_token_cap = cap;
+                } else {
+                    return Task::none();
+                }
+                self.save_config();
+            }
+            Message::MetricsFileChanged(file) => {
+                self.config.metrics.file = file;
+                self.save_config();
+            }
+            Message::MetricsPortChanged(text) => {
+                let text = text.trim();
+                let port = if text.is_empty() {
+                    0
+                } else {
+                    match text.parse() {
+                        Ok(port) => port,
+                        Err(_) => return Task::none(),
+                    }
+                };
+                self.config.metrics.port = port;
+                self.save_config();
+            }
+            Message::WatchMaxPromptsChanged(text) => {
+                let Some(limit) = parse_limit(&text) else {
+                    return Task::none();
+                };
+                self.config.watch_limits.max_prompts = limit;
+                self.save_config();
+            }
+            Message::WatchMaxMinutesChanged(text) => {
+                let Some(limit) = parse_limit(&text) else {
+                    return Task::none();
+                };
+                self.config.watch_limits.max_minutes = limit;
+                self.save_config();
+            }
+            Message::AddWorkspace => {
+                self.config.workspaces.push(Workspace {
+                    name: format!("Workspace {}", self.config.workspaces.len() + 1),
+                    ..Workspace::default()
+                });
+                self.save_config();
+                self.refresh_workspace_names();
+            }
+            Message::RemoveWorkspace(index) => {
+                if index < self.config.workspaces.len() {
+                    let removed = self.config.workspaces.remove(index);
+                    if removed.name == self.config.workspace {
+                        self.switch_workspace(String::new());
+                    }
+                    self.workspace_conversations.remove(&removed.name);
+                    self.save_config();
+                    self.refresh_workspace_names();
+                }
+            }
+            Message::WorkspaceNameChanged(index, name) => {
+                // Conversations are kept by workspace name, and no name means no workspace.
+                let error = if name.trim().is_empty() {
+                    Some("A workspace needs a name".to_string())
+                } else if self
+                    .config
+                    .workspaces
+                    .iter()
+                    .enumerate()
+                    .any(|(other, workspace)| other != index && workspace.name == name)
+                {
+                    Some(format!("Another workspace is named {name}"))
+                } else {
+                    None
+                };
+                self.settings.workspace_name_error = error.clone().map(|error| (index, error));
+                if error.is_some() {
+                    return Task::none();
+                }
+                if let Some(workspace) = self.config.workspaces.get_mut(index) {
+                    // Keep the workspace active and its conversation under the new name.
+                    if workspace.name == self.config.workspace {
+                        self.config.workspace = name.clone();
+                    } else if let Some(parked) =
+                        self.workspace_conversations.remove(&workspace.name)
+                    {
+                        self.workspace_conversations.insert(name.clone(), parked);
+                    }
+                    workspace.name = name;
+                    self.save_config();
+                    self.refresh_workspace_names();
+                }
+            }
+            Message::WorkspaceProviderSelected(index, provider) => {
+                if let Some(workspace) = self.config.workspaces.get_mut(index) {
+                    workspace.provider = provider
+                        .checked_sub(1)
+                        .and_then(|provider| Provider::ALL.get(provider).copied());
+                    self.save_config();
+                }
+            }
+            Message::WorkspaceModelChanged(index, model) => {
+                if let Some(workspace) = self.config.workspaces.get_mut(index) {
+                    workspace.model = model;
+                    self.save_config();
+                }
+            }
+            Message::WorkspacePresetSelected(index, preset) => {
+                if let Some(workspace) = self.config.workspaces.get_mut(index) {
+                    workspace.preset = preset
+                        .checked_sub(1)
+                        .and_then(|preset| GenerationPreset::ALL.get(preset).copied());
+                    self.save_config();
+                }
+            }
+            Message::AddPersona => {
+                self.config.personas.push(Persona {
+                    name: format!("Persona {}", self.config.personas.len() + 1),
+                    ..Persona::default()
+                });
+                self.save_config();
+            }
+            Message::RemovePersona(index) => {
+                if index < self.config.personas.len() {
+                    let removed = self.config.personas.remove(index);
+                    if removed.name == self.config.persona {
+                        self.config.persona.clear();
+                    }
+                    self.save_config();
+                }
+            }
+            Message::PersonaNameChanged(index, name) => {
+                if let Some(persona) = self.config.personas.get_mut(index) {
+                    // Keep the persona active under the new name.
+                    if persona.name == self.config.persona {
+                        self.config.persona = name.clone();
+                    }
+                    persona.name = name;
+                    self.save_config();
+                }
+            }
+            Message::PersonaInstructionsChanged(index, instructions) => {
+                if let Some(persona) = self.config.personas.get_mut(index) {
+                    persona.instructions = instructions;
+                    self.save_config();
+                }
+            }
+            Message::PersonaPresetSelected(index, preset) => {
+                if let Some(persona) = self.config.personas.get_mut(index) {
+                    persona.preset = preset
+                        .checked_sub(1)
+                        .and_then(|preset| GenerationPreset::ALL.get(preset).copied());
+                    self.save_config();
+                }
+            }
+            Message::WorkspaceInstructionsChanged(index, instructions) => {
+                if let Some(workspace) = self.config.workspaces.get_mut(index) {
+                    workspace.instructions = instructions;
+                    self.save_config();
+                }
+            }
+            Message::WorkspaceDirectoryChanged(index, directory) => {
+                if let Some(workspace) = self.config.workspaces.get_mut(index) {
+                    workspace.directory = directory;
+                    self.save_config();
+                }
+            }
+            Message::WorkspaceEnvironmentChanged(index, environment) => {
+                if let Some(workspace) = self.config.workspaces.get_mut(index) {
+                    workspace.environment = environment;
+                    self.save_config();
+                }
+            }
+            Message::ScriptingToggled(enabled) => {
+                self.config.scripting = enabled;
+                self.save_config();
+                self.scripts = if enabled {
+                    Scripts::load()
+                } else {
+                    Scripts::default()
+                };
+            }
+            Message::ReloadScripts => {
+                self.scripts = Scripts::load();
+            }
+            Message::AddQuickAction => {
+                self.config.quick_actions.push(QuickAction::default());
+                self.save_config();
+            }
+            Message::RemoveQuickAction(index) => {
+                if index < self.config.quick_actions.len() {
+                    self.config.quick_actions.remove(index);
+                    self.save_config();
+                }
+            }
+            Message::QuickActionNameChanged(index, name) => {
+                if let Some(action) = self.config.quick_actions.get_mut(index) {
+                    action.name = name;
+                    self.save_config();
+                }
+            }
+            Message::QuickActionTemplateChanged(index, template) => {
+                if let Some(action) = self.config.quick_actions.get_mut(index) {
+                    action.template = template;
+                    self.save_config();
+                }
+            }
+            Message::QuickActionInputSelected(index, input) => {
+                if let (Some(action), Some(input)) = (
+                    self.config.quick_actions.get_mut(index),
+                    QuickActionInput::ALL.get(input),
+                ) {
+                    action.input = *input;
+                    self.save_config();
+                }
+            }
+            Message::RemoveTemplate(index) => {
+                if index < self.config.templates.len() {
+                    let removed = self.config.templates.remove(index);
+                    if removed.name == self.config.template {
+                        self.config.template.clear();
+                    }
+                    self.save_config();
+                }
+            }
+            Message::TemplateNameChanged(index, name) => {
+                if let Some(template) = self.config.templates.get_mut(index) {
+                    // Keep the conversations started from it tied to the template.
+                    if template.name == self.config.template {
+                        self.config.template = name.clone();
+                    }
+                    for parked in self
+                        .workspace_conversations
+                        .values_mut()
+                        .chain(self.conversations.list.iter_mut())
+                    {
+                        if parked.template == template.name {
+                            parked.template = name.clone();
+                        }
+                    }
+                    template.name = name;
+                    self.save_config();
+                }
+            }
+            Message::TemplateProviderSelected(index, provider) => {
+                if let Some(template) = self.config.templates.get_mut(index) {
+                    template.provider = provider
+                        .checked_sub(1)
+                        .and_then(|provider| Provider::ALL.get(provider).copied());
+                    self.save_config();
+                }
+            }
+            Message::TemplateModelChanged(index, model) => {
+                if let Some(template) = self.config.templates.get_mut(index) {
+                    template.model = model;
+                    self.save_config();
+                }
+            }
+            Message::TemplateInstructionsChanged(index, instructions) => {
+                if let Some(template) = self.config.templates.get_mut(index) {
+                    template.instructions = instructions;
+                    self.save_config();
+                }
+            }
+            Message::TemplateFirstMessageChanged(index, message) => {
+                if let Some(template) = self.config.templates.get_mut(index) {
+                    template.first_message = message;
+                    self.save_config();
+                }
+            }
+            Message::SafetyPresetSelected(index) => {
+                if let Some(preset) = SafetyPreset::ALL.get(index) {
+                    self.config.safety.preset = *preset;
+                    self.save_config();
+                }
+            }
+            Message::HarmThresholdChanged(category, index) => {
+                let Some(threshold) = HarmThreshold::ALL.get(usize::from(index)) else {
+                    return Task::none();
+                };
+                // Adjusting a category turns the current preset into a custom one.
+                if let Some(preset_threshold) = self.config.safety.preset.threshold() {
+                    for category in HarmCategory::ALL {
+                        self.config.safety.set_custom(category, preset_threshold);
+                    }
+                }
+                self.config.safety.preset = SafetyPreset::Custom;
+                self.config.safety.set_custom(category, *threshold);
+                self.save_config();
+            }
+            Message::PassphraseChanged(passphrase) => {
+                self.settings.passphrase = passphrase;
+            }
+            Message::PassphraseConfirmationChanged(passphrase) => {
+                self.settings.passphrase_confirmation = passphrase;
+            }
+            Message::LockPolicy => {
+                if self.settings.passphrase.is_empty() {
+                    self.settings.policy_status = Some("Choose a passphrase first".into());
+                } else if self.settings.passphrase != self.settings.passphrase_confirmation {
+                    self.settings.policy_status = Some("The passphrases don't match".into());
+                } else {
+                    match PolicyLock::new(&self.settings.passphrase) {
+                        Ok(lock) => {
+                            self.config.policy_lock = Some(lock);
+                            self.save_config();
+                            self.settings.policy_status = None;
+                        }
+                        Err(err) => {
+                            self.settings.policy_status = Some(format!("Could not lock: {err}"));
+                        }
+                    }
+                }
+                self.settings.passphrase.clear();
+                self.settings.passphrase_confirmation.clear();
+            }
+            Message::UnlockPolicy => {
+                if self
+                    .config
+                    .policy_lock
+                    .as_ref()
+                    .is_some_and(|lock| lock.verify(&self.settings.passphrase))
+                {
+                    self.config.policy_lock = None;
+                    self.save_config();
+                    self.settings.policy_status = None;
+                } else {
+                    self.settings.policy_status = Some("Wrong passphrase".into());
+                }
+                self.settings.passphrase.clear();
+            }
+            Message::OpenDownloads => {
+                self.page = super::Page::Downloads;
+            }
+            Message::LocalServerBinaryChanged(binary) => {
+                self.config.local.server_binary = binary;
+                self.save_config();
+            }
+            Message::LocalContextSizeChanged(size) => {
+                let size = size.trim();
+                if size.is_empty() {
+                    self.config.local.context_size = 0;
+                } else if let Ok(size) = size.parse() {
+                    self.config.local.context_size = size;
+                } else {
+                    return Task::none();
+                }
+                self.save_config();
+            }
+            Message::BackupFolderChanged(folder) => {
+                self.config.backup.folder = folder;
+                self.save_config();
+                self.refresh_backups();
+            }
+            Message::BackupIntervalSelected(index) => {
+                if let Some(interval) = BackupInterval::ALL.get(index) {
+                    self.config.backup.interval = *interval;
+                    self.save_config();
+                }
+            }
+            Message::BackupKeepChanged(keep) => {
+                if let Ok(keep) = keep.trim().parse::<u32>()
+                    && keep > 0
+                {
+                    self.config.backup.keep = keep;
+                    self.save_config();
+                }
+            }
+            Message::RestoreConversationsToggled(restore) => {
+                self.config.forget_conversations = !restore;
+                self.save_config();
+                if !restore {
+                    // What was saved so far goes too, not only what comes next.
+                    if let Err(err) = self.history_store.save(&crate::session::Session::default()) {
+                        eprintln!("failed to remove the saved session: {err}");
+                    }
+                }
+                self.history_store = self.open_history_store();
+                self.save_session();
+            }
+            Message::RefreshStorage => {
+                let Some(data_dir) = self.profile.data_dir() else {
+                    return Task::none();
+                };
+                return cosmic::task::future(async move {
+                    let usage = tokio::task::spawn_blocking(move || storage::usage(&data_dir))
+                        .await
+                        .unwrap_or_default();
+                    super::Message::Settings(Message::StorageMeasured(usage))
+                });
+            }
+            Message::StorageMeasured(usage) => self.settings.storage = usage,
+            Message::CleanUpStorage => {
+                let Some(data_dir) = self.profile.data_dir() else {
+                    return Task::none();
+                };
+                // The session on disk must be current, or new images look orphaned.
+                self.save_session();
+                self.settings.cleaning_up = true;
+                let export_dirs: Vec<PathBuf> = super::share::export_dir().into_iter().collect();
+                return cosmic::task::future(async move {
+                    let result = tokio::task::spawn_blocking(move || {
+                        storage::clean_up(&data_dir, &export_dirs)
+                    })
+                    .await
+                    .map_err(|err| err.to_string())
+                    .and_then(|result| result.map_err(|err| err.to_string()));
+                    super::Message::Settings(Message::StorageCleaned(result))
+                });
+            }
+            Message::StorageCleaned(result) => {
+                self.settings.cleaning_up = false;
+                self.settings.cleanup_status = Some(match result {
+                    Ok(cleanup) if cleanup.files == 0 => "Nothing to clean up.".to_string(),
+                    Ok(cleanup) => format!(
+                        "Removed {} files, freeing {}.",
+                        cleanup.files,
+                        super::downloads::format_size(cleanup.bytes)
+                    ),
+                    Err(err) => format!("Cleanup failed: {err}"),
+                });
+                return self.update_settings(Message::RefreshStorage);
+            }
+            Message::BackUpNow => {
+                return self.start_backup(true);
+            }
+            Message::BackupFinished(result) => {
+                self.settings.backup_running = false;
+                match result {
+                    Ok(Some(path)) => {
+                        self.settings.backup_status =
+                            Some(format!("Backed up to {}", path.display()));
+                    }
+                    Ok(None) => {}
+                    Err(err) => {
+                        self.settings.backup_status = Some(format!("Backup failed: {err}"));
+                    }
+                }
+                self.refresh_backups();
+            }
+            Message::RestoreBackup(index) => {
+                let (Some(backup), Some(data_dir)) =
+                    (self.settings.backups.get(index), self.profile.data_dir())
+                else {
+                    return Task::none();
+                };
+                let backup = backup.path.clone();
+                let folder = PathBuf::from(self.config.backup.folder.trim());
+                let label = backup_label(&self.profile);
+                self.settings.backup_running = true;
+                self.settings.restoring = true;
+                self.settings.backup_status = None;
+                return cosmic::task::future(async move {
+                    let result = tokio::task::spawn_blocking(move || {
+                        // Keep the current data so the restore can be undone.
+                        backup::create(&data_dir, &folder, &label)?;
+                        backup::restore(&backup, &data_dir)?;
+                        Ok(backup)
+                    })
+                    .await
+                    .map_err(|err| err.to_string())
+                    .and_then(|result: std::io::Result<PathBuf>| {
+                        result.map_err(|err| err.to_string())
+                    });
+                    super::Message::Settings(Message::Restored(result))
+                });
+            }
+            Message::Restored(result) => {
+                self.settings.backup_running = false;
+                self.settings.restoring = false;
+                if result.is_ok() {
+                    self.reload_session();
+                    if let Some(dir) = self.profile.data_dir() {
+                        self.spending = spending::Spending::load(&dir);
+                    }
+                }
+                self.settings.backup_status = Some(match result {
+                    Ok(path) => format!(
+                        "Restored {}. The data it replaced was backed up first.",
+                        path.display()
+                    ),
+                    Err(err) => format!("Restore failed: {err}"),
+                });
+                self.refresh_backups();
+                self.refresh_preferences();
+            }
+            Message::SyncToggled(enabled) => {
+                self.config.sync.enabled = enabled;
+                self.save_config();
+            }
+            Message::SyncBackendSelected(index) => {
+                if let Some(backend) = SyncBackend::ALL.get(index) {
+                    self.config.sync.backend = *backend;
+                    self.save_config();
+                }
+            }
+            Message::SyncUrlChanged(url) => {
+                self.config.sync.url = url;
+                self.save_config();
+            }
+            Message::SyncBucketChanged(bucket) => {
+                self.config.sync.bucket = bucket;
+                self.save_config();
+            }
+            Message::SyncRegionChanged(region) => {
+                self.config.sync.region = region;
+                self.save_config();
+            }
+            Message::SyncUsernameChanged(username) => {
+                self.config.sync.username = username;
+                self.save_config();
+            }
+            Message::SyncNow => {
+                return self.start_sync();
+            }
+            Message::SyncFinished(result) => {
+                self.settings.sync_running = false;
+                self.settings.sync_status = Some(match result {
+                    Ok(summary) => {
+                        if summary.changed_locally() {
+                            self.refresh_preferences();
+                        }
+                        if summary.session_changed {
+                            self.reload_session();
+                        } else {
+                            self.save_session();
+                        }
+                        let mut status = format!(
+                            "Synced: {} sent, {} received",
+                            summary.uploaded, summary.downloaded
+                        );
+                        if !summary.conflicts.is_empty() {
+                            status.push_str(&format!(
+                                ". Changed on both sides: {}",
+                                summary.conflicts.join(", ")
+                            ));
+                        }
+                        status
+                    }
+                    Err(err) => format!("Sync failed: {err}"),
+                });
+            }
+            Message::RefreshModels => {
+                if let Err(err) = models::ensure_network_allowed(&self.config) {
+                    self.settings.models_error = Some(err);
+                    return Task::none();
+                }
+                let settings = self.config.gemini.clone();
+                return cosmic::task::future(async move {
+                    super::Message::Settings(Message::ModelsLoaded(
+                        gemini::list_models(settings).await,
+                    ))
+                });
+            }
+            Message::ModelsLoaded(result) => match result {
+                Ok(models) => {
+                    self.settings.models = models;
+                    self.settings.models_error = None;
+                }
+                Err(err) => self.settings.models_error = Some(err),
+            },
+            Message::RefreshOpenAiModels => {
+                if let Err(err) = models::ensure_network_allowed(&self.config) {
+                    self.settings.openai_models_error = Some(err);
+                    return Task::none();
+                }
+                let settings = self.config.openai.clone();
+                return cosmic::task::future(async move {
+                    super::Message::Settings(Message::OpenAiModelsLoaded(
+                        openai::list_models(settings).await,
+                    ))
+                });
+            }
+            Message::OpenAiModelsLoaded(result) => match result {
+                Ok(models) => {
+                    self.settings.openai_models = models;
+                    self.settings.openai_models_error = None;
+                }
+                Err(err) => self.settings.openai_models_error = Some(err),
+            },
+            Message::RefreshCompatibleModels => {
+                if !self.config.compatible.is_loopback()
+                    && let Err(err) = models::ensure_network_allowed(&self.config)
+                {
+                    self.settings.compatible_models_error = Some(err);
+                    return Task::none();
+                }
+                let settings = self.config.compatible.clone();
+                return cosmic::task::future(async move {
+                    super::Message::Settings(Message::CompatibleModelsLoaded(
+                        openai::compatible::list_models(settings).await,
+                    ))
+                });
+            }
+            Message::CompatibleModelsLoaded(result) => match result {
+                Ok(models) => {
+                    self.settings.compatible_models = models;
+                    self.settings.compatible_models_error = None;
+                }
+                Err(err) => self.settings.compatible_models_error = Some(err),
+            },
+            Message::AbRegenerationToggled(enabled) => {
+                self.config.ab_regeneration = enabled;
+                self.save_config();
+            }
+            Message::ResponseModalitiesSelected(index) => {
+                if let Some(modalities) = ResponseModalities::ALL.get(index) {
+                    self.config.response_modalities = *modalities;
+                    self.save_config();
+                }
+            }
+            Message::MediaResolutionSelected(index) => {
+                if let Some(resolution) = MediaResolution::ALL.get(index) {
+                    self.config.media_resolution = *resolution;
+                    self.save_config();
+                }
+            }
+            Message::OpenDiagnostics => {
+                self.page = super::Page::Diagnostics;
+            }
+            Message::LogprobsChanged(count) => {
+                let count = count.trim();
+                if count.is_empty() {
+                    self.config.custom_generation.logprobs = None;
+                } else if let Ok(count) = count.parse::<u32>() {
+                    // The API reports at most 20 alternatives.
+                    self.config.custom_generation.logprobs = Some(count.min(20));
+                } else {
+                    return Task::none();
+                }
+                self.save_config();
+            }
+        }
+
+        Task::none()
+    }
+
+    /// Reloads the answer preference tally shown on the settings page.
+    pub(super) fn refresh_preferences(&mut self) {
+        self.settings.preferences = self
+            .profile
+            .data_dir()
+            .map(|dir| preferences::summary(&dir))
+            .unwrap_or_default();
+    }
+
+    /// Rescans the backup folder.
+    pub(super) fn refresh_backups(&mut self) {
+        let folder = self.config.backup.folder.trim();
+        self.settings.backups = if folder.is_empty() {
+            Vec::new()
+        } else {
+            backup::list(&PathBuf::from(folder), &backup_label(&self.profile))
+        };
+    }
+
+    /// Whether a backup is running, with the outcome of the last one and when the
+    /// newest backup was made.
+    pub(super) fn backup_progress(&self) -> (bool, Option<&str>, Option<SystemTime>) {
+        (
+            self.settings.backup_running,
+            self.settings.backup_status.as_deref(),
+            self.settings.backups.first().map(|backup| backup.created),
+        )
+    }
+
+    /// Whether a sync is running, with the outcome of the last one.
+    pub(super) fn sync_progress(&self) -> (bool, Option<&str>) {
+        (
+            self.settings.sync_running,
+            self.settings.sync_status.as_deref(),
+        )
+    }
+
+    /// Backs up the profile's data and rotates old backups, when one is due or
+    /// `force` is set.
+    pub(super) fn start_backup(&mut self, force: bool) -> Task<cosmic::Action<super::Message>> {
+        let folder = self.config.backup.folder.trim();
+        let period = self.config.backup.interval.period();
+        if folder.is_empty() || self.settings.backup_running || (!force && period.is_none()) {
+            return Task::none();
+        }
+        // Conversations can't be exported while the policy is locked, nor copied away.
+        if self.config.is_locked() {
+            return Task::none();
+        }
+        let Some(data_dir) = self.profile.data_dir() else {
+            return Task::none();
+        };
+        let folder = PathBuf::from(folder);
+        let label = backup_label(&self.profile);
+        let keep = self.config.backup.keep as usize;
+        self.settings.backup_running = true;
+
+        cosmic::task::future(async move {
+            let result = tokio::task::spawn_blocking(move || {
+                if let Some(period) = period
+                    && !force
+                    && !backup::is_due(&folder, &label, period)
+                {
+                    return Ok(None);
+                }
+                let path = backup::create(&data_dir, &folder, &label)?;
+                backup::rotate(&folder, &label, keep)?;
+                Ok(Some(path))
+            })
+            .await
+            .map_err(|err| err.to_string())
+            .and_then(|result: std::io::Result<Option<PathBuf>>| {
+                result.map_err(|err| err.to_string())
+            });
+            super::Message::Settings(Message::BackupFinished(result))
+        })
+    }
+
+    /// Syncs the profile's data with the configured server.
+    pub(super) fn start_sync(&mut self) -> Task<cosmic::Action<super::Message>> {
+        // The session is synced too, so it can't change while answers are
+        // coming in.
+        if !self.config.sync.enabled
+            || self.settings.sync_running
+            || self.config.is_locked()
+            || self.is_loading
+            || !self.background_answers.is_empty()
+        {
+            return Task::none();
+        }
+        if let Err(err) = models::ensure_network_allowed(&self.config) {
+            self.settings.sync_status = Some(err);
+            return Task::none();
+        }
+        let Some(data_dir) = self.profile.data_dir() else {
+            return Task::none();
+        };
+        let settings = self.config.sync.clone();
+        let label = backup_label(&self.profile);
+        self.save_session();
+        self.settings.sync_running = true;
+
+        cosmic::task::future(async move {
+            super::Message::Settings(Message::SyncFinished(
+                sync::run(&settings, &data_dir, &label).await,
+            ))
+        })
+    }
+
+    /// Rescans the downloaded Piper voices.
+    pub(super) fn refresh_voices(&mut self) {
+        self.settings.voices = tts::installed_voices();
+    }
+
+    /// Rescans the local models directory for GGUF files.
+    pub(super) fn refresh_local_models(&mut self) {
+        self.settings.local_models = local::installed_models();
+        self.settings.local_model_names = self
+            .settings
+            .local_models
+            .iter()
+            .map(|path| {
+                path.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        self.refresh_installed_models();
+    }
+
+    /// Models listed by `provider`, empty until listed.
+    pub(super) fn settings_models(&self, provider: Provider) -> &[String] {
+        match provider {
+            Provider::OpenAi => &self.settings.openai_models,
+            Provider::Compatible => &self.settings.compatible_models,
+            _ => &self.settings.models,
+        }
+    }
+
+    /// Models known for `provider`, as labels with the value to configure.
+    ///
+    /// Until Gemini or OpenAI have listed their models, the well-known ones are
+    /// offered along with the configured one, so a model can be picked offline.
+    pub(super) fn model_choices(&self, provider: Provider) -> Vec<(String, String)> {
+        let config = self.config.for_workspace();
+        match provider {
+            Provider::Gemini => known_or_listed(
+                &self.settings.models,
+                &gemini::KNOWN_MODELS,
+                &gemini::model_name(&config.gemini),
+            )
+            .into_iter()
+            .map(|model| {
+                let label = model.strip_prefix("models/").unwrap_or(&model).to_string();
+                (label, model)
+            })
+            .collect(),
+            Provider::OpenAi => known_or_listed(
+                &self.settings.openai_models,
+                &openai::KNOWN_MODELS,
+                &openai::model_name(&config.openai),
+            )
+            .into_iter()
+            .map(|model| (model.clone(), model))
+            .collect(),
+            Provider::Compatible => self
+                .settings
+                .compatible_models
+                .iter()
+                .map(|model| (model.clone(), model.clone()))
+                .collect(),
+            Provider::Local => self
+                .settings
+                .local_model_names
+                .iter()
+                .zip(&self.settings.local_models)
+                .map(|(name, path)| (name.clone(), path.to_string_lossy().into_owned()))
+                .collect(),
+        }
+    }
+
+    /// Reads the API keys from the keyring, moving in keys left in the config.
+    pub(super) fn load_keyring(&self) -> Task<cosmic::Action<super::Message>> {
+        let profile = self.profile.config_id();
+        cosmic::task::future(async move {
+            super::Message::Settings(Message::KeyringLoaded(keyring::load(profile).await))
+        })
+    }
+
+    /// Saves `key` in `slot` of this profile, or removes it if `key` is empty.
+    fn store_key(&self, slot: Slot, key: String) -> Task<cosmic::Action<super::Message>> {
+        let profile = self.profile.config_id();
+        cosmic::task::future(async move {
+            super::Message::Settings(Message::KeyStored(
+                slot,
+                keyring::store(profile, slot, key).await,
+            ))
+        })
+    }
+
+    /// The input for the key in `slot`, with a button offering to move it from
+    /// its environment variable while the keyring has none.
+    fn key_item<'a>(&'a self, slot: Slot, label: &'a str) -> cosmic::Element<'a, Message> {
+        let draft = self
+            .settings
+            .key_drafts
+            .get(&slot)
+            .map_or("", String::as_str);
+        let mut item = widget::column::with_capacity(3)
+            .spacing(4)
+            .push(widget::settings::item(
+                label,
+                widget::row::with_children(vec![
+                    widget::secure_input(slot.var().unwrap_or("None"), draft, None, true)
+                        .on_input(move |key| Message::KeyChanged(slot, key))
+                        .on_submit(move |_| Message::StoreKey(slot))
+                        .width(cosmic::iced::Length::Fill)
+                        .into(),
+                    widget::button::standard("Save")
+                        .on_press(Message::StoreKey(slot))
+                        .into(),
+                ])
+                .spacing(8)
+                .align_y(cosmic::iced::Alignment::Center),
+            ));
+        if keyring::get(slot).is_none()
+            && let Some(var) = slot.var()
+            && credentials::lookup(var).is_some()
+        {
+            item = item.push(
+                widget::button::text(format!("Move {var} into the keyring"))
+                    .on_press(Message::MoveKeyFromEnv(slot)),
+            );
+        }
+        if let Some(status) = self.settings.key_status.get(&slot) {
+            item = item.push(widget::text::caption(status.as_str()));
+        }
+        item.into()
+    }
+
+    /// The key in `slot` as saved in the config by earlier versions.
+    fn legacy_key(&mut self, slot: Slot) -> &mut String {
+        match slot {
+            Slot::Gemini => &mut self.config.gemini.api_key,
+            Slot::OpenAi => &mut self.config.openai.api_key,
+            Slot::Compatible => &mut self.config.compatible.api_key,
+        }
+    }
+
+    /// Persists the current configuration.
+    pub(super) fn save_config(&self) {
+        if let Some(handler) = &self.config_handler
+            && let Err(err) = self.config.write_entry(handler)
+        {
+            eprintln!("failed to save settings: {err:?}");
+        }
+    }
+}
+
+/// `listed` models, or the `known` ones and `current` while none are listed.
+fn known_or_listed(listed: &[String], known: &[&str], current: &str) -> Vec<String> {
+    if !listed.is_empty() {
+        return listed.to_vec();
+    }
+    let mut models: Vec<String> = known.iter().map(|model| model.to_string()).collect();
+    if !models.iter().any(|model| model == current) {
+        models.push(current.to_string());
+    }
+    models
+}
+
+/// A spending cap in dollars, empty for none.
+fn parse_cap(text: &str) -> Option<f64> {
+    let text = text.trim().trim_start_matches('$');
+    if text.is_empty() {
+        return Some(0.0);
+    }
+    text.parse()
+        .ok()
+        .filter(|cap: &f64| cap.is_finite() && *cap >= 0.0)
+}
+
+/// A whole-number limit, empty for none.
+fn parse_limit(text: &str) -> Option<u32> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Some(0);
+    }
+    text.parse().ok()
+}
+
+fn limit_text(limit: u32) -> String {
+    match limit {
+        0 => String::new(),
+        limit => limit.to_string(),
+    }
+}
+
+/// Provider choices of a workspace, the first keeping the global provider.
+const WORKSPACE_PROVIDERS: [&str; 5] = [
+    "Global provider",
+    Provider::NAMES[0],
+    Provider::NAMES[1],
+    Provider::NAMES[2],
+    Provider::NAMES[3],
+];
+
+/// Provider choices of a template, the first keeping the workspace's or global one.
+const TEMPLATE_PROVIDERS: [&str; 5] = [
+    "Workspace provider",
+    Provider::NAMES[0],
+    Provider::NAMES[1],
+    Provider::NAMES[2],
+    Provider::NAMES[3],
+];
+
+/// Preset choices of a workspace, the first keeping the global preset.
+const WORKSPACE_PRESETS: [&str; 5] = [
+    "Global preset",
+    GenerationPreset::NAMES[0],
+    GenerationPreset::NAMES[1],
+    GenerationPreset::NAMES[2],
+    GenerationPreset::NAMES[3],
+];
+
+/// Names this profile's backups and synced data so several profiles can
+/// share a folder or server.
+fn backup_label(profile: &crate::profile::Profile) -> String {
+    profile.name().unwrap_or("default").to_string()
+}
+
+pub(super) fn format_age(time: SystemTime) -> String {
+    let seconds = time.elapsed().unwrap_or_default().as_secs();
+    match seconds {
+        0..60 => "Just now".into(),
+        60..3600 => format!("{} min ago", seconds / 60),
+        3600..86400 => format!("{} h ago", seconds / 3600),
+        _ => format!("{} days ago", seconds / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caps_are_parsed_in_dollars() {
+        assert_eq!(parse_cap(" $2.50 "), Some(2.5));
+        assert_eq!(parse_cap("10"), Some(10.0));
+        assert_eq!(parse_cap(""), Some(0.0));
+    }
+
+    #[test]
+    fn invalid_caps_are_refused() {
+        assert_eq!(parse_cap("-1"), None);
+        assert_eq!(parse_cap("inf"), None);
+        assert_eq!(parse_cap("ten"), None);
+    }
+}
@@ -0,0 +1,280 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Share page: shows exactly what will be uploaded before sharing the conversation,
+//! and saves it as a PDF or as a conversation file someone else can continue.
+//!
+//! With answers selected in the chat, only those and their prompts are shared.
+
+use super::{AppModel, Chat};
+use crate::{conversation_file, models, paste, pdf};
+use cosmic::iced::{Alignment, Length};
+use cosmic::prelude::*;
+use cosmic::widget;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// UI state of the share page.
+#[derive(Default)]
+pub struct State {
+    /// Redacted Markdown that will be uploaded.
+    preview: String,
+    uploading: bool,
+    /// Outcome of the last upload.
+    status: Option<String>,
+    /// Indices of the selected answers.
+    selected: BTreeSet<usize>,
+}
+
+impl State {
+    pub(super) fn is_selected(&self, index: usize) -> bool {
+        self.selected.contains(&index)
+    }
+
+    pub(super) fn clear_selection(&mut self) {
+        self.selected.clear();
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Open,
+    Upload,
+    Uploaded(Result<String, String>),
+    SaveAsPdf,
+    PdfSaved(Result<PathBuf, String>),
+    SaveConversationFile,
+    ConversationFileSaved(Result<PathBuf, String>),
+    ToggleSelected(usize),
+    CopySelection,
+    ClearSelection,
+}
+
+impl AppModel {
+    /// Actions for the selected answers, shown above the prompt while there are any.
+    pub(super) fn selection_view(&self) -> Option<cosmic::Element<'_, Message>> {
+        let count = self.share.selected.len();
+        if count == 0 {
+            return None;
+        }
+
+        Some(
+            widget::row::with_children(vec![
+                widget::text::caption(match count {
+                    1 => "1 answer selected".to_string(),
+                    count => format!("{count} answers selected"),
+                })
+                .width(Length::Fill)
+                .into(),
+                widget::button::text("Copy as Markdown")
+                    .on_press(Message::CopySelection)
+                    .into(),
+                widget::button::text("Share").on_press(Message::Open).into(),
+                widget::button::icon(widget::icon::from_name("window-close-symbolic"))
+                    .on_press(Message::ClearSelection)
+                    .tooltip("Clear selection")
+                    .into(),
+            ])
+            .spacing(8)
+            .align_y(Alignment::Center)
+            .into(),
+        )
+    }
+
+    pub(super) fn share_view(&self) -> cosmic::Element<'_, Message> {
+        let state = &self.share;
+
+        let mut section = widget::settings::section()
+            .title(if state.selected.is_empty() {
+                "Share conversation"
+            } else {
+                "Share selected answers"
+            })
+            .add(widget::text::caption(format!(
+                "This will be uploaded to {}. Known credentials are masked.",
+                paste::destination(&self.config.share)
+            )))
+            .add(
+                widget::container(widget::text::monotext(state.preview.as_str()))
+                    .class(cosmic::theme::Container::List)
+                    .padding(10)
+                    .width(Length::Fill),
+            )
+            .add(
+                widget::row::with_children(vec![
+                    widget::button::suggested(if state.uploading {
+                        "Uploading…"
+                    } else {
+                        "Upload"
+                    })
+                    .on_press_maybe((!state.uploading).then_some(Message::Upload))
+                    .into(),
+                    widget::button::standard("Save as PDF")
+                        .on_press(Message::SaveAsPdf)
+                        .into(),
+                    widget::button::standard("Save conversation file")
+                        .on_press(Message::SaveConversationFile)
+                        .into(),
+                ])
+                .spacing(8),
+            );
+        if let Some(status) = &state.status {
+            section = section.add(widget::text::caption(status.as_str()));
+        }
+
+        widget::scrollable(widget::settings::view_column(vec![section.into()]))
+            .height(Length::Fill)
+            .into()
+    }
+
+    pub(super) fn update_share(
+        &mut self,
+        message: Message,
+    ) -> Task<cosmic::Action<super::Message>> {
+        // A locked content policy forbids exporting the conversation.
+        if self.config.is_locked() {
+            return Task::none();
+        }
+
+        match message {
+            Message::Open => {
+                if self.page == super::Page::Share {
+                    self.page = super::Page::Chat;
+                    return Task::none();
+                }
+                self.share.preview =
+                    paste::redact(&paste::markdown(&self.shared_history()), &self.config);
+                self.share.status = None;
+                self.page = super::Page::Share;
+            }
+            Message::Upload => {
+                if let Err(err) = models::ensure_network_allowed(&self.config) {
+                    self.share.status = Some(err);
+                    return Task::none();
+                }
+                self.share.uploading = true;
+                let markdown = self.share.preview.clone();
+                let settings = self.config.share.clone();
+                return cosmic::task::future(async move {
+                    super::Message::Share(Message::Uploaded(
+                        paste::upload(markdown, settings).await,
+                    ))
+                });
+            }
+            Message::Uploaded(result) => {
+                self.share.uploading = false;
+                match result {
+                    Ok(link) => {
+                        self.share.status = Some(format!("Link copied: {link}"));
+                        return cosmic::iced::clipboard::write(link);
+                    }
+                    Err(err) => {
+                        self.share.status = Some(format!("Upload failed: {err}"));
+                    }
+                }
+            }
+            Message::SaveAsPdf => {
+                let history = self.shared_history();
+                return cosmic::task::future(async move {
+                    let result = tokio::task::spawn_blocking(move || {
+                        let path = export_path(".pdf")?;
+                        pdf::export(&history, &path).map(|()| path)
+                    })
+                    .await
+                    .unwrap_or_else(|err| Err(err.to_string()));
+                    super::Message::Share(Message::PdfSaved(result))
+                });
+            }
+            Message::PdfSaved(result) => {
+                self.share.status = Some(match result {
+                    Ok(path) => {
+                        super::open_uri(&path.to_string_lossy());
+                        format!("Saved {}", path.display())
+                    }
+                    Err(err) => format!("Could not save the PDF: {err}"),
+                });
+            }
+            Message::SaveConversationFile => {
+                let mut history = self.shared_history();
+                for chat in &mut history {
+                    chat.content = paste::redact(&chat.content, &self.config);
+                }
+                let title = self.title.clone();
+                return cosmic::task::future(async move {
+                    let result = tokio::task::spawn_blocking(move || {
+                        let path = export_path(conversation_file::EXTENSION)?;
+                        conversation_file::write(&path, title, history)
+                            .map(|()| path)
+                            .map_err(|err| err.to_string())
+                    })
+                    .await
+                    .unwrap_or_else(|err| Err(err.to_string()));
+                    super::Message::Share(Message::ConversationFileSaved(result))
+                });
+            }
+            Message::ConversationFileSaved(result) => {
+                self.share.status = Some(match result {
+                    Ok(path) => format!("Saved {}", path.display()),
+                    Err(err) => format!("Could not save the conversation: {err}"),
+                });
+            }
+            Message::ToggleSelected(index) => {
+                if !self.share.selected.remove(&index) {
+                    self.share.selected.insert(index);
+                }
+            }
+            Message::CopySelection => {
+                let markdown =
+                    paste::redact(&paste::markdown(&self.shared_history()), &self.config);
+                self.share.selected.clear();
+                return cosmic::iced::clipboard::write(markdown);
+            }
+            Message::ClearSelection => {
+                self.share.selected.clear();
+            }
+        }
+
+        Task::none()
+    }
+
+    /// The selected answers with the prompts they answer, or the whole
+    /// conversation if none are selected.
+    fn shared_history(&self) -> Vec<Chat> {
+        if self.share.selected.is_empty() {
+            return self.chat_history.to_vec();
+        }
+
+        let mut shared = Vec::new();
+        for &index in &self.share.selected {
+            let Some(answer) = self.chat_history.get(index) else {
+                continue;
+            };
+            let prompt = self.chat_history[..index]
+                .iter()
+                .rposition(|chat| chat.role == "user")
+                .map(|prompt| &self.chat_history[prompt]);
+            shared.extend(prompt.cloned());
+            shared.push(answer.clone());
+        }
+        shared
+    }
+}
+
+/// A new file ending in `extension` in the documents folder, or the home folder
+/// if there is none.
+fn export_path(extension: &str) -> Result<PathBuf, String> {
+    let dir = export_dir()?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    Ok(dir.join(format!("conversation-{now}{extension}")))
+}
+
+/// Where exports are written: the documents folder, or else the home folder.
+pub(super) fn export_dir() -> Result<PathBuf, String> {
+    let home = PathBuf::from(std::env::var_os("HOME").ok_or("HOME is not set")?);
+    let documents = home.join("Documents");
+    Ok(if documents.is_dir() { documents } else { home })
+}
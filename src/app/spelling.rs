@@ -0,0 +1,151 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Spelling suggestions for the prompt.
+//!
+//! The text input can't underline words, so misspelled words are listed under
+//! it with their suggestions instead.
+
+use super::AppModel;
+use crate::spellcheck::{self, Misspelling};
+use cosmic::iced::Alignment;
+use cosmic::prelude::*;
+use cosmic::widget;
+use regex::Regex;
+
+/// Most misspelled words listed at once.
+const MAX_SHOWN: usize = 4;
+/// Most suggestions offered per word.
+const MAX_SUGGESTIONS: usize = 3;
+
+#[derive(Default)]
+pub struct State {
+    misspellings: Vec<Misspelling>,
+    /// Bumped for every check, so results for older text are dropped.
+    generation: u64,
+    /// The last check failed and was logged, so later failures aren't logged
+    /// again on every keystroke.
+    failing: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Checked(u64, Result<Vec<Misspelling>, String>),
+    Apply { word: String, suggestion: String },
+    Ignore(String),
+}
+
+impl AppModel {
+    /// Misspelled words in the prompt with buttons to replace them.
+    pub(super) fn spelling_view(&self) -> Option<cosmic::Element<'_, Message>> {
+        if !self.config.spellcheck || self.spelling.misspellings.is_empty() {
+            return None;
+        }
+
+        let mut row: Vec<cosmic::Element<'_, Message>> = Vec::new();
+        for misspelling in self.spelling.misspellings.iter().take(MAX_SHOWN) {
+            row.push(
+                widget::button::text(misspelling.word.as_str())
+                    .on_press(Message::Ignore(misspelling.word.clone()))
+                    .tooltip("Ignore")
+                    .into(),
+            );
+            for suggestion in misspelling.suggestions.iter().take(MAX_SUGGESTIONS) {
+                row.push(
+                    widget::button::standard(suggestion.as_str())
+                        .on_press(Message::Apply {
+                            word: misspelling.word.clone(),
+                            suggestion: suggestion.clone(),
+                        })
+                        .into(),
+                );
+            }
+        }
+
+        Some(
+            widget::scrollable::horizontal(
+                widget::row::with_children(row)
+                    .spacing(6)
+                    .align_y(Alignment::Center),
+            )
+            .into(),
+        )
+    }
+
+    /// Drops the suggestions, e.g. once the prompt has been sent.
+    pub(super) fn clear_spelling(&mut self) {
+        self.spelling.generation += 1;
+        self.spelling.misspellings.clear();
+    }
+
+    /// Checks the prompt once a word has been finished.
+    pub(super) fn check_spelling(&mut self) -> Task<cosmic::Action<super::Message>> {
+        if !self.config.spellcheck {
+            return Task::none();
+        }
+        self.spelling.generation += 1;
+        if self.input_text.trim().is_empty() {
+            self.spelling.misspellings.clear();
+            return Task::none();
+        }
+        // Checking a word that's still being typed would flag it.
+        if !self
+            .input_text
+            .ends_with(|c: char| c.is_whitespace() || c.is_ascii_punctuation())
+        {
+            return Task::none();
+        }
+
+        let generation = self.spelling.generation;
+        let text = self.input_text.clone();
+        let dictionary = spellcheck::dictionary(&self.config.spellcheck_language);
+        cosmic::task::future(async move {
+            super::Message::Spelling(Message::Checked(
+                generation,
+                spellcheck::check(text, dictionary).await,
+            ))
+        })
+    }
+
+    pub(super) fn update_spelling(
+        &mut self,
+        message: Message,
+    ) -> Task<cosmic::Action<super::Message>> {
+        match message {
+            Message::Checked(generation, result) => {
+                if generation != self.spelling.generation {
+                    return Task::none();
+                }
+                match result {
+                    Ok(misspellings) => {
+                        self.spelling.misspellings = misspellings;
+                        self.spelling.failing = false;
+                    }
+                    Err(err) => {
+                        if !self.spelling.failing {
+                            eprintln!("spell check failed: {err}");
+                            self.spelling.failing = true;
+                        }
+                        self.spelling.misspellings.clear();
+                    }
+                }
+            }
+            Message::Apply { word, suggestion } => {
+                if let Ok(whole_word) = Regex::new(&format!(r"\b{}\b", regex::escape(&word))) {
+                    self.input_text = whole_word
+                        .replace_all(&self.input_text, regex::NoExpand(&suggestion))
+                        .into_owned();
+                }
+                self.spelling
+                    .misspellings
+                    .retain(|misspelling| misspelling.word != word);
+            }
+            Message::Ignore(word) => {
+                self.spelling
+                    .misspellings
+                    .retain(|misspelling| misspelling.word != word);
+            }
+        }
+
+        Task::none()
+    }
+}
@@ -0,0 +1,175 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Saved conversation setups, each starting a new conversation in one click.
+
+use super::{AppModel, Page};
+use crate::config::ConversationTemplate;
+use cosmic::iced::{Alignment, Length};
+use cosmic::prelude::*;
+use cosmic::widget;
+use std::path::PathBuf;
+
+#[derive(Default)]
+pub struct State {
+    /// Name for saving the current conversation as a template.
+    name: String,
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    NameChanged(String),
+    SaveCurrent,
+    Start(usize),
+}
+
+impl AppModel {
+    pub(super) fn templates_view(&self) -> cosmic::Element<'_, Message> {
+        let state = &self.templates;
+
+        let mut column = widget::column::with_children(vec![
+            widget::text::caption(
+                "Starting a template replaces the current conversation. Templates are edited \
+                 on the settings page.",
+            )
+            .into(),
+        ])
+        .spacing(8);
+
+        if self.config.templates.is_empty() {
+            column = column.push(widget::text::body("No templates yet."));
+        }
+        for (index, template) in self.config.templates.iter().enumerate() {
+            let mut details = Vec::new();
+            if !template.model.trim().is_empty() {
+                details.push(template.model.trim().to_string());
+            }
+            if !template.pinned_files.is_empty() {
+                details.push(format!("{} pinned", template.pinned_files.len()));
+            }
+            column = column.push(
+                widget::button::custom(
+                    widget::row::with_children(vec![
+                        widget::text::body(template.name.as_str())
+                            .width(Length::Fill)
+                            .into(),
+                        widget::text::caption(details.join(" · ")).into(),
+                    ])
+                    .align_y(Alignment::Center),
+                )
+                .class(cosmic::theme::Button::MenuItem)
+                .on_press(Message::Start(index))
+                .width(Length::Fill),
+            );
+        }
+
+        column = column.push(
+            widget::row::with_children(vec![
+                widget::text_input("Template name", &state.name)
+                    .on_input(Message::NameChanged)
+                    .on_submit(|_| Message::SaveCurrent)
+                    .width(Length::Fill)
+                    .into(),
+                widget::button::standard("Save current conversation")
+                    .on_press_maybe((!state.name.trim().is_empty()).then_some(Message::SaveCurrent))
+                    .into(),
+            ])
+            .spacing(8)
+            .align_y(Alignment::Center),
+        );
+        if let Some(error) = &state.error {
+            column = column.push(widget::text::caption(error.as_str()));
+        }
+
+        widget::scrollable(column).height(Length::Fill).into()
+    }
+
+    pub(super) fn update_templates(
+        &mut self,
+        message: Message,
+    ) -> Task<cosmic::Action<super::Message>> {
+        match message {
+            Message::NameChanged(name) => {
+                self.templates.name = name;
+                self.templates.error = None;
+            }
+            Message::SaveCurrent => {
+                let name = self.templates.name.trim().to_string();
+                if name.is_empty() {
+                    return Task::none();
+                }
+                if self
+                    .config
+                    .templates
+                    .iter()
+                    .any(|template| template.name == name)
+                {
+                    self.templates.error = Some(format!("A template named {name} already exists"));
+                    return Task::none();
+                }
+
+                let template = self.current_setup(name);
+                self.config.templates.push(template);
+                self.templates = State::default();
+                self.save_config();
+            }
+            Message::Start(index) => {
+                let Some(template) = self.config.templates.get(index).cloned() else {
+                    return Task::none();
+                };
+
+                let previous = self.park_conversation();
+                self.stop_answer_aside(&previous);
+                self.forget_cleared();
+                self.pinned.files = template.pinned_files.iter().map(PathBuf::from).collect();
+                self.config.template = template.name;
+                self.save_config();
+                self.save_session();
+                self.page = Page::Chat;
+
+                let first = template.first_message.trim();
+                if first.is_empty() {
+                    return Task::none();
+                }
+                // Goes through the same checks as a typed prompt.
+                return Task::done(cosmic::Action::App(super::Message::SubmitInput(
+                    first.to_string(),
+                )));
+            }
+        }
+
+        Task::none()
+    }
+
+    /// The setup of the current conversation, to be saved as a template.
+    fn current_setup(&self, name: String) -> ConversationTemplate {
+        let config = self.config.for_workspace();
+        let instructions = match (
+            self.config.active_template(),
+            self.config.active_workspace(),
+        ) {
+            (Some(template), _) => template.instructions.clone(),
+            (None, Some(workspace)) => workspace.instructions.clone(),
+            (None, None) => String::new(),
+        };
+
+        ConversationTemplate {
+            name,
+            provider: Some(config.provider),
+            model: config.model().to_string(),
+            instructions,
+            pinned_files: self
+                .pinned
+                .files
+                .iter()
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect(),
+            first_message: self
+                .chat_history
+                .iter()
+                .find(|chat| chat.role == "user")
+                .map(|chat| chat.content.clone())
+                .unwrap_or_default(),
+        }
+    }
+}
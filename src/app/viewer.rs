@@ -0,0 +1,216 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Image viewer page: shows an attached or generated image with zoom and pan,
+//! and saves or copies it.
+
+use super::{AppModel, Page};
+use crate::attachment::Attachment;
+use cosmic::dialog::file_chooser;
+use cosmic::iced::{Alignment, Length};
+use cosmic::prelude::*;
+use cosmic::widget;
+use std::path::PathBuf;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+
+#[derive(Default)]
+pub struct State {
+    /// The image being shown and its decoded handle.
+    image: Option<(Attachment, widget::image::Handle)>,
+    /// Outcome of the last save or copy.
+    status: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Open(Attachment),
+    /// Opens an image saved on disk, such as a generated one.
+    OpenFile(PathBuf),
+    Loaded(Result<Attachment, String>),
+    Save,
+    Saved(Result<Option<PathBuf>, String>),
+    Copy,
+    Copied(Result<(), String>),
+    Close,
+}
+
+impl AppModel {
+    pub(super) fn viewer_view(&self) -> cosmic::Element<'_, Message> {
+        let state = &self.viewer;
+        let Some((attachment, handle)) = &state.image else {
+            return widget::text::body(state.status.as_deref().unwrap_or_default()).into();
+        };
+
+        let mut actions = vec![
+            widget::text::heading(attachment.name.as_str()).into(),
+            widget::horizontal_space().into(),
+        ];
+        // Saving and copying take the image out of the applet.
+        if !self.config.is_locked() {
+            actions.push(
+                widget::button::icon(widget::icon::from_name("edit-copy-symbolic"))
+                    .on_press(Message::Copy)
+                    .tooltip("Copy image")
+                    .into(),
+            );
+            actions.push(
+                widget::button::icon(widget::icon::from_name("document-save-symbolic"))
+                    .on_press(Message::Save)
+                    .tooltip("Save image")
+                    .into(),
+            );
+        }
+        actions.push(
+            widget::button::icon(widget::icon::from_name("window-close-symbolic"))
+                .on_press(Message::Close)
+                .tooltip("Close")
+                .into(),
+        );
+
+        let mut column = widget::column::with_children(vec![
+            widget::row::with_children(actions)
+                .spacing(8)
+                .align_y(Alignment::Center)
+                .into(),
+            widget::container(
+                cosmic::iced::widget::image::viewer(handle.clone())
+                    .min_scale(0.25)
+                    .max_scale(8.0)
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+            )
+            .class(cosmic::theme::Container::List)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into(),
+            widget::text::caption("Scroll to zoom, drag to pan").into(),
+        ])
+        .spacing(8);
+        if let Some(status) = &state.status {
+            column = column.push(widget::text::caption(status.as_str()));
+        }
+
+        column.into()
+    }
+
+    pub(super) fn update_viewer(
+        &mut self,
+        message: Message,
+    ) -> Task<cosmic::Action<super::Message>> {
+        match message {
+            Message::Open(attachment) => {
+                self.viewer.status = None;
+                let handle = attachment
+                    .preview()
+                    .cloned()
+                    .unwrap_or_else(|| widget::image::Handle::from_bytes(attachment.data.to_vec()));
+                self.viewer.image = Some((attachment, handle));
+                self.page = Page::Viewer;
+            }
+            Message::OpenFile(path) => {
+                return cosmic::task::future(async move {
+                    super::Message::Viewer(Message::Loaded(Attachment::load(&path).await))
+                });
+            }
+            Message::Loaded(result) => match result {
+                Ok(attachment) => return self.update_viewer(Message::Open(attachment)),
+                Err(err) => {
+                    self.viewer.image = None;
+                    self.viewer.status = Some(format!("Could not open image: {err}"));
+                    self.page = Page::Viewer;
+                }
+            },
+            Message::Save => {
+                if self.config.is_locked() {
+                    return Task::none();
+                }
+                if let Some((attachment, _)) = &self.viewer.image {
+                    let attachment = attachment.clone();
+                    return cosmic::task::future(async move {
+                        super::Message::Viewer(Message::Saved(save(attachment).await))
+                    });
+                }
+            }
+            Message::Saved(result) => {
+                self.viewer.status = match result {
+                    Ok(Some(path)) => Some(format!("Saved to {}", path.display())),
+                    Ok(None) => None,
+                    Err(err) => Some(format!("Could not save image: {err}")),
+                };
+            }
+            Message::Copy => {
+                if self.config.is_locked() {
+                    return Task::none();
+                }
+                if let Some((attachment, _)) = &self.viewer.image {
+                    let attachment = attachment.clone();
+                    return cosmic::task::future(async move {
+                        super::Message::Viewer(Message::Copied(copy(attachment).await))
+                    });
+                }
+            }
+            Message::Copied(result) => {
+                self.viewer.status = Some(match result {
+                    Ok(()) => "Copied to the clipboard".into(),
+                    Err(err) => format!("Could not copy image: {err}"),
+                });
+            }
+            Message::Close => {
+                self.viewer.image = None;
+                self.page = Page::Chat;
+            }
+        }
+
+        Task::none()
+    }
+}
+
+/// Asks where to save the image through the desktop portal and writes it there.
+async fn save(attachment: Attachment) -> Result<Option<PathBuf>, String> {
+    let response = match file_chooser::save::Dialog::new()
+        .title("Save image")
+        .file_name(attachment.name.as_str())
+        .save_file()
+        .await
+    {
+        Ok(response) => response,
+        Err(file_chooser::Error::Cancelled) => return Ok(None),
+        Err(err) => return Err(err.to_string()),
+    };
+    let Some(url) = response.url() else {
+        return Ok(None);
+    };
+    let path = url
+        .to_file_path()
+        .map_err(|()| format!("{url} is not a local file"))?;
+
+    tokio::fs::write(&path, &attachment.data)
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(Some(path))
+}
+
+/// Puts the image on the Wayland clipboard with `wl-copy`, which the iced
+/// clipboard can't do since it only holds text.
+async fn copy(attachment: Attachment) -> Result<(), String> {
+    let mut wl_copy = tokio::process::Command::new("wl-copy")
+        .arg("--type")
+        .arg(&attachment.mime_type)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| format!("could not run wl-copy: {err}"))?;
+    if let Some(mut stdin) = wl_copy.stdin.take() {
+        stdin
+            .write_all(&attachment.data)
+            .await
+            .map_err(|err| err.to_string())?;
+    }
+
+    match wl_copy.wait().await {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("wl-copy exited with {status}")),
+        Err(err) => Err(err.to_string()),
+    }
+}
@@ -0,0 +1,259 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! What an empty conversation shows: conversations set aside and those of
+//! other workspaces, templates to start from and a tip.
+
+use super::{AppModel, Page, ParkedConversation, conversations, templates};
+use cosmic::iced::{Alignment, Length};
+use cosmic::prelude::*;
+use cosmic::widget;
+
+/// Templates listed before the rest are left to the templates page.
+const MAX_TEMPLATES: usize = 4;
+
+/// Conversations set aside listed before the rest are left to the
+/// conversations page.
+const MAX_SET_ASIDE: usize = 3;
+
+/// Longest label of a conversation, in characters.
+const MAX_LABEL: usize = 60;
+
+/// Tips with the page they're about.
+const TIPS: &[(&str, Page)] = &[
+    (
+        "Save a conversation as a template to start the same setup in one click.",
+        Page::Templates,
+    ),
+    (
+        "Quick actions run a saved prompt on the selected text or clipboard.",
+        Page::QuickActions,
+    ),
+    (
+        "Workspaces keep a conversation, model and instructions per project.",
+        Page::Settings,
+    ),
+    (
+        "Spending caps hold back prompts that would cost more than you want.",
+        Page::Settings,
+    ),
+    (
+        "Compare sends one prompt to two models and shows the answers side by side.",
+        Page::Compare,
+    ),
+    (
+        "Batch runs a list of independent prompts one after another.",
+        Page::Batch,
+    ),
+];
+
+#[derive(Default)]
+pub struct State {
+    /// Counts up each time the popup opens, picking the tip shown.
+    tip: usize,
+}
+
+impl State {
+    /// Shows the next tip.
+    pub fn next_tip(&mut self) {
+        self.tip = self.tip.wrapping_add(1);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// Switches to the workspace of this name, empty for no workspace.
+    OpenConversation(String),
+    /// Switches to the conversation set aside at this index.
+    OpenSetAside(usize),
+    StartTemplate(usize),
+    OpenTip(Page),
+    NextTip,
+}
+
+impl AppModel {
+    pub(super) fn welcome_view(&self) -> cosmic::Element<'_, Message> {
+        let mut column =
+            widget::column::with_children(vec![widget::text::title3("Start a new chat").into()])
+                .spacing(12)
+                .width(Length::Fill);
+
+        let mut conversations: Vec<_> = self
+            .workspace_conversations
+            .iter()
+            .filter(|(_, parked)| !parked.history.is_empty())
+            .collect();
+        conversations.sort_by(|(a, _), (b, _)| a.cmp(b));
+        if !conversations.is_empty() || !self.conversations.list.is_empty() {
+            let mut section = widget::column::with_children(vec![
+                widget::text::heading("Recent conversations").into(),
+            ])
+            .spacing(2);
+            for (index, parked) in self
+                .conversations
+                .list
+                .iter()
+                .enumerate()
+                .take(MAX_SET_ASIDE)
+            {
+                section = section.push(item(
+                    label(parked),
+                    "Set aside".to_string(),
+                    Message::OpenSetAside(index),
+                ));
+            }
+            for (workspace, parked) in conversations {
+                let label = label(parked);
+                let workspace_name = if workspace.is_empty() {
+                    "No workspace"
+                } else {
+                    workspace.as_str()
+                };
+                section = section.push(item(
+                    label,
+                    workspace_name.to_string(),
+                    Message::OpenConversation(workspace.clone()),
+                ));
+            }
+            column = column.push(section);
+        }
+
+        if !self.config.templates.is_empty() {
+            let mut section =
+                widget::column::with_children(vec![widget::text::heading("Templates").into()])
+                    .spacing(2);
+            for (index, template) in self.config.templates.iter().enumerate().take(MAX_TEMPLATES) {
+                section = section.push(item(
+                    template.name.clone(),
+                    template.model.trim().to_string(),
+                    Message::StartTemplate(index),
+                ));
+            }
+            if self.config.templates.len() > MAX_TEMPLATES {
+                section = section.push(item(
+                    "All templates…".to_string(),
+                    String::new(),
+                    Message::OpenTip(Page::Templates),
+                ));
+            }
+            column = column.push(section);
+        }
+
+        if let Some((tip, page)) = self.tip() {
+            column = column.push(
+                widget::container(
+                    widget::row::with_children(vec![
+                        widget::icon::from_name("dialog-information-symbolic")
+                            .size(16)
+                            .into(),
+                        widget::button::link(tip)
+                            .on_press(Message::OpenTip(page))
+                            .width(Length::Fill)
+                            .into(),
+                        widget::button::icon(widget::icon::from_name("go-next-symbolic"))
+                            .on_press(Message::NextTip)
+                            .tooltip("Next tip")
+                            .into(),
+                    ])
+                    .spacing(8)
+                    .align_y(Alignment::Center),
+                )
+                .class(cosmic::theme::Container::Card)
+                .padding(12),
+            );
+        }
+
+        widget::container(column)
+            .center_y(Length::Fill)
+            .center_x(Length::Fill)
+            .into()
+    }
+
+    /// The tip to show, leaving out the ones about pages the content policy locks.
+    fn tip(&self) -> Option<(&'static str, Page)> {
+        let locked = self.config.is_locked();
+        let tips: Vec<_> = TIPS
+            .iter()
+            .filter(|(_, page)| !locked || page.allowed_when_locked())
+            .collect();
+        if tips.is_empty() {
+            return None;
+        }
+        let (tip, page) = tips[self.welcome.tip % tips.len()];
+        Some((tip, *page))
+    }
+
+    pub(super) fn update_welcome(
+        &mut self,
+        message: Message,
+    ) -> Task<cosmic::Action<super::Message>> {
+        match message {
+            Message::OpenConversation(workspace) => {
+                let index = if workspace.is_empty() {
+                    0
+                } else {
+                    match self
+                        .config
+                        .workspaces
+                        .iter()
+                        .position(|candidate| candidate.name == workspace)
+                    {
+                        Some(index) => index + 1,
+                        None => return Task::none(),
+                    }
+                };
+                return Task::done(cosmic::Action::App(super::Message::WorkspaceSelected(
+                    index,
+                )));
+            }
+            Message::OpenSetAside(index) => {
+                return self.update_conversations(conversations::Message::Open(index));
+            }
+            Message::StartTemplate(index) => {
+                return self.update_templates(templates::Message::Start(index));
+            }
+            Message::OpenTip(page) => {
+                return Task::done(cosmic::Action::App(super::Message::TogglePage(page)));
+            }
+            Message::NextTip => {
+                self.welcome.next_tip();
+            }
+        }
+
+        Task::none()
+    }
+}
+
+fn item(label: String, detail: String, message: Message) -> cosmic::Element<'static, Message> {
+    widget::button::custom(
+        widget::row::with_children(vec![
+            widget::text::body(label).width(Length::Fill).into(),
+            widget::text::caption(detail).into(),
+        ])
+        .spacing(8)
+        .align_y(Alignment::Center),
+    )
+    .class(cosmic::theme::Button::MenuItem)
+    .on_press(message)
+    .width(Length::Fill)
+    .into()
+}
+
+/// The title of `parked`, or else the start of its first prompt.
+pub(super) fn label(parked: &ParkedConversation) -> String {
+    parked.title.clone().unwrap_or_else(|| {
+        let first = parked
+            .history
+            .iter()
+            .find(|chat| chat.role == "user")
+            .map_or("", |chat| chat.content.as_str());
+        shorten(first.lines().next().unwrap_or_default())
+    })
+}
+
+/// `text` cut to `MAX_LABEL` characters.
+fn shorten(text: &str) -> String {
+    match text.char_indices().nth(MAX_LABEL) {
+        Some((end, _)) => format!("{}…", text[..end].trim_end()),
+        None => text.to_string(),
+    }
+}
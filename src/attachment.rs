@@ -0,0 +1,238 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Files sent along with a prompt.
+
+use cosmic::widget::image::Handle;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Images up to this size are sent as they are.
+const MAX_IMAGE_BYTES: usize = 1024 * 1024;
+/// Longest side larger images are scaled down to.
+const MAX_IMAGE_SIDE: u32 = 2048;
+const JPEG_QUALITY: u8 = 85;
+/// Longer texts are refused, since they would fill most of a model's context.
+const MAX_TEXT_BYTES: usize = 512 * 1024;
+
+/// A file or pasted blob attached to a prompt.
+///
+/// Only the name, type and hash are saved with a message; the contents are
+/// kept in the [attachment store](crate::attachment_store).
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub name: String,
+    pub mime_type: String,
+    /// SHA-256 hash of `data`, as lowercase hex.
+    #[serde(default)]
+    pub hash: String,
+    #[serde(skip)]
+    pub data: Arc<[u8]>,
+    /// Decoded once for the previews, if this is an image.
+    #[serde(skip)]
+    preview: Option<Handle>,
+}
+
+impl fmt::Debug for Attachment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Attachment")
+            .field("name", &self.name)
+            .field("mime_type", &self.mime_type)
+            .field("len", &self.data.len())
+            .finish()
+    }
+}
+
+impl Attachment {
+    /// Reads the file at `path`, guessing its type from the extension.
+    pub async fn load(path: &Path) -> Result<Self, String> {
+        let data = tokio::fs::read(path)
+            .await
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+
+        let name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        Ok(Self::new(name, mime_type(path).to_string(), data))
+    }
+
+    fn new(name: String, mime_type: String, data: Vec<u8>) -> Self {
+        let mut attachment = Self {
+            name,
+            mime_type,
+            hash: hash(&data),
+            data: Arc::from([]),
+            preview: None,
+        };
+        attachment.set_data(data.into());
+        attachment
+    }
+
+    /// Text pasted from the clipboard.
+    pub fn pasted_text(text: String) -> Self {
+        Self::new("pasted.txt".into(), "text/plain".into(), text.into_bytes())
+    }
+
+    /// The same attachment with `text` as its contents.
+    pub fn with_text(&self, text: String) -> Self {
+        Self::new(self.name.clone(), self.mime_type.clone(), text.into_bytes())
+    }
+
+    /// Sets the contents, such as when they are read back from the store.
+    pub fn set_data(&mut self, data: Arc<[u8]>) {
+        self.preview = self.is_image().then(|| Handle::from_bytes(data.to_vec()));
+        self.data = data;
+    }
+
+    /// The image to show for this attachment, if it is one.
+    pub fn preview(&self) -> Option<&Handle> {
+        self.preview.as_ref()
+    }
+
+    /// The attachment made fit to send, with large images scaled down and
+    /// re-encoded. Texts too long to send are refused.
+    pub fn fit_to_send(self) -> Result<Self, String> {
+        if self.is_image() && self.data.len() > MAX_IMAGE_BYTES {
+            return Ok(self.downscaled().unwrap_or(self));
+        }
+        if self.text().is_some() && self.data.len() > MAX_TEXT_BYTES {
+            return Err(format!(
+                "{} is too long to send: {} KB (limit is {} KB)",
+                self.name,
+                self.data.len() / 1024,
+                MAX_TEXT_BYTES / 1024
+            ));
+        }
+        Ok(self)
+    }
+
+    /// The image scaled down to `MAX_IMAGE_SIDE`, as a JPEG unless it has
+    /// transparency. `None` if it can't be read or doesn't get any smaller.
+    fn downscaled(&self) -> Option<Self> {
+        let image = image::load_from_memory(&self.data).ok()?;
+        let image = if image.width().max(image.height()) > MAX_IMAGE_SIDE {
+            image.resize(MAX_IMAGE_SIDE, MAX_IMAGE_SIDE, FilterType::Triangle)
+        } else {
+            image
+        };
+
+        let mut data = Vec::new();
+        let (extension, mime_type) = if image.color().has_alpha() {
+            image
+                .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+                .ok()?;
+            ("png", "image/png")
+        } else {
+            DynamicImage::ImageRgb8(image.to_rgb8())
+                .write_with_encoder(JpegEncoder::new_with_quality(&mut data, JPEG_QUALITY))
+                .ok()?;
+            ("jpg", "image/jpeg")
+        };
+        if data.len() >= self.data.len() {
+            return None;
+        }
+
+        let stem = Path::new(&self.name)
+            .file_stem()
+            .map_or(self.name.clone(), |stem| {
+                stem.to_string_lossy().into_owned()
+            });
+        Some(Self::new(
+            format!("{stem}.{extension}"),
+            mime_type.into(),
+            data,
+        ))
+    }
+
+    pub fn is_image(&self) -> bool {
+        self.mime_type.starts_with("image/")
+    }
+
+    /// Whether the file at `path` would be attached as an image.
+    pub fn is_image_path(path: &Path) -> bool {
+        mime_type(path).starts_with("image/")
+    }
+
+    /// The contents, if this is a text file.
+    pub fn text(&self) -> Option<&str> {
+        self.mime_type
+            .starts_with("text/")
+            .then(|| std::str::from_utf8(&self.data).ok())
+            .flatten()
+    }
+}
+
+/// SHA-256 hash of `data`, as lowercase hex.
+pub fn hash(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Whether `name` has the form of a [`hash`], so it can name a file safely.
+pub fn is_hash(name: &str) -> bool {
+    name.len() == 64
+        && name
+            .bytes()
+            .all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "webp" => "image/webp",
+        "gif" => "image/gif",
+        "heic" => "image/heic",
+        "pdf" => "application/pdf",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "md" | "markdown" => "text/markdown",
+        "html" | "htm" => "text/html",
+        "csv" => "text/csv",
+        "json" => "application/json",
+        "txt" | "log" | "rs" | "py" | "js" | "ts" | "c" | "h" | "cpp" | "go" | "java" | "sh"
+        | "toml" | "yaml" | "yml" | "xml" | "ini" | "conf" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_text_is_refused() {
+        let text = "line\n".repeat(MAX_TEXT_BYTES / 5 + 1);
+        assert!(Attachment::pasted_text(text).fit_to_send().is_err());
+        assert!(
+            Attachment::pasted_text("short".into())
+                .fit_to_send()
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn only_hashes_name_stored_files() {
+        assert!(is_hash(&hash(b"contents")));
+        assert!(!is_hash("../../.ssh/id_rsa"));
+        assert!(!is_hash(&hash(b"contents").to_uppercase()));
+    }
+}
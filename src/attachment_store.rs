@@ -0,0 +1,71 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Contents of attachments, kept once per profile however many messages
+//! they are attached to.
+//!
+//! Each file is named after the SHA-256 hash of its contents, which messages
+//! refer to, so the same screenshot attached to five conversations takes the
+//! space of one.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::attachment::{self, Attachment};
+
+/// Directory in the profile's data directory holding the contents.
+pub const DIR: &str = "attachments";
+
+pub struct AttachmentStore {
+    dir: PathBuf,
+}
+
+impl AttachmentStore {
+    /// The store in the profile's data directory `data_dir`.
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            dir: data_dir.join(DIR),
+        }
+    }
+
+    /// Keeps the contents of `attachment`, unless they are kept already.
+    pub fn put(&self, attachment: &Attachment) -> io::Result<()> {
+        if attachment.hash.is_empty() || attachment.data.is_empty() {
+            return Ok(());
+        }
+        let path = self.path(&attachment.hash)?;
+        if path.exists() {
+            return Ok(());
+        }
+        fs::create_dir_all(&self.dir)?;
+        // Written aside first, so a crash can't leave half a file under the hash.
+        let partial = self.dir.join(format!("{}.partial", attachment.hash));
+        let mut file = File::create(&partial)?;
+        file.write_all(&attachment.data)?;
+        file.sync_all()?;
+        fs::rename(partial, path)
+    }
+
+    /// Fills in the contents of `attachment` from the store.
+    pub fn fill(&self, attachment: &mut Attachment) -> io::Result<()> {
+        if attachment.hash.is_empty() || !attachment.data.is_empty() {
+            return Ok(());
+        }
+        let data: Arc<[u8]> = fs::read(self.path(&attachment.hash)?)?.into();
+        attachment.set_data(data);
+        Ok(())
+    }
+
+    /// Where the contents hashed to `hash` are kept, refusing anything but a
+    /// hash since messages may come from files shared by others.
+    fn path(&self, hash: &str) -> io::Result<PathBuf> {
+        if !attachment::is_hash(hash) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid attachment hash {hash:?}"),
+            ));
+        }
+        Ok(self.dir.join(hash))
+    }
+}
@@ -0,0 +1,171 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Backups of a profile's data directory, such as saved images and answer
+//! preferences, to a folder of the user's choosing.
+//!
+//! Each backup is a plain copy named `<profile>-backup-<unix seconds>`, so it
+//! can also be browsed or restored by hand.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Shared downloads and other profiles' data, which aren't part of a backup.
+/// The sync state is left alone too, so a restored backup syncs as a local change.
+const SKIPPED: [&str; 5] = [
+    "models",
+    "voices",
+    "profiles",
+    crate::sync::STATE_FILE,
+    crate::sync::BASE_DIR,
+];
+
+/// Suffix of a backup that is still being written.
+const PARTIAL: &str = ".partial";
+
+/// A backup found in the backup folder.
+#[derive(Debug, Clone)]
+pub struct Backup {
+    pub path: PathBuf,
+    pub created: SystemTime,
+}
+
+/// Copies `data_dir` into a new backup in `folder`.
+pub fn create(data_dir: &Path, folder: &Path, label: &str) -> io::Result<PathBuf> {
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let path = folder.join(format!("{label}-backup-{stamp}"));
+    let partial = folder.join(format!("{label}-backup-{stamp}{PARTIAL}"));
+
+    fs::create_dir_all(&partial)?;
+    if data_dir.is_dir() {
+        copy_dir(data_dir, &partial)?;
+    }
+    // Only complete copies get the name `list` looks for.
+    fs::rename(&partial, &path)?;
+    Ok(path)
+}
+
+/// Backups of the profile `label` in `folder`, newest first.
+pub fn list(folder: &Path, label: &str) -> Vec<Backup> {
+    let prefix = format!("{label}-backup-");
+    let Ok(entries) = fs::read_dir(folder) else {
+        return Vec::new();
+    };
+
+    let mut backups: Vec<Backup> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let stamp: u64 = name.to_str()?.strip_prefix(&prefix)?.parse().ok()?;
+            entry.path().is_dir().then(|| Backup {
+                path: entry.path(),
+                created: UNIX_EPOCH + Duration::from_secs(stamp),
+            })
+        })
+        .collect();
+    backups.sort_by(|a, b| b.created.cmp(&a.created));
+    backups
+}
+
+/// Whether the newest backup is older than `period`.
+pub fn is_due(folder: &Path, label: &str, period: Duration) -> bool {
+    list(folder, label)
+        .first()
+        .and_then(|newest| newest.created.elapsed().ok())
+        .is_none_or(|age| age >= period)
+}
+
+/// Deletes all but the `keep` newest backups.
+pub fn rotate(folder: &Path, label: &str, keep: usize) -> io::Result<()> {
+    for backup in list(folder, label).into_iter().skip(keep.max(1)) {
+        fs::remove_dir_all(&backup.path)?;
+    }
+    Ok(())
+}
+
+/// Replaces the contents of `data_dir` with those of `backup`.
+///
+/// Downloaded models and voices are left in place.
+pub fn restore(backup: &Path, data_dir: &Path) -> io::Result<()> {
+    if !backup.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} is not a backup", backup.display()),
+        ));
+    }
+
+    if data_dir.is_dir() {
+        for entry in fs::read_dir(data_dir)?.flatten() {
+            if is_skipped(&entry.file_name()) {
+                continue;
+            }
+            let path = entry.path();
+            if path.is_dir() {
+                fs::remove_dir_all(&path)?;
+            } else {
+                fs::remove_file(&path)?;
+            }
+        }
+    }
+    fs::create_dir_all(data_dir)?;
+    copy_dir(backup, data_dir)
+}
+
+/// Whether the top level entry `name` is left out of backups.
+pub fn is_skipped(name: &std::ffi::OsStr) -> bool {
+    SKIPPED.iter().any(|skipped| name == *skipped)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(from)?.flatten() {
+        let name = entry.file_name();
+        if is_skipped(&name) {
+            continue;
+        }
+        let target = to.join(&name);
+        if entry.file_type()?.is_dir() {
+            fs::create_dir_all(&target)?;
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotation_keeps_the_newest_backups() {
+        let folder = std::env::temp_dir().join(format!("backup-rotate-{}", std::process::id()));
+        for name in ["p-backup-1", "p-backup-2", "p-backup-3", "q-backup-1"] {
+            fs::create_dir_all(folder.join(name)).unwrap();
+        }
+
+        rotate(&folder, "p", 2).unwrap();
+        let names = |label| -> Vec<PathBuf> {
+            list(&folder, label)
+                .into_iter()
+                .map(|backup| backup.path)
+                .collect()
+        };
+        assert_eq!(
+            names("p"),
+            [folder.join("p-backup-3"), folder.join("p-backup-2")]
+        );
+        // Other profiles' backups are theirs to rotate.
+        assert_eq!(names("q"), [folder.join("q-backup-1")]);
+
+        // The newest backup is always kept.
+        rotate(&folder, "p", 0).unwrap();
+        assert_eq!(names("p"), [folder.join("p-backup-3")]);
+
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
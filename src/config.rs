@@ -1,9 +1,1392 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use crate::redact::Pattern;
+use crate::{credentials, keyring};
+use argon2::Argon2;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use chacha20poly1305::aead::OsRng;
+use chacha20poly1305::aead::rand_core::RngCore;
+use cosmic::cosmic_config::{self, CosmicConfigEntry, cosmic_config_derive::CosmicConfigEntry};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{fs, io};
 
-#[derive(Debug, Default, Clone, CosmicConfigEntry, Eq, PartialEq)]
+#[derive(Debug, Default, Clone, CosmicConfigEntry, PartialEq, Serialize, Deserialize)]
 #[version = 1]
+#[serde(default)]
 pub struct Config {
-    demo: String,
+    /// Backend answering prompts.
+    pub provider: Provider,
+    /// Refuse cloud providers and network features, for air-gapped use.
+    pub local_only: bool,
+    /// Settings for the Gemini provider.
+    pub gemini: ProviderSettings,
+    /// Settings for the OpenAI provider.
+    pub openai: ProviderSettings,
+    /// Settings for a server with an OpenAI-compatible API.
+    pub compatible: CompatibleSettings,
+    /// Settings for local GGUF models.
+    pub local: LocalSettings,
+    /// Icon of the panel button.
+    pub panel_icon: PanelIcon,
+    /// What is shown with the panel icon.
+    pub panel_label_source: PanelLabel,
+    /// Text shown with the panel icon, empty for the icon alone.
+    pub panel_label: String,
+    /// Show the debug inspector button in the popup.
+    pub debug_inspector: bool,
+    /// Parse what can be parsed of a response that doesn't match what is expected,
+    /// instead of failing it.
+    pub lenient_parsing: bool,
+    /// Include the raw response in the error when it can't be parsed.
+    pub raw_on_parse_error: bool,
+    /// Serving version last seen for each model, keyed by provider and model.
+    pub model_versions: BTreeMap<String, String>,
+    /// Warn when a model starts being served by another version.
+    pub version_alerts: bool,
+    /// Selected generation parameter preset.
+    pub preset: GenerationPreset,
+    /// Parameters used by the custom preset.
+    pub custom_generation: GenerationParams,
+    /// Kinds of output requested from models that support more than text.
+    pub response_modalities: ResponseModalities,
+    /// Fidelity of image and video input, trading detail for tokens.
+    pub media_resolution: MediaResolution,
+    /// Keep both answers when regenerating and ask which one is better.
+    pub ab_regeneration: bool,
+    /// Read-aloud speed, from -100 (slowest) to 100 (fastest).
+    pub speech_rate: i32,
+    /// Program that reads answers aloud.
+    pub speech_engine: SpeechEngine,
+    /// Piper voice, e.g. `en_US-lessac-medium`.
+    pub piper_voice: String,
+    /// Models shown side by side in the compare view.
+    pub compare_models: Vec<String>,
+    /// Where shared conversations are uploaded.
+    pub share: ShareSettings,
+    /// What to do when a prompt contains secrets or personal data.
+    pub redaction: RedactionSettings,
+    /// Check the spelling of prompts.
+    pub spellcheck: bool,
+    /// Dictionary used for spell checking, empty for the session locale.
+    pub spellcheck_language: String,
+    /// Standing instructions given to the model in every conversation, such as
+    /// "answer briefly". Workspace and template instructions follow them.
+    pub instructions: String,
+    /// Language every answer is written in, empty to follow the prompt.
+    pub reply_language: String,
+    /// Command opening a cited file at a line, with `{file}` and `{line}` filled
+    /// in. Empty to open the file with its default application.
+    pub editor_command: String,
+    /// Suggest follow-up questions under each answer, at the cost of a second request.
+    pub follow_up_suggestions: bool,
+    /// Let the model title each conversation after its first answer.
+    pub auto_titles: bool,
+    /// Estimated tokens background requests may use per day, 0 for no cap.
+    pub background_token_cap: u32,
+    /// Spending limits that hold back prompts until confirmed.
+    pub cost_cap: CostCap,
+    /// Limits after which watched pinned files stop sending prompts.
+    pub watch_limits: WatchLimits,
+    /// Blocking thresholds for harmful content.
+    pub safety: SafetySettings,
+    /// Set while the content policy is locked, see [`PolicyLock`].
+    pub policy_lock: Option<PolicyLock>,
+    /// Keep conversations only while the applet runs, rather than restoring
+    /// them after a restart.
+    pub forget_conversations: bool,
+    /// Automatic backups of the profile's data.
+    pub backup: BackupSettings,
+    /// Encrypted sync of the profile's data with a server.
+    pub sync: SyncSettings,
+    /// Where request metrics are written or served.
+    pub metrics: MetricsSettings,
+    /// User-defined prompts offered in the quick action palette.
+    pub quick_actions: Vec<QuickAction>,
+    /// Run the Rhai scripts in the scripts directory on prompts and answers.
+    pub scripting: bool,
+    /// Named groups of conversations with their own defaults.
+    pub workspaces: Vec<Workspace>,
+    /// Name of the active workspace, empty for none.
+    pub workspace: String,
+    /// Characters the model can play, each with its own instructions.
+    pub personas: Vec<Persona>,
+    /// Name of the active persona, empty for none.
+    pub persona: String,
+    /// Saved conversation setups that new conversations can be started from.
+    pub templates: Vec<ConversationTemplate>,
+    /// Name of the template the current conversation was started from, empty for none.
+    pub template: String,
+    /// Tone asked of the model in the current conversation.
+    pub tone: Tone,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Provider {
+    #[default]
+    Gemini,
+    /// OpenAI's chat completions API.
+    OpenAi,
+    /// Any server with an OpenAI-compatible API, at a configured URL.
+    Compatible,
+    /// A GGUF model served by llama.cpp on this machine.
+    Local,
+}
+
+impl Provider {
+    pub const ALL: [Self; 4] = [Self::Gemini, Self::OpenAi, Self::Compatible, Self::Local];
+    pub const NAMES: [&str; 4] = ["Gemini", "OpenAI", "OpenAI-compatible", "Local model"];
+
+    pub fn index(self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|provider| *provider == self)
+            .unwrap_or_default()
+    }
+
+    /// Lowercase name, e.g. for labels of metrics.
+    pub fn id(self) -> &'static str {
+        match self {
+            Self::Gemini => "gemini",
+            Self::OpenAi => "openai",
+            Self::Compatible => "compatible",
+            Self::Local => "local",
+        }
+    }
+}
+
+/// A server with an OpenAI-compatible API, e.g. llama.cpp's server, LM Studio,
+/// vLLM or LocalAI.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompatibleSettings {
+    /// Base URL of the API, e.g. `http://localhost:1234/v1`.
+    pub base_url: String,
+    /// Model as the server names it, empty for the server's default.
+    pub model: String,
+    /// Bearer token saved by earlier versions, moved to the keyring when the
+    /// applet starts.
+    pub api_key: String,
+}
+
+impl CompatibleSettings {
+    /// Whether the server runs on this machine, going by its URL.
+    pub fn is_loopback(&self) -> bool {
+        let url = self.base_url.trim();
+        let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+        let host = rest.split('/').next().unwrap_or_default();
+        let host = match host.rsplit_once(':') {
+            Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host,
+            _ => host,
+        };
+        matches!(host, "localhost" | "[::1]") || host.starts_with("127.")
+    }
+}
+
+/// Settings for running GGUF models locally.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LocalSettings {
+    /// Path of the GGUF file to load.
+    pub model_path: String,
+    /// llama.cpp server executable, empty for `llama-server` on the `PATH`.
+    pub server_binary: String,
+    /// Context window in tokens, 0 for the model default.
+    pub context_size: u32,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpeechEngine {
+    #[default]
+    SpeechDispatcher,
+    /// Offline neural voices.
+    Piper,
+}
+
+impl SpeechEngine {
+    pub const ALL: [Self; 2] = [Self::SpeechDispatcher, Self::Piper];
+    pub const NAMES: [&str; 2] = ["Speech Dispatcher", "Piper"];
+
+    pub fn index(self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|engine| *engine == self)
+            .unwrap_or_default()
+    }
+}
+
+/// Harm categories that accept a blocking threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HarmCategory {
+    Harassment,
+    HateSpeech,
+    SexuallyExplicit,
+    DangerousContent,
+    CivicIntegrity,
+}
+
+impl HarmCategory {
+    pub const ALL: [Self; 5] = [
+        Self::Harassment,
+        Self::HateSpeech,
+        Self::SexuallyExplicit,
+        Self::DangerousContent,
+        Self::CivicIntegrity,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Harassment => "Harassment",
+            Self::HateSpeech => "Hate speech",
+            Self::SexuallyExplicit => "Sexually explicit",
+            Self::DangerousContent => "Dangerous content",
+            Self::CivicIntegrity => "Civic integrity",
+        }
+    }
+}
+
+/// How likely content must be to be harmful before it is blocked, most permissive first.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HarmThreshold {
+    /// Turns the filter off, including its ratings.
+    Off,
+    BlockNone,
+    BlockOnlyHigh,
+    #[default]
+    BlockMediumAndAbove,
+    BlockLowAndAbove,
+}
+
+impl HarmThreshold {
+    pub const ALL: [Self; 5] = [
+        Self::Off,
+        Self::BlockNone,
+        Self::BlockOnlyHigh,
+        Self::BlockMediumAndAbove,
+        Self::BlockLowAndAbove,
+    ];
+    pub const NAMES: [&str; 5] = [
+        "Off",
+        "Rate but don't block",
+        "Block high risk",
+        "Block medium risk and above",
+        "Block low risk and above",
+    ];
+
+    pub fn index(self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|threshold| *threshold == self)
+            .unwrap_or_default()
+    }
+
+    pub fn name(self) -> &'static str {
+        Self::NAMES[self.index()]
+    }
+}
+
+/// Named bundles of harm thresholds.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SafetyPreset {
+    /// Leaves the thresholds to the model.
+    #[default]
+    Default,
+    Relaxed,
+    Strict,
+    /// The per-category thresholds in [`SafetySettings`].
+    Custom,
+}
+
+impl SafetyPreset {
+    pub const ALL: [Self; 4] = [Self::Default, Self::Relaxed, Self::Strict, Self::Custom];
+    pub const NAMES: [&str; 4] = ["Default", "Relaxed", "Strict", "Custom"];
+
+    pub fn index(self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|preset| *preset == self)
+            .unwrap_or_default()
+    }
+
+    pub fn name(self) -> &'static str {
+        Self::NAMES[self.index()]
+    }
+
+    /// Threshold the preset applies to every category, if it's uniform.
+    pub fn threshold(self) -> Option<HarmThreshold> {
+        match self {
+            Self::Relaxed => Some(HarmThreshold::BlockOnlyHigh),
+            Self::Strict => Some(HarmThreshold::BlockLowAndAbove),
+            Self::Default | Self::Custom => None,
+        }
+    }
+}
+
+/// How formally answers are written.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Formality {
+    /// Leaves the register to the model.
+    #[default]
+    Default,
+    Formal,
+    Casual,
+}
+
+impl Formality {
+    pub const ALL: [Self; 3] = [Self::Default, Self::Formal, Self::Casual];
+    pub const NAMES: [&str; 3] = ["Default", "Formal", "Casual"];
+
+    pub fn index(self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|formality| *formality == self)
+            .unwrap_or_default()
+    }
+
+    pub fn name(self) -> &'static str {
+        Self::NAMES[self.index()]
+    }
+}
+
+/// How long answers are.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Verbosity {
+    /// Leaves the length to the model.
+    #[default]
+    Default,
+    Brief,
+    Detailed,
+}
+
+impl Verbosity {
+    pub const ALL: [Self; 3] = [Self::Default, Self::Brief, Self::Detailed];
+    pub const NAMES: [&str; 3] = ["Default", "Brief", "Detailed"];
+
+    pub fn index(self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|verbosity| *verbosity == self)
+            .unwrap_or_default()
+    }
+
+    pub fn name(self) -> &'static str {
+        Self::NAMES[self.index()]
+    }
+}
+
+/// Tone of a conversation, compiled into the system instruction.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tone {
+    pub formality: Formality,
+    pub verbosity: Verbosity,
+}
+
+impl Tone {
+    /// Instruction asking for the tone, if it isn't left to the model.
+    pub fn instruction(self) -> Option<String> {
+        let mut parts = Vec::new();
+        match self.formality {
+            Formality::Default => {}
+            Formality::Formal => parts.push("Write in a formal, professional register."),
+            Formality::Casual => parts.push("Write in a casual, conversational register."),
+        }
+        match self.verbosity {
+            Verbosity::Default => {}
+            Verbosity::Brief => {
+                parts.push("Keep answers brief and to the point, without preamble or recaps.")
+            }
+            Verbosity::Detailed => {
+                parts.push("Give thorough answers that explain the reasoning and cover edge cases.")
+            }
+        }
+        (!parts.is_empty()).then(|| parts.join(" "))
+    }
+
+    /// Short description for the header, "Default tone" when nothing is asked.
+    pub fn label(self) -> String {
+        match (self.formality, self.verbosity) {
+            (Formality::Default, Verbosity::Default) => "Default tone".to_string(),
+            (formality, Verbosity::Default) => formality.name().to_string(),
+            (Formality::Default, verbosity) => verbosity.name().to_string(),
+            (formality, verbosity) => {
+                format!("{}, {}", formality.name(), verbosity.name().to_lowercase())
+            }
+        }
+    }
+}
+
+/// Safety preset and the thresholds of the custom preset.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SafetySettings {
+    pub preset: SafetyPreset,
+    pub harassment: HarmThreshold,
+    pub hate_speech: HarmThreshold,
+    pub sexually_explicit: HarmThreshold,
+    pub dangerous_content: HarmThreshold,
+    pub civic_integrity: HarmThreshold,
+}
+
+impl SafetySettings {
+    /// Threshold of `category` in the custom preset.
+    pub fn custom(&self, category: HarmCategory) -> HarmThreshold {
+        match category {
+            HarmCategory::Harassment => self.harassment,
+            HarmCategory::HateSpeech => self.hate_speech,
+            HarmCategory::SexuallyExplicit => self.sexually_explicit,
+            HarmCategory::DangerousContent => self.dangerous_content,
+            HarmCategory::CivicIntegrity => self.civic_integrity,
+        }
+    }
+
+    pub fn set_custom(&mut self, category: HarmCategory, threshold: HarmThreshold) {
+        let field = match category {
+            HarmCategory::Harassment => &mut self.harassment,
+            HarmCategory::HateSpeech => &mut self.hate_speech,
+            HarmCategory::SexuallyExplicit => &mut self.sexually_explicit,
+            HarmCategory::DangerousContent => &mut self.dangerous_content,
+            HarmCategory::CivicIntegrity => &mut self.civic_integrity,
+        };
+        *field = threshold;
+    }
+}
+
+/// A locked content policy for shared machines.
+///
+/// While locked, safety settings are pinned to the strictest level, the
+/// conversation can't be exported, backed up or synced, and only the chat page
+/// is available.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyLock {
+    /// Argon2 hash of the passphrase as a PHC string, which includes its salt.
+    passphrase_hash: String,
+}
+
+impl PolicyLock {
+    pub fn new(passphrase: &str) -> Result<Self, String> {
+        let mut salt = [0; 16];
+        OsRng.fill_bytes(&mut salt);
+        let salt = SaltString::encode_b64(&salt).map_err(|err| err.to_string())?;
+        let hash = Argon2::default()
+            .hash_password(passphrase.as_bytes(), &salt)
+            .map_err(|err| err.to_string())?;
+
+        Ok(Self {
+            passphrase_hash: hash.to_string(),
+        })
+    }
+
+    pub fn verify(&self, passphrase: &str) -> bool {
+        PasswordHash::new(&self.passphrase_hash).is_ok_and(|hash| {
+            Argon2::default()
+                .verify_password(passphrase.as_bytes(), &hash)
+                .is_ok()
+        })
+    }
+}
+
+/// Handling of sensitive text in prompts, per kind of text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactionSettings {
+    pub api_keys: RedactionAction,
+    pub tokens: RedactionAction,
+    pub emails: RedactionAction,
+    pub private_ips: RedactionAction,
+    pub high_entropy: RedactionAction,
+}
+
+impl Default for RedactionSettings {
+    fn default() -> Self {
+        Self {
+            api_keys: RedactionAction::Warn,
+            tokens: RedactionAction::Warn,
+            emails: RedactionAction::Off,
+            private_ips: RedactionAction::Off,
+            high_entropy: RedactionAction::Off,
+        }
+    }
+}
+
+impl RedactionSettings {
+    pub fn action(&self, pattern: Pattern) -> RedactionAction {
+        match pattern {
+            Pattern::ApiKey => self.api_keys,
+            Pattern::Token => self.tokens,
+            Pattern::Email => self.emails,
+            Pattern::PrivateIp => self.private_ips,
+            Pattern::HighEntropy => self.high_entropy,
+        }
+    }
+
+    pub fn set_action(&mut self, pattern: Pattern, action: RedactionAction) {
+        let field = match pattern {
+            Pattern::ApiKey => &mut self.api_keys,
+            Pattern::Token => &mut self.tokens,
+            Pattern::Email => &mut self.emails,
+            Pattern::PrivateIp => &mut self.private_ips,
+            Pattern::HighEntropy => &mut self.high_entropy,
+        };
+        *field = action;
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RedactionAction {
+    #[default]
+    Off,
+    /// Ask before sending.
+    Warn,
+    /// Replace with a placeholder without asking.
+    Mask,
+}
+
+impl RedactionAction {
+    pub const ALL: [Self; 3] = [Self::Off, Self::Warn, Self::Mask];
+    pub const NAMES: [&str; 3] = ["Allow", "Warn", "Mask"];
+
+    pub fn index(self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|action| *action == self)
+            .unwrap_or_default()
+    }
+}
+
+/// Estimated spending, in US dollars, above which prompts need confirmation.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CostCap {
+    /// Limit for one conversation, 0 for none.
+    pub per_conversation: f64,
+    /// Limit for all requests of a day, 0 for none.
+    pub per_day: f64,
+    /// Limit for a single prompt, 0 for none.
+    pub per_prompt: f64,
+}
+
+/// Keeps a watched file that changes with every answer from prompting forever.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchLimits {
+    /// Prompts sent in a row without one typed in between, 0 for no limit.
+    pub max_prompts: u32,
+    /// Minutes after switching on watching, 0 for no limit.
+    pub max_minutes: u32,
+}
+
+impl Default for WatchLimits {
+    fn default() -> Self {
+        Self {
+            max_prompts: 10,
+            max_minutes: 60,
+        }
+    }
+}
+
+/// Where and how often the profile's data is backed up.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupSettings {
+    /// Folder that receives the backups, empty when none was chosen.
+    pub folder: String,
+    pub interval: BackupInterval,
+    /// Number of backups kept, older ones are deleted.
+    pub keep: u32,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            folder: String::new(),
+            interval: BackupInterval::Off,
+            keep: 7,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BackupInterval {
+    /// Only back up on request.
+    #[default]
+    Off,
+    Daily,
+    Weekly,
+}
+
+impl BackupInterval {
+    pub const ALL: [Self; 3] = [Self::Off, Self::Daily, Self::Weekly];
+    pub const NAMES: [&str; 3] = ["Manually", "Daily", "Weekly"];
+
+    pub fn index(self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|interval| *interval == self)
+            .unwrap_or_default()
+    }
+
+    /// Time between automatic backups.
+    pub fn period(self) -> Option<Duration> {
+        match self {
+            Self::Off => None,
+            Self::Daily => Some(Duration::from_secs(24 * 60 * 60)),
+            Self::Weekly => Some(Duration::from_secs(7 * 24 * 60 * 60)),
+        }
+    }
+}
+
+/// Request metrics in the Prometheus text format, both off by default.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetricsSettings {
+    /// File rewritten after every request, empty for none.
+    pub file: String,
+    /// Port of localhost serving the metrics, 0 for none.
+    pub port: u16,
+}
+
+/// Server the profile's data is synced with.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncSettings {
+    pub enabled: bool,
+    pub backend: SyncBackend,
+    /// WebDAV folder URL or S3 endpoint.
+    pub url: String,
+    /// S3 bucket, unused for WebDAV.
+    pub bucket: String,
+    /// S3 region, unused for WebDAV.
+    pub region: String,
+    /// WebDAV user name or S3 access key id.
+    pub username: String,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SyncBackend {
+    #[default]
+    WebDav,
+    /// Any S3-compatible object storage.
+    S3,
+}
+
+impl SyncBackend {
+    pub const ALL: [Self; 2] = [Self::WebDav, Self::S3];
+    pub const NAMES: [&str; 2] = ["WebDAV", "S3-compatible"];
+
+    pub fn index(self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|backend| *backend == self)
+            .unwrap_or_default()
+    }
+}
+
+/// Destination of shared conversations.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShareSettings {
+    pub target: ShareTarget,
+    /// Endpoint that takes the raw text in a POST body and answers with its link.
+    pub paste_url: String,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShareTarget {
+    #[default]
+    Paste,
+    /// A secret GitHub gist, created with the token in `GITHUB_TOKEN`.
+    Gist,
+}
+
+impl ShareTarget {
+    pub const ALL: [Self; 2] = [Self::Paste, Self::Gist];
+    pub const NAMES: [&str; 2] = ["Paste service", "GitHub gist"];
+
+    pub fn index(self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|target| *target == self)
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PanelIcon {
+    #[default]
+    Applet,
+    Chat,
+    Terminal,
+    Help,
+}
+
+impl PanelIcon {
+    pub const ALL: [Self; 4] = [Self::Applet, Self::Chat, Self::Terminal, Self::Help];
+    pub const NAMES: [&str; 4] = ["Applet", "Speech bubble", "Terminal", "Question mark"];
+
+    pub fn index(self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|icon| *icon == self)
+            .unwrap_or_default()
+    }
+
+    /// Name of the symbolic icon in the icon theme.
+    pub fn icon_name(self) -> &'static str {
+        match self {
+            Self::Applet => constcat::concat!(crate::app::APPID, "-symbolic"),
+            Self::Chat => "chat-bubble-empty-symbolic",
+            Self::Terminal => "utilities-terminal-symbolic",
+            Self::Help => "help-browser-symbolic",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PanelLabel {
+    /// The text of `Config::panel_label`.
+    #[default]
+    Text,
+    /// Name of the model answering in the active workspace.
+    Model,
+}
+
+impl PanelLabel {
+    pub const ALL: [Self; 2] = [Self::Text, Self::Model];
+    pub const NAMES: [&str; 2] = ["Custom text", "Active model"];
+
+    pub fn index(self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|label| *label == self)
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResponseModalities {
+    #[default]
+    Text,
+    TextAndImage,
+}
+
+impl ResponseModalities {
+    pub const ALL: [Self; 2] = [Self::Text, Self::TextAndImage];
+    pub const NAMES: [&str; 2] = ["Text", "Text and images"];
+
+    pub fn index(self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|modalities| *modalities == self)
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MediaResolution {
+    #[default]
+    Default,
+    Low,
+    Medium,
+    High,
+}
+
+impl MediaResolution {
+    pub const ALL: [Self; 4] = [Self::Default, Self::Low, Self::Medium, Self::High];
+    pub const NAMES: [&str; 4] = ["Model default", "Low", "Medium", "High"];
+
+    pub fn index(self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|resolution| *resolution == self)
+            .unwrap_or_default()
+    }
+}
+
+/// Sampling parameters sent with a request, `None` leaves the model default.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerationParams {
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    /// Number of likeliest tokens to sample from. Not supported by OpenAI.
+    pub top_k: Option<u32>,
+    pub max_output_tokens: Option<u32>,
+    pub presence_penalty: Option<f32>,
+    pub frequency_penalty: Option<f32>,
+    /// Number of alternative tokens to report log probabilities for.
+    pub logprobs: Option<u32>,
+}
+
+/// Named bundles of generation parameters.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GenerationPreset {
+    Precise,
+    #[default]
+    Balanced,
+    Creative,
+    Custom,
+}
+
+impl GenerationPreset {
+    pub const ALL: [Self; 4] = [Self::Precise, Self::Balanced, Self::Creative, Self::Custom];
+
+    /// Display names, in the order of [`GenerationPreset::ALL`].
+    pub const NAMES: [&str; 4] = ["Precise", "Balanced", "Creative", "Custom"];
+
+    pub fn index(self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|preset| *preset == self)
+            .unwrap_or_default()
+    }
+
+    pub fn name(self) -> &'static str {
+        Self::NAMES[self.index()]
+    }
+}
+
+/// Connection settings shared by all providers.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProviderSettings {
+    /// Model to use, empty for the provider's default.
+    pub model: String,
+    /// API key entered on the settings page by earlier versions, moved to the
+    /// keyring when the applet starts.
+    pub api_key: String,
+    /// Extra headers sent with every request, e.g. for API gateways.
+    pub headers: Vec<HttpHeader>,
+    /// OpenAI organization ID, empty for the key's default organization.
+    pub organization: String,
+    /// OpenAI project ID, or the Google Cloud project billed for Gemini
+    /// requests. Empty for the key's default.
+    pub project: String,
+    /// Google Cloud region, e.g. `us-central1`, sending Gemini requests to
+    /// Vertex AI in `project` instead. Empty for the Gemini API.
+    pub location: String,
+}
+
+/// A group of conversations with defaults that replace the global settings.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Workspace {
+    pub name: String,
+    /// Provider to use, `None` to keep the global one.
+    pub provider: Option<Provider>,
+    /// Model of the provider, empty to keep the global one.
+    pub model: String,
+    pub preset: Option<GenerationPreset>,
+    /// Instructions given to the model in every conversation of the workspace.
+    pub instructions: String,
+    /// Project folder, which programs run from the workspace start in and which
+    /// relative paths in answers are looked up in. Empty for none.
+    pub directory: String,
+    /// Variables set for programs run from the workspace, as `NAME=value` pairs
+    /// separated by spaces.
+    pub environment: String,
+}
+
+impl Workspace {
+    /// The project folder with `~` expanded, if one is set.
+    pub fn directory(&self) -> Option<PathBuf> {
+        let directory = self.directory.trim();
+        if directory.is_empty() {
+            return None;
+        }
+        Some(match directory.strip_prefix("~/") {
+            Some(rest) => PathBuf::from(std::env::var_os("HOME")?).join(rest),
+            None => PathBuf::from(directory),
+        })
+    }
+
+    /// The environment variables, skipping words that aren't `NAME=value`.
+    pub fn environment(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.environment
+            .split_whitespace()
+            .filter_map(|pair| pair.split_once('='))
+            .filter(|(name, _)| !name.is_empty())
+    }
+}
+
+/// A character the model plays, such as "Coder" or "Translator", switched
+/// from the header without leaving the conversation.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Persona {
+    pub name: String,
+    /// Instructions given to the model while the persona is active.
+    pub instructions: String,
+    /// Preset to use, `None` to keep the workspace's or global one.
+    pub preset: Option<GenerationPreset>,
+}
+
+/// A saved conversation setup, started from the templates page.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConversationTemplate {
+    pub name: String,
+    /// Provider the model belongs to, `None` to keep the workspace's or global one.
+    pub provider: Option<Provider>,
+    /// Model of the provider, empty to keep the workspace's or global one.
+    pub model: String,
+    /// Instructions given to the model throughout the conversation.
+    pub instructions: String,
+    /// Files pinned to the conversation when it starts.
+    pub pinned_files: Vec<String>,
+    /// Prompt sent as soon as the conversation starts, empty to send none.
+    pub first_message: String,
+}
+
+/// A named prompt template run from the quick action palette.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuickAction {
+    pub name: String,
+    /// Prompt sent to the model, `{input}` is replaced by the action's input.
+    pub template: String,
+    pub input: QuickActionInput,
+}
+
+impl QuickAction {
+    /// Placeholder in `template` for the text the action works on.
+    pub const PLACEHOLDER: &str = "{input}";
+
+    /// The prompt for `input`, which is appended when the template has no placeholder.
+    pub fn prompt(&self, input: &str) -> String {
+        if self.template.contains(Self::PLACEHOLDER) {
+            self.template.replace(Self::PLACEHOLDER, input)
+        } else if input.is_empty() {
+            self.template.clone()
+        } else {
+            format!("{}\n\n{input}", self.template)
+        }
+    }
+}
+
+/// Where a quick action takes its input from.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuickActionInput {
+    /// Text selected in any application.
+    #[default]
+    Selection,
+    Clipboard,
+    /// Whatever is typed in the prompt field.
+    Typed,
+}
+
+impl QuickActionInput {
+    pub const ALL: [Self; 3] = [Self::Selection, Self::Clipboard, Self::Typed];
+    pub const NAMES: [&str; 3] = ["Selected text", "Clipboard", "Typed text"];
+
+    pub fn index(self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|input| *input == self)
+            .unwrap_or_default()
+    }
+}
+
+/// A custom HTTP header.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpHeader {
+    pub name: String,
+    pub value: String,
+    /// The value is a credential and is left out of exports.
+    pub secret: bool,
+}
+
+/// File format used to move the configuration between machines.
+#[derive(Debug, Serialize, Deserialize)]
+struct ConfigExport {
+    version: u64,
+    config: Config,
+}
+
+impl Config {
+    /// Whether the selected provider answers on this machine, so it is allowed
+    /// in local-only mode.
+    pub fn runs_locally(&self) -> bool {
+        match self.provider {
+            Provider::Local => true,
+            Provider::Compatible => self.compatible.is_loopback(),
+            Provider::Gemini | Provider::OpenAi => false,
+        }
+    }
+
+    /// Whether the content policy is locked.
+    pub fn is_locked(&self) -> bool {
+        self.policy_lock.is_some()
+    }
+
+    /// Instructions sent ahead of the conversation, if any.
+    pub fn system_instruction(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if !self.instructions.trim().is_empty() {
+            parts.push(self.instructions.trim().to_string());
+        }
+        if let Some(persona) = self.active_persona()
+            && !persona.instructions.trim().is_empty()
+        {
+            parts.push(persona.instructions.trim().to_string());
+        }
+        if let Some(workspace) = self.active_workspace()
+            && !workspace.instructions.trim().is_empty()
+        {
+            parts.push(workspace.instructions.trim().to_string());
+        }
+        if let Some(template) = self.active_template()
+            && !template.instructions.trim().is_empty()
+        {
+            parts.push(template.instructions.trim().to_string());
+        }
+        if let Some(tone) = self.tone.instruction() {
+            parts.push(tone);
+        }
+        let language = self.reply_language.trim();
+        if !language.is_empty() {
+            parts.push(format!(
+                "Always reply in {language}, whatever language the prompt is written in."
+            ));
+        }
+        (!parts.is_empty()).then(|| parts.join("\n\n"))
+    }
+
+    pub fn active_workspace(&self) -> Option<&Workspace> {
+        if self.workspace.is_empty() {
+            return None;
+        }
+        self.workspaces
+            .iter()
+            .find(|workspace| workspace.name == self.workspace)
+    }
+
+    pub fn active_persona(&self) -> Option<&Persona> {
+        if self.persona.is_empty() {
+            return None;
+        }
+        self.personas
+            .iter()
+            .find(|persona| persona.name == self.persona)
+    }
+
+    pub fn active_template(&self) -> Option<&ConversationTemplate> {
+        if self.template.is_empty() {
+            return None;
+        }
+        self.templates
+            .iter()
+            .find(|template| template.name == self.template)
+    }
+
+    /// The configuration with the defaults of the active workspace, persona and
+    /// of the conversation's template applied.
+    pub fn for_workspace(&self) -> Self {
+        let mut config = self.clone();
+        if let Some(workspace) = self.active_workspace() {
+            if let Some(provider) = workspace.provider {
+                config.provider = provider;
+            }
+            config.set_model(&workspace.model);
+            if let Some(preset) = workspace.preset {
+                config.preset = preset;
+            }
+        }
+        if let Some(preset) = self.active_persona().and_then(|persona| persona.preset) {
+            config.preset = preset;
+        }
+        if let Some(template) = self.active_template() {
+            if let Some(provider) = template.provider {
+                config.provider = provider;
+            }
+            config.set_model(&template.model);
+        }
+        config
+    }
+
+    /// Model of the selected provider, empty for its default.
+    pub fn model(&self) -> &str {
+        match self.provider {
+            Provider::Gemini => &self.gemini.model,
+            Provider::OpenAi => &self.openai.model,
+            Provider::Compatible => &self.compatible.model,
+            Provider::Local => &self.local.model_path,
+        }
+    }
+
+    /// Switches to `model` where it is chosen for the current conversation: in the
+    /// active template or workspace if either replaces the model, otherwise globally.
+    pub fn choose_model(&mut self, model: &str) {
+        let provider = self.for_workspace().provider;
+        let (template, workspace) = (self.template.clone(), self.workspace.clone());
+        if let Some(template) = self.templates.iter_mut().find(|candidate| {
+            !template.is_empty() && candidate.name == template && !candidate.model.trim().is_empty()
+        }) {
+            template.provider = Some(provider);
+            template.model = model.to_string();
+            return;
+        }
+        if let Some(workspace) = self.workspaces.iter_mut().find(|candidate| {
+            !workspace.is_empty()
+                && candidate.name == workspace
+                && !candidate.model.trim().is_empty()
+        }) {
+            workspace.model = model.to_string();
+            return;
+        }
+        match provider {
+            Provider::Gemini => self.gemini.model = model.to_string(),
+            Provider::OpenAi => self.openai.model = model.to_string(),
+            Provider::Compatible => self.compatible.model = model.to_string(),
+            Provider::Local => self.local.model_path = model.to_string(),
+        }
+    }
+
+    /// Switches the selected provider to `model`, keeping the current one if it is empty.
+    fn set_model(&mut self, model: &str) {
+        let model = model.trim();
+        if model.is_empty() {
+            return;
+        }
+        match self.provider {
+            Provider::Gemini => self.gemini.model = model.to_string(),
+            Provider::OpenAi => self.openai.model = model.to_string(),
+            Provider::Compatible => self.compatible.model = model.to_string(),
+            Provider::Local => self.local.model_path = model.to_string(),
+        }
+    }
+
+    /// Safety preset in effect, always strict while the policy is locked.
+    pub fn safety_preset(&self) -> SafetyPreset {
+        if self.is_locked() {
+            SafetyPreset::Strict
+        } else {
+            self.safety.preset
+        }
+    }
+
+    /// Thresholds to send with requests, empty to leave them to the model.
+    pub fn safety_thresholds(&self) -> Vec<(HarmCategory, HarmThreshold)> {
+        let preset = self.safety_preset();
+        if preset == SafetyPreset::Default {
+            return Vec::new();
+        }
+
+        HarmCategory::ALL
+            .into_iter()
+            .map(|category| {
+                let threshold = preset
+                    .threshold()
+                    .unwrap_or_else(|| self.safety.custom(category));
+                (category, threshold)
+            })
+            .collect()
+    }
+
+    /// Generation parameters of the selected preset.
+    pub fn generation(&self) -> GenerationParams {
+        match self.preset {
+            GenerationPreset::Precise => GenerationParams {
+                temperature: Some(0.2),
+                top_p: Some(0.8),
+                max_output_tokens: Some(4096),
+                ..self.custom_generation.diagnostics()
+            },
+            GenerationPreset::Balanced => GenerationParams {
+                temperature: Some(1.0),
+                top_p: Some(0.95),
+                max_output_tokens: Some(8192),
+                ..self.custom_generation.diagnostics()
+            },
+            GenerationPreset::Creative => GenerationParams {
+                temperature: Some(1.5),
+                top_p: Some(0.98),
+                max_output_tokens: Some(8192),
+                ..self.custom_generation.diagnostics()
+            },
+            GenerationPreset::Custom => self.custom_generation,
+        }
+    }
+
+    /// Models to compare, padded to at least two slots.
+    pub fn compare_models(&self) -> Vec<String> {
+        let mut models = self.compare_models.clone();
+        if models.len() < 2 {
+            models.resize(2, String::new());
+        }
+        models
+    }
+
+    /// Returns a copy with credentials and other secrets cleared.
+    pub fn without_secrets(&self) -> Self {
+        let mut config = self.clone();
+        config.gemini.clear_secrets();
+        config.openai.clear_secrets();
+        config.compatible.api_key.clear();
+        // Environment variables often carry tokens.
+        for workspace in &mut config.workspaces {
+            workspace.environment.clear();
+        }
+        // The salted hash can be attacked offline.
+        config.policy_lock = None;
+        config
+    }
+
+    /// The configuration for a debug report: without secrets, and without what
+    /// the user wrote or what points at their files and servers.
+    pub fn for_report(&self) -> Self {
+        let mut config = self.without_secrets();
+        config.instructions.clear();
+        config.editor_command.clear();
+        config.share.paste_url.clear();
+        config.backup.folder.clear();
+        config.metrics.file.clear();
+        config.sync.url.clear();
+        config.sync.bucket.clear();
+        config.sync.username.clear();
+        for action in &mut config.quick_actions {
+            action.template.clear();
+        }
+        for workspace in &mut config.workspaces {
+            workspace.instructions.clear();
+            workspace.directory.clear();
+        }
+        for persona in &mut config.personas {
+            persona.instructions.clear();
+        }
+        for template in &mut config.templates {
+            template.instructions.clear();
+            template.first_message.clear();
+            template.pinned_files.clear();
+        }
+        config
+    }
+
+    /// Writes the configuration to `path` as JSON.
+    pub fn export(&self, path: &Path, include_secrets: bool) -> io::Result<()> {
+        let export = ConfigExport {
+            version: Self::VERSION,
+            config: if include_secrets {
+                self.clone()
+            } else {
+                self.without_secrets()
+            },
+        };
+
+        fs::write(path, serde_json::to_string_pretty(&export)?)
+    }
+
+    /// Reads a configuration previously written by [`Config::export`].
+    ///
+    /// Secrets missing from the file are kept from `self` so importing a shared
+    /// preset doesn't wipe local credentials.
+    pub fn import(&self, path: &Path) -> io::Result<Self> {
+        let export: ConfigExport = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+        if export.version > Self::VERSION {
+            return Err(io::Error::other(format!(
+                "settings were exported by a newer version (format {})",
+                export.version
+            )));
+        }
+
+        Ok(export.config.with_secrets_from(self))
+    }
+
+    /// Fills secrets that are empty in `self` from `other`.
+    fn with_secrets_from(mut self, other: &Self) -> Self {
+        self.gemini.fill_secrets(&other.gemini);
+        self.openai.fill_secrets(&other.openai);
+        if self.compatible.api_key.is_empty() {
+            self.compatible
+                .api_key
+                .clone_from(&other.compatible.api_key);
+        }
+        if self.policy_lock.is_none() {
+            self.policy_lock.clone_from(&other.policy_lock);
+        }
+        for workspace in &mut self.workspaces {
+            if workspace.environment.is_empty()
+                && let Some(local) = other
+                    .workspaces
+                    .iter()
+                    .find(|local| local.name == workspace.name)
+            {
+                workspace.environment.clone_from(&local.environment);
+            }
+        }
+        self
+    }
+}
+
+impl GenerationParams {
+    /// Keeps only the settings that don't affect sampling, which apply to every preset.
+    fn diagnostics(self) -> Self {
+        Self {
+            logprobs: self.logprobs,
+            ..Self::default()
+        }
+    }
+}
+
+impl ProviderSettings {
+    /// The API key entered on the settings page, or else the one in `var`.
+    pub fn api_key(&self, var: &str) -> Option<String> {
+        let key = self.api_key.trim();
+        if key.is_empty() {
+            keyring::get_for_var(var).or_else(|| credentials::lookup(var))
+        } else {
+            Some(key.to_string())
+        }
+    }
+
+    fn clear_secrets(&mut self) {
+        self.api_key.clear();
+        for header in self.headers.iter_mut().filter(|header| header.secret) {
+            header.value.clear();
+        }
+    }
+
+    fn fill_secrets(&mut self, other: &Self) {
+        if self.api_key.is_empty() {
+            self.api_key.clone_from(&other.api_key);
+        }
+        for header in self.headers.iter_mut() {
+            if header.secret
+                && header.value.is_empty()
+                && let Some(local) = other.headers.iter().find(|local| local.name == header.name)
+            {
+                header.value.clone_from(&local.value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action(template: &str) -> QuickAction {
+        QuickAction {
+            template: template.into(),
+            ..QuickAction::default()
+        }
+    }
+
+    #[test]
+    fn input_replaces_the_placeholder() {
+        assert_eq!(
+            action("Translate {input} to French").prompt("hello"),
+            "Translate hello to French"
+        );
+    }
+
+    #[test]
+    fn input_is_appended_without_a_placeholder() {
+        assert_eq!(action("Summarize:").prompt("text"), "Summarize:\n\ntext");
+        assert_eq!(action("Tell a joke").prompt(""), "Tell a joke");
+    }
 }
@@ -0,0 +1,218 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Conversation files (`*.clankers.json`), for handing a conversation to
+//! someone else to continue.
+//!
+//! Opening one from the file manager starts the applet's program with the file,
+//! which only queues it in the profile's inbox and exits. The applet in the
+//! panel takes it from there the next time its popup opens.
+
+use base64::Engine;
+use cosmic::iced::widget::markdown::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::app::Chat;
+
+/// Ending of conversation file names.
+pub const EXTENSION: &str = ".clankers.json";
+
+/// Version written to new files. Files of later versions are refused.
+const FORMAT_VERSION: u32 = 1;
+
+/// Directory in the profile's data directory where opened files wait.
+pub const INBOX_DIR: &str = "inbox";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConversationFile {
+    pub version: u32,
+    pub title: Option<String>,
+    /// Messages, whose attachments refer to `attachments`.
+    pub history: Vec<Chat>,
+    /// Contents of the attachments by their hash, as base64, each kept once.
+    pub attachments: HashMap<String, String>,
+}
+
+/// Writes `history` to `path`.
+pub fn write(path: &Path, title: Option<String>, history: Vec<Chat>) -> io::Result<()> {
+    let attachments = history
+        .iter()
+        .flat_map(|chat| &chat.attachments)
+        .filter(|attachment| !attachment.hash.is_empty())
+        .map(|attachment| {
+            let data = base64::engine::general_purpose::STANDARD.encode(&attachment.data);
+            (attachment.hash.clone(), data)
+        })
+        .collect();
+    let file = ConversationFile {
+        version: FORMAT_VERSION,
+        title,
+        history,
+        attachments,
+    };
+    fs::write(path, serde_json::to_string_pretty(&file)?)
+}
+
+/// Reads the conversation in `path`, ready to be continued.
+pub fn read(path: &Path) -> Result<ConversationFile, String> {
+    let contents = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let mut file: ConversationFile =
+        serde_json::from_str(&contents).map_err(|err| format!("not a conversation file: {err}"))?;
+    if file.version > FORMAT_VERSION {
+        return Err("the file was written by a newer version of the applet".into());
+    }
+    if file.history.is_empty() {
+        return Err("the conversation is empty".into());
+    }
+    // An A/B choice can't be made without the answer it was between.
+    for chat in &mut file.history {
+        chat.awaiting_choice = false;
+        // Attachments only refer to their contents by hash, so one whose
+        // contents don't match could point anywhere.
+        chat.attachments.retain_mut(|attachment| {
+            let data = file
+                .attachments
+                .get(&attachment.hash)
+                .and_then(|data| base64::engine::general_purpose::STANDARD.decode(data).ok())
+                .filter(|data| crate::attachment::hash(data) == attachment.hash);
+            match data {
+                Some(data) => {
+                    attachment.set_data(data.into());
+                    true
+                }
+                None => {
+                    eprintln!(
+                        "attachment {} is missing from {}",
+                        attachment.name,
+                        path.display()
+                    );
+                    false
+                }
+            }
+        });
+    }
+    Ok(file)
+}
+
+/// Conversation files given on the command line, as paths or `file://` URIs.
+pub fn from_args() -> Vec<PathBuf> {
+    std::env::args()
+        .skip(1)
+        .filter(|arg| arg.ends_with(EXTENSION))
+        .filter_map(|arg| {
+            if arg.starts_with("file://") {
+                Url::parse(&arg).ok()?.to_file_path().ok()
+            } else {
+                Some(PathBuf::from(arg))
+            }
+        })
+        .collect()
+}
+
+/// Copies `paths` into the inbox in `data_dir`, for the applet to pick up.
+pub fn queue(data_dir: &Path, paths: &[PathBuf]) -> io::Result<()> {
+    let inbox = data_dir.join(INBOX_DIR);
+    fs::create_dir_all(&inbox)?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    for (index, path) in paths.iter().enumerate() {
+        fs::copy(path, inbox.join(format!("{now}-{index}{EXTENSION}")))?;
+    }
+    Ok(())
+}
+
+/// Takes the files waiting in the inbox in `data_dir`, oldest first.
+pub fn take_inbox(data_dir: &Path) -> Vec<Result<ConversationFile, String>> {
+    let Ok(entries) = fs::read_dir(data_dir.join(INBOX_DIR)) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.to_string_lossy().ends_with(EXTENSION))
+        .collect();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let file = read(&path);
+            if let Err(err) = fs::remove_file(&path) {
+                eprintln!("failed to remove {} from the inbox: {err}", path.display());
+            }
+            file
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attachment::Attachment;
+
+    fn temp_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}-{name}{EXTENSION}", std::process::id()))
+    }
+
+    #[test]
+    fn written_conversations_are_read_back_with_their_attachments() {
+        let path = temp_file("round-trip");
+        let history = vec![
+            Chat {
+                attachments: vec![Attachment::pasted_text("notes".into())],
+                ..Chat::user("Look at this")
+            },
+            Chat {
+                awaiting_choice: true,
+                ..Chat::model("Done")
+            },
+        ];
+        write(&path, Some("Title".into()), history).unwrap();
+
+        let file = read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(file.title.as_deref(), Some("Title"));
+        assert_eq!(file.history.len(), 2);
+        assert_eq!(file.history[0].attachments[0].text(), Some("notes"));
+        assert!(!file.history[1].awaiting_choice);
+    }
+
+    #[test]
+    fn attachments_without_their_contents_are_dropped() {
+        let path = temp_file("unverified");
+        let conversation = r#"{
+            "version": 1,
+            "history": [{
+                "role": "user",
+                "content": "hi",
+                "attachments": [{"name": "id_rsa", "mime_type": "text/plain", "hash": "../../.ssh/id_rsa"}]
+            }]
+        }"#;
+        fs::write(&path, conversation).unwrap();
+
+        let file = read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(file.history[0].attachments.is_empty());
+    }
+
+    #[test]
+    fn empty_and_newer_files_are_refused() {
+        let path = temp_file("refused");
+        fs::write(&path, r#"{"version":1,"history":[]}"#).unwrap();
+        assert!(read(&path).is_err());
+
+        let newer = format!(
+            r#"{{"version":{},"history":[{{"role":"user","content":"hi"}}]}}"#,
+            FORMAT_VERSION + 1
+        );
+        fs::write(&path, newer).unwrap();
+        assert!(read(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,152 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Looks up provider credentials.
+//!
+//! Panel applets are started by the session and usually don't inherit variables
+//! exported from a shell profile, so besides the process environment we also read
+//! a dotenv style file from `~/.config/cosmic-ext-clankers/env`.
+
+use std::io::Write;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::{env, fs, io, path::PathBuf, process::Stdio, time::Duration};
+
+/// Name of the directory holding the applet's own files under the config dir.
+pub const CONFIG_DIR_NAME: &str = "cosmic-ext-clankers";
+
+/// How long the login shell gets to print a variable, since a shell profile
+/// waiting on something would otherwise hold up the applet.
+const LOGIN_SHELL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Path of the dotenv style credentials file.
+pub fn env_file_path() -> Option<PathBuf> {
+    let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(config_dir.join(CONFIG_DIR_NAME).join("env"))
+}
+
+/// Returns the value of `name`, preferring the process environment over the env file.
+pub fn lookup(name: &str) -> Option<String> {
+    if let Ok(value) = env::var(name)
+        && !value.is_empty()
+    {
+        return Some(value);
+    }
+
+    lookup_env_file(name)
+}
+
+/// Returns the value of `name` from the env file only.
+pub fn lookup_env_file(name: &str) -> Option<String> {
+    let contents = fs::read_to_string(env_file_path()?).ok()?;
+
+    parse_env_file(&contents)
+        .into_iter()
+        .rev()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value)
+        .filter(|value| !value.is_empty())
+}
+
+/// Parses `KEY=value` lines, the format shared by dotenv and systemd `EnvironmentFile`.
+///
+/// Blank lines and `#` comments are skipped, an optional `export ` prefix is allowed
+/// and values may be wrapped in single or double quotes.
+fn parse_env_file(contents: &str) -> Vec<(String, String)> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (key, value) = line.split_once('=')?;
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                .unwrap_or(value);
+
+            Some((key.trim().to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Checks whether `name` is exported by the user's login shell.
+///
+/// Used to tell apart a missing key from one that is only set in a shell profile,
+/// which the session (and so the applet) never sees. A shell that doesn't
+/// answer within [`LOGIN_SHELL_TIMEOUT`] is killed and taken as a no.
+pub async fn is_set_in_login_shell(name: &str) -> bool {
+    let shell = env::var("SHELL").unwrap_or_else(|_| "/bin/sh".into());
+
+    let output = tokio::process::Command::new(shell)
+        .args(["-l", "-i", "-c", &format!("printenv {name}")])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    match tokio::time::timeout(LOGIN_SHELL_TIMEOUT, output).await {
+        Ok(output) => output
+            .is_ok_and(|output| output.status.success() && !output.stdout.trim_ascii().is_empty()),
+        Err(_) => {
+            eprintln!("gave up on the login shell after {LOGIN_SHELL_TIMEOUT:?}");
+            false
+        }
+    }
+}
+
+/// Creates the env file with an empty entry for `name` if it doesn't exist yet.
+pub fn ensure_env_file(name: &str) -> io::Result<PathBuf> {
+    let path = env_file_path().ok_or_else(|| io::Error::other("no config directory"))?;
+
+    if !path.exists() {
+        // API keys and tokens go in there, which nobody else should read.
+        if let Some(parent) = path.parent() {
+            fs::DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(parent)?;
+        }
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+        {
+            Ok(mut file) => writeln!(file, "{name}=")?,
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_file_lines_are_parsed() {
+        let contents = "# comment\n\nexport A=1\nB = \"two words\"\nC='x'\nnot a variable\n";
+        assert_eq!(
+            parse_env_file(contents),
+            vec![
+                ("A".to_string(), "1".to_string()),
+                ("B".to_string(), "two words".to_string()),
+                ("C".to_string(), "x".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn values_may_contain_equals_signs() {
+        assert_eq!(
+            parse_env_file("KEY=abc=="),
+            vec![("KEY".to_string(), "abc==".to_string())]
+        );
+    }
+}
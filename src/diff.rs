@@ -0,0 +1,113 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Word-level diff used to compare a regenerated answer with the one it replaced.
+
+/// A run of text that is unchanged, removed from the old text or added in the new one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+/// Diffs `old` against `new` word by word, keeping whitespace attached to the words.
+pub fn words(old: &str, new: &str) -> Vec<Change> {
+    let old = tokens(old);
+    let new = tokens(new);
+
+    // Longest common subsequence table, filled from the end.
+    let mut lcs = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            changes.push(Change::Same(new[j].to_string()));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            changes.push(Change::Removed(old[i].to_string()));
+            i += 1;
+        } else {
+            changes.push(Change::Added(new[j].to_string()));
+            j += 1;
+        }
+    }
+    changes.extend(
+        old[i..]
+            .iter()
+            .map(|token| Change::Removed(token.to_string())),
+    );
+    changes.extend(
+        new[j..]
+            .iter()
+            .map(|token| Change::Added(token.to_string())),
+    );
+
+    changes
+}
+
+/// Splits text into words, each followed by the whitespace after it.
+fn tokens(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut in_space = false;
+
+    for (index, c) in text.char_indices() {
+        if c.is_whitespace() {
+            in_space = true;
+        } else if in_space {
+            tokens.push(&text[start..index]);
+            start = index;
+            in_space = false;
+        }
+    }
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changed_words_are_removed_and_added() {
+        assert_eq!(
+            words("the quick fox", "the slow fox"),
+            vec![
+                Change::Same("the ".into()),
+                Change::Removed("quick ".into()),
+                Change::Added("slow ".into()),
+                Change::Same("fox".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn identical_texts_are_unchanged() {
+        assert_eq!(
+            words("a b", "a b"),
+            vec![Change::Same("a ".into()), Change::Same("b".into())]
+        );
+    }
+
+    #[test]
+    fn everything_is_added_to_an_empty_text() {
+        assert_eq!(
+            words("", "new text"),
+            vec![Change::Added("new ".into()), Change::Added("text".into())]
+        );
+    }
+}
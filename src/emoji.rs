@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! GitHub-style `:shortcode:` emoji.
+
+use std::borrow::Cow;
+
+/// Replaces known `:shortcode:` sequences with their emoji, leaving code untouched.
+pub fn replace_shortcodes(text: &str) -> Cow<'_, str> {
+    if !text.contains(':') {
+        return Cow::Borrowed(text);
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut in_fence = false;
+    for (index, line) in text.split('\n').enumerate() {
+        if index > 0 {
+            out.push('\n');
+        }
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if in_fence || line.trim_start().starts_with("```") {
+            out.push_str(line);
+            continue;
+        }
+
+        // Odd chunks are inside inline code spans.
+        for (chunk_index, chunk) in line.split('`').enumerate() {
+            if chunk_index > 0 {
+                out.push('`');
+            }
+            if chunk_index % 2 == 1 {
+                out.push_str(chunk);
+            } else {
+                replace_in(chunk, &mut out);
+            }
+        }
+    }
+
+    Cow::Owned(out)
+}
+
+fn replace_in(text: &str, out: &mut String) {
+    let mut rest = text;
+    while let Some(start) = rest.find(':') {
+        let after = &rest[start + 1..];
+        let shortcode = after
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-')))
+            .filter(|end| *end > 0 && after[*end..].starts_with(':'))
+            .map(|end| &after[..end]);
+
+        if let Some(shortcode) = shortcode
+            && let Some(emoji) = emojis::get_by_shortcode(shortcode)
+        {
+            out.push_str(&rest[..start]);
+            out.push_str(emoji.as_str());
+            rest = &after[shortcode.len() + 1..];
+        } else {
+            out.push_str(&rest[..=start]);
+            rest = after;
+        }
+    }
+    out.push_str(rest);
+}
+
+/// Emoji whose name or shortcode contains `query`, all of them for an empty query.
+pub fn search(query: &str) -> impl Iterator<Item = &'static emojis::Emoji> {
+    let query = query.trim().to_lowercase();
+    emojis::iter().filter(move |emoji| {
+        query.is_empty()
+            || emoji.name().contains(&query)
+            || emoji
+                .shortcodes()
+                .any(|shortcode| shortcode.contains(&query))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_shortcodes_are_replaced() {
+        assert_eq!(replace_shortcodes("thanks :smile:"), "thanks 😄");
+    }
+
+    #[test]
+    fn unknown_shortcodes_and_times_are_left_alone() {
+        assert_eq!(
+            replace_shortcodes(":not_an_emoji: at 10:30:00"),
+            ":not_an_emoji: at 10:30:00"
+        );
+        assert!(matches!(replace_shortcodes("no colons"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn code_is_left_alone() {
+        let text = "`:smile:`\n```\n:smile:\n```";
+        assert_eq!(replace_shortcodes(text), text);
+    }
+}
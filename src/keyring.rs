@@ -0,0 +1,151 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! API keys kept in the desktop's keyring through the Secret Service API
+//! (`org.freedesktop.secrets`), rather than in plain text in the config.
+//!
+//! The keyring is read once when the applet starts and kept in memory, so
+//! requests can look keys up without waiting on D-Bus. Items are told apart
+//! by profile, and the keyring is only unlocked when there is a key to read
+//! or one is saved.
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, RwLock};
+
+use crate::models::{gemini, openai};
+
+/// Attribute telling the applet's items apart from other applications'.
+const APPLICATION: &str = "cosmic-ext-clankers";
+
+/// Keys read from or stored in the keyring since the applet started.
+static KEYS: LazyLock<RwLock<HashMap<Slot, String>>> = LazyLock::new(Default::default);
+
+/// A key the applet keeps in the keyring.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Slot {
+    Gemini,
+    OpenAi,
+    Compatible,
+}
+
+impl Slot {
+    pub const ALL: [Self; 3] = [Self::Gemini, Self::OpenAi, Self::Compatible];
+
+    /// Name of the item's `key` attribute.
+    fn id(self) -> &'static str {
+        match self {
+            Self::Gemini => "gemini",
+            Self::OpenAi => "openai",
+            Self::Compatible => "compatible",
+        }
+    }
+
+    /// Label of the item, as keyring managers like Seahorse show it.
+    fn label(self) -> &'static str {
+        match self {
+            Self::Gemini => "Clankers: Gemini API key",
+            Self::OpenAi => "Clankers: OpenAI API key",
+            Self::Compatible => "Clankers: OpenAI-compatible server token",
+        }
+    }
+
+    /// Environment variable the key was read from before it was in the
+    /// keyring, if there is one.
+    pub fn var(self) -> Option<&'static str> {
+        match self {
+            Self::Gemini => Some(gemini::API_KEY_VAR),
+            Self::OpenAi => Some(openai::API_KEY_VAR),
+            Self::Compatible => None,
+        }
+    }
+
+    /// Attributes of the item holding the key of `profile`, the profile's config id.
+    fn attributes(self, profile: &str) -> HashMap<&str, &str> {
+        HashMap::from([
+            ("application", APPLICATION),
+            ("profile", profile),
+            ("key", self.id()),
+        ])
+    }
+}
+
+/// The key in `slot`, if the keyring has one.
+pub fn get(slot: Slot) -> Option<String> {
+    KEYS.read().ok()?.get(&slot).cloned()
+}
+
+/// The key stored for the environment variable `var`, if the keyring has one.
+pub fn get_for_var(var: &str) -> Option<String> {
+    Slot::ALL
+        .into_iter()
+        .find(|slot| slot.var() == Some(var))
+        .and_then(get)
+}
+
+/// Reads every key of `profile` from the keyring.
+pub async fn load(profile: String) -> Result<(), String> {
+    let keyring = open().await?;
+    for slot in Slot::ALL {
+        let items = keyring
+            .search_items(&slot.attributes(&profile))
+            .await
+            .map_err(|err| err.to_string())?;
+        let Some(item) = items.first() else {
+            continue;
+        };
+        if item.is_locked().await.map_err(|err| err.to_string())? {
+            item.unlock()
+                .await
+                .map_err(|err| format!("the keyring stayed locked: {err}"))?;
+        }
+        let secret = item.secret().await.map_err(|err| err.to_string())?;
+        let key = String::from_utf8_lossy(secret.as_bytes())
+            .trim()
+            .to_string();
+        if !key.is_empty() {
+            remember(slot, Some(key));
+        }
+    }
+    Ok(())
+}
+
+/// Stores `key` in `slot` of `profile`, replacing the one there, or removes
+/// the key if `key` is empty.
+pub async fn store(profile: String, slot: Slot, key: String) -> Result<(), String> {
+    let key = key.trim().to_string();
+    let keyring = open().await?;
+    keyring
+        .unlock()
+        .await
+        .map_err(|err| format!("the keyring stayed locked: {err}"))?;
+    if key.is_empty() {
+        keyring
+            .delete(&slot.attributes(&profile))
+            .await
+            .map_err(|err| err.to_string())?;
+        remember(slot, None);
+    } else {
+        keyring
+            .create_item(slot.label(), &slot.attributes(&profile), key.as_str(), true)
+            .await
+            .map_err(|err| err.to_string())?;
+        remember(slot, Some(key));
+    }
+    Ok(())
+}
+
+/// The default collection, which may still be locked.
+async fn open() -> Result<oo7::Keyring, String> {
+    oo7::Keyring::new()
+        .await
+        .map_err(|err| format!("no keyring available: {err}"))
+}
+
+fn remember(slot: Slot, key: Option<String>) {
+    let Ok(mut keys) = KEYS.write() else {
+        return;
+    };
+    match key {
+        Some(key) => keys.insert(slot, key),
+        None => keys.remove(&slot),
+    };
+}
@@ -0,0 +1,32 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Detection of the language prompts are written in.
+
+use crate::app::Chat;
+
+/// English name of the language of `text`, when it can be told reliably.
+pub fn detect(text: &str) -> Option<&'static str> {
+    whatlang::detect(text)
+        .filter(whatlang::Info::is_reliable)
+        .map(|info| info.lang().eng_name())
+}
+
+/// Language most of the user's prompts are written in.
+pub fn of_conversation(history: &[Chat]) -> Option<&'static str> {
+    let mut counts: Vec<(&'static str, usize)> = Vec::new();
+    for language in history
+        .iter()
+        .filter(|chat| chat.role == "user")
+        .filter_map(|chat| detect(&chat.content))
+    {
+        match counts.iter_mut().find(|(name, _)| *name == language) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((language, 1)),
+        }
+    }
+
+    counts
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(language, _)| language)
+}
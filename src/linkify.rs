@@ -0,0 +1,135 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Links for bare URLs and file paths in answers, which models often write
+//! without Markdown link syntax.
+//!
+//! Relative paths are looked up in the active workspace's folder.
+
+use cosmic::iced::widget::markdown::Url;
+use regex::{Captures, Regex};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+static LINKABLE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"https?://[^\s<>()\[\]`]+|(?:~|\.\.?)?/?[\w.+@-]+(?:/[\w.+@-]+)+/?")
+        .expect("valid linkify regex")
+});
+
+/// Characters that end a sentence rather than the URL or path before them.
+const TRAILING: &[char] = &['.', ',', ';', ':', '!', '?', '\'', '"'];
+
+/// Turns bare URLs and paths of existing files into links, leaving code and
+/// existing links untouched. Relative paths are resolved against `directory`,
+/// and left alone without one.
+pub fn linkify(text: &str, directory: Option<&Path>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_fence = false;
+    for (index, line) in text.split('\n').enumerate() {
+        if index > 0 {
+            out.push('\n');
+        }
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if in_fence || line.trim_start().starts_with("```") {
+            out.push_str(line);
+            continue;
+        }
+
+        // Odd chunks are inside inline code spans.
+        for (chunk_index, chunk) in line.split('`').enumerate() {
+            if chunk_index > 0 {
+                out.push('`');
+            }
+            if chunk_index % 2 == 1 {
+                out.push_str(chunk);
+            } else {
+                out.push_str(&linkify_chunk(chunk, directory));
+            }
+        }
+    }
+    out
+}
+
+fn linkify_chunk(text: &str, directory: Option<&Path>) -> String {
+    LINKABLE
+        .replace_all(text, |captures: &Captures| {
+            let found = captures.get(0).expect("whole match");
+            let before = &text[..found.start()];
+            let whole = found.as_str();
+            // Already a link, its text, or part of a longer word.
+            if before.ends_with("](")
+                || before.ends_with('<')
+                || before.ends_with('[')
+                || before
+                    .chars()
+                    .last()
+                    .is_some_and(|c| c.is_alphanumeric() || c == '/')
+            {
+                return whole.to_string();
+            }
+
+            let target = whole.trim_end_matches(TRAILING);
+            let trailing = &whole[target.len()..];
+            let url = if target.starts_with("http") {
+                Url::parse(target).ok()
+            } else {
+                local_file(target, directory).and_then(|path| Url::from_file_path(path).ok())
+            };
+            match url {
+                Some(url) => format!("[{target}]({url}){trailing}"),
+                None => whole.to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// `path` with `~` expanded or resolved against `directory`, if it exists.
+fn local_file(path: &str, directory: Option<&Path>) -> Option<PathBuf> {
+    let path = if let Some(rest) = path.strip_prefix("~/") {
+        PathBuf::from(std::env::var_os("HOME")?).join(rest)
+    } else if path.starts_with('/') {
+        PathBuf::from(path)
+    } else if path.starts_with('~') {
+        return None;
+    } else {
+        directory?.join(path)
+    };
+    path.exists().then_some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn bare_urls_are_linked_without_trailing_punctuation() {
+        assert_eq!(
+            linkify("See https://example.com/docs.", None),
+            "See [https://example.com/docs](https://example.com/docs)."
+        );
+    }
+
+    #[test]
+    fn code_and_existing_links_are_left_alone() {
+        let text = "`https://example.com` and [docs](https://example.com/docs)\n```\nhttps://example.com\n```";
+        assert_eq!(linkify(text, None), text);
+    }
+
+    #[test]
+    fn existing_relative_paths_are_linked() {
+        let directory = std::env::temp_dir().join(format!("linkify-{}", std::process::id()));
+        fs::create_dir_all(directory.join("src")).unwrap();
+        fs::write(directory.join("src/lib.rs"), "").unwrap();
+
+        let url = Url::from_file_path(directory.join("src/lib.rs")).unwrap();
+        assert_eq!(
+            linkify("Edit src/lib.rs or src/missing.rs", Some(&directory)),
+            format!("Edit [src/lib.rs]({url}) or src/missing.rs")
+        );
+        assert_eq!(linkify("Edit src/lib.rs", None), "Edit src/lib.rs");
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -1,9 +1,35 @@
 // SPDX-License-Identifier: MPL-2.0
 
 mod app;
+mod attachment;
+mod attachment_store;
+mod backup;
 mod config;
+mod conversation_file;
+mod credentials;
+mod diff;
+mod emoji;
 mod i18n;
+mod keyring;
+mod language;
+mod linkify;
+mod metrics;
 mod models;
+mod partial_markdown;
+mod paste;
+mod pdf;
+mod preferences;
+mod profile;
+mod redact;
+mod remote;
+mod report;
+mod scripting;
+mod session;
+mod spellcheck;
+mod spending;
+mod storage;
+mod sync;
+mod tts;
 
 fn main() -> cosmic::iced::Result {
     // Get the system's preferred languages.
@@ -12,6 +38,30 @@ fn main() -> cosmic::iced::Result {
     // Enable localizations to be applied.
     i18n::init(&requested_languages);
 
-    // Starts the applet's event loop with `()` as the application's flags.
-    cosmic::applet::run::<app::AppModel>(())
+    let profile = profile::Profile::from_args();
+
+    // Run from a keyboard shortcut: pass the command on to the applet in the panel.
+    if let Some(command) = remote::Command::from_args() {
+        if let Err(err) = remote::send(&profile, command) {
+            eprintln!("failed to reach the applet in the panel: {err}");
+        }
+        return Ok(());
+    }
+
+    // Opened from the file manager: leave the files for the applet in the panel.
+    let files = conversation_file::from_args();
+    if !files.is_empty() {
+        match profile.data_dir() {
+            Some(dir) => {
+                if let Err(err) = conversation_file::queue(&dir, &files) {
+                    eprintln!("failed to queue the conversation files: {err}");
+                }
+            }
+            None => eprintln!("no data directory to queue the conversation files in"),
+        }
+        return Ok(());
+    }
+
+    // Starts the applet's event loop with the profile given on the command line.
+    cosmic::applet::run::<app::AppModel>(profile)
 }
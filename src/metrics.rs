@@ -0,0 +1,157 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Optional request metrics in the Prometheus text format, for machines that
+//! are monitored as a fleet.
+//!
+//! Counts start at zero with the applet. They can be written to a file, e.g. for
+//! node_exporter's textfile collector, or served on a port of localhost.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Upper bounds of the latency histogram's buckets, in seconds.
+const BUCKETS: [f64; 8] = [0.5, 1.0, 2.0, 5.0, 10.0, 20.0, 30.0, 60.0];
+
+static METRICS: LazyLock<Mutex<Metrics>> = LazyLock::new(Mutex::default);
+
+#[derive(Default)]
+struct Metrics {
+    /// Answered and failed requests by provider.
+    providers: BTreeMap<&'static str, Counts>,
+}
+
+#[derive(Default)]
+struct Counts {
+    answered: u64,
+    failed: u64,
+    /// Requests per latency bucket, with the last one for slower requests.
+    buckets: [u64; BUCKETS.len() + 1],
+    /// Total latency, in seconds.
+    seconds: f64,
+}
+
+/// Counts a finished request to `provider`.
+pub fn record(provider: &'static str, failed: bool, latency: Duration) {
+    let Ok(mut metrics) = METRICS.lock() else {
+        return;
+    };
+    let counts = metrics.providers.entry(provider).or_default();
+    if failed {
+        counts.failed += 1;
+    } else {
+        counts.answered += 1;
+    }
+    let seconds = latency.as_secs_f64();
+    let bucket = BUCKETS
+        .iter()
+        .position(|bound| seconds <= *bound)
+        .unwrap_or(BUCKETS.len());
+    counts.buckets[bucket] += 1;
+    counts.seconds += seconds;
+}
+
+/// The metrics in the Prometheus text format.
+pub fn render() -> String {
+    let Ok(metrics) = METRICS.lock() else {
+        return String::new();
+    };
+    let mut out = String::new();
+
+    out.push_str("# HELP clankers_requests_total Requests sent to a model, by outcome.\n");
+    out.push_str("# TYPE clankers_requests_total counter\n");
+    for (provider, counts) in &metrics.providers {
+        let _ = writeln!(
+            out,
+            "clankers_requests_total{{provider=\"{provider}\",outcome=\"answered\"}} {}",
+            counts.answered
+        );
+        let _ = writeln!(
+            out,
+            "clankers_requests_total{{provider=\"{provider}\",outcome=\"failed\"}} {}",
+            counts.failed
+        );
+    }
+
+    out.push_str(
+        "# HELP clankers_request_duration_seconds Time until a request was answered or \
+         failed.\n",
+    );
+    out.push_str("# TYPE clankers_request_duration_seconds histogram\n");
+    for (provider, counts) in &metrics.providers {
+        let mut cumulative = 0;
+        for (bound, count) in BUCKETS.iter().zip(counts.buckets) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "clankers_request_duration_seconds_bucket{{provider=\"{provider}\",le=\"{bound}\"}} {cumulative}"
+            );
+        }
+        let total = counts.answered + counts.failed;
+        let _ = writeln!(
+            out,
+            "clankers_request_duration_seconds_bucket{{provider=\"{provider}\",le=\"+Inf\"}} {total}"
+        );
+        let _ = writeln!(
+            out,
+            "clankers_request_duration_seconds_sum{{provider=\"{provider}\"}} {}",
+            counts.seconds
+        );
+        let _ = writeln!(
+            out,
+            "clankers_request_duration_seconds_count{{provider=\"{provider}\"}} {total}"
+        );
+    }
+    out
+}
+
+/// Writes the metrics to `path`, replacing it whole so a collector never reads
+/// half of it.
+pub fn write(path: &Path) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    fs::write(&partial, render())?;
+    fs::rename(partial, path)
+}
+
+/// Answers every connection on `port` of localhost with the metrics, until it
+/// fails to listen.
+pub async fn serve(port: u16) {
+    let listener = match TcpListener::bind(("127.0.0.1", port)).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("failed to serve metrics on port {port}: {err}");
+            return;
+        }
+    };
+    loop {
+        let mut stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                eprintln!("failed to accept a metrics connection: {err}");
+                continue;
+            }
+        };
+        // The request itself doesn't matter, every path gets the metrics.
+        let mut request = [0; 1024];
+        let _ = stream.read(&mut request).await;
+        let body = render();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        if let Err(err) = stream.write_all(response.as_bytes()).await {
+            eprintln!("failed to send metrics: {err}");
+        }
+    }
+}
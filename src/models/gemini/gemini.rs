@@ -5,7 +5,7 @@ use serde::Deserialize;
 pub struct GeminiResponse {
     pub candidates: Option<Vec<Candidate>>,
     pub prompt_feedback: Option<PromptFeedback>,
-    pub usage_meta_deta: Option<UsageMetaData>,
+    pub usage_metadata: Option<UsageMetadata>,
     pub model_version: Option<String>,
     pub response_id: Option<String>,
     pub model_status: Option<ModelStatus>,
@@ -16,6 +16,7 @@ pub struct GeminiResponse {
 #[serde(rename_all = "camelCase")]
 pub struct PromptFeedback {
     pub block_reason: Option<BlockReason>,
+    #[serde(default)]
     pub safety_ratings: Vec<SafetyRating>,
 }
 
@@ -35,10 +36,20 @@ pub enum BlockReason {
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct UsageMetaData {
-    pub prompt_token_count: String,
-    pub thoughts_token_count: String,
-    pub total_token_count: String,
+pub struct UsageMetadata {
+    #[serde(default)]
+    pub prompt_token_count: u32,
+    /// Part of the prompt served from the context cache.
+    #[serde(default)]
+    pub cached_content_token_count: u32,
+    #[serde(default)]
+    pub candidates_token_count: u32,
+    #[serde(default)]
+    pub tool_use_prompt_token_count: u32,
+    #[serde(default)]
+    pub thoughts_token_count: u32,
+    #[serde(default)]
+    pub total_token_count: u32,
 }
 
 #[derive(Debug, Deserialize)]
@@ -66,11 +77,63 @@ pub enum ModelStage {
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Candidate {
+    /// Missing when the answer was blocked.
+    #[serde(default)]
     pub content: Content,
     pub finish_reason: Option<FinishReason>,
     pub safety_ratings: Option<Vec<SafetyRating>>,
     pub index: u32,
     pub finish_message: Option<String>,
+    pub avg_logprobs: Option<f64>,
+    pub logprobs_result: Option<LogprobsResult>,
+    pub grounding_metadata: Option<GroundingMetadata>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroundingMetadata {
+    #[serde(default)]
+    pub grounding_chunks: Vec<GroundingChunk>,
+    #[serde(default)]
+    pub web_search_queries: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GroundingChunk {
+    pub web: Option<WebSource>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebSource {
+    #[serde(default)]
+    pub uri: String,
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogprobsResult {
+    #[serde(default)]
+    pub top_candidates: Vec<TopCandidates>,
+    #[serde(default)]
+    pub chosen_candidates: Vec<LogprobCandidate>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TopCandidates {
+    #[serde(default)]
+    pub candidates: Vec<LogprobCandidate>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogprobCandidate {
+    #[serde(default)]
+    pub token: String,
+    pub token_id: Option<i64>,
+    #[serde(default)]
+    pub log_probability: f64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -105,6 +168,7 @@ pub enum FinishReason {
 pub struct SafetyRating {
     pub category: HarmCategory,
     pub probability: HarmProbability,
+    #[serde(default)]
     pub blocked: bool,
 }
 
@@ -140,9 +204,10 @@ pub enum HarmCategory {
     Unkown,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Content {
+    #[serde(default)]
     pub parts: Vec<Part>,
     pub role: Option<String>,
 }
@@ -180,8 +245,55 @@ pub struct ApiError {
 }
 
 #[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct GeminiRequest<'a> {
     pub contents: Vec<GeminiContent<'a>>,
+    pub generation_config: GenerationConfig,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub safety_settings: Vec<SafetySetting>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_instruction: Option<SystemInstruction>,
+}
+
+#[derive(serde::Serialize)]
+pub struct SystemInstruction {
+    pub parts: Vec<TextPart>,
+}
+
+#[derive(serde::Serialize)]
+pub struct TextPart {
+    pub text: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct SafetySetting {
+    pub category: &'static str,
+    pub threshold: &'static str,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerationConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub response_logprobs: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_modalities: Option<&'static [&'static str]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_resolution: Option<&'static str>,
 }
 
 #[derive(serde::Serialize)]
@@ -191,6 +303,47 @@ pub struct GeminiContent<'a> {
 }
 
 #[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct GeminiPart<'a> {
-    pub text: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inline_data: Option<InlineData<'a>>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlineData<'a> {
+    pub mime_type: &'a str,
+    /// Base64 encoded contents.
+    pub data: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelList {
+    #[serde(default)]
+    pub models: Vec<ModelInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelInfo {
+    pub name: String,
+    #[serde(default)]
+    pub supported_generation_methods: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TunedModelList {
+    #[serde(default)]
+    pub tuned_models: Vec<TunedModelInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TunedModelInfo {
+    pub name: String,
+    pub state: Option<String>,
 }
@@ -0,0 +1,120 @@
+//! Lenient parsing of responses, for when the API changes under us. Fields that
+//! fail to parse are dropped instead of failing the whole response, and they are
+//! reported together with the fields the applet doesn't know.
+
+use serde_json::Value;
+
+use super::gemini::{Candidate, Content, GeminiResponse};
+use crate::models::lenient::{deserialize, field, join, unknown_fields};
+
+const RESPONSE_FIELDS: &[&str] = &[
+    "candidates",
+    "promptFeedback",
+    "usageMetadata",
+    "modelVersion",
+    "responseId",
+    "modelStatus",
+    "error",
+];
+
+const CANDIDATE_FIELDS: &[&str] = &[
+    "content",
+    "finishReason",
+    "safetyRatings",
+    "index",
+    "finishMessage",
+    "avgLogprobs",
+    "logprobsResult",
+    "groundingMetadata",
+];
+
+const CONTENT_FIELDS: &[&str] = &["parts", "role"];
+
+const PART_FIELDS: &[&str] = &[
+    "thought",
+    "thoughtSignature",
+    "text",
+    "inlineData",
+    "fileData",
+];
+
+/// Parses what it can of `raw`, with a line per problem found. Fails only if
+/// `raw` isn't a JSON object.
+pub fn parse(raw: &str) -> Result<(GeminiResponse, Vec<String>), serde_json::Error> {
+    let Value::Object(object) = serde_json::from_str::<Value>(raw)? else {
+        return Err(serde::de::Error::custom("expected a JSON object"));
+    };
+    let mut issues = Vec::new();
+    unknown_fields(&object, RESPONSE_FIELDS, "", &mut issues);
+
+    let candidates = match object.get("candidates") {
+        Some(Value::Array(candidates)) => Some(
+            candidates
+                .iter()
+                .enumerate()
+                .filter_map(|(index, value)| {
+                    candidate(value, &format!("candidates[{index}]"), &mut issues)
+                })
+                .collect(),
+        ),
+        None | Some(Value::Null) => None,
+        Some(_) => {
+            issues.push("candidates: expected a list".into());
+            None
+        }
+    };
+    let response = GeminiResponse {
+        candidates,
+        prompt_feedback: field(&object, "promptFeedback", "", &mut issues),
+        usage_metadata: field(&object, "usageMetadata", "", &mut issues),
+        model_version: field(&object, "modelVersion", "", &mut issues),
+        response_id: field(&object, "responseId", "", &mut issues),
+        model_status: field(&object, "modelStatus", "", &mut issues),
+        error: field(&object, "error", "", &mut issues),
+    };
+    Ok((response, issues))
+}
+
+fn candidate(value: &Value, path: &str, issues: &mut Vec<String>) -> Option<Candidate> {
+    let Value::Object(object) = value else {
+        issues.push(format!("{path}: expected an object"));
+        return None;
+    };
+    unknown_fields(object, CANDIDATE_FIELDS, path, issues);
+
+    let content = match object.get("content") {
+        Some(Value::Object(content)) => {
+            let path = join(path, "content");
+            unknown_fields(content, CONTENT_FIELDS, &path, issues);
+            let parts = match content.get("parts") {
+                Some(Value::Array(parts)) => parts
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, part)| {
+                        let path = format!("{path}.parts[{index}]");
+                        if let Value::Object(part) = part {
+                            unknown_fields(part, PART_FIELDS, &path, issues);
+                        }
+                        deserialize(part, &path, issues)
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            Content {
+                parts,
+                role: field(content, "role", &path, issues),
+            }
+        }
+        _ => Content::default(),
+    };
+    Some(Candidate {
+        content,
+        finish_reason: field(object, "finishReason", path, issues),
+        safety_ratings: field(object, "safetyRatings", path, issues),
+        index: field(object, "index", path, issues).unwrap_or_default(),
+        finish_message: field(object, "finishMessage", path, issues),
+        avg_logprobs: field(object, "avgLogprobs", path, issues),
+        logprobs_result: field(object, "logprobsResult", path, issues),
+        grounding_metadata: field(object, "groundingMetadata", path, issues),
+    })
+}
@@ -1,90 +1,912 @@
+use base64::Engine;
+use futures_util::future::BoxFuture;
 use reqwest::Client;
 use serde_json::json;
-use std::{env, sync::Arc};
+use std::sync::Arc;
+use std::time::Instant;
 mod gemini;
-use gemini::{GeminiContent, GeminiPart, GeminiRequest, GeminiResponse};
+mod lenient;
+use gemini::{
+    GeminiContent, GeminiPart, GeminiRequest, GeminiResponse, GenerationConfig, InlineData,
+    LogprobsResult, ModelList, SafetySetting, SystemInstruction, TextPart, TunedModelList,
+};
 
 use crate::app::Chat; // Ensure Part is imported
+use crate::config::{
+    Config, HarmCategory, HarmThreshold, MediaResolution, ProviderSettings, ResponseModalities,
+    SafetyPreset,
+};
+use crate::credentials;
+use crate::models::{self, Health, HealthStatus, Replay, SLOW_RESPONSE};
+
+/// Environment variable holding the API key.
+pub const API_KEY_VAR: &str = "GEMINI_API_KEY";
+
+/// Environment variable holding an OAuth access token, needed by some tuned models.
+pub const ACCESS_TOKEN_VAR: &str = "GEMINI_ACCESS_TOKEN";
+
+/// Base URL of the Generative Language API.
+const API_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+/// Model used when none is configured.
+pub const DEFAULT_MODEL: &str = "models/gemini-2.5-flash";
+
+/// Models offered by the model picker until the API has listed them.
+pub const KNOWN_MODELS: [&str; 3] = [
+    DEFAULT_MODEL,
+    "models/gemini-2.5-pro",
+    "models/gemini-2.5-flash-lite",
+];
+
+/// Page where users can create an API key.
+pub const API_KEY_URL: &str = "https://aistudio.google.com/apikey";
+
+/// Largest request body the API accepts for inline content.
+pub const MAX_REQUEST_BYTES: usize = 20 * 1024 * 1024;
 
 #[derive(Debug, Clone)]
 pub enum Message {
-    ApiKeyNotSet,
+    ApiKeyNotSet {
+        in_login_shell: bool,
+    },
     RequestError(String),
     ApiResultParsingError(String),
     ApiError(String),
     PromptBlocked(String),
-    Response(String),
+    /// The request body's size, with the provider's limit if it is known.
+    RequestTooLarge {
+        size: usize,
+        limit: Option<usize>,
+    },
+    Response(Reply),
     EmptyResponse,
 }
 
-pub fn convert_to_gemini_request<'a>(history: &'a Arc<Vec<Chat>>) -> GeminiRequest<'a> {
+impl Message {
+    /// Collapses the outcome into a reply or a short description of what went wrong.
+    pub fn into_result(self) -> Result<Reply, String> {
+        match self {
+            Self::Response(reply) => Ok(reply),
+            Self::EmptyResponse => Ok(Reply::default()),
+            Self::ApiKeyNotSet { .. } => Err("API key not set".into()),
+            Self::RequestTooLarge { .. } => Err("Request too large".into()),
+            Self::RequestError(error)
+            | Self::ApiResultParsingError(error)
+            | Self::ApiError(error)
+            | Self::PromptBlocked(error) => Err(error),
+        }
+    }
+}
+
+/// A successful answer together with what the debug inspector shows about it.
+#[derive(Debug, Clone, Default)]
+pub struct Reply {
+    pub text: String,
+    /// Images returned inline when image output was requested.
+    pub images: Vec<GeneratedImage>,
+    /// Log probabilities of the chosen tokens, when requested.
+    pub logprobs: Vec<TokenLogprob>,
+    /// Token counts reported by the provider.
+    pub usage: Option<Usage>,
+    /// Why the model stopped, as the provider puts it.
+    pub finish_reason: Option<String>,
+    /// The provider's explanation of an unusual finish.
+    pub finish_message: Option<String>,
+    /// Version of the model that served the answer, when the provider says.
+    pub model_version: Option<String>,
+    /// Safety ratings of the answer, as category and probability.
+    pub safety_ratings: Vec<(String, String)>,
+    /// Web pages the answer is grounded in.
+    pub sources: Vec<Source>,
+    /// Searches run to ground the answer.
+    pub search_queries: Vec<String>,
+    /// Every part of the answer, thoughts included, described one by one.
+    pub parts: Vec<String>,
+    /// Fields of the response that lenient parsing skipped or didn't know.
+    pub parse_issues: Vec<String>,
+    pub raw_request: String,
+    pub raw_response: String,
+}
+
+/// A web page an answer is grounded in.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Source {
+    pub title: String,
+    pub uri: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Usage {
+    pub prompt_tokens: u32,
+    /// Part of the prompt the provider had cached.
+    pub cached_tokens: u32,
+    /// Tokens of the answer, including any spent thinking.
+    pub completion_tokens: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct GeneratedImage {
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TokenLogprob {
+    pub token: String,
+    pub log_probability: f64,
+    /// The most likely tokens at this position.
+    pub alternatives: Vec<(String, f64)>,
+}
+
+/// The Gemini API.
+pub struct Gemini;
+
+impl super::Provider for Gemini {
+    fn complete(&self, history: Arc<Vec<Chat>>, config: Config) -> BoxFuture<'static, Message> {
+        Box::pin(get_gemini_response(history, config))
+    }
+
+    fn stream<'a>(
+        &'a self,
+        history: Arc<Vec<Chat>>,
+        config: Config,
+        on_text: Box<dyn FnMut(&str) + Send + 'a>,
+    ) -> BoxFuture<'a, Message> {
+        Box::pin(stream_gemini_response(history, config, on_text))
+    }
+
+    fn replay(&self, body: String, config: Config) -> BoxFuture<'static, Replay> {
+        Box::pin(replay_gemini(body, config))
+    }
+
+    fn model_name(&self, config: &Config) -> String {
+        model_name(&config.gemini)
+    }
+
+    fn price_per_million(&self, config: &Config) -> (f64, f64) {
+        price_per_million(&model_name(&config.gemini))
+    }
+
+    fn context_limit(&self, config: &Config) -> u32 {
+        context_window(&model_name(&config.gemini))
+    }
+
+    fn api_key_var(&self) -> Option<&'static str> {
+        Some(API_KEY_VAR)
+    }
+
+    fn api_key_url(&self) -> Option<&'static str> {
+        Some(API_KEY_URL)
+    }
+}
+
+/// How a request is authorized.
+enum Auth {
+    ApiKey(String),
+    Bearer(String),
+}
+
+impl Auth {
+    /// Picks credentials for `model`; tuned models and Vertex AI prefer an
+    /// OAuth token when one is set.
+    fn for_model(model: &str, settings: &ProviderSettings) -> Option<Self> {
+        if (model.starts_with("tunedModels/") || vertex_url(settings).is_some())
+            && let Some(token) = credentials::lookup(ACCESS_TOKEN_VAR)
+        {
+            return Some(Self::Bearer(token));
+        }
+
+        settings.api_key(API_KEY_VAR).map(Self::ApiKey)
+    }
+
+    fn apply(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match self {
+            Self::ApiKey(key) => request.header("x-goog-api-key", key),
+            Self::Bearer(token) => request.bearer_auth(token),
+        }
+    }
+}
+
+/// Adds the Google Cloud project to bill and the user's custom headers to a request.
+fn with_headers(
+    mut request: reqwest::RequestBuilder,
+    settings: &ProviderSettings,
+) -> reqwest::RequestBuilder {
+    if !settings.project.trim().is_empty() {
+        request = request.header("x-goog-user-project", settings.project.trim());
+    }
+    for header in settings
+        .headers
+        .iter()
+        .filter(|header| !header.name.is_empty())
+    {
+        request = request.header(header.name.as_str(), header.value.as_str());
+    }
+    request
+}
+
+/// Base URL of Vertex AI's Google models in the configured project and
+/// location, `None` unless both are set.
+fn vertex_url(settings: &ProviderSettings) -> Option<String> {
+    let (project, location) = (settings.project.trim(), settings.location.trim());
+    if project.is_empty() || location.is_empty() {
+        return None;
+    }
+    let host = if location == "global" {
+        "aiplatform.googleapis.com".to_string()
+    } else {
+        format!("{location}-aiplatform.googleapis.com")
+    };
+    Some(format!(
+        "https://{host}/v1/projects/{project}/locations/{location}/publishers/google"
+    ))
+}
+
+/// Resource name of the configured model, e.g. `models/gemini-2.5-flash` or `tunedModels/my-tune`.
+pub fn model_name(settings: &ProviderSettings) -> String {
+    let model = settings.model.trim();
+    if model.is_empty() {
+        DEFAULT_MODEL.to_string()
+    } else if model.contains('/') {
+        model.to_string()
+    } else {
+        format!("models/{model}")
+    }
+}
+
+/// Input token limit of `model`, by model family.
+pub fn context_window(model: &str) -> u32 {
+    if model.contains("gemma") {
+        32_768
+    } else if model.contains("1.5-pro") {
+        2_097_152
+    } else {
+        1_048_576
+    }
+}
+
+/// Approximate list prices of `model` in US dollars per million input and
+/// output tokens, for cost estimates.
+pub fn price_per_million(model: &str) -> (f64, f64) {
+    if model.contains("gemma") {
+        (0.0, 0.0)
+    } else if model.contains("flash-lite") {
+        (0.10, 0.40)
+    } else if model.contains("flash") {
+        (0.30, 2.50)
+    } else if model.contains("pro") {
+        (1.25, 10.0)
+    } else {
+        (0.30, 2.50)
+    }
+}
+
+/// Checks the key by listing the models, and that the configured one is among them.
+pub async fn check_health(settings: &ProviderSettings) -> Health {
+    let model = model_name(settings);
+    let started = Instant::now();
+    let result = list_models(settings.clone()).await;
+    let latency = started.elapsed();
+
+    let (status, detail) = match result {
+        Err(err) => (HealthStatus::Failing, err),
+        Ok(models) if !models.contains(&model) => (
+            HealthStatus::Degraded,
+            format!("{model} is not offered to this key"),
+        ),
+        Ok(_) if latency > SLOW_RESPONSE => (
+            HealthStatus::Degraded,
+            format!("{model} is available, but slow to respond"),
+        ),
+        Ok(_) => (HealthStatus::Healthy, format!("{model} is available")),
+    };
+    Health {
+        provider: "Gemini",
+        status,
+        detail,
+        latency: (status != HealthStatus::Failing).then_some(latency),
+    }
+}
+
+/// Lists base models that can generate content, followed by the user's tuned models.
+pub async fn list_models(settings: ProviderSettings) -> Result<Vec<String>, String> {
+    let client = Client::new();
+    let Some(auth) = Auth::for_model("", &settings) else {
+        return Err("API key not set".into());
+    };
+
+    let list: ModelList = with_headers(
+        auth.apply(client.get(format!("{API_URL}/models?pageSize=1000"))),
+        &settings,
+    )
+    .send()
+    .await
+    .and_then(|response| response.error_for_status())
+    .map_err(|err| err.to_string())?
+    .json()
+    .await
+    .map_err(|err| err.to_string())?;
+
+    let mut models: Vec<String> = list
+        .models
+        .into_iter()
+        .filter(|model| {
+            model
+                .supported_generation_methods
+                .iter()
+                .any(|method| method == "generateContent")
+        })
+        .map(|model| model.name)
+        .collect();
+
+    // Tuned models may need an OAuth token; without one they're just left out.
+    let tuned_auth = Auth::for_model("tunedModels/", &settings).unwrap_or(auth);
+    let request = with_headers(
+        tuned_auth.apply(client.get(format!("{API_URL}/tunedModels?pageSize=1000"))),
+        &settings,
+    );
+    if let Ok(response) = request.send().await
+        && let Ok(response) = response.error_for_status()
+        && let Ok(tuned) = response.json::<TunedModelList>().await
+    {
+        models.extend(
+            tuned
+                .tuned_models
+                .into_iter()
+                .filter(|model| model.state.as_deref().is_none_or(|state| state == "ACTIVE"))
+                .map(|model| model.name),
+        );
+    }
+
+    Ok(models)
+}
+
+pub fn convert_to_gemini_request<'a>(
+    history: &'a Arc<Vec<Chat>>,
+    config: &Config,
+) -> GeminiRequest<'a> {
+    let generation = config.generation();
     let contents = history
         .iter()
         .map(|chat| GeminiContent {
             role: &chat.role,
-            parts: vec![GeminiPart {
-                text: &chat.content,
-            }],
+            parts: chat
+                .attachments
+                .iter()
+                .map(|attachment| GeminiPart {
+                    text: None,
+                    inline_data: Some(InlineData {
+                        mime_type: &attachment.mime_type,
+                        data: base64::engine::general_purpose::STANDARD.encode(&attachment.data),
+                    }),
+                })
+                .chain((!chat.content.is_empty()).then_some(GeminiPart {
+                    text: Some(&chat.content),
+                    inline_data: None,
+                }))
+                .collect(),
         })
         .collect();
 
-    GeminiRequest { contents }
+    GeminiRequest {
+        contents,
+        generation_config: GenerationConfig {
+            temperature: generation.temperature,
+            top_p: generation.top_p,
+            top_k: generation.top_k,
+            max_output_tokens: generation.max_output_tokens,
+            presence_penalty: generation.presence_penalty,
+            frequency_penalty: generation.frequency_penalty,
+            response_logprobs: generation.logprobs.is_some(),
+            logprobs: generation.logprobs.filter(|count| *count > 0),
+            response_modalities: match config.response_modalities {
+                ResponseModalities::Text => None,
+                ResponseModalities::TextAndImage => Some(&["TEXT", "IMAGE"]),
+            },
+            media_resolution: match config.media_resolution {
+                MediaResolution::Default => None,
+                MediaResolution::Low => Some("MEDIA_RESOLUTION_LOW"),
+                MediaResolution::Medium => Some("MEDIA_RESOLUTION_MEDIUM"),
+                MediaResolution::High => Some("MEDIA_RESOLUTION_HIGH"),
+            },
+        },
+        safety_settings: config
+            .safety_thresholds()
+            .into_iter()
+            .map(|(category, threshold)| SafetySetting {
+                category: match category {
+                    HarmCategory::Harassment => "HARM_CATEGORY_HARASSMENT",
+                    HarmCategory::HateSpeech => "HARM_CATEGORY_HATE_SPEECH",
+                    HarmCategory::SexuallyExplicit => "HARM_CATEGORY_SEXUALLY_EXPLICIT",
+                    HarmCategory::DangerousContent => "HARM_CATEGORY_DANGEROUS_CONTENT",
+                    HarmCategory::CivicIntegrity => "HARM_CATEGORY_CIVIC_INTEGRITY",
+                },
+                threshold: match threshold {
+                    HarmThreshold::Off => "OFF",
+                    HarmThreshold::BlockNone => "BLOCK_NONE",
+                    HarmThreshold::BlockOnlyHigh => "BLOCK_ONLY_HIGH",
+                    HarmThreshold::BlockMediumAndAbove => "BLOCK_MEDIUM_AND_ABOVE",
+                    HarmThreshold::BlockLowAndAbove => "BLOCK_LOW_AND_ABOVE",
+                },
+            })
+            .collect(),
+        system_instruction: config.system_instruction().map(|text| SystemInstruction {
+            parts: vec![TextPart { text }],
+        }),
+    }
 }
 
-pub async fn get_gemini_response(history: Arc<Vec<Chat>>) -> Message {
-    let client = Client::new();
-    let api_key = match env::var("GEMINI_API_KEY") {
-        Ok(key) => key,
-        Err(_) => return Message::ApiKeyNotSet,
+/// The request for `history` to the model's `method`, with its body, or why it
+/// can't be sent.
+async fn build_request(
+    history: &Arc<Vec<Chat>>,
+    config: &Config,
+    method: &str,
+) -> Result<(reqwest::RequestBuilder, Vec<u8>), Message> {
+    let prompt = convert_to_gemini_request(history, config);
+    let body = request_body(&json!(prompt))?;
+    Ok((endpoint(config, method).await?, body))
+}
+
+/// The serialized `prompt`, checked for size up front since the API would
+/// reject it anyway.
+fn request_body(prompt: &serde_json::Value) -> Result<Vec<u8>, Message> {
+    let body = serde_json::to_vec(prompt).map_err(|err| Message::RequestError(err.to_string()))?;
+    if body.len() > MAX_REQUEST_BYTES {
+        return Err(Message::RequestTooLarge {
+            size: body.len(),
+            limit: Some(MAX_REQUEST_BYTES),
+        });
+    }
+    Ok(body)
+}
+
+/// A request to the model's `method`, authorized and with the custom headers.
+async fn endpoint(config: &Config, method: &str) -> Result<reqwest::RequestBuilder, Message> {
+    let model = model_name(&config.gemini);
+    let Some(auth) = Auth::for_model(&model, &config.gemini) else {
+        return Err(Message::ApiKeyNotSet {
+            in_login_shell: credentials::is_set_in_login_shell(API_KEY_VAR).await,
+        });
+    };
+
+    // Tuned models only exist in the Gemini API.
+    let base_url = vertex_url(&config.gemini)
+        .filter(|_| model.starts_with("models/"))
+        .unwrap_or_else(|| API_URL.to_string());
+    Ok(with_headers(
+        auth.apply(Client::new().post(format!("{base_url}/{model}:{method}")))
+            .header("Content-Type", "application/json"),
+        &config.gemini,
+    ))
+}
+
+/// Sends `body` as it is to `generateContent` and parses the response like an
+/// answer, for replaying an edited request from the inspector.
+pub async fn replay_gemini(body: String, config: Config) -> Replay {
+    let request = match endpoint(&config, "generateContent").await {
+        Ok(request) => request,
+        Err(outcome) => return Replay::failed(outcome),
+    };
+
+    let raw_response = match request.body(body.clone()).send().await {
+        Ok(response) => match response.text().await {
+            Ok(text) => text,
+            Err(err) => return Replay::failed(Message::RequestError(err.to_string())),
+        },
+        Err(err) => return Replay::failed(Message::RequestError(err.to_string())),
+    };
+    let outcome = match parse_response(&raw_response, &config) {
+        Ok((response, issues)) => {
+            into_message(response, issues, &config, body, raw_response.clone())
+        }
+        Err(outcome) => outcome,
     };
+    Replay {
+        raw_response,
+        outcome,
+    }
+}
 
-    let prompt = convert_to_gemini_request(&history);
-
-    let response: GeminiResponse = match client.post("https://generativelanguage.googleapis.com/v1beta/models/gemini-2.5-flash:generateContent")
-        .header("x-goog-api-key", &api_key)
-        .header("Content-Type", "application/json")
-        .json(&json!(prompt))
-        .send()
-        .await {
-           Ok(result) => {
-               match result.json().await {
-                   Ok(result) => result,
-                   Err(err) => return Message::ApiResultParsingError(err.to_string())
-               }
-           },
-           Err(err) => return Message::RequestError(err.to_string())
+pub async fn get_gemini_response(history: Arc<Vec<Chat>>, config: Config) -> Message {
+    let (request, body) = match build_request(&history, &config, "generateContent").await {
+        Ok(request) => request,
+        Err(message) => return message,
+    };
+
+    let raw_request = String::from_utf8_lossy(&body).into_owned();
+    let raw_response = match request.body(body).send().await {
+        Ok(result) => match result.text().await {
+            Ok(result) => result,
+            Err(err) => return Message::RequestError(err.to_string()),
+        },
+        Err(err) => return Message::RequestError(err.to_string()),
+    };
+    let (response, issues) = match parse_response(&raw_response, &config) {
+        Ok(parsed) => parsed,
+        Err(message) => return message,
+    };
+
+    into_message(response, issues, &config, raw_request, raw_response)
+}
+
+/// Parses a response, or in lenient mode what can be parsed of it along with
+/// the fields that couldn't be or aren't known.
+fn parse_response(raw: &str, config: &Config) -> Result<(GeminiResponse, Vec<String>), Message> {
+    let parsed = if config.lenient_parsing {
+        lenient::parse(raw)
+    } else {
+        serde_json::from_str(raw).map(|response| (response, Vec::new()))
+    };
+    parsed.map_err(|err| models::parse_error(err, raw, config))
+}
+
+/// Like [`get_gemini_response`], but streams the answer from `streamGenerateContent`,
+/// calling `on_text` with the text so far whenever more of it arrives.
+pub async fn stream_gemini_response(
+    history: Arc<Vec<Chat>>,
+    config: Config,
+    mut on_text: impl FnMut(&str) + Send,
+) -> Message {
+    let (request, body) =
+        match build_request(&history, &config, "streamGenerateContent?alt=sse").await {
+            Ok(request) => request,
+            Err(message) => return message,
         };
 
+    let raw_request = String::from_utf8_lossy(&body).into_owned();
+    let mut response = match request.body(body).send().await {
+        Ok(response) => response,
+        Err(err) => return Message::RequestError(err.to_string()),
+    };
+
+    // Events are lines starting with `data:`, each holding a whole response
+    // with the next part of the answer. Chunks may end anywhere, even inside
+    // a character, so only complete lines are decoded.
+    let mut raw_response = String::new();
+    let mut buffer = Vec::new();
+    let mut chunks = Vec::new();
+    let mut issues = Vec::new();
+    let mut text = String::new();
+    let mut finished = false;
+    while !finished {
+        match response.chunk().await {
+            Ok(Some(bytes)) => buffer.extend_from_slice(&bytes),
+            Ok(None) => {
+                // The last line may lack its line break.
+                buffer.push(b'\n');
+                finished = true;
+            }
+            Err(err) => return Message::RequestError(err.to_string()),
+        }
+        while let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            raw_response.push_str(&line);
+            let Some(data) = line.trim_end().strip_prefix("data:") else {
+                continue;
+            };
+            let chunk = match parse_response(data.trim(), &config) {
+                Ok((chunk, chunk_issues)) => {
+                    issues.extend(chunk_issues);
+                    chunk
+                }
+                Err(message) => return message,
+            };
+            let before = text.len();
+            for part in chunk
+                .candidates
+                .iter()
+                .flatten()
+                .take(1)
+                .flat_map(|candidate| &candidate.content.parts)
+                .filter(|part| part.thought != Some(true))
+            {
+                text.push_str(part.text.as_deref().unwrap_or_default());
+            }
+            if text.len() > before {
+                on_text(&text);
+            }
+            chunks.push(chunk);
+        }
+    }
+
+    // Errors come back as a plain response instead of events, sometimes
+    // wrapped in an array.
+    let response = if chunks.is_empty() {
+        match parse_response(&raw_response, &config) {
+            Ok((response, response_issues)) => {
+                issues = response_issues;
+                response
+            }
+            Err(message) => match serde_json::from_str::<Vec<GeminiResponse>>(&raw_response) {
+                Ok(responses) if !responses.is_empty() => merge_chunks(responses),
+                _ => return message,
+            },
+        }
+    } else {
+        merge_chunks(chunks)
+    };
+    into_message(response, issues, &config, raw_request, raw_response)
+}
+
+/// Joins the responses of a stream into the one a plain request would have
+/// returned: the parts of the first candidate in order, and the latest of
+/// everything else.
+fn merge_chunks(chunks: Vec<GeminiResponse>) -> GeminiResponse {
+    let mut chunks = chunks.into_iter();
+    let mut merged = chunks.next().expect("at least one chunk");
+    for chunk in chunks {
+        if chunk.error.is_some() {
+            merged.error = chunk.error;
+        }
+        if chunk.prompt_feedback.is_some() {
+            merged.prompt_feedback = chunk.prompt_feedback;
+        }
+        if chunk.usage_metadata.is_some() {
+            merged.usage_metadata = chunk.usage_metadata;
+        }
+        let Some(candidate) = chunk.candidates.into_iter().flatten().next() else {
+            continue;
+        };
+        let candidates = merged.candidates.get_or_insert_default();
+        let Some(first) = candidates.first_mut() else {
+            candidates.push(candidate);
+            continue;
+        };
+        first.content.parts.extend(candidate.content.parts);
+        if candidate.finish_reason.is_some() {
+            first.finish_reason = candidate.finish_reason;
+        }
+        if candidate.safety_ratings.is_some() {
+            first.safety_ratings = candidate.safety_ratings;
+        }
+        if candidate.grounding_metadata.is_some() {
+            first.grounding_metadata = candidate.grounding_metadata;
+        }
+        if let Some(logprobs) = candidate.logprobs_result {
+            match &mut first.logprobs_result {
+                Some(merged) => {
+                    merged.top_candidates.extend(logprobs.top_candidates);
+                    merged.chosen_candidates.extend(logprobs.chosen_candidates);
+                }
+                None => first.logprobs_result = Some(logprobs),
+            }
+        }
+    }
+    merged
+}
+
+/// Turns a parsed response into the answer, or into why there is none.
+fn into_message(
+    response: GeminiResponse,
+    parse_issues: Vec<String>,
+    config: &Config,
+    raw_request: String,
+    raw_response: String,
+) -> Message {
     // 1. Handle API-Level Errors immediately
     if let Some(err) = response.error {
         return Message::ApiError(err.message);
     }
 
-    for candidate in response.candidates.iter().flatten() {
+    let preset = config.safety_preset();
+    let blocked_by = |category: &gemini::HarmCategory| {
+        let category = format!("{category:?}");
+        let category = category.trim_start_matches("HarmCategory");
+        if preset == SafetyPreset::Default {
+            category.to_string()
+        } else {
+            format!("{category} by your {} safety preset", preset.name())
+        }
+    };
+    if let Some(feedback) = &response.prompt_feedback
+        && let Some(reason) = &feedback.block_reason
+    {
+        return Message::PromptBlocked(
+            match feedback.safety_ratings.iter().find(|rating| rating.blocked) {
+                Some(rating) => blocked_by(&rating.category),
+                None => format!("{reason:?}"),
+            },
+        );
+    }
+
+    let usage = response.usage_metadata.map(|usage| Usage {
+        prompt_tokens: usage.prompt_token_count,
+        cached_tokens: usage.cached_content_token_count,
+        completion_tokens: usage.candidates_token_count + usage.thoughts_token_count,
+    });
+    for candidate in response.candidates.into_iter().flatten() {
         for rating in candidate.safety_ratings.iter().flatten() {
             if rating.blocked {
-                return Message::PromptBlocked(format!(
-                    "⚠️ Prompt Blocked by category: {:?}",
-                    rating.category
-                ));
+                return Message::PromptBlocked(blocked_by(&rating.category));
             }
         }
-        // --- Finish Reason ---
-        /*
-        match candidate.finish_reason.as_ref() {
-            Some(FinishReason::Stop) => println!("✅ Response complete"),
-            Some(FinishReason::Safety) => println!("⛔ Finished due to Safety"),
-            Some(reason) => println!("ℹ️ Finished due to other reason: {:?}", reason),
-            None => println!("Finished due to unkown reason"),
+        let finish_reason = candidate.finish_reason.map(|reason| format!("{reason:?}"));
+        let parts = candidate.content.parts.iter().map(describe_part).collect();
+        let mut text = String::new();
+        let mut images = Vec::new();
+        for part in candidate
+            .content
+            .parts
+            .iter()
+            .filter(|part| part.thought != Some(true))
+        {
+            if let Some(part_text) = part.text.as_deref() {
+                text.push_str(part_text);
+            }
+            if let Some(blob) = &part.inline_data
+                && let Ok(data) = base64::engine::general_purpose::STANDARD.decode(&blob.data)
+            {
+                images.push(GeneratedImage {
+                    mime_type: blob.mime_type.clone(),
+                    data,
+                });
+            }
         }
 
-        */
-        if let Some(part) = candidate.content.parts.iter().last() {
-            if let Some(text) = part.text.as_deref() {
-                return Message::Response(text.to_string());
-            }
+        if !text.is_empty() || !images.is_empty() {
+            let grounding = candidate.grounding_metadata.unwrap_or_default();
+            return Message::Response(Reply {
+                text,
+                images,
+                logprobs: candidate
+                    .logprobs_result
+                    .map(token_logprobs)
+                    .unwrap_or_default(),
+                usage,
+                finish_reason,
+                finish_message: candidate.finish_message,
+                model_version: response.model_version,
+                safety_ratings: candidate
+                    .safety_ratings
+                    .iter()
+                    .flatten()
+                    .map(|rating| {
+                        let category = format!("{:?}", rating.category);
+                        (
+                            category.trim_start_matches("HarmCategory").to_string(),
+                            format!("{:?}", rating.probability),
+                        )
+                    })
+                    .collect(),
+                sources: grounding
+                    .grounding_chunks
+                    .into_iter()
+                    .filter_map(|chunk| chunk.web)
+                    .map(|web| Source {
+                        title: web.title.unwrap_or_else(|| web.uri.clone()),
+                        uri: web.uri,
+                    })
+                    .collect(),
+                search_queries: grounding.web_search_queries,
+                parts,
+                parse_issues,
+                raw_request,
+                raw_response,
+            });
+        }
+        // Nothing to show, but the model said why.
+        if let Some(caption) = finish_reason
+            .as_deref()
+            .and_then(|reason| models::finish_caption(reason, candidate.finish_message.as_deref()))
+        {
+            return Message::ApiError(caption);
         }
     }
 
     Message::EmptyResponse
 }
+
+/// One line about `part`: its text, or the kind and size of its data.
+fn describe_part(part: &gemini::Part) -> String {
+    let kind = if part.thought == Some(true) {
+        "thought"
+    } else {
+        "text"
+    };
+    if let Some(text) = &part.text {
+        format!("{kind}: {text}")
+    } else if let Some(blob) = &part.inline_data {
+        // Base64 takes four characters for every three bytes.
+        format!(
+            "inline data: {}, {} bytes",
+            blob.mime_type,
+            blob.data.len() / 4 * 3
+        )
+    } else if let Some(file) = &part.file_data {
+        format!("file: {}, {}", file.mime_type, file.file_uri)
+    } else if part.thought_signature.is_some() {
+        "thought signature".to_string()
+    } else {
+        "empty".to_string()
+    }
+}
+
+fn token_logprobs(result: LogprobsResult) -> Vec<TokenLogprob> {
+    let mut top = result.top_candidates.into_iter();
+
+    result
+        .chosen_candidates
+        .into_iter()
+        .map(|chosen| TokenLogprob {
+            token: chosen.token,
+            log_probability: chosen.log_probability,
+            alternatives: top
+                .next()
+                .map(|top| {
+                    top.candidates
+                        .into_iter()
+                        .map(|candidate| (candidate.token, candidate.log_probability))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(json: &str) -> GeminiResponse {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn texts(response: &GeminiResponse) -> Vec<&str> {
+        response.candidates.as_ref().unwrap()[0]
+            .content
+            .parts
+            .iter()
+            .filter_map(|part| part.text.as_deref())
+            .collect()
+    }
+
+    #[test]
+    fn chunks_are_joined_into_one_answer() {
+        let merged = merge_chunks(vec![
+            chunk(r#"{"candidates":[{"content":{"parts":[{"text":"Hel"}]},"index":0}]}"#),
+            chunk(
+                r#"{"candidates":[{"content":{"parts":[{"text":"lo"}]},"index":0,"finishReason":"STOP"}],
+                "usageMetadata":{"promptTokenCount":3,"candidatesTokenCount":2}}"#,
+            ),
+        ]);
+        assert_eq!(texts(&merged), ["Hel", "lo"]);
+        let candidate = &merged.candidates.as_ref().unwrap()[0];
+        assert!(matches!(
+            candidate.finish_reason,
+            Some(gemini::FinishReason::Stop)
+        ));
+        assert_eq!(merged.usage_metadata.unwrap().prompt_token_count, 3);
+    }
+
+    #[test]
+    fn chunks_without_candidates_are_skipped() {
+        let merged = merge_chunks(vec![
+            chunk(r#"{"usageMetadata":{"promptTokenCount":1}}"#),
+            chunk(r#"{"candidates":[{"content":{"parts":[{"text":"Hi"}]},"index":0}]}"#),
+            chunk(r#"{"usageMetadata":{"promptTokenCount":4}}"#),
+        ]);
+        assert_eq!(texts(&merged), ["Hi"]);
+        assert_eq!(merged.usage_metadata.unwrap().prompt_token_count, 4);
+    }
+
+    #[test]
+    fn request_body_accepts_a_prompt_within_the_limit() {
+        let prompt = json!({ "contents": [{ "parts": [{ "text": "hello" }] }] });
+        let body = request_body(&prompt).unwrap();
+        assert_eq!(body, serde_json::to_vec(&prompt).unwrap());
+    }
+
+    #[test]
+    fn request_body_refuses_a_prompt_over_the_limit() {
+        let prompt = json!({ "text": "a".repeat(MAX_REQUEST_BYTES) });
+        match request_body(&prompt) {
+            Err(Message::RequestTooLarge { size, limit }) => {
+                assert!(size > MAX_REQUEST_BYTES);
+                assert_eq!(limit, Some(MAX_REQUEST_BYTES));
+            }
+            other => panic!("expected RequestTooLarge, got {other:?}"),
+        }
+    }
+}
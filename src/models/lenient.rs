@@ -0,0 +1,48 @@
+//! Field by field parsing for the providers' lenient response parsers.
+
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+/// The field `name` of `object`, `None` if it is missing or doesn't parse.
+pub(super) fn field<T: DeserializeOwned>(
+    object: &Map<String, Value>,
+    name: &str,
+    path: &str,
+    issues: &mut Vec<String>,
+) -> Option<T> {
+    let value = object.get(name).filter(|value| !value.is_null())?;
+    deserialize(value, &join(path, name), issues)
+}
+
+pub(super) fn deserialize<T: DeserializeOwned>(
+    value: &Value,
+    path: &str,
+    issues: &mut Vec<String>,
+) -> Option<T> {
+    match T::deserialize(value) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            issues.push(format!("{path}: {err}"));
+            None
+        }
+    }
+}
+
+pub(super) fn unknown_fields(
+    object: &Map<String, Value>,
+    known: &[&str],
+    path: &str,
+    issues: &mut Vec<String>,
+) {
+    for name in object.keys().filter(|name| !known.contains(&name.as_str())) {
+        issues.push(format!("{}: unknown field", join(path, name)));
+    }
+}
+
+pub(super) fn join(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{path}.{name}")
+    }
+}
@@ -0,0 +1,189 @@
+//! Downloads GGUF models into the models directory, e.g. from Hugging Face.
+
+use futures_util::SinkExt;
+use futures_util::Stream;
+use futures_util::channel::mpsc;
+use reqwest::{Client, Url};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::io::AsyncWriteExt;
+
+use crate::credentials;
+
+/// Environment variable holding a Hugging Face token, needed for gated models.
+pub const HF_TOKEN_VAR: &str = "HF_TOKEN";
+
+const HUGGING_FACE_HOST: &str = "huggingface.co";
+
+const HUGGING_FACE_URL: &str = "https://huggingface.co";
+
+/// Bytes received between progress reports.
+const PROGRESS_STEP: u64 = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    Progress {
+        received: u64,
+        total: Option<u64>,
+    },
+    /// The downloaded file and its SHA-256 checksum.
+    Finished(Result<(PathBuf, String), String>),
+}
+
+/// Turns a URL or a Hugging Face path like `owner/repo/model.gguf` into a download URL.
+pub fn resolve_url(source: &str) -> Result<String, String> {
+    let source = source.trim();
+    if source.starts_with("https://") || source.starts_with("http://") {
+        // Links copied from the file page point to the viewer, not the file.
+        return Ok(source.replacen("/blob/", "/resolve/", 1));
+    }
+
+    let parts: Vec<&str> = source.split('/').filter(|part| !part.is_empty()).collect();
+    match parts.as_slice() {
+        [owner, repo, file @ ..] if !file.is_empty() => Ok(format!(
+            "{HUGGING_FACE_URL}/{owner}/{repo}/resolve/main/{}",
+            file.join("/")
+        )),
+        _ => Err("Enter a URL or a Hugging Face path like owner/repo/model.gguf".into()),
+    }
+}
+
+/// Downloads `url` into the models directory, reporting progress.
+///
+/// The file is checked against `sha256` when one is given, and removed if it
+/// doesn't match or `cancel` is set.
+pub fn download(
+    url: String,
+    sha256: Option<String>,
+    cancel: Arc<AtomicBool>,
+) -> impl Stream<Item = Event> {
+    cosmic::iced::stream::channel(16, move |mut output| async move {
+        let result = fetch(&url, sha256.as_deref(), &cancel, &mut output).await;
+        _ = output.send(Event::Finished(result)).await;
+    })
+}
+
+async fn fetch(
+    url: &str,
+    sha256: Option<&str>,
+    cancel: &AtomicBool,
+    output: &mut mpsc::Sender<Event>,
+) -> Result<(PathBuf, String), String> {
+    let name = url
+        .split(['?', '#'])
+        .next()
+        .and_then(|url| url.rsplit('/').next())
+        .filter(|name| name.ends_with(".gguf"))
+        .ok_or("Only .gguf files can be downloaded")?;
+    let dir = super::models_dir().ok_or("Could not find the data directory")?;
+    let path = dir.join(name);
+    if path.exists() {
+        return Err(format!("{name} is already downloaded"));
+    }
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let partial = path.with_extension("gguf.part");
+    let result = write(url, &partial, cancel, output)
+        .await
+        .and_then(|digest| {
+            match sha256
+                .map(str::trim)
+                .filter(|expected| !expected.is_empty())
+            {
+                Some(expected) if !expected.eq_ignore_ascii_case(&digest) => Err(format!(
+                    "Checksum mismatch: expected {expected}, got {digest}"
+                )),
+                _ => Ok(digest),
+            }
+        });
+
+    match result {
+        Ok(digest) => {
+            tokio::fs::rename(&partial, &path)
+                .await
+                .map_err(|err| err.to_string())?;
+            Ok((path, digest))
+        }
+        Err(err) => {
+            _ = tokio::fs::remove_file(&partial).await;
+            Err(err)
+        }
+    }
+}
+
+/// Whether `url` goes to Hugging Face itself, the only host the token is sent to.
+fn is_hugging_face(url: &str) -> bool {
+    Url::parse(url)
+        .is_ok_and(|url| url.scheme() == "https" && url.host_str() == Some(HUGGING_FACE_HOST))
+}
+
+/// Streams `url` into `path` and returns the SHA-256 of what was written.
+async fn write(
+    url: &str,
+    path: &Path,
+    cancel: &AtomicBool,
+    output: &mut mpsc::Sender<Event>,
+) -> Result<String, String> {
+    let mut request = Client::new().get(url);
+    if is_hugging_face(url)
+        && let Some(token) = credentials::lookup(HF_TOKEN_VAR)
+    {
+        request = request.bearer_auth(token);
+    }
+    let mut response = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| err.to_string())?;
+
+    let total = response.content_length();
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .map_err(|err| err.to_string())?;
+    let mut hasher = Sha256::new();
+    let mut received = 0;
+    let mut reported = 0;
+
+    while let Some(chunk) = response.chunk().await.map_err(|err| err.to_string())? {
+        if cancel.load(Ordering::Relaxed) {
+            return Err("Download cancelled".into());
+        }
+        hasher.update(&chunk);
+        file.write_all(&chunk)
+            .await
+            .map_err(|err| err.to_string())?;
+
+        received += chunk.len() as u64;
+        if received - reported >= PROGRESS_STEP {
+            reported = received;
+            _ = output.send(Event::Progress { received, total }).await;
+        }
+    }
+    file.flush().await.map_err(|err| err.to_string())?;
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_token_only_goes_to_hugging_face() {
+        assert!(is_hugging_face(
+            "https://huggingface.co/owner/repo/resolve/main/model.gguf"
+        ));
+        assert!(!is_hugging_face(
+            "https://huggingface.co.evil.example/model.gguf"
+        ));
+        assert!(!is_hugging_face("http://huggingface.co/model.gguf"));
+    }
+}
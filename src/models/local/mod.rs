@@ -0,0 +1,434 @@
+//! Local inference with GGUF models through llama.cpp's `llama-server`.
+//!
+//! The server is started on demand for the selected model file, bound to a
+//! free port on localhost, and restarted when another model is picked. Nothing
+//! leaves the machine.
+
+pub mod download;
+
+use futures_util::future::BoxFuture;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::process::{Child, Command};
+use tokio::sync::Mutex;
+
+use crate::app::Chat;
+use crate::config::{Config, LocalSettings};
+use crate::credentials::CONFIG_DIR_NAME;
+use crate::models::gemini::{Message, Reply, Usage};
+use crate::models::{self, Health, HealthStatus, Replay, SLOW_RESPONSE};
+
+/// How long to wait for a model to load.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The running server.
+static SERVER: Mutex<Option<Server>> = Mutex::const_new(None);
+
+struct Server {
+    /// The model it serves.
+    model: PathBuf,
+    child: Child,
+    /// Port it listens on, picked when it started so several profiles' servers
+    /// don't clash.
+    port: u16,
+}
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    messages: Vec<ChatMessage<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+}
+
+#[derive(Serialize)]
+struct ChatMessage<'a> {
+    role: &'a str,
+    content: Cow<'a, str>,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    #[serde(default)]
+    choices: Vec<Choice>,
+    usage: Option<ResponseUsage>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: ResponseMessage,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ResponseUsage {
+    #[serde(default)]
+    prompt_tokens: u32,
+    #[serde(default)]
+    completion_tokens: u32,
+}
+
+#[derive(Deserialize)]
+struct ResponseMessage {
+    content: Option<String>,
+}
+
+/// GGUF models served by llama.cpp on this machine.
+pub struct Local;
+
+impl super::Provider for Local {
+    fn complete(&self, history: Arc<Vec<Chat>>, config: Config) -> BoxFuture<'static, Message> {
+        Box::pin(get_local_response(history, config))
+    }
+
+    fn replay(&self, body: String, config: Config) -> BoxFuture<'static, Replay> {
+        Box::pin(replay_local(body, config))
+    }
+
+    fn model_name(&self, config: &Config) -> String {
+        model_name(&config.local)
+    }
+
+    /// Local models are free.
+    fn price_per_million(&self, _config: &Config) -> (f64, f64) {
+        (0.0, 0.0)
+    }
+
+    fn context_limit(&self, config: &Config) -> u32 {
+        match config.local.context_size {
+            // llama-server's default context size.
+            0 => 4096,
+            size => size,
+        }
+    }
+}
+
+/// Directory where downloaded models are kept.
+pub fn models_dir() -> Option<PathBuf> {
+    let data_dir = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/share"),
+    };
+
+    Some(data_dir.join(CONFIG_DIR_NAME).join("models"))
+}
+
+/// GGUF files in the models directory.
+pub fn installed_models() -> Vec<PathBuf> {
+    let Some(entries) = models_dir().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+
+    let mut models: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "gguf")
+        })
+        .collect();
+    models.sort();
+    models
+}
+
+/// File name of the configured model, without the extension.
+pub fn model_name(settings: &LocalSettings) -> String {
+    Path::new(settings.model_path.trim())
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "local model".to_string())
+}
+
+/// Checks that the model and the server exist, and how fast a running server answers.
+pub async fn check_health(settings: &LocalSettings) -> Health {
+    let health = |status, detail: String, latency| Health {
+        provider: "Local model",
+        status,
+        detail,
+        latency,
+    };
+
+    let model = PathBuf::from(settings.model_path.trim());
+    if !model.is_file() {
+        return health(
+            HealthStatus::Failing,
+            format!("Model not found: {}", model.display()),
+            None,
+        );
+    }
+    let binary = server_binary(settings);
+    if !is_executable(binary) {
+        return health(
+            HealthStatus::Failing,
+            format!("{binary} is not installed"),
+            None,
+        );
+    }
+
+    let port = SERVER
+        .lock()
+        .await
+        .as_ref()
+        .filter(|server| server.model == model)
+        .map(|server| server.port);
+    let Some(port) = port else {
+        return health(
+            HealthStatus::Healthy,
+            format!("{} starts on first use", model_name(settings)),
+            None,
+        );
+    };
+    let started = Instant::now();
+    let result = Client::new()
+        .get(format!("http://127.0.0.1:{port}/health"))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    let latency = started.elapsed();
+    match result {
+        Ok(_) if latency > SLOW_RESPONSE => health(
+            HealthStatus::Degraded,
+            format!("{} is loaded, but slow to respond", model_name(settings)),
+            Some(latency),
+        ),
+        Ok(_) => health(
+            HealthStatus::Healthy,
+            format!("{} is loaded", model_name(settings)),
+            Some(latency),
+        ),
+        Err(err) => health(HealthStatus::Failing, err.to_string(), None),
+    }
+}
+
+/// Program started as the server, `llama-server` from the `PATH` by default.
+fn server_binary(settings: &LocalSettings) -> &str {
+    match settings.server_binary.trim() {
+        "" => "llama-server",
+        binary => binary,
+    }
+}
+
+/// Whether `binary` is a path to a file or found on the `PATH`.
+fn is_executable(binary: &str) -> bool {
+    if binary.contains('/') {
+        return Path::new(binary).is_file();
+    }
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(binary).is_file()))
+}
+
+pub async fn get_local_response(history: Arc<Vec<Chat>>, config: Config) -> Message {
+    let model = PathBuf::from(config.local.model_path.trim());
+    if !model.is_file() {
+        return Message::RequestError(format!("Local model not found: {}", model.display()));
+    }
+    let port = match ensure_server(&model, &config).await {
+        Ok(port) => port,
+        Err(err) => return Message::RequestError(err),
+    };
+
+    let generation = config.generation();
+    let system_instruction = config.system_instruction();
+    let request = ChatRequest {
+        messages: system_instruction
+            .iter()
+            .map(|instruction| ChatMessage {
+                role: "system",
+                content: Cow::Borrowed(instruction),
+            })
+            .chain(
+                history
+                    .iter()
+                    // Failures shown in place of answers aren't part of the conversation.
+                    .filter(|chat| !chat.error)
+                    .filter_map(|chat| {
+                        let role = match chat.role.as_str() {
+                            "user" => "user",
+                            "model" | "assistant" => "assistant",
+                            _ => return None,
+                        };
+                        Some(ChatMessage {
+                            role,
+                            content: with_text_attachments(chat),
+                        })
+                    }),
+            )
+            .collect(),
+        temperature: generation.temperature,
+        top_p: generation.top_p,
+        top_k: generation.top_k,
+        max_tokens: generation.max_output_tokens,
+        presence_penalty: generation.presence_penalty,
+        frequency_penalty: generation.frequency_penalty,
+    };
+    let raw_request = serde_json::to_string(&request).unwrap_or_default();
+
+    let raw_response = match send(port, raw_request.clone()).await {
+        Ok(text) => text,
+        Err(err) => return Message::RequestError(err),
+    };
+    into_message(raw_request, raw_response, &config)
+}
+
+/// Sends `body` as it is to the local server and parses the response like an
+/// answer, for replaying an edited request from the inspector.
+pub async fn replay_local(body: String, config: Config) -> Replay {
+    let model = PathBuf::from(config.local.model_path.trim());
+    if !model.is_file() {
+        return Replay::failed(Message::RequestError(format!(
+            "Local model not found: {}",
+            model.display()
+        )));
+    }
+    let port = match ensure_server(&model, &config).await {
+        Ok(port) => port,
+        Err(err) => return Replay::failed(Message::RequestError(err)),
+    };
+
+    match send(port, body.clone()).await {
+        Ok(raw_response) => Replay {
+            outcome: into_message(body, raw_response.clone(), &config),
+            raw_response,
+        },
+        Err(err) => Replay::failed(Message::RequestError(err)),
+    }
+}
+
+/// Posts a chat completion request to the server on `port`, returning the
+/// response text.
+async fn send(port: u16, body: String) -> Result<String, String> {
+    Client::new()
+        .post(format!("http://127.0.0.1:{port}/v1/chat/completions"))
+        .header("Content-Type", "application/json")
+        .body(body)
+        .send()
+        .await
+        .map_err(|err| err.to_string())?
+        .text()
+        .await
+        .map_err(|err| err.to_string())
+}
+
+/// Turns a response of the server into the answer, or into why there is none.
+fn into_message(raw_request: String, raw_response: String, config: &Config) -> Message {
+    let response: ChatResponse = match serde_json::from_str(&raw_response) {
+        Ok(response) => response,
+        Err(err) => return models::parse_error(err, &raw_response, config),
+    };
+
+    let usage = response.usage.map(|usage| Usage {
+        prompt_tokens: usage.prompt_tokens,
+        cached_tokens: 0,
+        completion_tokens: usage.completion_tokens,
+    });
+    let Some(choice) = response.choices.into_iter().next() else {
+        return Message::EmptyResponse;
+    };
+    match choice.message.content {
+        Some(text) if !text.is_empty() => Message::Response(Reply {
+            text,
+            usage,
+            finish_reason: choice.finish_reason,
+            raw_request,
+            raw_response,
+            ..Reply::default()
+        }),
+        _ => Message::EmptyResponse,
+    }
+}
+
+/// The message with text attachments inlined; other files can't be read by text models.
+fn with_text_attachments(chat: &Chat) -> Cow<'_, str> {
+    if chat.attachments.is_empty() {
+        return Cow::Borrowed(&chat.content);
+    }
+
+    let mut content = String::new();
+    for attachment in &chat.attachments {
+        if let Some(text) = attachment.text() {
+            content.push_str(&format!("{}:\n```\n{text}\n```\n\n", attachment.name));
+        }
+    }
+    content.push_str(&chat.content);
+    Cow::Owned(content)
+}
+
+/// Starts `llama-server` for `model` unless it is already serving it,
+/// returning the port it listens on.
+async fn ensure_server(model: &Path, config: &Config) -> Result<u16, String> {
+    let mut server = SERVER.lock().await;
+
+    if let Some(running) = server.as_mut()
+        && running.model == model
+        && matches!(running.child.try_wait(), Ok(None))
+    {
+        return Ok(running.port);
+    }
+    // Dropping the old child kills it.
+    *server = None;
+
+    let port = free_port().map_err(|err| format!("No free port for the local model: {err}"))?;
+    let binary = server_binary(&config.local);
+    let mut command = Command::new(binary);
+    command
+        .arg("--model")
+        .arg(model)
+        .args(["--host", "127.0.0.1", "--port", &port.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true);
+    if config.local.context_size > 0 {
+        command.args(["--ctx-size", &config.local.context_size.to_string()]);
+    }
+    let child = command
+        .spawn()
+        .map_err(|err| format!("Could not start {binary}: {err}"))?;
+    *server = Some(Server {
+        model: model.to_path_buf(),
+        child,
+        port,
+    });
+
+    // Wait for the model to finish loading.
+    let client = Client::new();
+    let deadline = tokio::time::Instant::now() + STARTUP_TIMEOUT;
+    while tokio::time::Instant::now() < deadline {
+        if let Some(running) = server.as_mut()
+            && let Ok(Some(status)) = running.child.try_wait()
+        {
+            *server = None;
+            return Err(format!("{binary} exited with {status}"));
+        }
+        if client
+            .get(format!("http://127.0.0.1:{port}/health"))
+            .send()
+            .await
+            .is_ok_and(|response| response.status().is_success())
+        {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+
+    *server = None;
+    Err("Timed out waiting for the local model to load".into())
+}
+
+/// A port on localhost nothing listens on right now, as the system picks it.
+fn free_port() -> std::io::Result<u16> {
+    Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.port())
+}
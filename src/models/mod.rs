@@ -1 +1,280 @@
 pub mod gemini;
+mod lenient;
+pub mod local;
+pub mod openai;
+mod prepare;
+
+use futures_util::future::BoxFuture;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::app::Chat;
+use crate::config::{self, Config};
+
+/// Reason given for refusing anything that would leave the machine.
+pub const LOCAL_ONLY_ERROR: &str =
+    "Local-only mode is on: cloud providers and network features are disabled";
+
+/// A model backend. Everything goes through [`provider`] instead of matching on
+/// the configured provider, so a new backend only implements this and is added
+/// there.
+pub trait Provider: Send + Sync {
+    /// Answers the conversation.
+    fn complete(
+        &self,
+        history: Arc<Vec<Chat>>,
+        config: Config,
+    ) -> BoxFuture<'static, gemini::Message>;
+
+    /// Answers the conversation, calling `on_text` with the answer so far while it
+    /// arrives. Backends that can't stream answer all at once.
+    fn stream<'a>(
+        &'a self,
+        history: Arc<Vec<Chat>>,
+        config: Config,
+        _on_text: Box<dyn FnMut(&str) + Send + 'a>,
+    ) -> BoxFuture<'a, gemini::Message> {
+        self.complete(history, config)
+    }
+
+    /// Sends a request body as it is, e.g. one edited in the inspector, and
+    /// parses the response like an answer.
+    fn replay(&self, body: String, config: Config) -> BoxFuture<'static, Replay>;
+
+    /// Name of the model that answers with `config`.
+    fn model_name(&self, config: &Config) -> String;
+
+    /// Approximate prices of the model in US dollars per million input and output tokens.
+    fn price_per_million(&self, config: &Config) -> (f64, f64);
+
+    /// Context window of the model, in tokens.
+    fn context_limit(&self, config: &Config) -> u32;
+
+    /// Environment variable holding the API key, for backends that need one.
+    fn api_key_var(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Page where users can create an API key.
+    fn api_key_url(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+/// The backend behind `id`.
+pub fn provider(id: config::Provider) -> &'static dyn Provider {
+    match id {
+        config::Provider::Gemini => &gemini::Gemini,
+        config::Provider::OpenAi => &openai::OpenAi,
+        config::Provider::Compatible => &openai::compatible::Compatible,
+        config::Provider::Local => &local::Local,
+    }
+}
+
+/// Sends the history to the provider selected in `config`, without the failures
+/// and empty messages the chat shows.
+///
+/// In local-only mode, cloud providers are refused here so no caller can reach them.
+pub async fn get_response(history: Arc<Vec<Chat>>, config: Config) -> gemini::Message {
+    if config.local_only && !config.runs_locally() {
+        return gemini::Message::RequestError(LOCAL_ONLY_ERROR.into());
+    }
+
+    let history = Arc::new(prepare::history(&history));
+    provider(config.provider).complete(history, config).await
+}
+
+/// Like [`get_response`], but calls `on_text` with the answer so far while it
+/// arrives, for providers that stream. Others answer all at once.
+pub async fn stream_response(
+    history: Arc<Vec<Chat>>,
+    config: Config,
+    on_text: impl FnMut(&str) + Send,
+) -> gemini::Message {
+    if config.local_only && !config.runs_locally() {
+        return gemini::Message::RequestError(LOCAL_ONLY_ERROR.into());
+    }
+
+    let history = Arc::new(prepare::history(&history));
+    provider(config.provider)
+        .stream(history, config, Box::new(on_text))
+        .await
+}
+
+/// Response to a replayed request body, with what it parses to.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    /// The response as received, empty when none arrived.
+    pub raw_response: String,
+    pub outcome: gemini::Message,
+}
+
+impl Replay {
+    /// A replay that failed before there was a response.
+    pub fn failed(outcome: gemini::Message) -> Self {
+        Self {
+            raw_response: String::new(),
+            outcome,
+        }
+    }
+}
+
+/// Sends a hand-edited request `body` to the provider selected in `config`.
+pub async fn replay(body: String, config: Config) -> Replay {
+    if config.local_only && !config.runs_locally() {
+        return Replay::failed(gemini::Message::RequestError(LOCAL_ONLY_ERROR.into()));
+    }
+
+    provider(config.provider).replay(body, config).await
+}
+
+/// Explains an answer that ended for another reason than being complete, from
+/// the `reason` a provider gives and its own explanation if any. `None` for
+/// complete answers.
+pub fn finish_caption(reason: &str, message: Option<&str>) -> Option<String> {
+    let caption = match reason {
+        "Stop" | "stop" | "FinishReasonUnspecified" | "tool_calls" | "function_call" => {
+            return None;
+        }
+        "MaxTokens" | "length" => "Cut off at the output token limit",
+        "Safety" => "Stopped for safety",
+        "Recitation" => "Stopped for reciting training data",
+        "Language" => "Stopped because the language isn't supported",
+        "Blocklist" => "Stopped for a blocked term",
+        "ProhibitedContent" => "Stopped for prohibited content",
+        "Spii" => "Stopped for sensitive personal information",
+        "MalformedFunctionCall" => "Stopped on a malformed function call",
+        "UnexpectedToolCall" => "Stopped on a call to a tool that isn't available",
+        "TooManyToolCalls" => "Stopped after too many tool calls",
+        "MissingThoughtSignature" => "Stopped for a missing thought signature",
+        "ImageSafety" | "ImageProhibitedContent" => "Image withheld for safety",
+        "ImageRecitation" => "Image withheld for reciting training data",
+        "NoImage" | "ImageOther" => "No image was generated",
+        "content_filter" => "Stopped by the content filter",
+        _ => "Stopped early",
+    };
+    Some(
+        match message.map(str::trim).filter(|message| !message.is_empty()) {
+            Some(message) => format!("{caption}: {message}"),
+            None => caption.to_string(),
+        },
+    )
+}
+
+/// Answers slower than this count as degraded in a health check.
+pub const SLOW_RESPONSE: Duration = Duration::from_secs(2);
+
+/// How a provider fared in a health check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthStatus {
+    Healthy,
+    Degraded,
+    Failing,
+}
+
+/// Result of checking one provider.
+#[derive(Debug, Clone)]
+pub struct Health {
+    pub provider: &'static str,
+    pub status: HealthStatus,
+    pub detail: String,
+    /// Round trip of the check, when it reached the provider.
+    pub latency: Option<Duration>,
+}
+
+/// Checks every provider that is configured, or selected, and allowed.
+pub async fn check_health(config: Config) -> Vec<Health> {
+    let mut results = Vec::new();
+    if !config.local_only {
+        if config.provider == config::Provider::Gemini
+            || config.gemini.api_key(gemini::API_KEY_VAR).is_some()
+        {
+            results.push(gemini::check_health(&config.gemini).await);
+        }
+        if config.provider == config::Provider::OpenAi
+            || config.openai.api_key(openai::API_KEY_VAR).is_some()
+        {
+            results.push(openai::check_health(&config.openai).await);
+        }
+    }
+    let compatible = &config.compatible;
+    if (!config.local_only || compatible.is_loopback())
+        && (config.provider == config::Provider::Compatible
+            || !compatible.base_url.trim().is_empty())
+    {
+        results.push(openai::compatible::check_health(compatible).await);
+    }
+    if config.provider == config::Provider::Local || !config.local.model_path.trim().is_empty() {
+        results.push(local::check_health(&config.local).await);
+    }
+    results
+}
+
+/// The error for a response that didn't parse, with its raw body when the
+/// settings ask for it.
+pub fn parse_error(
+    err: impl std::fmt::Display,
+    raw_response: &str,
+    config: &Config,
+) -> gemini::Message {
+    if config.raw_on_parse_error {
+        gemini::Message::ApiResultParsingError(format!("{err}\n\n```\n{raw_response}\n```"))
+    } else {
+        gemini::Message::ApiResultParsingError(err.to_string())
+    }
+}
+
+/// Fails in local-only mode, for features that talk to the network.
+pub fn ensure_network_allowed(config: &Config) -> Result<(), String> {
+    if config.local_only {
+        Err(LOCAL_ONLY_ERROR.into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Name of the model that answers with `config`, for labelling answers.
+pub fn model_name(config: &Config) -> String {
+    provider(config.provider).model_name(config)
+}
+
+/// Rough token count of `text`, about four characters per token.
+pub fn estimate_tokens(text: &str) -> u32 {
+    text.chars().count().div_ceil(4) as u32
+}
+
+/// Estimated price in US dollars of a request with `config`, local models are free.
+pub fn estimate_cost(config: &Config, input_tokens: u32, output_tokens: u32) -> f64 {
+    let (input, output) = provider(config.provider).price_per_million(config);
+    (f64::from(input_tokens) * input + f64::from(output_tokens) * output) / 1_000_000.0
+}
+
+/// Tokens of the prompt and of `reply` to `history`, counted from the text
+/// when the provider didn't report them.
+pub fn reply_tokens(history: &[Chat], reply: &gemini::Reply) -> (u32, u32) {
+    match reply.usage {
+        Some(usage) => (usage.prompt_tokens, usage.completion_tokens),
+        None => (
+            history
+                .iter()
+                .map(|chat| estimate_tokens(&chat.content))
+                .sum(),
+            estimate_tokens(&reply.text),
+        ),
+    }
+}
+
+/// Estimated price in US dollars of `message` answering `history` with
+/// `config`. Requests that failed aren't billed.
+pub fn message_cost(config: &Config, history: &[Chat], message: &gemini::Message) -> f64 {
+    let gemini::Message::Response(reply) = message else {
+        return 0.0;
+    };
+    let (input, output) = reply_tokens(history, reply);
+    estimate_cost(config, input, output)
+}
+
+/// Context window of the configured model, in tokens.
+pub fn context_limit(config: &Config) -> u32 {
+    provider(config.provider).context_limit(config)
+}
@@ -0,0 +1,129 @@
+//! Servers with an OpenAI-compatible chat completions API at a configured URL,
+//! e.g. llama.cpp's server, LM Studio, vLLM or LocalAI.
+
+use futures_util::future::BoxFuture;
+use std::sync::Arc;
+use std::time::Instant;
+
+use super::{Endpoint, complete, convert_to_openai_request, fetch_models, replay};
+use crate::app::Chat;
+use crate::config::{CompatibleSettings, Config};
+use crate::keyring::{self, Slot};
+use crate::models::gemini::Message;
+use crate::models::{Health, HealthStatus, Replay, SLOW_RESPONSE};
+
+/// Context window assumed for every model, since servers don't report theirs.
+const CONTEXT_WINDOW: u32 = 32_768;
+
+/// The configured OpenAI-compatible server.
+pub struct Compatible;
+
+impl crate::models::Provider for Compatible {
+    fn complete(&self, history: Arc<Vec<Chat>>, config: Config) -> BoxFuture<'static, Message> {
+        Box::pin(get_compatible_response(history, config))
+    }
+
+    fn replay(&self, body: String, config: Config) -> BoxFuture<'static, Replay> {
+        Box::pin(replay_compatible(body, config))
+    }
+
+    fn model_name(&self, config: &Config) -> String {
+        model_name(&config.compatible)
+    }
+
+    /// Self-hosted models cost nothing per token.
+    fn price_per_million(&self, _config: &Config) -> (f64, f64) {
+        (0.0, 0.0)
+    }
+
+    fn context_limit(&self, _config: &Config) -> u32 {
+        CONTEXT_WINDOW
+    }
+}
+
+/// The configured model, or a label for the server's default.
+pub fn model_name(settings: &CompatibleSettings) -> String {
+    let model = settings.model.trim();
+    if model.is_empty() {
+        "server default".to_string()
+    } else {
+        model.to_string()
+    }
+}
+
+/// The server, or why it can't be reached.
+fn endpoint(settings: &CompatibleSettings) -> Result<Endpoint<'_>, String> {
+    if settings.base_url.trim().is_empty() {
+        return Err("No base URL set for the OpenAI-compatible server".into());
+    }
+    let key = settings.api_key.trim();
+    Ok(Endpoint {
+        base_url: &settings.base_url,
+        key: if key.is_empty() {
+            keyring::get(Slot::Compatible)
+        } else {
+            Some(key.to_string())
+        },
+        headers: &[],
+        organization: "",
+        project: "",
+    })
+}
+
+pub async fn get_compatible_response(history: Arc<Vec<Chat>>, config: Config) -> Message {
+    let endpoint = match endpoint(&config.compatible) {
+        Ok(endpoint) => endpoint,
+        Err(err) => return Message::RequestError(err),
+    };
+    let mut request = convert_to_openai_request(
+        &history,
+        &config,
+        config.compatible.model.trim().to_string(),
+    );
+    request.max_tokens = request.max_completion_tokens;
+    complete(request, &endpoint, &config).await
+}
+
+/// Sends `body` as it is to the server, for replaying an edited request from
+/// the inspector.
+pub async fn replay_compatible(body: String, config: Config) -> Replay {
+    match endpoint(&config.compatible) {
+        Ok(endpoint) => replay(body, &endpoint, &config).await,
+        Err(err) => Replay::failed(Message::RequestError(err)),
+    }
+}
+
+/// Checks that the server answers, and offers the configured model if one is set.
+pub async fn check_health(settings: &CompatibleSettings) -> Health {
+    let model = settings.model.trim();
+    let started = Instant::now();
+    let result = list_models(settings.clone()).await;
+    let latency = started.elapsed();
+
+    let (status, detail) = match result {
+        Err(err) => (HealthStatus::Failing, err),
+        Ok(models) if !model.is_empty() && !models.iter().any(|id| id == model) => (
+            HealthStatus::Degraded,
+            format!("{model} is not offered by the server"),
+        ),
+        Ok(_) if latency > SLOW_RESPONSE => (
+            HealthStatus::Degraded,
+            "The server is up, but slow to respond".to_string(),
+        ),
+        Ok(_) => (HealthStatus::Healthy, "The server is up".to_string()),
+    };
+    Health {
+        provider: "OpenAI-compatible",
+        status,
+        detail,
+        latency: (status != HealthStatus::Failing).then_some(latency),
+    }
+}
+
+/// Lists every model the server offers.
+pub async fn list_models(settings: CompatibleSettings) -> Result<Vec<String>, String> {
+    let endpoint = endpoint(&settings)?;
+    let mut models = fetch_models(&endpoint).await?;
+    models.sort();
+    Ok(models)
+}
@@ -0,0 +1,100 @@
+//! Lenient parsing of chat completions, for servers whose responses stray from
+//! OpenAI's. Fields that fail to parse are dropped instead of failing the whole
+//! response, and they are reported together with the fields the applet doesn't know.
+
+use serde_json::Value;
+
+use super::{ChatResponse, Choice, ResponseMessage};
+use crate::models::lenient::{field, join, unknown_fields};
+
+const RESPONSE_FIELDS: &[&str] = &[
+    "id",
+    "object",
+    "created",
+    "model",
+    "choices",
+    "usage",
+    "system_fingerprint",
+    "service_tier",
+    "error",
+];
+
+const CHOICE_FIELDS: &[&str] = &["index", "message", "finish_reason", "logprobs"];
+
+const MESSAGE_FIELDS: &[&str] = &["role", "content", "refusal", "annotations", "tool_calls"];
+
+/// Parses what it can of `raw`, with a line per problem found. Fails only if
+/// `raw` isn't a JSON object.
+pub fn parse(raw: &str) -> Result<(ChatResponse, Vec<String>), serde_json::Error> {
+    let Value::Object(object) = serde_json::from_str::<Value>(raw)? else {
+        return Err(serde::de::Error::custom("expected a JSON object"));
+    };
+    let mut issues = Vec::new();
+    unknown_fields(&object, RESPONSE_FIELDS, "", &mut issues);
+
+    let choices = match object.get("choices") {
+        Some(Value::Array(choices)) => choices
+            .iter()
+            .enumerate()
+            .filter_map(|(index, value)| choice(value, &format!("choices[{index}]"), &mut issues))
+            .collect(),
+        None | Some(Value::Null) => Vec::new(),
+        Some(_) => {
+            issues.push("choices: expected a list".into());
+            Vec::new()
+        }
+    };
+    let response = ChatResponse {
+        model: field(&object, "model", "", &mut issues),
+        choices,
+        usage: field(&object, "usage", "", &mut issues),
+        error: field(&object, "error", "", &mut issues),
+    };
+    Ok((response, issues))
+}
+
+fn choice(value: &Value, path: &str, issues: &mut Vec<String>) -> Option<Choice> {
+    let Value::Object(object) = value else {
+        issues.push(format!("{path}: expected an object"));
+        return None;
+    };
+    unknown_fields(object, CHOICE_FIELDS, path, issues);
+
+    let message = match object.get("message") {
+        Some(Value::Object(message)) => {
+            let path = join(path, "message");
+            unknown_fields(message, MESSAGE_FIELDS, &path, issues);
+            ResponseMessage {
+                content: field(message, "content", &path, issues),
+                refusal: field(message, "refusal", &path, issues),
+            }
+        }
+        _ => ResponseMessage::default(),
+    };
+    Some(Choice {
+        message,
+        finish_reason: field(object, "finish_reason", path, issues),
+        logprobs: field(object, "logprobs", path, issues),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_that_dont_parse_are_skipped_and_reported() {
+        let raw = r#"{
+            "model": 4,
+            "choices": [{"message": {"content": "Hi", "reasoning": "..."}, "finish_reason": "stop"}],
+            "timings": {}
+        }"#;
+        let (response, issues) = parse(raw).unwrap();
+        assert_eq!(response.model, None);
+        assert_eq!(response.choices[0].message.content.as_deref(), Some("Hi"));
+        assert_eq!(response.choices[0].finish_reason.as_deref(), Some("stop"));
+        assert_eq!(issues.len(), 3);
+        assert!(issues.contains(&"timings: unknown field".to_string()));
+        assert!(issues.contains(&"choices[0].message.reasoning: unknown field".to_string()));
+    }
+}
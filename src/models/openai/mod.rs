@@ -0,0 +1,590 @@
+//! OpenAI's chat completions API, also spoken by the servers in [`compatible`].
+//!
+//! Text attachments are inlined into the prompt, images and PDFs are sent as
+//! content parts, and other files are left out.
+
+pub mod compatible;
+mod lenient;
+
+use base64::Engine;
+use futures_util::future::BoxFuture;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::app::Chat;
+use crate::config::{Config, HttpHeader, ProviderSettings};
+use crate::credentials;
+use crate::models::gemini::{Message, Reply, TokenLogprob, Usage};
+use crate::models::{self, Health, HealthStatus, Replay, SLOW_RESPONSE};
+
+/// Environment variable holding the API key.
+pub const API_KEY_VAR: &str = "OPENAI_API_KEY";
+
+/// Base URL of the API.
+const API_URL: &str = "https://api.openai.com/v1";
+
+/// Model used when none is configured.
+pub const DEFAULT_MODEL: &str = "gpt-4o-mini";
+
+/// Models offered by the model picker until the API has listed them.
+pub const KNOWN_MODELS: [&str; 4] = [DEFAULT_MODEL, "gpt-4o", "gpt-4.1-mini", "gpt-4.1"];
+
+/// Page where users can create an API key.
+pub const API_KEY_URL: &str = "https://platform.openai.com/api-keys";
+
+/// Most alternatives the API reports per token.
+const MAX_TOP_LOGPROBS: u32 = 20;
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    /// Empty for a server's default model.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    model: String,
+    messages: Vec<ChatMessage<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_completion_tokens: Option<u32>,
+    /// What `max_completion_tokens` replaced, still the one many servers read.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    logprobs: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_logprobs: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct ChatMessage<'a> {
+    role: &'a str,
+    content: Content<'a>,
+}
+
+/// Plain text, or parts when there are files the model reads itself.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Content<'a> {
+    Text(Cow<'a, str>),
+    Parts(Vec<ContentPart<'a>>),
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentPart<'a> {
+    Text { text: Cow<'a, str> },
+    ImageUrl { image_url: ImageUrl },
+    File { file: File<'a> },
+}
+
+#[derive(Serialize)]
+struct ImageUrl {
+    url: String,
+}
+
+#[derive(Serialize)]
+struct File<'a> {
+    filename: &'a str,
+    file_data: String,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    /// The dated snapshot that answered, e.g. `gpt-4o-mini-2024-07-18`.
+    model: Option<String>,
+    #[serde(default)]
+    choices: Vec<Choice>,
+    usage: Option<ResponseUsage>,
+    error: Option<ApiError>,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: ResponseMessage,
+    finish_reason: Option<String>,
+    logprobs: Option<Logprobs>,
+}
+
+#[derive(Deserialize)]
+struct ResponseUsage {
+    #[serde(default)]
+    prompt_tokens: u32,
+    #[serde(default)]
+    completion_tokens: u32,
+    prompt_tokens_details: Option<PromptTokensDetails>,
+}
+
+#[derive(Deserialize)]
+struct PromptTokensDetails {
+    #[serde(default)]
+    cached_tokens: u32,
+}
+
+#[derive(Default, Deserialize)]
+struct ResponseMessage {
+    content: Option<String>,
+    /// Set instead of the content when the model declines to answer.
+    refusal: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Logprobs {
+    #[serde(default)]
+    content: Vec<LogprobToken>,
+}
+
+#[derive(Deserialize)]
+struct LogprobToken {
+    token: String,
+    logprob: f64,
+    #[serde(default)]
+    top_logprobs: Vec<TopLogprob>,
+}
+
+#[derive(Deserialize)]
+struct TopLogprob {
+    token: String,
+    logprob: f64,
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    #[serde(default)]
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+/// The OpenAI API.
+pub struct OpenAi;
+
+impl super::Provider for OpenAi {
+    fn complete(&self, history: Arc<Vec<Chat>>, config: Config) -> BoxFuture<'static, Message> {
+        Box::pin(get_openai_response(history, config))
+    }
+
+    fn replay(&self, body: String, config: Config) -> BoxFuture<'static, Replay> {
+        Box::pin(replay_openai(body, config))
+    }
+
+    fn model_name(&self, config: &Config) -> String {
+        model_name(&config.openai)
+    }
+
+    fn price_per_million(&self, config: &Config) -> (f64, f64) {
+        price_per_million(&model_name(&config.openai))
+    }
+
+    fn context_limit(&self, config: &Config) -> u32 {
+        context_window(&model_name(&config.openai))
+    }
+
+    fn api_key_var(&self) -> Option<&'static str> {
+        Some(API_KEY_VAR)
+    }
+
+    fn api_key_url(&self) -> Option<&'static str> {
+        Some(API_KEY_URL)
+    }
+}
+
+/// The configured model, e.g. `gpt-4o-mini`.
+pub fn model_name(settings: &ProviderSettings) -> String {
+    let model = settings.model.trim();
+    if model.is_empty() {
+        DEFAULT_MODEL.to_string()
+    } else {
+        model.to_string()
+    }
+}
+
+/// Context window of `model` in tokens, by model family.
+pub fn context_window(model: &str) -> u32 {
+    if model.starts_with("gpt-4.1") {
+        1_047_576
+    } else if model.starts_with("gpt-5") {
+        400_000
+    } else if model.starts_with('o') {
+        200_000
+    } else {
+        128_000
+    }
+}
+
+/// Approximate list prices of `model` in US dollars per million input and
+/// output tokens, for cost estimates.
+pub fn price_per_million(model: &str) -> (f64, f64) {
+    if model.starts_with("gpt-5-nano") {
+        (0.05, 0.40)
+    } else if model.starts_with("gpt-5-mini") {
+        (0.25, 2.0)
+    } else if model.starts_with("gpt-5") {
+        (1.25, 10.0)
+    } else if model.starts_with("gpt-4.1-nano") {
+        (0.10, 0.40)
+    } else if model.starts_with("gpt-4.1-mini") {
+        (0.40, 1.60)
+    } else if model.starts_with("gpt-4.1") || model.starts_with("o3") {
+        (2.0, 8.0)
+    } else if model.starts_with("o4-mini") {
+        (1.10, 4.40)
+    } else if model.starts_with("gpt-4o-mini") {
+        (0.15, 0.60)
+    } else {
+        (2.50, 10.0)
+    }
+}
+
+/// Checks the key by listing the models, and that the configured one is among them.
+pub async fn check_health(settings: &ProviderSettings) -> Health {
+    let model = model_name(settings);
+    let started = Instant::now();
+    let result = list_models(settings.clone()).await;
+    let latency = started.elapsed();
+
+    let (status, detail) = match result {
+        Err(err) => (HealthStatus::Failing, err),
+        Ok(models) if !models.contains(&model) => (
+            HealthStatus::Degraded,
+            format!("{model} is not offered to this key"),
+        ),
+        Ok(_) if latency > SLOW_RESPONSE => (
+            HealthStatus::Degraded,
+            format!("{model} is available, but slow to respond"),
+        ),
+        Ok(_) => (HealthStatus::Healthy, format!("{model} is available")),
+    };
+    Health {
+        provider: "OpenAI",
+        status,
+        detail,
+        latency: (status != HealthStatus::Failing).then_some(latency),
+    }
+}
+
+/// Lists the chat models offered to the key, leaving out embedding, speech and
+/// image models.
+pub async fn list_models(settings: ProviderSettings) -> Result<Vec<String>, String> {
+    let Some(key) = settings.api_key(API_KEY_VAR) else {
+        return Err("API key not set".into());
+    };
+    let endpoint = Endpoint {
+        base_url: API_URL,
+        key: Some(key),
+        headers: &settings.headers,
+        organization: &settings.organization,
+        project: &settings.project,
+    };
+
+    let mut models: Vec<String> = fetch_models(&endpoint)
+        .await?
+        .into_iter()
+        .filter(|id| {
+            (id.starts_with("gpt-") || id.starts_with("chatgpt-") || id.starts_with('o'))
+                && ![
+                    "audio",
+                    "realtime",
+                    "transcribe",
+                    "tts",
+                    "image",
+                    "search",
+                    "moderation",
+                ]
+                .iter()
+                .any(|kind| id.contains(kind))
+        })
+        .collect();
+    models.sort();
+    Ok(models)
+}
+
+/// Where requests go and how they are authorized.
+struct Endpoint<'a> {
+    base_url: &'a str,
+    /// Bearer token, `None` for servers that don't check one.
+    key: Option<String>,
+    headers: &'a [HttpHeader],
+    /// Organization and project IDs, empty for the key's defaults.
+    organization: &'a str,
+    project: &'a str,
+}
+
+impl Endpoint<'_> {
+    /// A request to `path` under the base URL, authorized and with the custom headers.
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let url = format!("{}/{path}", self.base_url.trim().trim_end_matches('/'));
+        let mut request = Client::new().request(method, url);
+        if let Some(key) = &self.key {
+            request = request.bearer_auth(key);
+        }
+        if !self.organization.trim().is_empty() {
+            request = request.header("OpenAI-Organization", self.organization.trim());
+        }
+        if !self.project.trim().is_empty() {
+            request = request.header("OpenAI-Project", self.project.trim());
+        }
+        for header in self.headers.iter().filter(|header| !header.name.is_empty()) {
+            request = request.header(header.name.as_str(), header.value.as_str());
+        }
+        request
+    }
+}
+
+/// The OpenAI API with the configured key.
+async fn openai_endpoint(config: &Config) -> Result<Endpoint<'_>, Message> {
+    match config.openai.api_key(API_KEY_VAR) {
+        Some(key) => Ok(Endpoint {
+            base_url: API_URL,
+            key: Some(key),
+            headers: &config.openai.headers,
+            organization: &config.openai.organization,
+            project: &config.openai.project,
+        }),
+        None => Err(Message::ApiKeyNotSet {
+            in_login_shell: credentials::is_set_in_login_shell(API_KEY_VAR).await,
+        }),
+    }
+}
+
+/// Ids of every model the endpoint offers.
+async fn fetch_models(endpoint: &Endpoint<'_>) -> Result<Vec<String>, String> {
+    let list: ModelList = endpoint
+        .request(reqwest::Method::GET, "models")
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| err.to_string())?
+        .json()
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(list.data.into_iter().map(|model| model.id).collect())
+}
+
+fn convert_to_openai_request<'a>(
+    history: &'a [Chat],
+    config: &'a Config,
+    model: String,
+) -> ChatRequest<'a> {
+    let generation = config.generation();
+    let system_instruction = config.system_instruction();
+    ChatRequest {
+        model,
+        messages: system_instruction
+            .map(|instruction| ChatMessage {
+                role: "system",
+                content: Content::Text(Cow::Owned(instruction)),
+            })
+            .into_iter()
+            .chain(history.iter().map(|chat| ChatMessage {
+                role: if chat.role == "model" {
+                    "assistant"
+                } else {
+                    "user"
+                },
+                content: content(chat),
+            }))
+            .collect(),
+        temperature: generation.temperature,
+        top_p: generation.top_p,
+        max_completion_tokens: generation.max_output_tokens,
+        max_tokens: None,
+        presence_penalty: generation.presence_penalty,
+        frequency_penalty: generation.frequency_penalty,
+        logprobs: generation.logprobs.is_some(),
+        top_logprobs: generation
+            .logprobs
+            .filter(|count| *count > 0)
+            .map(|count| count.min(MAX_TOP_LOGPROBS)),
+    }
+}
+
+/// The message with text attachments inlined, and images and PDFs as parts.
+fn content(chat: &Chat) -> Content<'_> {
+    let mut text = String::new();
+    let mut parts = Vec::new();
+    for attachment in &chat.attachments {
+        if let Some(contents) = attachment.text() {
+            text.push_str(&format!("{}:\n```\n{contents}\n```\n\n", attachment.name));
+            continue;
+        }
+        let data_url = format!(
+            "data:{};base64,{}",
+            attachment.mime_type,
+            base64::engine::general_purpose::STANDARD.encode(&attachment.data)
+        );
+        if attachment.mime_type.starts_with("image/") {
+            parts.push(ContentPart::ImageUrl {
+                image_url: ImageUrl { url: data_url },
+            });
+        } else if attachment.mime_type == "application/pdf" {
+            parts.push(ContentPart::File {
+                file: File {
+                    filename: &attachment.name,
+                    file_data: data_url,
+                },
+            });
+        }
+    }
+
+    let text = if text.is_empty() {
+        Cow::Borrowed(chat.content.as_str())
+    } else {
+        text.push_str(&chat.content);
+        Cow::Owned(text)
+    };
+    if parts.is_empty() {
+        return Content::Text(text);
+    }
+    if !text.is_empty() {
+        parts.push(ContentPart::Text { text });
+    }
+    Content::Parts(parts)
+}
+
+pub async fn get_openai_response(history: Arc<Vec<Chat>>, config: Config) -> Message {
+    let endpoint = match openai_endpoint(&config).await {
+        Ok(endpoint) => endpoint,
+        Err(message) => return message,
+    };
+    let request = convert_to_openai_request(&history, &config, model_name(&config.openai));
+    complete(request, &endpoint, &config).await
+}
+
+/// Sends `request` to the endpoint's chat completions.
+async fn complete(request: ChatRequest<'_>, endpoint: &Endpoint<'_>, config: &Config) -> Message {
+    let raw_request = match serde_json::to_string(&request) {
+        Ok(body) => body,
+        Err(err) => return Message::RequestError(err.to_string()),
+    };
+
+    let raw_response = match send(raw_request.clone(), endpoint).await {
+        Ok(text) => text,
+        Err(message) => return message,
+    };
+    into_message(raw_request, raw_response, config)
+}
+
+/// Sends `body` as it is to the chat completions endpoint and parses the
+/// response like an answer, for replaying an edited request from the inspector.
+pub async fn replay_openai(body: String, config: Config) -> Replay {
+    match openai_endpoint(&config).await {
+        Ok(endpoint) => replay(body, &endpoint, &config).await,
+        Err(outcome) => Replay::failed(outcome),
+    }
+}
+
+async fn replay(body: String, endpoint: &Endpoint<'_>, config: &Config) -> Replay {
+    match send(body.clone(), endpoint).await {
+        Ok(raw_response) => Replay {
+            outcome: into_message(body, raw_response.clone(), config),
+            raw_response,
+        },
+        Err(outcome) => Replay::failed(outcome),
+    }
+}
+
+/// Posts a request body, returning the response text. Error statuses come back
+/// as text too, since their body explains them.
+async fn send(body: String, endpoint: &Endpoint<'_>) -> Result<String, Message> {
+    let size = body.len();
+    let response = endpoint
+        .request(reqwest::Method::POST, "chat/completions")
+        .header("Content-Type", "application/json")
+        .body(body)
+        .send()
+        .await
+        .map_err(|err| Message::RequestError(err.to_string()))?;
+    if response.status() == reqwest::StatusCode::PAYLOAD_TOO_LARGE {
+        // The server doesn't say what its limit is.
+        return Err(Message::RequestTooLarge { size, limit: None });
+    }
+    response
+        .text()
+        .await
+        .map_err(|err| Message::RequestError(err.to_string()))
+}
+
+/// Turns a response into the answer, or into why there is none.
+fn into_message(raw_request: String, raw_response: String, config: &Config) -> Message {
+    let (response, parse_issues) = match parse_response(&raw_response, config) {
+        Ok(parsed) => parsed,
+        Err(message) => return message,
+    };
+    if let Some(error) = response.error {
+        return Message::ApiError(error.message);
+    }
+
+    let usage = response.usage.map(|usage| Usage {
+        prompt_tokens: usage.prompt_tokens,
+        cached_tokens: usage
+            .prompt_tokens_details
+            .map_or(0, |details| details.cached_tokens),
+        completion_tokens: usage.completion_tokens,
+    });
+    let Some(choice) = response.choices.into_iter().next() else {
+        return Message::EmptyResponse;
+    };
+    if let Some(refusal) = choice.message.refusal {
+        return Message::PromptBlocked(refusal);
+    }
+    match choice.message.content {
+        Some(text) if !text.is_empty() => Message::Response(Reply {
+            text,
+            logprobs: choice.logprobs.map(token_logprobs).unwrap_or_default(),
+            usage,
+            finish_reason: choice.finish_reason,
+            model_version: response.model,
+            raw_request,
+            raw_response,
+            parse_issues,
+            ..Reply::default()
+        }),
+        _ if choice.finish_reason.as_deref() == Some("content_filter") => {
+            Message::PromptBlocked("OpenAI's content filter".into())
+        }
+        _ => Message::EmptyResponse,
+    }
+}
+
+/// Parses a response, or in lenient mode what can be parsed of it along with
+/// the fields that couldn't be or aren't known.
+fn parse_response(raw: &str, config: &Config) -> Result<(ChatResponse, Vec<String>), Message> {
+    let parsed = if config.lenient_parsing {
+        lenient::parse(raw)
+    } else {
+        serde_json::from_str(raw).map(|response| (response, Vec::new()))
+    };
+    parsed.map_err(|err| models::parse_error(err, raw, config))
+}
+
+fn token_logprobs(logprobs: Logprobs) -> Vec<TokenLogprob> {
+    logprobs
+        .content
+        .into_iter()
+        .map(|token| TokenLogprob {
+            token: token.token,
+            log_probability: token.logprob,
+            alternatives: token
+                .top_logprobs
+                .into_iter()
+                .map(|alternative| (alternative.token, alternative.logprob))
+                .collect(),
+        })
+        .collect()
+}
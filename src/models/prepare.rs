@@ -0,0 +1,66 @@
+//! Turns the conversation as shown into the history a provider is sent.
+//!
+//! The chat keeps everything it displays, including failures reported in place
+//! of answers and messages left empty. Providers expect turns alternating
+//! between the user and the model, so those are left out and what remains is
+//! tidied up.
+
+use crate::app::Chat;
+
+/// The history to send for `chats`: failures and empty messages are dropped,
+/// line endings and trailing whitespace are normalized, and consecutive
+/// messages of the same role are joined into one turn.
+pub fn history(chats: &[Chat]) -> Vec<Chat> {
+    let mut history: Vec<Chat> = Vec::with_capacity(chats.len());
+    for chat in chats.iter().filter(|chat| !chat.error) {
+        let Some(role) = role(&chat.role) else {
+            eprintln!("leaving out a message with unknown role {:?}", chat.role);
+            continue;
+        };
+        let content = normalize(&chat.content);
+        if content.is_empty() && chat.attachments.is_empty() {
+            continue;
+        }
+
+        match history.last_mut() {
+            Some(last) if last.role == role => {
+                if !content.is_empty() {
+                    if !last.content.is_empty() {
+                        last.content.push_str("\n\n");
+                    }
+                    last.content.push_str(&content);
+                }
+                last.attachments.extend(chat.attachments.iter().cloned());
+            }
+            _ => history.push(Chat {
+                role: role.to_string(),
+                content,
+                attachments: chat.attachments.clone(),
+                ..Chat::default()
+            }),
+        }
+    }
+    history
+}
+
+/// The role as providers name it, `None` for roles no provider knows.
+fn role(role: &str) -> Option<&'static str> {
+    match role {
+        "user" => Some("user"),
+        "model" | "assistant" => Some("model"),
+        _ => None,
+    }
+}
+
+/// `content` with Unix line endings and without trailing whitespace, on each
+/// line and at the end.
+fn normalize(content: &str) -> String {
+    content
+        .replace("\r\n", "\n")
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_end()
+        .to_string()
+}
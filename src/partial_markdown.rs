@@ -0,0 +1,175 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Closes the Markdown left open in an answer that is still streaming in.
+//!
+//! A half-received code block would otherwise turn everything after its opening
+//! fence into code, and a lone `**` everything after it bold, until the closing
+//! marker arrives.
+
+use std::borrow::Cow;
+
+/// Inline markers that wrap text until the same marker comes again.
+const EMPHASIS: [&str; 3] = ["**", "*", "~~"];
+
+/// `text` with unclosed code fences, code spans and emphasis closed. Markers
+/// with nothing after them yet are dropped instead.
+pub fn close(text: &str) -> Cow<'_, str> {
+    let mut fence: Option<(char, usize)> = None;
+    // Start of the paragraph being received, outside of code blocks.
+    let mut paragraph = 0;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let marker = fence_marker(line);
+        match (fence, marker) {
+            (Some((open, length)), Some((marker, count)))
+                if marker == open
+                    && count >= length
+                    && line.trim_start_matches(' ')[count..].trim().is_empty() =>
+            {
+                fence = None;
+            }
+            (None, Some(marker)) => fence = Some(marker),
+            _ => {}
+        }
+        offset += line.len();
+        if fence.is_some() || marker.is_some() || line.trim().is_empty() {
+            paragraph = offset;
+        }
+    }
+
+    if let Some((marker, length)) = fence {
+        let newline = if text.ends_with('\n') { "" } else { "\n" };
+        let closing = marker.to_string().repeat(length);
+        return Cow::Owned(format!("{text}{newline}{closing}"));
+    }
+
+    let mut end = text.trim_end().len().max(paragraph);
+    let mut open = open_markers(&text[paragraph..end]);
+    // A marker that nothing follows yet would only close onto itself.
+    while let Some((start, marker)) = open.last()
+        && paragraph + start + marker.len() >= end
+    {
+        end = paragraph + start;
+        open.pop();
+    }
+    if open.is_empty() && end == text.trim_end().len() {
+        return Cow::Borrowed(text);
+    }
+
+    let mut closed = text[..end].trim_end().to_string();
+    for (_, marker) in open.iter().rev() {
+        closed.push_str(marker);
+    }
+    Cow::Owned(closed)
+}
+
+/// The character and length of the fence `line` opens or closes, if it is one.
+fn fence_marker(line: &str) -> Option<(char, usize)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let marker = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let length = trimmed.chars().take_while(|c| *c == marker).count();
+    (length >= 3).then_some((marker, length))
+}
+
+/// Code spans and emphasis opened in `paragraph` and not closed, with where
+/// each starts, innermost last.
+fn open_markers(paragraph: &str) -> Vec<(usize, String)> {
+    let mut open: Vec<(usize, String)> = Vec::new();
+    let bytes = paragraph.as_bytes();
+    let mut index = 0;
+    while index < bytes.len() {
+        let byte = bytes[index];
+        let in_code = open
+            .last()
+            .is_some_and(|(_, marker)| marker.starts_with('`'));
+
+        if byte == b'\\' && !in_code {
+            index += 2;
+            continue;
+        }
+        if byte == b'`' {
+            let run = bytes[index..].iter().take_while(|b| **b == b'`').count();
+            match open.last() {
+                Some((_, marker)) if in_code && marker.len() == run => {
+                    open.pop();
+                }
+                _ if !in_code => open.push((index, "`".repeat(run))),
+                _ => {}
+            }
+            index += run;
+            continue;
+        }
+        if in_code || (byte != b'*' && byte != b'~') {
+            index += 1;
+            continue;
+        }
+
+        let run = bytes[index..].iter().take_while(|b| **b == byte).count();
+        let before = paragraph[..index].chars().next_back();
+        let after = paragraph[index + run..].chars().next();
+        let at_line_start = paragraph[..index]
+            .rsplit('\n')
+            .next()
+            .is_some_and(|line| line.trim().is_empty());
+        // Bullets and markers surrounded by spaces, like `2 * 3`, aren't emphasis.
+        let spaced =
+            after.is_none_or(char::is_whitespace) && before.is_none_or(char::is_whitespace);
+        let bullet =
+            at_line_start && byte == b'*' && run == 1 && after.is_some_and(char::is_whitespace);
+        if !spaced && !bullet {
+            let mut rest = run;
+            let mut start = index;
+            while rest > 0 {
+                let Some(marker) = EMPHASIS
+                    .into_iter()
+                    .find(|marker| marker.as_bytes()[0] == byte && marker.len() <= rest)
+                else {
+                    break;
+                };
+                match open.iter().rposition(|(_, open)| open == marker) {
+                    Some(position) => {
+                        open.remove(position);
+                    }
+                    None => open.push((start, marker.to_string())),
+                }
+                start += marker.len();
+                rest -= marker.len();
+            }
+        }
+        index += run;
+    }
+    open
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn balanced_text_is_unchanged() {
+        assert!(matches!(close("a **b** and `c`"), Cow::Borrowed(_)));
+        assert!(matches!(close("2 * 3 = 6"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn open_emphasis_and_code_spans_are_closed() {
+        assert_eq!(close("Some **bold"), "Some **bold**");
+        assert_eq!(close("Run `cargo"), "Run `cargo`");
+        assert_eq!(close("*a **b"), "*a **b***");
+    }
+
+    #[test]
+    fn open_code_blocks_are_closed() {
+        assert_eq!(close("```rust\nfn main"), "```rust\nfn main\n```");
+        assert_eq!(close("~~~~\ncode\n"), "~~~~\ncode\n~~~~");
+    }
+
+    #[test]
+    fn markers_with_nothing_after_them_are_dropped() {
+        assert_eq!(close("Hello world**"), "Hello world");
+        assert_eq!(close("* item"), "* item");
+    }
+}
@@ -0,0 +1,136 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Sharing conversations through a paste service or a GitHub gist.
+
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::app::Chat;
+use crate::config::{Config, ShareSettings, ShareTarget};
+use crate::credentials;
+use crate::keyring;
+use crate::models::local::download::HF_TOKEN_VAR;
+use crate::models::{gemini, openai};
+use crate::redact;
+use crate::sync;
+
+/// Environment variable holding the token used to create gists.
+pub const GITHUB_TOKEN_VAR: &str = "GITHUB_TOKEN";
+
+const GISTS_URL: &str = "https://api.github.com/gists";
+
+/// Shorter values are too likely to appear in ordinary text to be masked.
+const MIN_SECRET_LEN: usize = 8;
+
+#[derive(Deserialize)]
+struct Gist {
+    html_url: String,
+}
+
+/// The conversation as Markdown, one section per message.
+pub fn markdown(history: &[Chat]) -> String {
+    history
+        .iter()
+        .map(|chat| {
+            let speaker = if chat.role == "user" { "You" } else { "Model" };
+            format!("**{speaker}:**\n\n{}\n", chat.content)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Masks the credentials the applet knows about, and whatever the redaction
+/// settings detect in prompts, without asking.
+pub fn redact(text: &str, config: &Config) -> String {
+    let mut secrets: Vec<String> = config
+        .gemini
+        .headers
+        .iter()
+        .chain(&config.openai.headers)
+        .filter(|header| header.secret)
+        .map(|header| header.value.clone())
+        .collect();
+    secrets.extend(
+        [
+            gemini::API_KEY_VAR,
+            gemini::ACCESS_TOKEN_VAR,
+            openai::API_KEY_VAR,
+            HF_TOKEN_VAR,
+            GITHUB_TOKEN_VAR,
+            sync::SECRET_VAR,
+            sync::PASSPHRASE_VAR,
+        ]
+        .into_iter()
+        .filter_map(credentials::lookup),
+    );
+    secrets.extend([
+        config.gemini.api_key.clone(),
+        config.openai.api_key.clone(),
+        config.compatible.api_key.clone(),
+    ]);
+    secrets.extend(keyring::Slot::ALL.into_iter().filter_map(keyring::get));
+
+    let mut text = text.to_string();
+    for secret in secrets.iter().map(|secret| secret.trim()) {
+        if secret.len() >= MIN_SECRET_LEN {
+            text = text.replace(secret, "[redacted]");
+        }
+    }
+    let findings = redact::scan(&text, &config.redaction);
+    redact::mask(&text, &findings)
+}
+
+/// Human readable destination of an upload.
+pub fn destination(settings: &ShareSettings) -> String {
+    match settings.target {
+        ShareTarget::Paste => settings.paste_url.trim().to_string(),
+        ShareTarget::Gist => "a secret GitHub gist".to_string(),
+    }
+}
+
+/// Uploads `markdown` and returns its link.
+pub async fn upload(markdown: String, settings: ShareSettings) -> Result<String, String> {
+    let client = reqwest::Client::new();
+
+    match settings.target {
+        ShareTarget::Paste => {
+            let url = settings.paste_url.trim();
+            if url.is_empty() {
+                return Err("Set a paste endpoint on the settings page".into());
+            }
+            let link = client
+                .post(url)
+                .body(markdown)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|err| err.to_string())?
+                .text()
+                .await
+                .map_err(|err| err.to_string())?;
+            Ok(link.trim().to_string())
+        }
+        ShareTarget::Gist => {
+            let token = credentials::lookup(GITHUB_TOKEN_VAR)
+                .ok_or(format!("{GITHUB_TOKEN_VAR} is not set"))?;
+            let gist: Gist = client
+                .post(GISTS_URL)
+                .bearer_auth(token)
+                .header(reqwest::header::USER_AGENT, env!("CARGO_PKG_NAME"))
+                .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+                .json(&json!({
+                    "description": "Shared conversation",
+                    "public": false,
+                    "files": { "conversation.md": { "content": markdown } },
+                }))
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|err| err.to_string())?
+                .json()
+                .await
+                .map_err(|err| err.to_string())?;
+            Ok(gist.html_url)
+        }
+    }
+}
@@ -0,0 +1,277 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Renders a conversation to a paginated A4 PDF.
+//!
+//! Text is set in the system's sans-serif and monospace fonts, found through
+//! fontconfig and embedded. Without them only the standard PDF fonts are left,
+//! which cover Latin-1 alone, so conversations in other scripts are refused
+//! rather than written as garbage. Code blocks keep their line breaks in a
+//! monospace font, and images saved next to answers are embedded.
+
+use cosmic::iced::widget::markdown::Url;
+use printpdf::{
+    BuiltinFont, Image, ImageTransform, IndirectFontRef, Mm, PdfDocument, PdfDocumentReference,
+    PdfLayerReference,
+};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::app::Chat;
+
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 20.0;
+
+/// Characters per line that fit between the margins, per font.
+const TEXT_COLUMNS: usize = 95;
+const CODE_COLUMNS: usize = 88;
+
+const MAX_IMAGE_HEIGHT: f32 = 120.0;
+
+/// Writes `history` to `path`.
+pub fn export(history: &[Chat], path: &Path) -> Result<(), String> {
+    let (doc, page, layer) =
+        PdfDocument::new("Conversation", Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+    let (text, bold, code, unicode) = match unicode_fonts(&doc) {
+        Some((text, bold, code)) => (text, bold, code, true),
+        None => {
+            if !history
+                .iter()
+                .all(|chat| chat.content.chars().all(is_latin1))
+            {
+                return Err(
+                    "the conversation has characters the standard PDF fonts can't \
+                            show, and no TrueType sans-serif and monospace fonts were found \
+                            to embed"
+                        .into(),
+                );
+            }
+            let font = |font| doc.add_builtin_font(font).map_err(|err| err.to_string());
+            (
+                font(BuiltinFont::Helvetica)?,
+                font(BuiltinFont::HelveticaBold)?,
+                font(BuiltinFont::Courier)?,
+                false,
+            )
+        }
+    };
+    let mut writer = Writer {
+        layer: doc.get_page(page).get_layer(layer),
+        text,
+        bold,
+        code,
+        unicode,
+        doc: &doc,
+        y: PAGE_HEIGHT - MARGIN,
+    };
+
+    for chat in history {
+        let speaker = if chat.role == "user" { "You" } else { "Model" };
+        writer.space(4.0);
+        writer.line(speaker, Style::Heading);
+
+        let mut in_code = false;
+        for line in chat.content.lines() {
+            if line.trim_start().starts_with("```") {
+                in_code = !in_code;
+                writer.space(1.0);
+            } else if in_code {
+                for chunk in wrap_chars(line, CODE_COLUMNS) {
+                    writer.line(&chunk, Style::Code);
+                }
+            } else if let Some(image) = image_path(line) {
+                writer.image(&image);
+            } else if line.trim().is_empty() {
+                writer.space(2.5);
+            } else {
+                for chunk in wrap_words(line, TEXT_COLUMNS) {
+                    writer.line(&chunk, Style::Text);
+                }
+            }
+        }
+    }
+
+    doc.save(&mut BufWriter::new(
+        File::create(path).map_err(|err| err.to_string())?,
+    ))
+    .map_err(|err| err.to_string())
+}
+
+#[derive(Clone, Copy)]
+enum Style {
+    Heading,
+    Text,
+    Code,
+}
+
+struct Writer<'a> {
+    doc: &'a PdfDocumentReference,
+    layer: PdfLayerReference,
+    text: IndirectFontRef,
+    bold: IndirectFontRef,
+    code: IndirectFontRef,
+    /// Whether the fonts are embedded ones that go beyond Latin-1.
+    unicode: bool,
+    /// Baseline of the next line, from the bottom of the page.
+    y: f32,
+}
+
+impl Writer<'_> {
+    fn line(&mut self, line: &str, style: Style) {
+        let (font, size, height, indent) = match style {
+            Style::Heading => (&self.bold, 11.0, 6.0, 0.0),
+            Style::Text => (&self.text, 10.0, 5.0, 0.0),
+            Style::Code => (&self.code, 9.0, 4.2, 4.0),
+        };
+        self.reserve(height);
+        self.y -= height;
+        let line = if self.unicode {
+            line.replace(char::is_control, "")
+        } else {
+            latin1(line)
+        };
+        self.layer
+            .use_text(line, size, Mm(MARGIN + indent), Mm(self.y), font);
+    }
+
+    fn space(&mut self, height: f32) {
+        self.y -= height;
+    }
+
+    /// Embeds the image at `path`, or notes that it's missing.
+    fn image(&mut self, path: &Path) {
+        let Ok(image) = printpdf::image_crate::open(path) else {
+            self.line(
+                &format!("[image {} not found]", path.display()),
+                Style::Text,
+            );
+            return;
+        };
+
+        let (width, height) = (image.width() as f32, image.height() as f32);
+        // Pick the resolution that fits the image between the margins.
+        let max_width = PAGE_WIDTH - 2.0 * MARGIN;
+        let dpi = (width * 25.4 / max_width)
+            .max(height * 25.4 / MAX_IMAGE_HEIGHT)
+            .max(150.0);
+        let height_mm = height * 25.4 / dpi;
+
+        self.reserve(height_mm + 2.0);
+        self.y -= height_mm + 2.0;
+        Image::from_dynamic_image(&image).add_to_layer(
+            self.layer.clone(),
+            ImageTransform {
+                translate_x: Some(Mm(MARGIN)),
+                translate_y: Some(Mm(self.y)),
+                dpi: Some(dpi),
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Starts a new page unless `height` still fits on this one.
+    fn reserve(&mut self, height: f32) {
+        if self.y - height >= MARGIN {
+            return;
+        }
+        let (page, layer) = self
+            .doc
+            .add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+        self.layer = self.doc.get_page(page).get_layer(layer);
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+}
+
+/// Path of a saved image linked as `[name](file:///path)` on its own line.
+fn image_path(line: &str) -> Option<PathBuf> {
+    let line = line.trim();
+    let start = line.find("](file://")? + "](".len();
+    let url = line.strip_suffix(')')?.get(start..)?;
+    Url::parse(url).ok()?.to_file_path().ok()
+}
+
+/// The system's regular, bold and monospace fonts, embedded in `doc`.
+fn unicode_fonts(
+    doc: &PdfDocumentReference,
+) -> Option<(IndirectFontRef, IndirectFontRef, IndirectFontRef)> {
+    let font = |pattern| {
+        let path = system_font(pattern)?;
+        let file = File::open(&path).ok()?;
+        match doc.add_external_font(file) {
+            Ok(font) => Some(font),
+            Err(err) => {
+                eprintln!("failed to embed {}: {err}", path.display());
+                None
+            }
+        }
+    };
+    Some((
+        font("sans-serif")?,
+        font("sans-serif:bold")?,
+        font("monospace")?,
+    ))
+}
+
+/// The TrueType file fontconfig picks for `pattern`, if it picks one.
+fn system_font(pattern: &str) -> Option<PathBuf> {
+    let output = Command::new("fc-match")
+        .args(["--format=%{file}", pattern])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let path = PathBuf::from(String::from_utf8(output.stdout).ok()?.trim());
+    // printpdf only reads TrueType outlines.
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    (extension == "ttf").then_some(path)
+}
+
+fn is_latin1(c: char) -> bool {
+    (c as u32) < 256
+}
+
+/// The standard fonts only cover Latin-1.
+fn latin1(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if is_latin1(c) && !c.is_control() {
+                c
+            } else {
+                '?'
+            }
+        })
+        .collect()
+}
+
+fn wrap_words(line: &str, columns: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in line.split_whitespace() {
+        if !current.is_empty() && current.chars().count() + 1 + word.chars().count() > columns {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+/// Breaks code at a fixed width so indentation is kept.
+fn wrap_chars(line: &str, columns: usize) -> Vec<String> {
+    let chars: Vec<char> = line.replace('\t', "    ").chars().collect();
+    if chars.is_empty() {
+        return vec![String::new()];
+    }
+    chars
+        .chunks(columns)
+        .map(|chunk| chunk.iter().collect())
+        .collect()
+}
@@ -0,0 +1,73 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Answer preferences collected from A/B regenerations.
+//!
+//! Each choice is appended to `preferences.jsonl` in the profile's data directory,
+//! recording which model and preset produced the preferred answer.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const FILE_NAME: &str = "preferences.jsonl";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Choice {
+    /// Source of the preferred answer, see [`crate::app::Chat::generated_by`].
+    winner: String,
+    loser: String,
+    /// Seconds since the Unix epoch.
+    at: u64,
+}
+
+/// How often answers from one model and preset were preferred.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Tally {
+    pub wins: u32,
+    pub losses: u32,
+}
+
+/// Appends a choice to the preferences file in `dir`.
+pub fn record(dir: &Path, winner: &str, loser: &str) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let choice = Choice {
+        winner: winner.to_string(),
+        loser: loser.to_string(),
+        at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+    };
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(FILE_NAME))?;
+    writeln!(file, "{}", serde_json::to_string(&choice)?)
+}
+
+/// Wins and losses per source, best win rate first.
+pub fn summary(dir: &Path) -> Vec<(String, Tally)> {
+    let Ok(contents) = fs::read_to_string(dir.join(FILE_NAME)) else {
+        return Vec::new();
+    };
+
+    let mut tallies: HashMap<String, Tally> = HashMap::new();
+    for choice in contents
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Choice>(line).ok())
+    {
+        tallies.entry(choice.winner).or_default().wins += 1;
+        tallies.entry(choice.loser).or_default().losses += 1;
+    }
+
+    let mut tallies: Vec<_> = tallies.into_iter().collect();
+    tallies.sort_by(|(_, a), (_, b)| {
+        let rate = |tally: &Tally| f64::from(tally.wins) / f64::from(tally.wins + tally.losses);
+        rate(b).total_cmp(&rate(a))
+    });
+    tallies
+}
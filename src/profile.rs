@@ -0,0 +1,74 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Named profiles, so several instances of the applet can live in the panel.
+//!
+//! Each instance is started with `--profile <name>` from its own desktop entry
+//! and keeps its configuration and data apart from the others.
+
+use crate::app::APPID;
+use crate::credentials::CONFIG_DIR_NAME;
+use std::{env, path::PathBuf};
+
+/// The profile an applet instance is bound to.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Profile {
+    /// Profile name, `None` for the default profile.
+    name: Option<String>,
+}
+
+impl Profile {
+    /// Reads the profile from the command line arguments.
+    pub fn from_args() -> Self {
+        let mut args = env::args().skip(1);
+        let mut name = None;
+
+        while let Some(arg) = args.next() {
+            if let Some(value) = arg.strip_prefix("--profile=") {
+                name = Some(value.to_string());
+            } else if arg == "--profile" {
+                name = args.next();
+            }
+        }
+
+        Self::new(name)
+    }
+
+    pub fn new(name: Option<String>) -> Self {
+        // Only keep characters that are safe in config ids and file names.
+        let name = name
+            .map(|name| {
+                name.chars()
+                    .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+                    .collect::<String>()
+            })
+            .filter(|name| !name.is_empty());
+
+        Self { name }
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Id under which cosmic-config stores this profile's configuration.
+    pub fn config_id(&self) -> String {
+        match &self.name {
+            Some(name) => format!("{APPID}.profiles.{name}"),
+            None => APPID.to_string(),
+        }
+    }
+
+    /// Directory for this profile's data, such as chat history.
+    pub fn data_dir(&self) -> Option<PathBuf> {
+        let data_dir = match env::var_os("XDG_DATA_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(env::var_os("HOME")?).join(".local/share"),
+        };
+        let data_dir = data_dir.join(CONFIG_DIR_NAME);
+
+        Some(match &self.name {
+            Some(name) => data_dir.join("profiles").join(name),
+            None => data_dir,
+        })
+    }
+}
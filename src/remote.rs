@@ -0,0 +1,87 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Commands run from a keyboard shortcut, passed on to the applet in the panel.
+//!
+//! The applet listens on a Unix socket in the runtime directory, one per
+//! profile. Starting the program with a command's argument, such as
+//! `--quick-reply`, sends it there and exits.
+
+use std::env;
+use std::io::{self, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncReadExt;
+use tokio::net::UnixListener;
+
+use crate::profile::Profile;
+
+/// Longest command read from a connection, in bytes.
+const MAX_COMMAND: u64 = 64;
+
+/// Something to do in the applet in the panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Opens the popover for a one-shot question.
+    QuickReply,
+}
+
+impl Command {
+    pub const ALL: [Self; 1] = [Self::QuickReply];
+
+    /// Command line argument, and what is sent over the socket.
+    fn arg(self) -> &'static str {
+        match self {
+            Self::QuickReply => "--quick-reply",
+        }
+    }
+
+    /// The command given on the command line, if there is one.
+    pub fn from_args() -> Option<Self> {
+        env::args()
+            .skip(1)
+            .find_map(|arg| Self::ALL.into_iter().find(|command| command.arg() == arg))
+    }
+}
+
+/// Socket the applet of `profile` listens on, if there is a runtime directory.
+pub fn socket_path(profile: &Profile) -> Option<PathBuf> {
+    let dir = env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty())?;
+    Some(PathBuf::from(dir).join(format!("{}.sock", profile.config_id())))
+}
+
+/// Sends `command` to the applet of `profile`.
+pub fn send(profile: &Profile, command: Command) -> io::Result<()> {
+    let path = socket_path(profile)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "XDG_RUNTIME_DIR isn't set"))?;
+    UnixStream::connect(path)?.write_all(command.arg().as_bytes())
+}
+
+/// Starts listening on `path`, replacing the socket an earlier run left behind.
+pub fn listen(path: &Path) -> io::Result<UnixListener> {
+    match std::fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+        _ => {}
+    }
+    UnixListener::bind(path)
+}
+
+/// Waits for the next command sent to `listener`. Connections sending
+/// something else are ignored.
+pub async fn next(listener: &UnixListener) -> io::Result<Command> {
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let mut received = String::new();
+        if let Err(err) = stream.take(MAX_COMMAND).read_to_string(&mut received).await {
+            eprintln!("failed to read a command: {err}");
+            continue;
+        }
+        let received = received.trim();
+        match Command::ALL
+            .into_iter()
+            .find(|command| command.arg() == received)
+        {
+            Some(command) => return Ok(command),
+            None => eprintln!("ignored unknown command {received:?}"),
+        }
+    }
+}
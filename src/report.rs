@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Debug reports to attach to bug reports.
+//!
+//! A report is a zip file with version information, the configuration without
+//! secrets or anything the user wrote, the last request with its prompt text left out, and the applet's
+//! log messages of the last hour.
+
+use crate::config::Config;
+use serde_json::Value;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::Command;
+use zip::write::SimpleFileOptions;
+
+/// Fields of a request holding what the user wrote or attached.
+const CONTENT_FIELDS: [&str; 3] = ["text", "data", "content"];
+
+/// Writes a report to `path`.
+pub fn create(
+    path: &Path,
+    profile: Option<&str>,
+    config: &Config,
+    last_request: Option<&str>,
+) -> io::Result<()> {
+    let mut zip = zip::ZipWriter::new(File::create(path)?);
+    let options = SimpleFileOptions::default();
+
+    zip.start_file("version.txt", options)?;
+    write!(
+        zip,
+        "{} {}\nos: {} {}\nprofile: {}\n",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        profile.unwrap_or("default")
+    )?;
+
+    zip.start_file("config.json", options)?;
+    zip.write_all(serde_json::to_string_pretty(&config.for_report())?.as_bytes())?;
+
+    if let Some(request) = last_request {
+        zip.start_file("last-request.json", options)?;
+        let request = match serde_json::from_str::<Value>(request) {
+            Ok(mut value) => {
+                redact_contents(&mut value);
+                serde_json::to_string_pretty(&value)?
+            }
+            Err(_) => format!("(not JSON, {} bytes)", request.len()),
+        };
+        zip.write_all(request.as_bytes())?;
+    }
+
+    zip.start_file("log.txt", options)?;
+    zip.write_all(recent_log().as_bytes())?;
+
+    zip.finish()?;
+    Ok(())
+}
+
+/// Replaces prompt and attachment contents with their length, keeping the
+/// rest of the request's structure.
+fn redact_contents(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                match value {
+                    Value::String(text) if CONTENT_FIELDS.contains(&key.as_str()) => {
+                        *value = Value::String(format!("({} characters)", text.chars().count()));
+                    }
+                    _ => redact_contents(value),
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact_contents),
+        _ => {}
+    }
+}
+
+/// The applet's messages of the last hour from the user journal, where the
+/// session collects them.
+fn recent_log() -> String {
+    // The journal knows processes by their first 15 characters.
+    let command: String = env!("CARGO_PKG_NAME").chars().take(15).collect();
+    match Command::new("journalctl")
+        .args(["--user", "--no-pager", "--output=short-iso", "--since=-1h"])
+        .arg(format!("_COMM={command}"))
+        .output()
+    {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        }
+        Ok(output) => format!(
+            "journalctl failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(err) => format!("journalctl is not available: {err}"),
+    }
+}
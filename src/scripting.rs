@@ -0,0 +1,154 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! User scripts written in [Rhai](https://rhai.rs) that hook into prompts and
+//! answers.
+//!
+//! Every `*.rhai` file in `~/.config/cosmic-ext-clankers/scripts` is loaded and
+//! may define any of these functions:
+//!
+//! - `on_prompt(text)` returns the prompt to send instead of `text`.
+//! - `on_response(text)` returns the answer to show instead of `text`.
+//! - `command_<name>(args)` runs when a prompt starts with `/<name>`, and
+//!   returns the prompt to send.
+//!
+//! Returning anything but a string leaves the text as it was.
+
+use crate::credentials;
+use rhai::{AST, Dynamic, Engine, Scope};
+use std::fs;
+use std::path::PathBuf;
+
+/// Keeps a runaway script from freezing the applet.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+const COMMAND_PREFIX: &str = "command_";
+
+/// Scripts loaded from the scripts directory.
+#[derive(Default)]
+pub struct Scripts {
+    engine: Engine,
+    scripts: Vec<(String, AST)>,
+    /// Scripts that failed to load, with the reason.
+    pub errors: Vec<String>,
+}
+
+impl Scripts {
+    /// Loads every script in [`dir`].
+    pub fn load() -> Self {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        let mut scripts = Self {
+            engine,
+            ..Self::default()
+        };
+
+        let Some(entries) = dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+            return scripts;
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "rhai")
+            })
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let name = path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+            match fs::read_to_string(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|source| {
+                    scripts
+                        .engine
+                        .compile(source)
+                        .map_err(|err| err.to_string())
+                }) {
+                Ok(ast) => scripts.scripts.push((name, ast)),
+                Err(err) => scripts.errors.push(format!("{name}: {err}")),
+            }
+        }
+        scripts
+    }
+
+    /// Names of the loaded scripts.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.scripts.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Names of the commands scripts define, without the slash.
+    pub fn commands(&self) -> impl Iterator<Item = &str> {
+        self.scripts.iter().flat_map(|(_, ast)| {
+            ast.iter_functions()
+                .filter(|function| function.params.len() == 1)
+                .filter_map(|function| function.name.strip_prefix(COMMAND_PREFIX))
+        })
+    }
+
+    /// Passes a prompt through every `on_prompt` hook, or runs the command it
+    /// starts with.
+    pub fn on_prompt(&self, text: String) -> Result<String, String> {
+        if let Some(command) = text.strip_prefix('/') {
+            let (name, args) = command.split_once(' ').unwrap_or((command, ""));
+            let function = format!("{COMMAND_PREFIX}{name}");
+            if let Some(result) = self.call_first(&function, args.trim())? {
+                return Ok(result);
+            }
+        }
+        self.call_all("on_prompt", text)
+    }
+
+    /// Passes an answer through every `on_response` hook.
+    pub fn on_response(&self, text: String) -> Result<String, String> {
+        self.call_all("on_response", text)
+    }
+
+    /// Calls `function` in each script defining it, feeding each result to the next.
+    fn call_all(&self, function: &str, mut text: String) -> Result<String, String> {
+        for (name, ast) in &self.scripts {
+            if defines(ast, function)
+                && let Some(result) = self.call(name, ast, function, text.clone())?
+            {
+                text = result;
+            }
+        }
+        Ok(text)
+    }
+
+    /// Calls `function` in the first script defining it.
+    fn call_first(&self, function: &str, argument: &str) -> Result<Option<String>, String> {
+        match self.scripts.iter().find(|(_, ast)| defines(ast, function)) {
+            Some((name, ast)) => self.call(name, ast, function, argument.to_string()),
+            None => Ok(None),
+        }
+    }
+
+    fn call(
+        &self,
+        name: &str,
+        ast: &AST,
+        function: &str,
+        argument: String,
+    ) -> Result<Option<String>, String> {
+        let result: Dynamic = self
+            .engine
+            .call_fn(&mut Scope::new(), ast, function, (argument,))
+            .map_err(|err| format!("{name}: {function}: {err}"))?;
+        Ok(result.into_string().ok())
+    }
+}
+
+/// Directory scripts are loaded from.
+pub fn dir() -> Option<PathBuf> {
+    Some(credentials::env_file_path()?.parent()?.join("scripts"))
+}
+
+fn defines(ast: &AST, function: &str) -> bool {
+    ast.iter_functions()
+        .any(|candidate| candidate.name == function && candidate.params.len() == 1)
+}
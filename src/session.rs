@@ -0,0 +1,180 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The conversations and popup state restored when the applet starts again.
+//!
+//! Kept in `.session.json` in the profile's data directory. Unlike other dot
+//! files there, it is synced, merged conversation by conversation.
+//!
+//! The app only goes through [`HistoryStore`], so where sessions are kept can
+//! change without touching it.
+
+use crate::app::Chat;
+use crate::attachment_store::AttachmentStore;
+use crate::config::Tone;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub const FILE_NAME: &str = ".session.json";
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    /// Conversations by workspace name, empty for none.
+    pub conversations: HashMap<String, Conversation>,
+    /// Conversations set aside to switch back to, most recent first.
+    pub set_aside: Vec<Conversation>,
+    /// Relative scroll position of the active conversation, 1 at its end.
+    pub scroll: f32,
+    pub popup_open: bool,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Conversation {
+    /// Messages, whose attachments refer to the attachment store.
+    pub history: Vec<Chat>,
+    pub pinned_files: Vec<PathBuf>,
+    /// Name of the template it was started from.
+    pub template: String,
+    pub tone: Tone,
+    pub title: Option<String>,
+    /// Estimated spending so far, in US dollars.
+    pub cost: f64,
+    /// Kept for reference and not to be changed.
+    pub locked: bool,
+    /// The locked conversation this one was copied from.
+    pub original: Option<Box<Conversation>>,
+}
+
+impl Session {
+    /// Calls `f` with every message of every conversation.
+    pub fn for_each_chat_ref(&self, f: &mut impl FnMut(&Chat)) {
+        for conversation in self.conversations.values().chain(&self.set_aside) {
+            conversation.for_each_chat_ref(f);
+        }
+    }
+}
+
+impl Conversation {
+    /// Calls `f` with every message, including those of the original.
+    fn for_each_chat(&mut self, f: &mut impl FnMut(&mut Chat)) {
+        self.history.iter_mut().for_each(&mut *f);
+        if let Some(original) = &mut self.original {
+            original.for_each_chat(f);
+        }
+    }
+
+    fn for_each_chat_ref(&self, f: &mut impl FnMut(&Chat)) {
+        self.history.iter().for_each(&mut *f);
+        if let Some(original) = &self.original {
+            original.for_each_chat_ref(f);
+        }
+    }
+}
+
+/// Where the session is kept between runs.
+pub trait HistoryStore: Send {
+    /// The saved session, empty if there is none.
+    fn load(&self) -> Session;
+
+    fn save(&self, session: &Session) -> io::Result<()>;
+}
+
+/// Without a better place, sessions only last as long as the applet runs.
+impl Default for Box<dyn HistoryStore> {
+    fn default() -> Self {
+        Box::new(MemoryStore::default())
+    }
+}
+
+/// Keeps the session as JSON in a directory.
+pub struct JsonStore {
+    dir: PathBuf,
+}
+
+impl JsonStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+}
+
+impl HistoryStore for JsonStore {
+    fn load(&self) -> Session {
+        load(&self.dir)
+    }
+
+    fn save(&self, session: &Session) -> io::Result<()> {
+        save(&self.dir, session)
+    }
+}
+
+/// Keeps the session in memory, for when there's nowhere to write it.
+#[derive(Default)]
+pub struct MemoryStore {
+    session: Mutex<Session>,
+}
+
+impl HistoryStore for MemoryStore {
+    fn load(&self) -> Session {
+        self.session
+            .lock()
+            .map(|session| session.clone())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, session: &Session) -> io::Result<()> {
+        if let Ok(mut saved) = self.session.lock() {
+            *saved = session.clone();
+        }
+        Ok(())
+    }
+}
+
+/// The session saved in `dir`, empty if there is none.
+fn load(dir: &Path) -> Session {
+    let mut session: Session = fs::read_to_string(dir.join(FILE_NAME))
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default();
+    let store = AttachmentStore::new(dir);
+    for conversation in session
+        .conversations
+        .values_mut()
+        .chain(session.set_aside.iter_mut())
+    {
+        conversation.for_each_chat(&mut |chat| {
+            for attachment in &mut chat.attachments {
+                if let Err(err) = store.fill(attachment) {
+                    eprintln!("failed to read attachment {}: {err}", attachment.name);
+                }
+            }
+        });
+    }
+    session
+}
+
+fn save(dir: &Path, session: &Session) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    // The contents go in first, so the session never refers to missing ones.
+    let store = AttachmentStore::new(dir);
+    let mut result = Ok(());
+    session.for_each_chat_ref(&mut |chat| {
+        for attachment in &chat.attachments {
+            if result.is_ok() {
+                result = store.put(attachment);
+            }
+        }
+    });
+    result?;
+    // Written aside and flushed to disk first, so a crash can't leave half a
+    // session behind.
+    let partial = dir.join(format!("{FILE_NAME}.partial"));
+    let mut file = File::create(&partial)?;
+    file.write_all(serde_json::to_string(session)?.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(partial, dir.join(FILE_NAME))
+}
@@ -0,0 +1,91 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Spell checking through Enchant's ispell-compatible pipe mode.
+
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// A word that isn't in the dictionary.
+#[derive(Debug, Clone)]
+pub struct Misspelling {
+    pub word: String,
+    pub suggestions: Vec<String>,
+}
+
+/// Dictionary for `language`, or for the session locale when it's empty.
+pub fn dictionary(language: &str) -> String {
+    let language = language.trim();
+    if !language.is_empty() {
+        return language.to_string();
+    }
+
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|locale| !locale.is_empty() && locale != "C" && locale != "POSIX")
+        // `en_US.UTF-8` names the `en_US` dictionary.
+        .and_then(|locale| locale.split(['.', '@']).next().map(str::to_string))
+        .unwrap_or_else(|| "en_US".to_string())
+}
+
+/// Checks `text` against `dictionary`, returning each misspelled word once.
+pub async fn check(text: String, dictionary: String) -> Result<Vec<Misspelling>, String> {
+    let mut enchant = Command::new("enchant-2")
+        .args(["-a", "-d", &dictionary])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|err| format!("could not run enchant-2: {err}"))?;
+
+    if let Some(mut stdin) = enchant.stdin.take() {
+        // A leading `^` keeps lines from being read as pipe mode commands.
+        let input: String = text.lines().map(|line| format!("^{line}\n")).collect();
+        stdin
+            .write_all(input.as_bytes())
+            .await
+            .map_err(|err| err.to_string())?;
+    }
+    let output = enchant
+        .wait_with_output()
+        .await
+        .map_err(|err| err.to_string())?;
+    if !output.status.success() {
+        return Err(format!("enchant-2 exited with {}", output.status));
+    }
+
+    let mut misspellings: Vec<Misspelling> = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        // `& word count offset: suggestion, ...` or `# word offset` without suggestions.
+        let misspelling = if let Some(rest) = line.strip_prefix("& ") {
+            let (head, suggestions) = rest.split_once(": ").unwrap_or((rest, ""));
+            Misspelling {
+                word: head.split(' ').next().unwrap_or_default().to_string(),
+                suggestions: suggestions
+                    .split(", ")
+                    .filter(|suggestion| !suggestion.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            }
+        } else if let Some(rest) = line.strip_prefix("# ") {
+            Misspelling {
+                word: rest.split(' ').next().unwrap_or_default().to_string(),
+                suggestions: Vec::new(),
+            }
+        } else {
+            continue;
+        };
+
+        if !misspelling.word.is_empty()
+            && !misspellings
+                .iter()
+                .any(|known| known.word == misspelling.word)
+        {
+            misspellings.push(misspelling);
+        }
+    }
+
+    Ok(misspellings)
+}
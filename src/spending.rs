@@ -0,0 +1,130 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Estimated cost of answered prompts, for the daily spending cap.
+//!
+//! Each billed request's cost is appended to `spending.jsonl` in the profile's
+//! data directory. The file is read once at startup, when entries from before
+//! today are dropped, and today's total is kept in memory from then on.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const FILE_NAME: &str = "spending.jsonl";
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    /// Estimated price in US dollars.
+    cost: f64,
+    /// Seconds since the Unix epoch.
+    at: u64,
+}
+
+/// Estimated spending since midnight UTC.
+#[derive(Debug, Default)]
+pub struct Spending {
+    /// Day the total was counted on, in days since the epoch.
+    day: u64,
+    total: f64,
+}
+
+impl Spending {
+    /// Adds up today's entries in `dir`, rewriting the file without older ones.
+    pub fn load(dir: &Path) -> Self {
+        let path = dir.join(FILE_NAME);
+        let Ok(contents) = fs::read_to_string(&path) else {
+            return Self::default();
+        };
+
+        let day = now() / SECONDS_PER_DAY;
+        let (kept, total) = today(&contents, day);
+        if kept.len() < contents.lines().count() {
+            let tmp = path.with_extension("jsonl.tmp");
+            let pruned = kept
+                .iter()
+                .map(|line| format!("{line}\n"))
+                .collect::<String>();
+            if let Err(err) = fs::write(&tmp, pruned).and_then(|()| fs::rename(&tmp, &path)) {
+                eprintln!("failed to drop old spending entries: {err}");
+            }
+        }
+        Self { day, total }
+    }
+
+    /// Estimated spending since midnight UTC.
+    pub fn today(&self) -> f64 {
+        if self.day == now() / SECONDS_PER_DAY {
+            self.total
+        } else {
+            0.0
+        }
+    }
+
+    /// Adds `cost` to today's total and appends it to the spending file in `dir`.
+    pub fn record(&mut self, dir: Option<&Path>, cost: f64) -> io::Result<()> {
+        let at = now();
+        self.total = self.today() + cost;
+        self.day = at / SECONDS_PER_DAY;
+
+        let Some(dir) = dir else {
+            return Ok(());
+        };
+        fs::create_dir_all(dir)?;
+        let entry = Entry { cost, at };
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(FILE_NAME))?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)
+    }
+}
+
+/// The lines of `contents` recorded on `day`, with the sum of their costs.
+fn today(contents: &str, day: u64) -> (Vec<&str>, f64) {
+    let midnight = day * SECONDS_PER_DAY;
+    let mut total = 0.0;
+    let kept = contents
+        .lines()
+        .filter(|line| {
+            let Ok(entry) = serde_json::from_str::<Entry>(line) else {
+                return false;
+            };
+            if entry.at < midnight {
+                return false;
+            }
+            total += entry.cost;
+            true
+        })
+        .collect();
+    (kept, total)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_todays_entries_are_kept_and_added_up() {
+        let day = 20_000;
+        let midnight = day * SECONDS_PER_DAY;
+        let contents = format!(
+            "{{\"cost\":1.5,\"at\":{}}}\nnot json\n{{\"cost\":0.25,\"at\":{midnight}}}\n{{\"cost\":0.5,\"at\":{}}}\n",
+            midnight - 1,
+            midnight + 60,
+        );
+        let (kept, total) = today(&contents, day);
+        assert_eq!(kept.len(), 2);
+        assert_eq!(total, 0.75);
+    }
+}
@@ -0,0 +1,191 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Disk usage of a profile's data directory, and cleaning up what it no
+//! longer needs: generated images and attachments no conversation refers to
+//! anymore and files left half-written by a crash.
+//!
+//! Message bodies aren't compressed. They live as plain JSON in the session
+//! file, which sync merges by its contents, and they take little space next
+//! to the attachments.
+
+use cosmic::iced::widget::markdown::Url;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::app::Chat;
+use crate::attachment_store;
+use crate::conversation_file::{self, ConversationFile};
+use crate::session::{self, Session};
+
+/// Files younger than this are kept, since a cleared conversation that can
+/// still be brought back may link to them.
+const GRACE_PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Suffix of files written aside before replacing the real one.
+const PARTIAL: &str = ".partial";
+
+/// Directory where generated images are saved.
+const IMAGES_DIR: &str = "images";
+
+/// What was removed by [`clean_up`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cleanup {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Space taken by each part of `data_dir`, largest first.
+pub fn usage(data_dir: &Path) -> Vec<(String, u64)> {
+    let Ok(entries) = fs::read_dir(data_dir) else {
+        return Vec::new();
+    };
+    let mut usage: Vec<(String, u64)> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            // Other profiles' data lives there.
+            if name == "profiles" {
+                return None;
+            }
+            let label = match name.as_str() {
+                session::FILE_NAME => "Conversations".to_string(),
+                IMAGES_DIR => "Generated images".to_string(),
+                attachment_store::DIR => "Attachments".to_string(),
+                "models" => "Local models".to_string(),
+                "voices" => "Voices".to_string(),
+                "inbox" => "Opened conversation files".to_string(),
+                _ => name,
+            };
+            Some((label, size(&entry.path())))
+        })
+        .collect();
+    usage.sort_by(|a, b| b.1.cmp(&a.1));
+    usage
+}
+
+/// Files messages refer to: attachments by their hash and generated images
+/// by the path they are linked with.
+#[derive(Default)]
+struct Referenced {
+    hashes: HashSet<String>,
+    paths: HashSet<PathBuf>,
+}
+
+impl Referenced {
+    fn add(&mut self, chat: &Chat) {
+        for attachment in &chat.attachments {
+            self.hashes.insert(attachment.hash.clone());
+        }
+        for content in std::iter::once(&chat.content).chain(&chat.previous) {
+            self.paths.extend(linked_files(content));
+        }
+    }
+
+    fn contains(&self, path: &Path) -> bool {
+        self.paths.contains(path)
+            || path
+                .file_name()
+                .is_some_and(|name| self.hashes.contains(&*name.to_string_lossy()))
+    }
+}
+
+/// Removes generated images and attachments that neither the saved session
+/// nor a conversation file in `export_dirs` or the inbox refers to, and
+/// leftover partial files, except those written within the grace period.
+pub fn clean_up(data_dir: &Path, export_dirs: &[PathBuf]) -> io::Result<Cleanup> {
+    let mut cleanup = Cleanup::default();
+    let mut referenced = Referenced::default();
+    match fs::read_to_string(data_dir.join(session::FILE_NAME)) {
+        Ok(contents) => {
+            // Better to remove nothing than what an unreadable session refers to.
+            let session: Session = serde_json::from_str(&contents).map_err(io::Error::other)?;
+            session.for_each_chat_ref(&mut |chat| referenced.add(chat));
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err),
+    }
+    // Exported conversations may link to images that were saved here.
+    let inbox = data_dir.join(conversation_file::INBOX_DIR);
+    for dir in export_dirs.iter().chain([&inbox]) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(Result::ok) {
+            if !entry
+                .file_name()
+                .to_string_lossy()
+                .ends_with(conversation_file::EXTENSION)
+            {
+                continue;
+            }
+            let file: Option<ConversationFile> = fs::read_to_string(entry.path())
+                .ok()
+                .and_then(|contents| serde_json::from_str(&contents).ok());
+            for chat in file.iter().flat_map(|file| &file.history) {
+                referenced.add(chat);
+            }
+        }
+    }
+
+    for dir in [IMAGES_DIR, attachment_store::DIR] {
+        let Ok(entries) = fs::read_dir(data_dir.join(dir)) else {
+            continue;
+        };
+        for entry in entries.filter_map(Result::ok) {
+            if !referenced.contains(&entry.path()) {
+                remove_if_old(&entry.path(), &mut cleanup)?;
+            }
+        }
+    }
+    for entry in fs::read_dir(data_dir)?.filter_map(Result::ok) {
+        if entry.file_name().to_string_lossy().ends_with(PARTIAL) {
+            remove_if_old(&entry.path(), &mut cleanup)?;
+        }
+    }
+    Ok(cleanup)
+}
+
+/// Local files linked from the Markdown `content`.
+fn linked_files(content: &str) -> impl Iterator<Item = PathBuf> + '_ {
+    content.split("](").skip(1).filter_map(|rest| {
+        let target = rest.split([')', ' ']).next()?;
+        Url::parse(target).ok()?.to_file_path().ok()
+    })
+}
+
+fn remove_if_old(path: &Path, cleanup: &mut Cleanup) -> io::Result<()> {
+    let metadata = fs::metadata(path)?;
+    let age = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .unwrap_or_default();
+    if !metadata.is_file() || age < GRACE_PERIOD {
+        return Ok(());
+    }
+    fs::remove_file(path)?;
+    cleanup.files += 1;
+    cleanup.bytes += metadata.len();
+    Ok(())
+}
+
+/// Size of the file or directory at `path`, with everything in it.
+fn size(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| size(&entry.path()))
+                .sum()
+        })
+        .unwrap_or_default()
+}
@@ -0,0 +1,652 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Opt-in sync of a profile's data with a WebDAV or S3-compatible endpoint.
+//!
+//! Files are encrypted before they leave the machine with a key derived from
+//! the passphrase in [`PASSPHRASE_VAR`]. The remote holds an encrypted manifest
+//! of file hashes and one encrypted object per file version. Each sync merges
+//! three ways against the manifest seen at the last sync: a side that didn't
+//! change takes the other side's version, and files changed on both sides are
+//! merged or kept as a conflict copy.
+//!
+//! JSON Lines files are merged line by line and the session conversation by
+//! conversation, against their contents at the last sync, which are kept in
+//! [`BASE_DIR`].
+
+mod s3;
+mod webdav;
+
+use crate::backup;
+use crate::config::{SyncBackend, SyncSettings};
+use crate::credentials;
+use crate::session::{self, Session};
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+/// Environment variable holding the passphrase that encrypts synced data.
+pub const PASSPHRASE_VAR: &str = "SYNC_PASSPHRASE";
+/// Environment variable holding the WebDAV password or S3 secret key.
+pub const SECRET_VAR: &str = "SYNC_SECRET";
+
+/// Last synced manifest, kept in the data directory and never synced itself.
+pub const STATE_FILE: &str = ".sync-state.json";
+/// Contents of the mergeable files as of the last sync, kept in the data
+/// directory and never synced themselves.
+pub const BASE_DIR: &str = ".sync-base";
+/// Dot files that are synced anyway, unlike other local state.
+const SYNCED_DOT_FILES: [&str; 1] = [session::FILE_NAME];
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Relative path of every synced file mapped to the SHA-256 of its contents.
+type Manifest = BTreeMap<String, String>;
+
+/// What a sync changed.
+#[derive(Debug, Clone, Default)]
+pub struct Summary {
+    pub uploaded: usize,
+    pub downloaded: usize,
+    /// Files changed on both sides, merged or kept as a conflict copy.
+    pub conflicts: Vec<String>,
+    /// The saved session was replaced and is to be loaded again.
+    pub session_changed: bool,
+}
+
+impl Summary {
+    pub fn changed_locally(&self) -> bool {
+        self.downloaded > 0 || !self.conflicts.is_empty()
+    }
+}
+
+/// Storage reached through one of the supported protocols.
+enum Remote {
+    WebDav(webdav::Client),
+    S3(s3::Client),
+}
+
+impl Remote {
+    fn new(settings: &SyncSettings, secret: String) -> Result<Self, String> {
+        Ok(match settings.backend {
+            SyncBackend::WebDav => Self::WebDav(webdav::Client::new(settings, secret)?),
+            SyncBackend::S3 => Self::S3(s3::Client::new(settings, secret)?),
+        })
+    }
+
+    /// Reads `key`, `None` if it doesn't exist.
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+        match self {
+            Self::WebDav(client) => client.get(key).await,
+            Self::S3(client) => client.get(key).await,
+        }
+    }
+
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), String> {
+        match self {
+            Self::WebDav(client) => client.put(key, data).await,
+            Self::S3(client) => client.put(key, data).await,
+        }
+    }
+
+    /// Writes `key` unless it exists, returning whether it was written.
+    async fn create(&self, key: &str, data: Vec<u8>) -> Result<bool, String> {
+        match self {
+            Self::WebDav(client) => client.create(key, data).await,
+            Self::S3(client) => client.create(key, data).await,
+        }
+    }
+}
+
+/// Syncs `data_dir` with the remote under the folder `label`.
+pub async fn run(settings: &SyncSettings, data_dir: &Path, label: &str) -> Result<Summary, String> {
+    let passphrase =
+        credentials::lookup(PASSPHRASE_VAR).ok_or(format!("{PASSPHRASE_VAR} is not set"))?;
+    let secret = credentials::lookup(SECRET_VAR).ok_or(format!("{SECRET_VAR} is not set"))?;
+    let remote = Remote::new(settings, secret)?;
+
+    // The salt is shared by every machine, so the first one to sync creates it.
+    // Machines syncing for the first time at once all use the one that won.
+    let salt_key = format!("{label}/salt");
+    let salt = match remote.get(&salt_key).await? {
+        Some(salt) => salt,
+        None => {
+            let mut salt = vec![0; SALT_LEN];
+            OsRng.fill_bytes(&mut salt);
+            if remote.create(&salt_key, salt.clone()).await? {
+                salt
+            } else {
+                remote
+                    .get(&salt_key)
+                    .await?
+                    .ok_or("the sync salt disappeared from the server")?
+            }
+        }
+    };
+    let key = derive_key(&passphrase, &salt)?;
+
+    let manifest_key = format!("{label}/manifest");
+    let remote_manifest: Manifest = match remote.get(&manifest_key).await? {
+        Some(data) => serde_json::from_slice(&open(&key, &data)?)
+            .map_err(|err| format!("unreadable manifest: {err}"))?,
+        None => Manifest::new(),
+    };
+    let base: Manifest = fs::read(data_dir.join(STATE_FILE))
+        .ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default();
+    let local = local_files(data_dir).map_err(|err| err.to_string())?;
+    let base_dir = data_dir.join(BASE_DIR);
+
+    let object_key = |hash: &str| format!("{label}/objects/{}", object_id(&key, hash));
+    let mut merged = Manifest::new();
+    let mut summary = Summary::default();
+    let paths: BTreeSet<&String> = local.keys().chain(remote_manifest.keys()).collect();
+
+    for path in paths {
+        let ours = local.get(path);
+        let theirs = remote_manifest.get(path);
+        let before = base.get(path);
+        let file = data_dir.join(path);
+
+        if ours == theirs {
+            if let Some(hash) = ours {
+                merged.insert(path.clone(), hash.clone());
+            }
+        } else if ours == before {
+            // Only the remote changed.
+            match theirs {
+                Some(hash) => {
+                    let data = download(&remote, &key, &object_key(hash)).await?;
+                    write_file(&file, &data)?;
+                    merged.insert(path.clone(), hash.clone());
+                }
+                None => {
+                    fs::remove_file(&file).map_err(|err| err.to_string())?;
+                }
+            }
+            summary.downloaded += 1;
+            summary.session_changed |= path == session::FILE_NAME;
+        } else if theirs == before {
+            // Only this machine changed.
+            if let Some(hash) = ours {
+                upload(&remote, &key, &object_key(hash), &file).await?;
+                merged.insert(path.clone(), hash.clone());
+            }
+            summary.uploaded += 1;
+        } else {
+            let theirs_data = match theirs {
+                Some(hash) => Some(download(&remote, &key, &object_key(hash)).await?),
+                None => None,
+            };
+            match (ours, theirs_data) {
+                (Some(_), Some(theirs_data)) if is_mergeable(path) => {
+                    let ours_data = fs::read(&file).map_err(|err| err.to_string())?;
+                    let base_data = fs::read(base_dir.join(path)).ok();
+                    let data = merge(path, base_data.as_deref(), &ours_data, &theirs_data)?;
+                    write_file(&file, &data)?;
+                    let hash = hex(&Sha256::digest(&data));
+                    upload(&remote, &key, &object_key(&hash), &file).await?;
+                    merged.insert(path.clone(), hash);
+                    summary.session_changed |= path == session::FILE_NAME;
+                }
+                (Some(hash), Some(theirs_data)) => {
+                    let copy = conflict_path(path, |candidate| {
+                        local.contains_key(candidate)
+                            || remote_manifest.contains_key(candidate)
+                            || merged.contains_key(candidate)
+                    });
+                    write_file(&data_dir.join(&copy), &theirs_data)?;
+                    let copy_hash = hex(&Sha256::digest(&theirs_data));
+                    upload(
+                        &remote,
+                        &key,
+                        &object_key(&copy_hash),
+                        &data_dir.join(&copy),
+                    )
+                    .await?;
+                    upload(&remote, &key, &object_key(hash), &file).await?;
+                    merged.insert(copy, copy_hash);
+                    merged.insert(path.clone(), hash.clone());
+                }
+                // Deleted on one side and changed on the other, keep the change.
+                (Some(hash), None) => {
+                    upload(&remote, &key, &object_key(hash), &file).await?;
+                    merged.insert(path.clone(), hash.clone());
+                }
+                (None, Some(theirs_data)) => {
+                    write_file(&file, &theirs_data)?;
+                    if let Some(hash) = theirs {
+                        merged.insert(path.clone(), hash.clone());
+                    }
+                    summary.session_changed |= path == session::FILE_NAME;
+                }
+                (None, None) => {}
+            }
+            summary.conflicts.push(path.clone());
+        }
+    }
+
+    if merged != remote_manifest {
+        let manifest = serde_json::to_vec(&merged).map_err(|err| err.to_string())?;
+        remote.put(&manifest_key, seal(&key, &manifest)?).await?;
+    }
+    let state = serde_json::to_vec(&merged).map_err(|err| err.to_string())?;
+    fs::write(data_dir.join(STATE_FILE), state).map_err(|err| err.to_string())?;
+    keep_bases(data_dir, &base_dir, &merged)?;
+
+    Ok(summary)
+}
+
+/// Keeps the synced contents of the mergeable files for the next merge.
+fn keep_bases(data_dir: &Path, base_dir: &Path, merged: &Manifest) -> Result<(), String> {
+    if base_dir.exists() {
+        fs::remove_dir_all(base_dir).map_err(|err| err.to_string())?;
+    }
+    for path in merged.keys().filter(|path| is_mergeable(path)) {
+        let data = fs::read(data_dir.join(path)).map_err(|err| err.to_string())?;
+        write_file(&base_dir.join(path), &data)?;
+    }
+    Ok(())
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key, String> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| err.to_string())?;
+    Ok(key)
+}
+
+/// Encrypts `data`, prefixing the random nonce.
+fn seal(key: &Key, data: &[u8]) -> Result<Vec<u8>, String> {
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = ChaCha20Poly1305::new(key)
+        .encrypt(&nonce, data)
+        .map_err(|err| err.to_string())?;
+    Ok([nonce.as_slice(), &ciphertext].concat())
+}
+
+fn open(key: &Key, data: &[u8]) -> Result<Vec<u8>, String> {
+    if data.len() < NONCE_LEN {
+        return Err("encrypted data is truncated".into());
+    }
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    ChaCha20Poly1305::new(key)
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| format!("could not decrypt synced data, check {PASSPHRASE_VAR}"))
+}
+
+/// Name of the remote object holding the file with `hash`, which doesn't
+/// reveal the hash to the server.
+fn object_id(key: &Key, hash: &str) -> String {
+    hex(&Sha256::new()
+        .chain_update(key)
+        .chain_update(hash.as_bytes())
+        .finalize())
+}
+
+async fn download(remote: &Remote, key: &Key, object: &str) -> Result<Vec<u8>, String> {
+    let data = remote
+        .get(object)
+        .await?
+        .ok_or_else(|| format!("{object} is missing on the server"))?;
+    open(key, &data)
+}
+
+async fn upload(remote: &Remote, key: &Key, object: &str, file: &Path) -> Result<(), String> {
+    let data = fs::read(file).map_err(|err| format!("{}: {err}", file.display()))?;
+    remote.put(object, seal(key, &data)?).await
+}
+
+fn write_file(path: &Path, data: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    fs::write(path, data).map_err(|err| format!("{}: {err}", path.display()))
+}
+
+/// Hashes every synced file in `data_dir`.
+fn local_files(data_dir: &Path) -> std::io::Result<Manifest> {
+    let mut files = Manifest::new();
+    if data_dir.is_dir() {
+        walk(data_dir, "", &mut files)?;
+    }
+    Ok(files)
+}
+
+fn walk(dir: &Path, prefix: &str, files: &mut Manifest) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)?.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        // Hidden files hold local state, such as the last synced manifest.
+        let hidden = name.starts_with('.')
+            && !(prefix.is_empty() && SYNCED_DOT_FILES.contains(&name.as_str()));
+        if hidden || (prefix.is_empty() && backup::is_skipped(&entry.file_name())) {
+            continue;
+        }
+        let path = format!("{prefix}{name}");
+        if entry.file_type()?.is_dir() {
+            walk(&entry.path(), &format!("{path}/"), files)?;
+        } else {
+            files.insert(path, hex(&Sha256::digest(fs::read(entry.path())?)));
+        }
+    }
+    Ok(())
+}
+
+/// Whether changes to `path` from both sides can be combined.
+fn is_mergeable(path: &str) -> bool {
+    path.ends_with(".jsonl") || path == session::FILE_NAME
+}
+
+/// Combines two versions of the mergeable file at `path` that changed since `base`.
+fn merge(path: &str, base: Option<&[u8]>, ours: &[u8], theirs: &[u8]) -> Result<Vec<u8>, String> {
+    if path != session::FILE_NAME {
+        return Ok(merge_lines(base, ours, theirs));
+    }
+    let parse = |data: &[u8]| {
+        serde_json::from_slice::<Session>(data).map_err(|err| format!("unreadable session: {err}"))
+    };
+    let base = base.and_then(|base| parse(base).ok());
+    let merged = merge_sessions(base.as_ref(), parse(ours)?, parse(theirs)?);
+    serde_json::to_vec(&merged).map_err(|err| err.to_string())
+}
+
+/// Combines two versions of a JSON Lines file line by line, see [`merge_lists`].
+fn merge_lines(base: Option<&[u8]>, ours: &[u8], theirs: &[u8]) -> Vec<u8> {
+    let lines = |data: &'_ [u8]| -> Vec<Vec<u8>> {
+        data.split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(<[u8]>::to_vec)
+            .collect()
+    };
+    let base = base.map(lines);
+    let mut merged = Vec::new();
+    for line in merge_lists(base.as_deref(), &lines(ours), &lines(theirs)) {
+        merged.extend_from_slice(&line);
+        merged.push(b'\n');
+    }
+    merged
+}
+
+/// Combines two versions of a session conversation by conversation. One
+/// changed on both sides stays as ours, with theirs set aside, so neither is
+/// lost. This machine's scroll position and popup state are kept.
+fn merge_sessions(base: Option<&Session>, mut ours: Session, mut theirs: Session) -> Session {
+    let id = |conversation: &session::Conversation| {
+        serde_json::to_string(conversation).unwrap_or_default()
+    };
+
+    // Set-aside conversations are merged like lines, by their contents.
+    let mut by_id: BTreeMap<String, session::Conversation> = BTreeMap::new();
+    let mut ids = |list: Vec<session::Conversation>| -> Vec<String> {
+        list.into_iter()
+            .map(|conversation| {
+                let key = id(&conversation);
+                by_id.insert(key.clone(), conversation);
+                key
+            })
+            .collect()
+    };
+    let ours_aside = ids(std::mem::take(&mut ours.set_aside));
+    let theirs_aside = ids(std::mem::take(&mut theirs.set_aside));
+    let base_aside: Option<Vec<String>> = base.map(|base| base.set_aside.iter().map(id).collect());
+    let mut set_aside: Vec<session::Conversation> =
+        merge_lists(base_aside.as_deref(), &ours_aside, &theirs_aside)
+            .into_iter()
+            .filter_map(|key| by_id.get(&key).cloned())
+            .collect();
+
+    let workspaces: BTreeSet<String> = ours
+        .conversations
+        .keys()
+        .chain(theirs.conversations.keys())
+        .cloned()
+        .collect();
+    let mut conversations = std::collections::HashMap::new();
+    for workspace in workspaces {
+        let before = base
+            .and_then(|base| base.conversations.get(&workspace))
+            .map(id);
+        let mine = ours.conversations.remove(&workspace);
+        let other = theirs.conversations.remove(&workspace);
+        let (mine_id, other_id) = (mine.as_ref().map(id), other.as_ref().map(id));
+        let kept = if mine_id == other_id || other_id == before {
+            mine
+        } else if mine_id == before {
+            other
+        } else {
+            match (mine, other) {
+                (Some(mine), Some(other)) => {
+                    set_aside.insert(0, other);
+                    Some(mine)
+                }
+                // Removed on one side and changed on the other, keep the change.
+                (mine, other) => mine.or(other),
+            }
+        };
+        if let Some(conversation) = kept {
+            conversations.insert(workspace, conversation);
+        }
+    }
+
+    Session {
+        conversations,
+        set_aside,
+        ..ours
+    }
+}
+
+/// Three-way merge of two versions of a list that changed since `base`: items
+/// added on either side are kept and items removed on either side dropped,
+/// ours in their order followed by theirs. Equal items count separately.
+/// Without a base, items both sides have count as unchanged.
+fn merge_lists<T: Clone + Ord>(base: Option<&[T]>, ours: &[T], theirs: &[T]) -> Vec<T> {
+    let count = |items: &[T]| {
+        let mut counts: BTreeMap<T, usize> = BTreeMap::new();
+        for item in items {
+            *counts.entry(item.clone()).or_default() += 1;
+        }
+        counts
+    };
+    let (ours_count, theirs_count) = (count(ours), count(theirs));
+    let base_count = match base {
+        Some(base) => count(base),
+        None => ours_count
+            .iter()
+            .filter_map(|(item, n)| Some((item.clone(), (*n).min(*theirs_count.get(item)?))))
+            .collect(),
+    };
+    let how_many = |counts: &BTreeMap<T, usize>, item: &T| counts.get(item).copied().unwrap_or(0);
+
+    // Removed on their side, by how often.
+    let mut removed: BTreeMap<T, usize> = base_count
+        .iter()
+        .map(|(item, n)| {
+            (
+                item.clone(),
+                n.saturating_sub(how_many(&theirs_count, item)),
+            )
+        })
+        .collect();
+    // Added on their side, by how often.
+    let mut added: BTreeMap<T, usize> = theirs_count
+        .iter()
+        .map(|(item, n)| (item.clone(), n.saturating_sub(how_many(&base_count, item))))
+        .collect();
+
+    let mut merged = Vec::with_capacity(ours.len());
+    for item in ours {
+        match removed.get_mut(item) {
+            Some(n) if *n > 0 => *n -= 1,
+            _ => merged.push(item.clone()),
+        }
+    }
+    for item in theirs {
+        if let Some(n) = added.get_mut(item)
+            && *n > 0
+        {
+            *n -= 1;
+            merged.push(item.clone());
+        }
+    }
+    merged
+}
+
+/// The first of `images/a.conflict.png`, `images/a.conflict-2.png` and so on
+/// for `images/a.png` that isn't `taken`.
+fn conflict_path(path: &str, taken: impl Fn(&str) -> bool) -> String {
+    let (dir, name) = path
+        .rsplit_once('/')
+        .map_or(("", path), |(dir, name)| (dir, name));
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, Some(extension)),
+        _ => (name, None),
+    };
+    (1..)
+        .map(|attempt| {
+            let suffix = match attempt {
+                1 => "conflict".to_string(),
+                attempt => format!("conflict-{attempt}"),
+            };
+            let name = match extension {
+                Some(extension) => format!("{stem}.{suffix}.{extension}"),
+                None => format!("{name}.{suffix}"),
+            };
+            if dir.is_empty() {
+                name
+            } else {
+                format!("{dir}/{name}")
+            }
+        })
+        .find(|candidate| !taken(candidate))
+        .expect("some name is free")
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::Chat;
+    use session::Conversation;
+
+    fn conversation(messages: &[&str]) -> Conversation {
+        Conversation {
+            history: messages
+                .iter()
+                .map(|content| Chat {
+                    role: "user".into(),
+                    content: content.to_string(),
+                    ..Chat::default()
+                })
+                .collect(),
+            ..Conversation::default()
+        }
+    }
+
+    fn session(conversations: &[(&str, &[&str])], set_aside: &[&[&str]]) -> Session {
+        Session {
+            conversations: conversations
+                .iter()
+                .map(|(workspace, messages)| (workspace.to_string(), conversation(messages)))
+                .collect(),
+            set_aside: set_aside
+                .iter()
+                .map(|messages| conversation(messages))
+                .collect(),
+            ..Session::default()
+        }
+    }
+
+    fn contents(conversation: &Conversation) -> Vec<&str> {
+        conversation
+            .history
+            .iter()
+            .map(|chat| chat.content.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn lines_added_on_both_sides_are_kept() {
+        let merged = merge_lines(Some(&b"a\n"[..]), b"a\nb\n", b"a\nc\n");
+        assert_eq!(merged, b"a\nb\nc\n");
+    }
+
+    #[test]
+    fn deleted_lines_stay_deleted() {
+        let merged = merge_lines(Some(&b"a\nb\n"[..]), b"a\nb\nc\n", b"b\n");
+        assert_eq!(merged, b"b\nc\n");
+    }
+
+    #[test]
+    fn duplicate_lines_are_kept() {
+        let merged = merge_lines(Some(&b"x\n"[..]), b"x\nx\n", b"x\ny\n");
+        assert_eq!(merged, b"x\nx\ny\n");
+    }
+
+    #[test]
+    fn without_a_base_lines_on_both_sides_count_as_unchanged() {
+        let merged = merge_lines(None, b"a\nb\n", b"a\nc\n");
+        assert_eq!(merged, b"a\nb\nc\n");
+    }
+
+    #[test]
+    fn conflict_copies_get_free_names() {
+        assert_eq!(
+            conflict_path("images/a.png", |_| false),
+            "images/a.conflict.png"
+        );
+        assert_eq!(conflict_path("README", |_| false), "README.conflict");
+        assert_eq!(
+            conflict_path("notes.txt", |path| path == "notes.conflict.txt"),
+            "notes.conflict-2.txt"
+        );
+    }
+
+    #[test]
+    fn conversations_changed_on_one_side_take_the_change() {
+        let base = session(&[("", &["a"])], &[]);
+        let ours = session(&[("", &["a"])], &[]);
+        let theirs = session(&[("", &["a", "b"])], &[]);
+        let merged = merge_sessions(Some(&base), ours, theirs);
+        assert_eq!(contents(&merged.conversations[""]), ["a", "b"]);
+        assert!(merged.set_aside.is_empty());
+    }
+
+    #[test]
+    fn conversations_changed_on_both_sides_keep_theirs_aside() {
+        let base = session(&[("", &["a"])], &[]);
+        let ours = session(&[("", &["a", "b"])], &[]);
+        let theirs = session(&[("", &["a", "c"])], &[]);
+        let merged = merge_sessions(Some(&base), ours, theirs);
+        assert_eq!(contents(&merged.conversations[""]), ["a", "b"]);
+        assert_eq!(merged.set_aside.len(), 1);
+        assert_eq!(contents(&merged.set_aside[0]), ["a", "c"]);
+    }
+
+    #[test]
+    fn conversations_removed_on_one_side_and_changed_on_the_other_are_kept() {
+        let base = session(&[("work", &["a"])], &[]);
+        let ours = session(&[], &[]);
+        let theirs = session(&[("work", &["a", "c"])], &[]);
+        let merged = merge_sessions(Some(&base), ours, theirs);
+        assert_eq!(contents(&merged.conversations["work"]), ["a", "c"]);
+    }
+
+    #[test]
+    fn set_aside_conversations_are_merged() {
+        let base = session(&[], &[&["x"]]);
+        let ours = session(&[], &[&["x"], &["y"]]);
+        let theirs = session(&[], &[]);
+        let merged = merge_sessions(Some(&base), ours, theirs);
+        assert_eq!(merged.set_aside.len(), 1);
+        assert_eq!(contents(&merged.set_aside[0]), ["y"]);
+    }
+}
@@ -0,0 +1,208 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! S3-compatible object storage, such as AWS, MinIO or Backblaze B2, using
+//! path-style URLs and Signature Version 4.
+
+use super::hex;
+use crate::config::SyncSettings;
+use hmac::{Hmac, Mac};
+use reqwest::{Method, StatusCode, Url};
+use sha2::{Digest, Sha256};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub struct Client {
+    http: reqwest::Client,
+    endpoint: Url,
+    bucket: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+}
+
+impl Client {
+    pub fn new(settings: &SyncSettings, secret_key: String) -> Result<Self, String> {
+        let endpoint =
+            Url::parse(settings.url.trim()).map_err(|err| format!("invalid S3 endpoint: {err}"))?;
+        if settings.bucket.trim().is_empty() {
+            return Err("No S3 bucket is set".into());
+        }
+        let region = settings.region.trim();
+
+        Ok(Self {
+            http: reqwest::Client::new(),
+            endpoint,
+            bucket: settings.bucket.trim().to_string(),
+            region: if region.is_empty() {
+                "us-east-1"
+            } else {
+                region
+            }
+            .to_string(),
+            access_key: settings.username.trim().to_string(),
+            secret_key,
+        })
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+        let response = self
+            .request(Method::GET, key, Vec::new())
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = response.error_for_status().map_err(|err| err.to_string())?;
+
+        Ok(Some(
+            response
+                .bytes()
+                .await
+                .map_err(|err| err.to_string())?
+                .to_vec(),
+        ))
+    }
+
+    pub async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), String> {
+        self.request(Method::PUT, key, data)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| err.to_string())?;
+        Ok(())
+    }
+
+    /// Writes `key` unless it exists, returning whether it was written.
+    pub async fn create(&self, key: &str, data: Vec<u8>) -> Result<bool, String> {
+        let response = self
+            .request(Method::PUT, key, data)
+            .header(reqwest::header::IF_NONE_MATCH, "*")
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+        if response.status() == StatusCode::PRECONDITION_FAILED {
+            return Ok(false);
+        }
+        response.error_for_status().map_err(|err| err.to_string())?;
+        Ok(true)
+    }
+
+    /// Builds a signed request for `key` in the bucket.
+    fn request(&self, method: Method, key: &str, body: Vec<u8>) -> reqwest::RequestBuilder {
+        let path = format!(
+            "{}/{}/{key}",
+            self.endpoint.path().trim_end_matches('/'),
+            self.bucket
+        );
+        let mut url = self.endpoint.clone();
+        url.set_path(&path);
+
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{host}:{port}"),
+            (Some(host), None) => host.to_string(),
+            (None, _) => String::new(),
+        };
+        let (date, time) = timestamp(SystemTime::now());
+        let payload_hash = hex(&Sha256::digest(&body));
+        let scope = format!("{date}/{}/s3/aws4_request", self.region);
+
+        let canonical_request = format!(
+            "{method}\n{}\n\nhost:{host}\nx-amz-content-sha256:{payload_hash}\nx-amz-date:{time}\n\n\
+             host;x-amz-content-sha256;x-amz-date\n{payload_hash}",
+            url.path()
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{time}\n{scope}\n{}",
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let signing_key = [date.as_str(), &self.region, "s3", "aws4_request"]
+            .into_iter()
+            .fold(
+                format!("AWS4{}", self.secret_key).into_bytes(),
+                |key, part| hmac(&key, part.as_bytes()),
+            );
+        let signature = hex(&hmac(&signing_key, string_to_sign.as_bytes()));
+
+        self.http
+            .request(method, url)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", time)
+            .header(
+                reqwest::header::AUTHORIZATION,
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{scope}, \
+                     SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={signature}",
+                    self.access_key
+                ),
+            )
+            .body(body)
+    }
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// `YYYYMMDD` and `YYYYMMDDTHHMMSSZ` in UTC, as used by the signature.
+fn timestamp(time: SystemTime) -> (String, String) {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, seconds) = (seconds / 86400, seconds % 86400);
+
+    // Civil date from days since the epoch, after Howard Hinnant's algorithm.
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    let date = format!("{year:04}{month:02}{day:02}");
+    let time = format!(
+        "{date}T{:02}{:02}{:02}Z",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    );
+    (date, time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn at(seconds: u64) -> (String, String) {
+        timestamp(UNIX_EPOCH + Duration::from_secs(seconds))
+    }
+
+    #[test]
+    fn timestamps_are_in_utc() {
+        assert_eq!(at(0), ("19700101".into(), "19700101T000000Z".into()));
+        assert_eq!(
+            at(1_700_000_000),
+            ("20231114".into(), "20231114T221320Z".into())
+        );
+    }
+
+    #[test]
+    fn leap_days_are_counted() {
+        assert_eq!(
+            at(951_782_400),
+            ("20000229".into(), "20000229T000000Z".into())
+        );
+    }
+}
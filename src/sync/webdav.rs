@@ -0,0 +1,121 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! WebDAV storage, such as Nextcloud or ownCloud.
+
+use crate::config::SyncSettings;
+use reqwest::{Method, StatusCode};
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+pub struct Client {
+    http: reqwest::Client,
+    /// Collection URL that keys are resolved against, ending in `/`.
+    base: String,
+    username: String,
+    password: String,
+    /// Collections known to exist.
+    created: Mutex<HashSet<String>>,
+}
+
+impl Client {
+    pub fn new(settings: &SyncSettings, password: String) -> Result<Self, String> {
+        let base = settings.url.trim();
+        if base.is_empty() {
+            return Err("No WebDAV URL is set".into());
+        }
+
+        Ok(Self {
+            http: reqwest::Client::new(),
+            base: format!("{}/", base.trim_end_matches('/')),
+            username: settings.username.clone(),
+            password,
+            created: Mutex::default(),
+        })
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+        let response = self
+            .http
+            .get(format!("{}{key}", self.base))
+            .basic_auth(&self.username, Some(&self.password))
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = response.error_for_status().map_err(|err| err.to_string())?;
+
+        Ok(Some(
+            response
+                .bytes()
+                .await
+                .map_err(|err| err.to_string())?
+                .to_vec(),
+        ))
+    }
+
+    pub async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), String> {
+        self.create_parents(key).await?;
+        self.http
+            .put(format!("{}{key}", self.base))
+            .basic_auth(&self.username, Some(&self.password))
+            .body(data)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| err.to_string())?;
+        Ok(())
+    }
+
+    /// Writes `key` unless it exists, returning whether it was written.
+    pub async fn create(&self, key: &str, data: Vec<u8>) -> Result<bool, String> {
+        self.create_parents(key).await?;
+        let response = self
+            .http
+            .put(format!("{}{key}", self.base))
+            .basic_auth(&self.username, Some(&self.password))
+            .header(reqwest::header::IF_NONE_MATCH, "*")
+            .body(data)
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+        if response.status() == StatusCode::PRECONDITION_FAILED {
+            return Ok(false);
+        }
+        response.error_for_status().map_err(|err| err.to_string())?;
+        Ok(true)
+    }
+
+    /// WebDAV doesn't create missing collections on upload.
+    async fn create_parents(&self, key: &str) -> Result<(), String> {
+        let Some((parents, _)) = key.rsplit_once('/') else {
+            return Ok(());
+        };
+        let mut collection = String::new();
+        for part in parents.split('/') {
+            collection.push_str(part);
+            collection.push('/');
+            if self.created.lock().unwrap().contains(&collection) {
+                continue;
+            }
+            let status = self
+                .http
+                .request(
+                    Method::from_bytes(b"MKCOL").expect("MKCOL is a valid method"),
+                    format!("{}{collection}", self.base),
+                )
+                .basic_auth(&self.username, Some(&self.password))
+                .send()
+                .await
+                .map_err(|err| err.to_string())?
+                .status();
+            // 405 means the collection already exists.
+            if !status.is_success() && status != StatusCode::METHOD_NOT_ALLOWED {
+                return Err(format!("could not create {collection}: {status}"));
+            }
+            self.created.lock().unwrap().insert(collection.clone());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Text to speech through speech-dispatcher's `spd-say` or Piper.
+
+use std::path::PathBuf;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::Notify;
+
+use crate::config::{Config, SpeechEngine};
+use crate::credentials::CONFIG_DIR_NAME;
+
+const PIPER_VOICES_URL: &str = "https://huggingface.co/rhasspy/piper-voices/resolve/main";
+
+/// Wakes Piper playback up to stop it.
+static CANCEL: Notify = Notify::const_new();
+
+/// How answers are spoken.
+#[derive(Debug, Clone)]
+pub enum Voice {
+    SpeechDispatcher,
+    /// Piper with the given voice model.
+    Piper(PathBuf),
+}
+
+impl Voice {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        match config.speech_engine {
+            SpeechEngine::SpeechDispatcher => Ok(Self::SpeechDispatcher),
+            SpeechEngine::Piper => {
+                let name = config.piper_voice.trim();
+                if name.is_empty() {
+                    return Err("no Piper voice selected".into());
+                }
+                let path = voices_dir()
+                    .ok_or("could not find the data directory")?
+                    .join(format!("{name}.onnx"));
+                Ok(Self::Piper(path))
+            }
+        }
+    }
+}
+
+/// Speaks `text` and returns once it has been read out or cancelled.
+///
+/// `rate` goes from -100 (slowest) to 100 (fastest).
+pub async fn speak(text: String, rate: i32, voice: Voice) -> Result<(), String> {
+    match voice {
+        Voice::SpeechDispatcher => speak_with_spd(text, rate).await,
+        Voice::Piper(model) => speak_with_piper(text, rate, model).await,
+    }
+}
+
+async fn speak_with_spd(text: String, rate: i32) -> Result<(), String> {
+    let status = Command::new("spd-say")
+        .arg("--wait")
+        .arg("--rate")
+        .arg(rate.clamp(-100, 100).to_string())
+        .arg("--")
+        .arg(text)
+        .status()
+        .await
+        .map_err(|err| format!("could not run spd-say: {err}"))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("spd-say exited with {status}"))
+    }
+}
+
+/// Synthesizes `text` to a temporary WAV file with Piper, then plays it.
+async fn speak_with_piper(text: String, rate: i32, model: PathBuf) -> Result<(), String> {
+    let wav = std::env::temp_dir().join(format!("{CONFIG_DIR_NAME}-{}.wav", std::process::id()));
+    // Piper's length scale stretches speech, so slower rates get larger values.
+    let length_scale = 2f32.powf(-(rate.clamp(-100, 100) as f32) / 100.0);
+
+    let mut piper = Command::new("piper")
+        .arg("--model")
+        .arg(&model)
+        .arg("--length_scale")
+        .arg(length_scale.to_string())
+        .arg("--output_file")
+        .arg(&wav)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|err| format!("could not run piper: {err}"))?;
+    if let Some(mut stdin) = piper.stdin.take() {
+        stdin
+            .write_all(text.as_bytes())
+            .await
+            .map_err(|err| format!("could not send text to piper: {err}"))?;
+    }
+
+    let cancelled = CANCEL.notified();
+    tokio::pin!(cancelled);
+    tokio::select! {
+        status = piper.wait() => match status {
+            Ok(status) if status.success() => {}
+            Ok(status) => return Err(format!("piper exited with {status}")),
+            Err(err) => return Err(err.to_string()),
+        },
+        () = &mut cancelled => return Ok(()),
+    }
+
+    let mut player = Command::new("pw-play")
+        .arg(&wav)
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|err| format!("could not run pw-play: {err}"))?;
+    let result = tokio::select! {
+        status = player.wait() => match status {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => Err(format!("pw-play exited with {status}")),
+            Err(err) => Err(err.to_string()),
+        },
+        () = &mut cancelled => Ok(()),
+    };
+    _ = tokio::fs::remove_file(&wav).await;
+
+    result
+}
+
+/// Cancels whatever is being spoken.
+pub fn stop(engine: SpeechEngine) {
+    match engine {
+        SpeechEngine::SpeechDispatcher => {
+            if let Err(err) = std::process::Command::new("spd-say")
+                .arg("--cancel")
+                .spawn()
+            {
+                eprintln!("could not run spd-say: {err}");
+            }
+        }
+        SpeechEngine::Piper => CANCEL.notify_waiters(),
+    }
+}
+
+/// Directory where Piper voices are kept.
+pub fn voices_dir() -> Option<PathBuf> {
+    let data_dir = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/share"),
+    };
+
+    Some(data_dir.join(CONFIG_DIR_NAME).join("voices"))
+}
+
+/// Names of the downloaded Piper voices.
+pub fn installed_voices() -> Vec<String> {
+    let Some(entries) = voices_dir().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+
+    let mut voices: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            entry
+                .file_name()
+                .to_str()?
+                .strip_suffix(".onnx")
+                .map(str::to_string)
+        })
+        .collect();
+    voices.sort();
+    voices
+}
+
+/// Downloads a Piper voice such as `en_US-lessac-medium` with its config.
+pub async fn download_voice(name: String) -> Result<String, String> {
+    let name = name.trim().to_string();
+    // Voices are stored as `<lang>/<locale>/<speaker>/<quality>/<name>.onnx`.
+    let [locale, speaker, quality] = name.splitn(3, '-').collect::<Vec<_>>()[..] else {
+        return Err("Voice names look like en_US-lessac-medium".into());
+    };
+    let language = locale.split('_').next().unwrap_or(locale);
+    let base = format!("{PIPER_VOICES_URL}/{language}/{locale}/{speaker}/{quality}/{name}");
+
+    let dir = voices_dir().ok_or("Could not find the data directory")?;
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let client = reqwest::Client::new();
+    for extension in ["onnx.json", "onnx"] {
+        let bytes = client
+            .get(format!("{base}.{extension}"))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| err.to_string())?
+            .bytes()
+            .await
+            .map_err(|err| err.to_string())?;
+        tokio::fs::write(dir.join(format!("{name}.{extension}")), bytes)
+            .await
+            .map_err(|err| err.to_string())?;
+    }
+
+    Ok(name)
+}
+
+/// Splits markdown text into sentences for reading aloud, dropping formatting characters.
+pub fn sentences(text: &str) -> Vec<String> {
+    let plain: String = text
+        .chars()
+        .filter(|c| !matches!(c, '*' | '_' | '`' | '#' | '>' | '|'))
+        .collect();
+
+    let mut sentences = Vec::new();
+    let mut current = String::new();
+    let mut chars = plain.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            current.push(' ');
+        } else {
+            current.push(c);
+        }
+
+        let ends_sentence =
+            matches!(c, '.' | '!' | '?') && chars.peek().is_none_or(|next| next.is_whitespace());
+        if ends_sentence || (c == '\n' && chars.peek() == Some(&'\n')) {
+            let sentence = current.trim();
+            if !sentence.is_empty() {
+                sentences.push(sentence.to_string());
+            }
+            current.clear();
+        }
+    }
+    let sentence = current.trim();
+    if !sentence.is_empty() {
+        sentences.push(sentence.to_string());
+    }
+
+    sentences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_is_split_into_sentences_without_formatting() {
+        assert_eq!(
+            sentences("Hello **world**. How are you?\nFine"),
+            vec!["Hello world.", "How are you?", "Fine"]
+        );
+    }
+
+    #[test]
+    fn decimal_points_do_not_end_sentences() {
+        assert_eq!(
+            sentences("Version 1.5 is out."),
+            vec!["Version 1.5 is out."]
+        );
+    }
+
+    #[test]
+    fn paragraphs_end_sentences() {
+        assert_eq!(
+            sentences("First line\n\nSecond"),
+            vec!["First line", "Second"]
+        );
+    }
+}